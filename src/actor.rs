@@ -1,5 +1,10 @@
 use crate::{
-    bot::Bot, character::Character, level::UpdateContext, message::Message, player::Player,
+    bot::Bot,
+    character::{Character, Team},
+    item::PICKUP_RADIUS,
+    level::UpdateContext,
+    message::Message,
+    player::Player,
 };
 use fyrox::{
     core::{
@@ -7,9 +12,17 @@ use fyrox::{
         pool::{Handle, Pool},
         visitor::{Visit, VisitResult, Visitor},
     },
-    scene::Scene,
+    scene::{node::Node, Scene},
 };
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+/// Amount of bots that make decisions during one tick, the rest keep following their previous
+/// decisions. Decisions are the most expensive part of AI, so this keeps AI time flat no
+/// matter how many bots are there.
+const AI_DECISIONS_PER_TICK: usize = 4;
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Visit)]
@@ -70,7 +83,12 @@ impl DerefMut for Actor {
 pub struct TargetDescriptor {
     pub handle: Handle<Actor>,
     pub health: f32,
+    /// Chest of the actor, see [`crate::character::Character::aim_position`].
     pub position: Vector3<f32>,
+    /// Direction of flashlight beam, if actor shines it.
+    pub flashlight: Option<Vector3<f32>>,
+    pub body: Handle<Node>,
+    pub team: Team,
 }
 
 #[derive(Default, Visit)]
@@ -78,6 +96,9 @@ pub struct ActorContainer {
     pool: Pool<Actor>,
     #[visit(skip)]
     target_descriptors: Vec<TargetDescriptor>,
+    /// Index of a bot that makes decision first on next tick.
+    #[visit(skip)]
+    ai_cursor: usize,
 }
 
 impl ActorContainer {
@@ -85,6 +106,7 @@ impl ActorContainer {
         Self {
             pool: Default::default(),
             target_descriptors: Default::default(),
+            ai_cursor: 0,
         }
     }
 
@@ -104,6 +126,14 @@ impl ActorContainer {
         self.pool.borrow_mut(actor)
     }
 
+    pub fn try_get(&self, actor: Handle<Actor>) -> Option<&Actor> {
+        self.pool.try_borrow(actor)
+    }
+
+    pub fn try_get_mut(&mut self, actor: Handle<Actor>) -> Option<&mut Actor> {
+        self.pool.try_borrow_mut(actor)
+    }
+
     pub fn free(&mut self, actor_handle: Handle<Actor>) {
         for actor in self.pool.iter_mut() {
             if let Actor::Bot(bot) = actor {
@@ -118,55 +148,94 @@ impl ActorContainer {
         self.pool.alive_count()
     }
 
-    pub fn update(&mut self, context: &mut UpdateContext) {
+    /// Updates every actor, returns time spent on bot decisions.
+    pub fn update(&mut self, context: &mut UpdateContext) -> Duration {
         self.target_descriptors.clear();
         for (handle, actor) in self.pool.pair_iter() {
             self.target_descriptors.push(TargetDescriptor {
                 handle,
                 health: actor.health,
-                position: actor.position(&context.scene.graph),
+                position: actor.aim_position(&context.scene.graph),
+                flashlight: match actor {
+                    Actor::Player(player) => player.flashlight_direction(),
+                    _ => None,
+                },
+                body: actor.get_body(),
+                team: actor.team,
             });
         }
 
+        // Bots take turns to make decisions, round-robin.
+        let bot_count = self
+            .pool
+            .iter()
+            .filter(|actor| matches!(actor, Actor::Bot(_)))
+            .count();
+        let first_thinker = if bot_count > 0 {
+            self.ai_cursor % bot_count
+        } else {
+            0
+        };
+        let mut bot_index = 0;
+        let mut ai_time = Duration::default();
+
+        let mut nearby_items = Vec::new();
         for (handle, actor) in self.pool.pair_iter_mut() {
             let is_dead = actor.is_dead();
 
             match actor {
-                Actor::Bot(bot) => bot.update(handle, context, &self.target_descriptors),
+                Actor::Bot(bot) => {
+                    if (bot_index + bot_count - first_thinker) % bot_count < AI_DECISIONS_PER_TICK {
+                        let clock = Instant::now();
+                        bot.think(handle, context, &self.target_descriptors);
+                        ai_time += clock.elapsed();
+                    }
+                    bot_index += 1;
+                    bot.update(context, &self.target_descriptors);
+                }
                 Actor::Player(player) => player.update(context),
             }
             if !is_dead {
-                for (item_handle, item) in context.items.pair_iter() {
-                    let distance = (context.scene.graph[item.get_pivot()].global_position()
-                        - actor.position(&context.scene.graph))
-                    .norm();
-                    if distance < 1.25 && !item.is_picked_up() {
-                        actor
-                            .sender
-                            .as_ref()
-                            .unwrap()
-                            .send(Message::PickUpItem {
-                                actor: handle,
-                                item: item_handle,
-                            })
-                            .unwrap();
+                let position = actor.position(&context.scene.graph);
+                nearby_items.clear();
+                context
+                    .item_grid
+                    .query_sphere(position, PICKUP_RADIUS, &mut nearby_items);
+                for &item_handle in nearby_items.iter() {
+                    let item = context.items.get(item_handle);
+                    let distance =
+                        (context.scene.graph[item.get_pivot()].global_position() - position).norm();
+                    if distance < PICKUP_RADIUS && item.is_available() {
+                        actor.sender.send(Message::PickUpItem {
+                            actor: handle,
+                            item: item_handle,
+                        });
                     }
                 }
             }
 
             if actor.can_be_removed() {
                 // Abuse the fact that actor has sender and use it to send message.
-                actor
-                    .sender
-                    .clone()
-                    .as_ref()
-                    .unwrap()
-                    .send(Message::RespawnActor { actor: handle })
-                    .unwrap();
+                actor.sender.send(Message::RespawnActor { actor: handle });
             }
         }
 
+        self.ai_cursor = first_thinker + AI_DECISIONS_PER_TICK;
+
         self.handle_event(context);
+
+        ai_time
+    }
+
+    /// Update of a mirrored level, see [`crate::net`]. Bots do not think and do not move on
+    /// their own, host moves them with snapshots, they only play their death.
+    pub fn update_mirror(&mut self, context: &mut UpdateContext) {
+        for actor in self.pool.iter_mut() {
+            match actor {
+                Actor::Bot(bot) => bot.update_mirror(context),
+                Actor::Player(player) => player.update(context),
+            }
+        }
     }
 
     fn handle_event(&mut self, context: &mut UpdateContext) {
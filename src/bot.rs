@@ -1,10 +1,15 @@
 use crate::{
-    actor::{Actor, TargetDescriptor},
-    character::Character,
-    item::ItemContainer,
+    actor::{Actor, ActorContainer, TargetDescriptor},
+    character::{Character, Team, FOOTSTEP_SOUNDS},
+    item::{Item, ItemContainer},
     level::UpdateContext,
-    message::Message,
-    weapon::WeaponContainer,
+    logger::Logger,
+    message::{Message, MessageSender},
+    mutators::Mutators,
+    projectile::Projectile,
+    query_buffer::QueryBufferPool,
+    spatial_grid::SpatialGrid,
+    weapon::{WeaponContainer, WeaponKind},
     GameTime,
 };
 use fyrox::scene::graph::physics::CoefficientCombineRule;
@@ -37,12 +42,11 @@ use fyrox::{
         transform::TransformBuilder,
         Scene,
     },
-    utils::log::{Log, MessageKind},
+    utils::log::MessageKind,
 };
 use std::{
     ops::{Deref, DerefMut},
     path::Path,
-    sync::mpsc::Sender,
 };
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Visit)]
@@ -70,13 +74,75 @@ impl BotKind {
     }
 }
 
+/// Bots look for weapons that are better than theirs within this radius.
+const WEAPON_SEARCH_RADIUS: f32 = 20.0;
+/// Bot gives up a weapon it could not reach within this time, in seconds.
+const WEAPON_DETOUR_TIMEOUT: f64 = 8.0;
+/// Pause between weapon detours, so bot won't run back and forth between items.
+const WEAPON_DETOUR_COOLDOWN: f64 = 6.0;
+/// Each unit of weapon priority is worth this many meters of detour.
+const WEAPON_PRIORITY_DISTANCE: f32 = 10.0;
+/// Bots notice flashlight beams pointed at them from this distance.
+const FLASHLIGHT_NOTICE_DISTANCE: f32 = 25.0;
+/// Half-angle of flashlight beam cone in degrees, within which bots notice it.
+const FLASHLIGHT_NOTICE_ANGLE: f32 = 20.0;
+/// Limit of vertical aim in degrees, bots can't bend further.
+const MAX_AIM_PITCH: f32 = 60.0;
+/// Bots do not shoot into walls closer than splash radius of their projectile plus this
+/// margin, in meters.
+const LINE_OF_FIRE_MARGIN: f32 = 1.0;
+/// Other actors closer than this push bot aside, in meters.
+const AVOIDANCE_RADIUS: f32 = 1.5;
+/// Limit of sideways steering relative to path direction, so it can't turn bot around.
+const MAX_AVOIDANCE: f32 = 0.75;
+/// Bot that advances slower than this between decisions is not making progress, in m/s.
+const BLOCKED_SPEED: f32 = 0.5;
+/// Bots that can't get past each other for this long resolve it, one of them steps aside.
+const BLOCKED_TIMEOUT: f64 = 1.0;
+/// How long yielding bot walks sideways, in seconds.
+const SIDE_STEP_DURATION: f64 = 0.6;
+/// Bot reports its intent no more often than this, in seconds.
+const INTENT_REPORT_INTERVAL: f64 = 2.0;
+
+/// Weapon item that bot goes for, it takes precedence over target and point of interest.
+#[derive(Debug)]
+struct WeaponDetour {
+    item: Handle<Item>,
+    kind: WeaponKind,
+    started: f64,
+}
+
+/// What bot is busy with. Changes are reported to the intent feed on HUD, which can be turned
+/// on in options.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BotIntent {
+    /// Nobody to shoot at, goes from item to item.
+    Roaming,
+    Engaging(Handle<Actor>),
+    FetchingWeapon(WeaponKind),
+}
+
+impl BotIntent {
+    /// Line of intent feed, names of actors are taken from given container.
+    pub fn describe(self, bot: &Bot, actors: &ActorContainer) -> String {
+        let action = match self {
+            BotIntent::Roaming => "roaming".to_owned(),
+            BotIntent::Engaging(target) => match actors.try_get(target) {
+                Some(target) => format!("engaging {}", target.name),
+                None => "engaging".to_owned(),
+            },
+            BotIntent::FetchingWeapon(kind) => format!("going for {}", kind.display_name()),
+        };
+        format!("{}: {}", bot.name, action)
+    }
+}
+
 #[derive(Debug, Default, Visit)]
 pub struct Target {
     position: Vector3<f32>,
     handle: Handle<Actor>,
 }
 
-#[derive(Visit)]
 pub struct Bot {
     target: Option<Target>,
     kind: BotKind,
@@ -87,7 +153,6 @@ pub struct Bot {
     dying_machine: DyingMachine,
     last_health: f32,
     restoration_time: f32,
-    #[visit(skip)]
     navmesh_agent: NavmeshAgent,
     frustum: Frustum,
     last_poi_update_time: f64,
@@ -96,6 +161,70 @@ pub struct Bot {
     spine: Handle<Node>,
     yaw: SmoothAngle,
     pitch: SmoothAngle,
+    weapon_detour: Option<WeaponDetour>,
+    last_detour_end_time: f64,
+    /// Target dummy of practice mode: stands still, never picks targets and never shoots.
+    inert: bool,
+    /// Result of the last line of fire check, see `is_line_of_fire_clear`.
+    line_of_fire_clear: bool,
+    /// Sum of pushes from nearby actors, see `update_avoidance`.
+    repulsion: Vector3<f32>,
+    /// Actor in the way and time since when it blocks the bot.
+    blocked_by: Option<(Handle<Actor>, f64)>,
+    /// Position and time of previous decision, tells whether bot makes progress.
+    last_decision: Option<(Vector3<f32>, f64)>,
+    /// Direction of a side step and time when it ends, set when bot gives way.
+    side_step: Option<(Vector3<f32>, f64)>,
+    /// Sideways steering applied on last update, it is shown in debug draw.
+    steering: Vector3<f32>,
+    /// Last intent sent to the intent feed and time when it was sent.
+    reported_intent: Option<(BotIntent, f64)>,
+    /// View distance after map lighting is applied, targets without flashlight are not seen
+    /// further than that.
+    view_distance: f32,
+    /// Position of eyes and look direction on last frustum update, they're shown in debug draw.
+    eyes: (Vector3<f32>, Vector3<f32>),
+}
+
+impl Visit for Bot {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.target.visit("target", visitor)?;
+        self.kind.visit("kind", visitor)?;
+        self.model.visit("model", visitor)?;
+        self.character.visit("character", visitor)?;
+        self.locomotion_machine
+            .visit("locomotion_machine", visitor)?;
+        self.combat_machine.visit("combat_machine", visitor)?;
+        self.dying_machine.visit("dying_machine", visitor)?;
+        self.last_health.visit("last_health", visitor)?;
+        self.restoration_time.visit("restoration_time", visitor)?;
+        self.frustum.visit("frustum", visitor)?;
+        self.last_poi_update_time
+            .visit("last_poi_update_time", visitor)?;
+        self.point_of_interest.visit("point_of_interest", visitor)?;
+        self.last_move_dir.visit("last_move_dir", visitor)?;
+        self.spine.visit("spine", visitor)?;
+        self.yaw.visit("yaw", visitor)?;
+        self.pitch.visit("pitch", visitor)?;
+
+        // Saves made before practice mode have no such field, every bot in them is hostile.
+        let _ = self.inert.visit("inert", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+/// Limits elevation of a direction to the range bots can aim at, see [`MAX_AIM_PITCH`].
+fn clamp_aim_pitch(direction: Vector3<f32>) -> Vector3<f32> {
+    let horizontal = Vector3::new(direction.x, 0.0, direction.z).norm();
+    let max_rise = horizontal * MAX_AIM_PITCH.to_radians().tan();
+    if horizontal > f32::EPSILON && direction.y.abs() > max_rise {
+        Vector3::new(direction.x, direction.y.signum() * max_rise, direction.z)
+    } else {
+        direction
+    }
 }
 
 impl Deref for Bot {
@@ -140,10 +269,45 @@ impl Default for Bot {
                 target: 0.0,
                 speed: 260.0f32.to_radians(), // rad/s
             },
+            weapon_detour: None,
+            last_detour_end_time: -WEAPON_DETOUR_COOLDOWN,
+            inert: false,
+            line_of_fire_clear: true,
+            repulsion: Default::default(),
+            blocked_by: None,
+            last_decision: None,
+            side_step: None,
+            steering: Default::default(),
+            reported_intent: None,
+            view_distance: BotPerception::DEFAULT.view_distance,
+            eyes: Default::default(),
         }
     }
 }
 
+/// How far and how wide a bot sees and how far it hears.
+pub struct BotPerception {
+    /// Far plane of view frustum, in meters.
+    pub view_distance: f32,
+    /// Vertical field of view, horizontal one is wider by 16:9 aspect.
+    pub fov_degrees: f32,
+    /// Bot turns to an attacker that hits it only from within this distance.
+    pub hearing_radius: f32,
+}
+
+impl BotPerception {
+    pub const DEFAULT: Self = Self {
+        view_distance: 20.0,
+        fov_degrees: 90.0,
+        hearing_radius: f32::MAX,
+    };
+
+    /// Horizontal field of view in radians.
+    fn horizontal_fov(&self) -> f32 {
+        2.0 * ((self.fov_degrees.to_radians() * 0.5).tan() * 16.0 / 9.0).atan()
+    }
+}
+
 pub struct BotDefinition {
     pub scale: f32,
     pub health: f32,
@@ -165,6 +329,21 @@ pub struct BotDefinition {
     pub right_leg_name: &'static str,
     pub spine: &'static str,
     pub v_aim_angle_hack: f32,
+    /// Own senses of the kind, used only with Bot Senses mutator, see [`Self::perception`].
+    pub senses: BotPerception,
+}
+
+impl BotDefinition {
+    /// Perception in effect. Every kind sees and hears the same by default, distinct senses
+    /// are opt-in since they change balance. Difficulty multipliers on top of them are not
+    /// done yet, there is no difficulty setting to drive them.
+    pub fn perception(&self, mutators: &Mutators) -> &BotPerception {
+        if mutators.bot_senses {
+            &self.senses
+        } else {
+            &BotPerception::DEFAULT
+        }
+    }
 }
 
 fn prepare_animation(
@@ -671,6 +850,7 @@ impl Bot {
                     weapon_scale: 2.6,
                     health: 100.0,
                     v_aim_angle_hack: -2.0,
+                    senses: BotPerception::DEFAULT,
                 };
                 &DEFINITION
             }
@@ -696,6 +876,12 @@ impl Bot {
                     weapon_scale: 2.5,
                     health: 100.0,
                     v_aim_angle_hack: 12.0,
+                    // Short-sighted brawler.
+                    senses: BotPerception {
+                        view_distance: 12.0,
+                        fov_degrees: 110.0,
+                        hearing_radius: 25.0,
+                    },
                 };
                 &DEFINITION
             }
@@ -721,6 +907,12 @@ impl Bot {
                     weapon_scale: 2.5,
                     health: 100.0,
                     v_aim_angle_hack: 16.0,
+                    // Spots enemies across the map, but has narrow sight.
+                    senses: BotPerception {
+                        view_distance: 35.0,
+                        fov_degrees: 70.0,
+                        hearing_radius: 40.0,
+                    },
                 };
                 &DEFINITION
             }
@@ -732,7 +924,7 @@ impl Bot {
         resource_manager: ResourceManager,
         scene: &mut Scene,
         position: Vector3<f32>,
-        sender: Sender<Message>,
+        sender: MessageSender,
     ) -> Self {
         let definition = Self::get_definition(kind);
 
@@ -755,7 +947,7 @@ impl Bot {
 
         let spine = scene.graph.find_by_name(model, definition.spine);
         if spine.is_none() {
-            Log::writeln(
+            Logger::writeln(
                 MessageKind::Warning,
                 "Spine bone not found, bot won't aim vertically!".to_owned(),
             );
@@ -820,7 +1012,7 @@ impl Bot {
                 collider,
                 weapon_pivot,
                 health: definition.health,
-                sender: Some(sender),
+                sender,
                 name: format!("{:?}", kind),
                 ..Default::default()
             },
@@ -839,6 +1031,14 @@ impl Bot {
         self.dying_machine.machine.active_state() == self.dying_machine.dead_state
     }
 
+    pub fn set_inert(&mut self, inert: bool) {
+        self.inert = inert;
+    }
+
+    pub fn is_inert(&self) -> bool {
+        self.inert
+    }
+
     pub fn can_shoot(&self) -> bool {
         self.combat_machine.machine.active_state() == self.combat_machine.aim_state
     }
@@ -853,67 +1053,235 @@ impl Bot {
         let position = self.character.position(&scene.graph);
         let mut closest_distance = std::f32::MAX;
 
-        let mut query_buffer = Vec::default();
-        'target_loop: for desc in targets {
-            if desc.handle != self_handle && self.frustum.is_contains_point(desc.position) {
-                let ray = Ray::from_two_points(desc.position, position);
-                scene.graph.physics.cast_ray(
-                    RayCastOptions {
-                        ray_origin: Point3::from(ray.origin),
-                        ray_direction: ray.dir,
-                        groups: InteractionGroups::default(),
-                        max_len: ray.dir.norm(),
-                        sort_results: true,
-                    },
-                    &mut query_buffer,
-                );
-
-                'hit_loop: for hit in query_buffer.iter() {
-                    let collider = scene.graph[hit.collider].as_collider();
-                    let body = collider.parent();
-
-                    if matches!(collider.shape(), ColliderShape::Trimesh(_)) {
-                        // Target is behind something.
-                        continue 'target_loop;
-                    } else {
-                        // Prevent setting self as target.
-                        if self.character.body == body {
-                            continue 'hit_loop;
+        QueryBufferPool::with(|query_buffer| {
+            'target_loop: for desc in targets {
+                // Flashlight gives target away even where darkness hides it.
+                let visible = desc.flashlight.is_some()
+                    || desc.position.metric_distance(&position) <= self.view_distance;
+                if desc.handle != self_handle
+                    && visible
+                    && self.frustum.is_contains_point(desc.position)
+                {
+                    let ray = Ray::from_two_points(desc.position, position);
+                    scene.graph.physics.cast_ray(
+                        RayCastOptions {
+                            ray_origin: Point3::from(ray.origin),
+                            ray_direction: ray.dir,
+                            groups: InteractionGroups::default(),
+                            max_len: ray.dir.norm(),
+                            sort_results: true,
+                        },
+                        query_buffer,
+                    );
+
+                    'hit_loop: for hit in query_buffer.iter() {
+                        let collider = scene.graph[hit.collider].as_collider();
+                        let body = collider.parent();
+
+                        if matches!(collider.shape(), ColliderShape::Trimesh(_)) {
+                            // Target is behind something.
+                            continue 'target_loop;
+                        } else {
+                            // Prevent setting self as target.
+                            if self.character.body == body {
+                                continue 'hit_loop;
+                            }
                         }
                     }
+
+                    let sqr_d = position.sqr_distance(&desc.position);
+                    if sqr_d < closest_distance {
+                        self.target = Some(Target {
+                            position: desc.position,
+                            handle: desc.handle,
+                        });
+                        closest_distance = sqr_d;
+                    }
                 }
+            }
+        });
+    }
 
-                let sqr_d = position.sqr_distance(&desc.position);
-                if sqr_d < closest_distance {
-                    self.target = Some(Target {
-                        position: desc.position,
-                        handle: desc.handle,
-                    });
-                    closest_distance = sqr_d;
+    /// Casts a ray along the shot at current target. Line of fire is blocked if the shot would
+    /// hit a wall so close that splash reaches the bot, or would hit a teammate first. Bot holds
+    /// fire then and keeps moving towards the target, which gives it another angle.
+    fn is_line_of_fire_clear(
+        &self,
+        scene: &Scene,
+        weapons: &WeaponContainer,
+        targets: &[TargetDescriptor],
+    ) -> bool {
+        let (target, weapon) = match (
+            self.target.as_ref(),
+            self.character
+                .weapons
+                .get(self.character.current_weapon as usize),
+        ) {
+            (Some(target), Some(weapon)) if weapons.contains(*weapon) => {
+                (target, &weapons[*weapon])
+            }
+            _ => return true,
+        };
+
+        let projectile = Projectile::get_definition(weapon.definition().projectile);
+        let safe_distance = projectile.splash_radius + LINE_OF_FIRE_MARGIN;
+        let ray = Ray::from_two_points(weapon.get_shot_position(&scene.graph), target.position);
+
+        QueryBufferPool::with(|query_buffer| {
+            scene.graph.physics.cast_ray(
+                RayCastOptions {
+                    ray_origin: Point3::from(ray.origin),
+                    ray_direction: ray.dir,
+                    groups: InteractionGroups::default(),
+                    max_len: ray.dir.norm(),
+                    sort_results: true,
+                },
+                query_buffer,
+            );
+
+            for hit in query_buffer.iter() {
+                let collider = scene.graph[hit.collider].as_collider();
+                if matches!(collider.shape(), ColliderShape::Trimesh(_)) {
+                    return hit.toi > safe_distance;
+                }
+                let body = collider.parent();
+                if body == self.character.body {
+                    continue;
+                }
+                if let Some(desc) = targets.iter().find(|desc| desc.body == body) {
+                    return self.character.team == Team::None || desc.team != self.character.team;
                 }
             }
-        }
+            true
+        })
     }
 
-    fn select_point_of_interest(&mut self, items: &ItemContainer, scene: &Scene, time: &GameTime) {
+    fn select_point_of_interest(
+        &mut self,
+        items: &ItemContainer,
+        item_grid: &SpatialGrid<Handle<Item>>,
+        scene: &Scene,
+        time: &GameTime,
+    ) {
         if time.elapsed - self.last_poi_update_time >= 1.25 {
             // Select closest non-despawned item as point of interest.
             let self_position = self.position(&scene.graph);
-            let mut closest_distance = std::f32::MAX;
-            for item in items.iter() {
-                if !item.is_picked_up() {
-                    let item_position = item.position(&scene.graph);
-                    let sqr_d = item_position.sqr_distance(&self_position);
-                    if sqr_d < closest_distance {
-                        closest_distance = sqr_d;
-                        self.point_of_interest = item_position;
-                    }
-                }
+            if let Some(item) =
+                item_grid.closest(self_position, |item| !items.get(item).is_picked_up())
+            {
+                self.point_of_interest = items.get(item).position(&scene.graph);
             }
             self.last_poi_update_time = time.elapsed;
         }
     }
 
+    /// Bot that has nothing to shoot at goes to check where a flashlight beam pointed at it
+    /// comes from.
+    fn notice_flashlights(
+        &mut self,
+        self_handle: Handle<Actor>,
+        scene: &Scene,
+        targets: &[TargetDescriptor],
+        time: &GameTime,
+    ) {
+        if self.target.is_some() {
+            return;
+        }
+
+        let self_position = self.position(&scene.graph);
+        let cos_cone = FLASHLIGHT_NOTICE_ANGLE.to_radians().cos();
+        for desc in targets {
+            if desc.handle == self_handle {
+                continue;
+            }
+            if let Some(beam) = desc.flashlight {
+                let to_self = self_position - desc.position;
+                let distance = to_self.norm();
+                if distance > 0.0
+                    && distance <= FLASHLIGHT_NOTICE_DISTANCE
+                    && beam.dot(&to_self) / distance >= cos_cone
+                {
+                    self.point_of_interest = desc.position;
+                    self.last_poi_update_time = time.elapsed;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns true if bot has weapon of given kind with some ammo left.
+    fn has_loaded_weapon(&self, kind: WeaponKind, weapons: &WeaponContainer) -> bool {
+        self.character.weapons().iter().any(|&handle| {
+            let weapon = &weapons[handle];
+            weapon.get_kind() == kind && weapon.ammo() > 0
+        })
+    }
+
+    /// Checks whether bot should go for a weapon lying nearby. Weapons are scored by how much
+    /// better they are than the best weapon bot has, minus distance to them. Item is picked
+    /// up by usual proximity check once bot gets there.
+    fn select_weapon_detour(
+        &mut self,
+        items: &ItemContainer,
+        item_grid: &SpatialGrid<Handle<Item>>,
+        weapons: &WeaponContainer,
+        scene: &Scene,
+        time: &GameTime,
+    ) {
+        if let Some(detour) = self.weapon_detour.as_ref() {
+            let reached = !items.contains(detour.item)
+                || items.get(detour.item).is_picked_up()
+                || self.has_loaded_weapon(detour.kind, weapons);
+            if reached || time.elapsed - detour.started >= WEAPON_DETOUR_TIMEOUT {
+                self.weapon_detour = None;
+                self.last_detour_end_time = time.elapsed;
+            }
+            return;
+        }
+
+        if time.elapsed - self.last_detour_end_time < WEAPON_DETOUR_COOLDOWN {
+            return;
+        }
+
+        let best_priority = self
+            .character
+            .weapons()
+            .iter()
+            .map(|&handle| &weapons[handle])
+            .filter(|weapon| weapon.ammo() > 0)
+            .map(|weapon| weapon.get_kind().priority() as f32)
+            .fold(-1.0, f32::max);
+
+        let position = self.position(&scene.graph);
+        let mut nearby_items = Vec::new();
+        item_grid.query_sphere(position, WEAPON_SEARCH_RADIUS, &mut nearby_items);
+
+        let mut best = None;
+        let mut best_score = 0.0;
+        for handle in nearby_items {
+            let item = items.get(handle);
+            let kind = match item.get_kind().weapon_kind() {
+                Some(kind) if !item.is_picked_up() && !self.has_loaded_weapon(kind, weapons) => {
+                    kind
+                }
+                _ => continue,
+            };
+            let distance = item.position(&scene.graph).metric_distance(&position);
+            let score =
+                (kind.priority() as f32 - best_priority) * WEAPON_PRIORITY_DISTANCE - distance;
+            if score > best_score {
+                best_score = score;
+                best = Some(WeaponDetour {
+                    item: handle,
+                    kind,
+                    started: time.elapsed,
+                });
+            }
+        }
+
+        self.weapon_detour = best;
+    }
+
     fn select_weapon(&mut self, weapons: &WeaponContainer) {
         if self.character.current_weapon().is_some()
             && weapons[self.character.current_weapon()].ammo() == 0
@@ -927,7 +1295,7 @@ impl Bot {
         }
     }
 
-    pub fn debug_draw(&self, context: &mut SceneDrawingContext) {
+    pub fn debug_draw(&self, context: &mut SceneDrawingContext, mutators: &Mutators) {
         for pts in self.navmesh_agent.path().windows(2) {
             let a = pts[0];
             let b = pts[1];
@@ -940,16 +1308,67 @@ impl Bot {
 
         context.draw_sphere(self.navmesh_agent.position(), 10, 10, 0.25, Color::RED);
 
+        if self.steering.norm_squared() > f32::EPSILON {
+            let begin = self.navmesh_agent.position() + Vector3::new(0.0, 1.0, 0.0);
+            context.add_line(scene::debug::Line {
+                begin,
+                end: begin + self.steering.scale(2.0),
+                color: Color::opaque(255, 0, 255),
+            });
+        }
+
         context.draw_frustum(&self.frustum, Color::from_rgba(0, 200, 0, 255));
+
+        // View distance ring and horizontal field of view wedge.
+        let (eyes, look) = self.eyes;
+        let color = Color::opaque(255, 200, 0);
+        let segments = 32;
+        let ring_point = |i: usize| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            eyes + Vector3::new(angle.cos(), 0.0, angle.sin()).scale(self.view_distance)
+        };
+        for i in 0..segments {
+            context.add_line(scene::debug::Line {
+                begin: ring_point(i),
+                end: ring_point(i + 1),
+                color,
+            });
+        }
+        let half_fov = self.definition().perception(mutators).horizontal_fov() * 0.5;
+        for &angle in &[-half_fov, half_fov] {
+            let direction = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle)
+                .transform_vector(&Vector3::new(look.x, 0.0, look.z));
+            if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+                context.add_line(scene::debug::Line {
+                    begin: eyes,
+                    end: eyes + direction.scale(self.view_distance),
+                    color,
+                });
+            }
+        }
     }
 
-    fn update_frustum(&mut self, position: Vector3<f32>, graph: &Graph) {
+    fn update_frustum(
+        &mut self,
+        position: Vector3<f32>,
+        graph: &Graph,
+        view_distance_scale: f32,
+        mutators: &Mutators,
+    ) {
+        let perception = Self::get_definition(self.kind).perception(mutators);
+        self.view_distance = perception.view_distance * view_distance_scale;
         let head_pos = position + Vector3::new(0.0, 0.8, 0.0);
         let up = graph[self.model].up_vector();
         let look_at = head_pos + graph[self.model].look_vector();
+        self.eyes = (head_pos, graph[self.model].look_vector());
         let view_matrix = Matrix4::look_at_rh(&Point3::from(head_pos), &Point3::from(look_at), &up);
-        let projection_matrix =
-            Matrix4::new_perspective(16.0 / 9.0, 90.0f32.to_radians(), 0.1, 20.0);
+        // Far plane is not scaled by lighting, targets with flashlight are seen at full distance.
+        let projection_matrix = Matrix4::new_perspective(
+            16.0 / 9.0,
+            perception.fov_degrees.to_radians(),
+            0.1,
+            perception.view_distance,
+        );
         let view_projection_matrix = projection_matrix * view_matrix;
         self.frustum = Frustum::from(view_projection_matrix).unwrap();
     }
@@ -960,11 +1379,12 @@ impl Bot {
 
     fn aim_vertically(&mut self, look_dir: Vector3<f32>, graph: &mut Graph, time: GameTime) {
         let angle = self.pitch.angle();
+        let max_pitch = MAX_AIM_PITCH.to_radians();
+        let pitch = (look_dir.dot(&Vector3::y()).acos() - std::f32::consts::PI / 2.0)
+            .max(-max_pitch)
+            .min(max_pitch);
         self.pitch
-            .set_target(
-                look_dir.dot(&Vector3::y()).acos() - std::f32::consts::PI / 2.0
-                    + self.definition().v_aim_angle_hack.to_radians(),
-            )
+            .set_target(pitch + self.definition().v_aim_angle_hack.to_radians())
             .update(time.delta);
 
         if self.spine.is_some() {
@@ -985,12 +1405,233 @@ impl Bot {
             .set_rotation(UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle));
     }
 
-    pub fn update(
+    /// Decision layer of AI: picks target, weapon and where to go. It is expensive, so only a
+    /// few bots make decisions each tick (see `ActorContainer::update`), the rest follow their
+    /// previous decisions.
+    pub fn think(
         &mut self,
         self_handle: Handle<Actor>,
         context: &mut UpdateContext,
         targets: &[TargetDescriptor],
     ) {
+        if self.character.is_dead() || self.inert {
+            return;
+        }
+
+        self.select_target(self_handle, context.scene, targets);
+        self.select_weapon(context.weapons);
+        self.line_of_fire_clear =
+            self.is_line_of_fire_clear(context.scene, context.weapons, targets);
+        self.select_point_of_interest(
+            context.items,
+            context.item_grid,
+            context.scene,
+            &context.time,
+        );
+        self.notice_flashlights(self_handle, context.scene, targets, &context.time);
+        self.select_weapon_detour(
+            context.items,
+            context.item_grid,
+            context.weapons,
+            context.scene,
+            &context.time,
+        );
+
+        // Path is recalculated only when target changes.
+        let destination = match self.weapon_detour.as_ref() {
+            Some(detour) => context
+                .items
+                .get(detour.item)
+                .position(&context.scene.graph),
+            None => self
+                .target
+                .as_ref()
+                .map_or(self.point_of_interest, |target| target.position),
+        };
+        self.navmesh_agent.set_target(destination);
+
+        self.update_avoidance(
+            self_handle,
+            &context.scene.graph,
+            targets,
+            destination,
+            &context.time,
+        );
+
+        self.report_intent(self_handle, &context.time);
+    }
+
+    fn intent(&self) -> BotIntent {
+        if let Some(detour) = self.weapon_detour.as_ref() {
+            BotIntent::FetchingWeapon(detour.kind)
+        } else if let Some(target) = self.target.as_ref() {
+            BotIntent::Engaging(target.handle)
+        } else {
+            BotIntent::Roaming
+        }
+    }
+
+    /// Sends current intent if it differs from the last reported one. Changes that come too
+    /// soon are sent by one of the next decisions, if they still hold by then.
+    fn report_intent(&mut self, self_handle: Handle<Actor>, time: &GameTime) {
+        let intent = self.intent();
+        if let Some((reported, reported_at)) = self.reported_intent {
+            if reported == intent || time.elapsed - reported_at < INTENT_REPORT_INTERVAL {
+                return;
+            }
+        }
+        self.reported_intent = Some((intent, time.elapsed));
+        self.character.sender.send(Message::BotIntentChanged {
+            bot: self_handle,
+            intent,
+        });
+    }
+
+    /// Collects pushes from actors around the bot and checks whether one of them blocks the
+    /// way. Actors ahead also push to the right, so bots walking into each other pass by
+    /// instead of pushing head-on. When two bots block each other for too long, the one with
+    /// lower handle index steps aside.
+    fn update_avoidance(
+        &mut self,
+        self_handle: Handle<Actor>,
+        graph: &Graph,
+        targets: &[TargetDescriptor],
+        destination: Vector3<f32>,
+        time: &GameTime,
+    ) {
+        let position = graph[self.body].global_position();
+        let path_dir = Vector3::new(self.last_move_dir.x, 0.0, self.last_move_dir.z)
+            .try_normalize(f32::EPSILON);
+
+        self.repulsion = Vector3::default();
+        let mut blocker = None;
+        for desc in targets {
+            if desc.handle == self_handle || desc.health <= 0.0 {
+                continue;
+            }
+            let offset = Vector3::new(
+                position.x - desc.position.x,
+                0.0,
+                position.z - desc.position.z,
+            );
+            let distance = offset.norm();
+            if distance >= AVOIDANCE_RADIUS {
+                continue;
+            }
+            let weight = 1.0 - distance / AVOIDANCE_RADIUS;
+            self.repulsion += offset
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_default()
+                .scale(weight);
+            if let Some(path_dir) = path_dir {
+                if offset.dot(&path_dir) < 0.0 {
+                    self.repulsion += path_dir.cross(&Vector3::y()).scale(weight);
+                    blocker = Some(desc.handle);
+                }
+            }
+        }
+
+        // Flight after a jump pad is not measured, it may go almost straight up and lasts
+        // long in low gravity - such bot is not stuck.
+        let airborne = !self.character.has_ground_contact(graph);
+        let stuck = match self.last_decision {
+            Some((last_position, last_time)) if time.elapsed > last_time && !airborne => {
+                let speed = (position - last_position).norm() / (time.elapsed - last_time) as f32;
+                speed < BLOCKED_SPEED
+            }
+            _ => false,
+        };
+        self.last_decision = if airborne {
+            None
+        } else {
+            Some((position, time.elapsed))
+        };
+
+        // Bot that reached its destination stands still on purpose.
+        let arrived = (destination - position).norm() <= AVOIDANCE_RADIUS;
+        self.blocked_by = match (blocker, self.blocked_by) {
+            (Some(blocker), Some((previous, since)))
+                if blocker == previous && stuck && !arrived =>
+            {
+                Some((blocker, since))
+            }
+            (Some(blocker), _) if stuck && !arrived => Some((blocker, time.elapsed)),
+            _ => None,
+        };
+
+        if let (Some((blocker, since)), Some(path_dir)) = (self.blocked_by, path_dir) {
+            if time.elapsed - since >= BLOCKED_TIMEOUT && self_handle.index() < blocker.index() {
+                self.side_step = Some((
+                    path_dir.cross(&Vector3::y()),
+                    time.elapsed + SIDE_STEP_DURATION,
+                ));
+                self.blocked_by = None;
+            }
+        }
+    }
+
+    /// Bends path direction away from nearby actors. Only the part of repulsion across the
+    /// path is used and it is capped, so bot never turns back.
+    fn steer(&mut self, move_dir: Vector3<f32>, time: &GameTime) -> Vector3<f32> {
+        if let Some((side, until)) = self.side_step {
+            if time.elapsed < until {
+                self.steering = side;
+                return side;
+            }
+            self.side_step = None;
+        }
+
+        self.steering = match Vector3::new(move_dir.x, 0.0, move_dir.z).try_normalize(f32::EPSILON)
+        {
+            Some(path_dir) => {
+                let sideways = self.repulsion - path_dir.scale(self.repulsion.dot(&path_dir));
+                let length = sideways.norm();
+                if length > MAX_AVOIDANCE {
+                    sideways.scale(MAX_AVOIDANCE / length)
+                } else {
+                    sideways
+                }
+            }
+            None => Vector3::default(),
+        };
+
+        (move_dir + self.steering)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(move_dir)
+    }
+
+    /// Keeps position of chosen target up to date between decisions.
+    fn follow_target(&mut self, targets: &[TargetDescriptor]) {
+        if let Some(target) = self.target.as_mut() {
+            if let Some(desc) = targets.iter().find(|desc| desc.handle == target.handle) {
+                target.position = desc.position;
+                return;
+            }
+            // Target is gone.
+            self.target = None;
+        }
+    }
+
+    /// Update of a bot on a mirrored level: no decisions, no movement, no shots, only
+    /// animations. Health and position come from host.
+    pub fn update_mirror(&mut self, context: &mut UpdateContext) {
+        if self.character.is_dead() {
+            self.dying_machine.apply(context.scene, context.time, true);
+        } else {
+            let has_ground_contact = self.character.has_ground_contact(&context.scene.graph);
+            self.locomotion_machine.apply(
+                context.scene,
+                context.time,
+                false,
+                false,
+                has_ground_contact,
+            );
+            self.combat_machine
+                .apply(context.scene, context.time, false, false, true);
+        }
+    }
+
+    pub fn update(&mut self, context: &mut UpdateContext, targets: &[TargetDescriptor]) {
         if self.character.is_dead() {
             self.dying_machine
                 .apply(context.scene, context.time, self.character.is_dead());
@@ -1002,16 +1643,16 @@ impl Bot {
             lin_vel.z = 0.0;
             body.set_lin_vel(lin_vel);
         } else {
-            self.select_target(self_handle, context.scene, targets);
-            self.select_weapon(context.weapons);
-            self.select_point_of_interest(context.items, context.scene, &context.time);
+            self.follow_target(targets);
 
             let has_ground_contact = self.character.has_ground_contact(&context.scene.graph);
+            // Bot looks from its chest to chest of the target, so it aims up and down too.
+            let eye_position = self.character.aim_position(&context.scene.graph);
             let body = context.scene.graph[self.character.body].as_rigid_body_mut();
             let (in_close_combat, look_dir) = match self.target.as_ref() {
                 None => (false, self.point_of_interest - body.global_position()),
                 Some(target) => {
-                    let d = target.position - body.global_position();
+                    let d = target.position - eye_position;
                     let close_combat_threshold = 2.0;
                     (d.norm() <= close_combat_threshold, d)
                 }
@@ -1022,11 +1663,6 @@ impl Bot {
             if let Some(navmesh) = context.scene.navmeshes.at_mut(0) {
                 self.navmesh_agent
                     .set_position(position - Vector3::new(0.0, 0.45, 0.0));
-                if let Some(target) = self.target.as_ref() {
-                    self.navmesh_agent.set_target(target.position);
-                } else {
-                    self.navmesh_agent.set_target(self.point_of_interest);
-                }
                 self.navmesh_agent.set_speed(self.definition().walk_speed);
                 let _ = self.navmesh_agent.update(context.time.delta, navmesh);
             }
@@ -1049,15 +1685,16 @@ impl Bot {
             let can_aim = self.restoration_time <= 0.0;
             self.last_health = self.character.health;
 
-            if !in_close_combat {
+            if !in_close_combat && !self.inert {
                 if has_ground_contact {
                     if let Some(move_dir) =
                         (self.navmesh_agent.position() - position).try_normalize(f32::EPSILON)
                     {
+                        self.last_move_dir = move_dir;
+                        let move_dir = self.steer(move_dir, &context.time);
                         let mut vel = move_dir.scale(1.0 / context.time.delta);
                         vel.y = body.lin_vel().y;
                         body.set_lin_vel(vel);
-                        self.last_move_dir = move_dir;
                     }
                 } else {
                     // A bit of air control. This helps jump of ledges when there is jump pad below bot.
@@ -1069,9 +1706,14 @@ impl Bot {
                 }
             }
 
-            self.update_frustum(position, &context.scene.graph);
+            self.update_frustum(
+                position,
+                &context.scene.graph,
+                context.view_distance_scale,
+                context.options.mutators(),
+            );
 
-            if let Some(look_dir) = look_dir.try_normalize(f32::EPSILON) {
+            if let Some(look_dir) = look_dir.try_normalize(f32::EPSILON).filter(|_| !self.inert) {
                 self.aim_vertically(look_dir, &mut context.scene.graph, context.time);
                 self.aim_horizontally(look_dir, &mut context.scene.graph, context.time);
             }
@@ -1091,21 +1733,29 @@ impl Bot {
                 can_aim,
             );
 
-            let sender = self.character.sender.as_ref().unwrap();
-
-            if !in_close_combat && can_aim && self.can_shoot() && self.target.is_some() {
-                if let Some(weapon) = self
-                    .character
-                    .weapons
-                    .get(self.character.current_weapon as usize)
-                {
-                    sender
-                        .send(Message::ShootWeapon {
-                            weapon: *weapon,
-                            initial_velocity: Vector3::default(),
-                            direction: Some(look_dir),
-                        })
-                        .unwrap();
+            let sender = &self.character.sender;
+
+            if !in_close_combat && can_aim && self.line_of_fire_clear && self.can_shoot() {
+                if let (Some(weapon), Some(target)) = (
+                    self.character
+                        .weapons
+                        .get(self.character.current_weapon as usize),
+                    self.target.as_ref(),
+                ) {
+                    // Shots go from the muzzle, not from the chest, otherwise they would pass
+                    // by the target with an offset.
+                    let direction = if context.weapons.contains(*weapon) {
+                        target.position
+                            - context.weapons[*weapon].get_shot_position(&context.scene.graph)
+                    } else {
+                        look_dir
+                    };
+                    let direction = clamp_aim_pitch(direction);
+                    sender.send(Message::ShootWeapon {
+                        weapon: *weapon,
+                        initial_velocity: Vector3::default(),
+                        direction: Some(direction),
+                    });
                 }
             }
 
@@ -1118,13 +1768,13 @@ impl Bot {
                     .pop_event()
                 {
                     if event.signal_id == CombatMachine::HIT_SIGNAL && in_close_combat {
-                        sender
-                            .send(Message::DamageActor {
-                                actor: target.handle,
-                                who: Default::default(),
-                                amount: 20.0,
-                            })
-                            .unwrap();
+                        sender.send(Message::DamageActor {
+                            actor: target.handle,
+                            who: Default::default(),
+                            who_name: None,
+                            amount: 20.0,
+                            weapon: None,
+                        });
                     }
                 }
             }
@@ -1138,22 +1788,15 @@ impl Bot {
                     .pop_event()
                 {
                     if event.signal_id == LocomotionMachine::STEP_SIGNAL && has_ground_contact {
-                        let footsteps = [
-                            "data/sounds/footsteps/FootStep_shoe_stone_step1.wav",
-                            "data/sounds/footsteps/FootStep_shoe_stone_step2.wav",
-                            "data/sounds/footsteps/FootStep_shoe_stone_step3.wav",
-                            "data/sounds/footsteps/FootStep_shoe_stone_step4.wav",
-                        ];
-                        sender
-                            .send(Message::PlaySound {
-                                path: footsteps[rand::thread_rng().gen_range(0..footsteps.len())]
-                                    .into(),
-                                position,
-                                gain: 1.0,
-                                rolloff_factor: 2.0,
-                                radius: 3.0,
-                            })
-                            .unwrap();
+                        sender.send(Message::PlaySound {
+                            path: FOOTSTEP_SOUNDS
+                                [rand::thread_rng().gen_range(0..FOOTSTEP_SOUNDS.len())]
+                            .into(),
+                            position,
+                            gain: 1.0,
+                            rolloff_factor: 2.0,
+                            radius: 3.0,
+                        });
                     }
                 }
             }
@@ -0,0 +1,127 @@
+use crate::{
+    effects::EffectKind,
+    message::{Message, MessageSender},
+};
+use fyrox::{
+    core::{
+        algebra::Vector3,
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        graph::Graph,
+        light::{directional::DirectionalLight, point::PointLight, spot::SpotLight},
+        node::Node,
+    },
+};
+use std::path::PathBuf;
+
+/// Projectile impacts closer than this distance to a fixture damage it.
+pub const HIT_RADIUS: f32 = 0.75;
+const HEALTH: f32 = 10.0;
+pub const BREAK_SOUND: &str = "data/sounds/bullet_impact_metal.ogg";
+
+/// Light fixture that can be shot out. Fixture is a node named "Breakable_Light*", every light
+/// in its hierarchy goes dark when fixture is broken. If fixture has children named "Intact"
+/// and "Broken", they're swapped.
+#[derive(Visit)]
+pub struct BreakableLight {
+    node: Handle<Node>,
+    health: f32,
+}
+
+impl Default for BreakableLight {
+    fn default() -> Self {
+        Self {
+            node: Default::default(),
+            health: HEALTH,
+        }
+    }
+}
+
+impl BreakableLight {
+    pub fn new(node: Handle<Node>) -> Self {
+        Self {
+            node,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    pub fn position(&self, graph: &Graph) -> Vector3<f32> {
+        graph[self.node].global_position()
+    }
+
+    fn shatter(&self, graph: &mut Graph) {
+        let mut stack = vec![self.node];
+        while let Some(handle) = stack.pop() {
+            let node = &mut graph[handle];
+            if is_light(node) {
+                node.set_visibility(false);
+            }
+            stack.extend_from_slice(node.children());
+        }
+
+        let intact = graph.find_by_name(self.node, "Intact");
+        if intact.is_some() {
+            graph[intact].set_visibility(false);
+        }
+        let broken = graph.find_by_name(self.node, "Broken");
+        if broken.is_some() {
+            graph[broken].set_visibility(true);
+        }
+    }
+}
+
+fn is_light(node: &Node) -> bool {
+    node.cast::<PointLight>().is_some()
+        || node.cast::<SpotLight>().is_some()
+        || node.cast::<DirectionalLight>().is_some()
+}
+
+#[derive(Default, Visit)]
+pub struct BreakableLightContainer {
+    pool: Pool<BreakableLight>,
+}
+
+impl BreakableLightContainer {
+    pub fn add(&mut self, light: BreakableLight) -> Handle<BreakableLight> {
+        self.pool.spawn(light)
+    }
+
+    /// Damages every intact fixture near impact point.
+    pub fn handle_impact(
+        &mut self,
+        graph: &mut Graph,
+        position: Vector3<f32>,
+        amount: f32,
+        sender: &MessageSender,
+    ) {
+        for light in self.pool.iter_mut() {
+            if light.is_broken() || light.position(graph).metric_distance(&position) > HIT_RADIUS {
+                continue;
+            }
+
+            light.health -= amount.abs();
+            if light.is_broken() {
+                light.shatter(graph);
+
+                let position = light.position(graph);
+                sender.send(Message::CreateEffect {
+                    kind: EffectKind::BulletImpact,
+                    position,
+                });
+                sender.send(Message::PlaySound {
+                    path: PathBuf::from(BREAK_SOUND),
+                    position,
+                    gain: 1.0,
+                    rolloff_factor: 3.0,
+                    radius: 3.0,
+                });
+            }
+        }
+    }
+}
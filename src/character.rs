@@ -1,27 +1,68 @@
-use crate::{message::Message, weapon::Weapon};
+use crate::{
+    message::{Message, MessageSender},
+    weapon::Weapon,
+};
 use fyrox::{
     core::{
         algebra::Vector3,
         pool::Handle,
         visitor::{Visit, VisitResult, Visitor},
     },
-    scene::{graph::Graph, node::Node, Scene},
+    scene::{collider::ColliderShape, graph::Graph, node::Node, Scene},
 };
-use std::sync::mpsc::Sender;
 
-#[derive(Visit)]
+/// Height of chest as a fraction of distance from center of collider to its top.
+const CHEST_HEIGHT: f32 = 0.5;
+/// Health of a character when it spawns, health bars are full at this value.
+pub const BASE_HEALTH: f32 = 100.0;
+/// Ordinary healing can't raise health above this value.
+pub const HEAL_LIMIT: f32 = 150.0;
+
+/// Step sounds shared by every kind of character, one of them is picked randomly per step.
+pub const FOOTSTEP_SOUNDS: [&str; 4] = [
+    "data/sounds/footsteps/FootStep_shoe_stone_step1.wav",
+    "data/sounds/footsteps/FootStep_shoe_stone_step2.wav",
+    "data/sounds/footsteps/FootStep_shoe_stone_step3.wav",
+    "data/sounds/footsteps/FootStep_shoe_stone_step4.wav",
+];
+
 pub struct Character {
     pub name: String,
     pub body: Handle<Node>,
     pub collider: Handle<Node>,
     pub health: f32,
     pub armor: f32,
+    pub armor_tier: ArmorTier,
     pub weapons: Vec<Handle<Weapon>>,
     pub current_weapon: u32,
     pub weapon_pivot: Handle<Node>,
-    #[visit(skip)]
-    pub sender: Option<Sender<Message>>,
+    pub sender: MessageSender,
     pub team: Team,
+    /// Invulnerability flag, used for debugging purposes only so it is not saved.
+    pub god_mode: bool,
+    /// Game time of the last damage, health bar of a bot is shown for a while after it.
+    pub last_damage_time: Option<f64>,
+}
+
+impl Visit for Character {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("name", visitor)?;
+        self.body.visit("body", visitor)?;
+        self.collider.visit("collider", visitor)?;
+        self.health.visit("health", visitor)?;
+        self.armor.visit("armor", visitor)?;
+        self.weapons.visit("weapons", visitor)?;
+        self.current_weapon.visit("current_weapon", visitor)?;
+        self.weapon_pivot.visit("weapon_pivot", visitor)?;
+        self.team.visit("team", visitor)?;
+
+        // Saves made before armor tiers have no such field, armor in them is green.
+        let _ = self.armor_tier.visit("armor_tier", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Visit)]
@@ -31,6 +72,69 @@ pub enum Team {
     Blue,
 }
 
+impl Team {
+    pub fn name(self) -> &'static str {
+        match self {
+            Team::None => "No",
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+
+    /// Returns opposing team, `Team::None` has no opponent.
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::None => Team::None,
+            Team::Red => Team::Blue,
+            Team::Blue => Team::Red,
+        }
+    }
+}
+
+/// Kind of armor that character wears. Better armor absorbs larger share of damage and can
+/// be charged higher.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Visit)]
+pub enum ArmorTier {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl Default for ArmorTier {
+    fn default() -> Self {
+        ArmorTier::Green
+    }
+}
+
+impl ArmorTier {
+    /// Share of damage that is taken by armor instead of health.
+    pub fn absorption(self) -> f32 {
+        match self {
+            ArmorTier::Green => 0.5,
+            ArmorTier::Yellow => 0.66,
+            ArmorTier::Red => 0.8,
+        }
+    }
+
+    /// Amount of armor that is given by a pickup of this tier.
+    pub fn amount(self) -> f32 {
+        match self {
+            ArmorTier::Green => 50.0,
+            ArmorTier::Yellow => 100.0,
+            ArmorTier::Red => 150.0,
+        }
+    }
+
+    /// Pickup of this tier can't charge armor above this value.
+    pub fn cap(self) -> f32 {
+        match self {
+            ArmorTier::Green => 100.0,
+            ArmorTier::Yellow => 150.0,
+            ArmorTier::Red => 200.0,
+        }
+    }
+}
+
 impl Default for Team {
     fn default() -> Self {
         Team::None
@@ -43,13 +147,16 @@ impl Default for Character {
             name: Default::default(),
             body: Default::default(),
             collider: Default::default(),
-            health: 100.0,
+            health: BASE_HEALTH,
             armor: 100.0,
+            armor_tier: ArmorTier::Green,
             weapons: Vec::new(),
             current_weapon: 0,
             weapon_pivot: Handle::NONE,
-            sender: None,
+            sender: Default::default(),
             team: Team::None,
+            god_mode: false,
+            last_damage_time: None,
         }
     }
 }
@@ -97,26 +204,67 @@ impl Character {
         graph[self.body].global_position()
     }
 
-    pub fn damage(&mut self, amount: f32) {
-        let amount = amount.abs();
-        if self.armor > 0.0 {
-            self.armor -= amount;
-            if self.armor < 0.0 {
-                self.health += self.armor;
-            }
-        } else {
-            self.health -= amount;
+    /// Point others should aim at: chest of the character. It follows current height of the
+    /// collider, so crouching characters are aimed lower.
+    pub fn aim_position(&self, graph: &Graph) -> Vector3<f32> {
+        let collider = &graph[self.collider];
+        collider.global_position() + Vector3::new(0.0, self.collider_top(graph) * CHEST_HEIGHT, 0.0)
+    }
+
+    /// Top of the collider, follows crouching the same way as [`Self::aim_position`].
+    pub fn head_position(&self, graph: &Graph) -> Vector3<f32> {
+        graph[self.collider].global_position() + Vector3::new(0.0, self.collider_top(graph), 0.0)
+    }
+
+    fn collider_top(&self, graph: &Graph) -> f32 {
+        match graph[self.collider].as_collider().shape() {
+            ColliderShape::Capsule(capsule) => capsule.begin.y.max(capsule.end.y) + capsule.radius,
+            _ => 0.0,
+        }
+    }
+
+    /// Deals damage to character, returns part of damage that was absorbed by armor.
+    pub fn damage(&mut self, amount: f32) -> f32 {
+        if self.god_mode {
+            return 0.0;
         }
+
+        let amount = amount.abs();
+        let absorbed = (amount * self.armor_tier.absorption()).min(self.armor.max(0.0));
+        self.armor -= absorbed;
+        self.health -= amount - absorbed;
+        absorbed
     }
 
     pub fn heal(&mut self, amount: f32) {
-        self.health += amount.abs();
+        self.heal_up_to(amount, HEAL_LIMIT);
+    }
 
-        if self.health > 150.0 {
-            self.health = 150.0;
+    /// Heals character, but health won't exceed given limit. Health that is already above the
+    /// limit is not reduced.
+    pub fn heal_up_to(&mut self, amount: f32, limit: f32) {
+        if self.health < limit {
+            self.health = (self.health + amount.abs()).min(limit);
         }
     }
 
+    /// Picks up armor of given tier. Armor of a lower tier never replaces a better one that
+    /// is still worn, it only tops armor up to the cap of the lower tier.
+    pub fn add_armor(&mut self, tier: ArmorTier) {
+        if tier >= self.armor_tier || self.armor <= 0.0 {
+            self.armor_tier = tier;
+            self.armor = (self.armor.max(0.0) + tier.amount())
+                .min(tier.cap())
+                .max(self.armor);
+        } else if self.armor < tier.cap() {
+            self.armor = (self.armor + tier.amount()).min(tier.cap());
+        }
+    }
+
+    pub fn armor_tier(&self) -> ArmorTier {
+        self.armor_tier
+    }
+
     pub fn is_dead(&self) -> bool {
         self.health <= 0.0
     }
@@ -129,22 +277,26 @@ impl Character {
         &self.weapons
     }
 
-    pub fn add_weapon(&mut self, weapon: Handle<Weapon>) {
-        if let Some(sender) = self.sender.as_ref() {
-            for other_weapon in self.weapons.iter() {
-                sender
-                    .send(Message::ShowWeapon {
-                        weapon: *other_weapon,
-                        state: false,
-                    })
-                    .unwrap();
-            }
-        }
-
-        self.current_weapon = self.weapons.len() as u32;
+    /// Adds weapon to inventory. New weapon becomes current if `switch` is set or if it is
+    /// the only weapon.
+    pub fn add_weapon(&mut self, weapon: Handle<Weapon>, switch: bool) {
         self.weapons.push(weapon);
 
-        self.request_current_weapon_visible(true);
+        if switch || self.weapons.len() == 1 {
+            self.set_current_weapon(self.weapons.len() - 1);
+        }
+    }
+
+    /// Removes weapon from inventory, index of current weapon is kept in bounds. Caller must
+    /// switch to another weapon if the removed one was current.
+    pub fn remove_weapon(&mut self, weapon: Handle<Weapon>) {
+        if let Some(i) = self.weapons.iter().position(|&w| w == weapon) {
+            self.weapons.remove(i);
+            let current = self.current_weapon as usize;
+            if i < current || current >= self.weapons.len() {
+                self.current_weapon = current.saturating_sub(1) as u32;
+            }
+        }
     }
 
     pub fn current_weapon(&self) -> Handle<Weapon> {
@@ -155,46 +307,30 @@ impl Character {
         }
     }
 
-    fn request_current_weapon_visible(&self, state: bool) {
-        if let Some(sender) = self.sender.as_ref() {
-            if let Some(current_weapon) = self.weapons.get(self.current_weapon as usize) {
-                sender
-                    .send(Message::ShowWeapon {
-                        weapon: *current_weapon,
-                        state,
-                    })
-                    .unwrap()
-            }
-        }
-    }
-
     pub fn next_weapon(&mut self) {
         if !self.weapons.is_empty() && (self.current_weapon as usize) < self.weapons.len() - 1 {
-            self.request_current_weapon_visible(false);
-
-            self.current_weapon += 1;
-
-            self.request_current_weapon_visible(true);
+            self.set_current_weapon(self.current_weapon as usize + 1);
         }
     }
 
     pub fn prev_weapon(&mut self) {
         if self.current_weapon > 0 {
-            self.request_current_weapon_visible(false);
-
-            self.current_weapon -= 1;
-
-            self.request_current_weapon_visible(true);
+            self.set_current_weapon(self.current_weapon as usize - 1);
         }
     }
 
+    /// Switch is animated, new weapon can't shoot until it is taken out, see
+    /// [`crate::weapon::WeaponState`].
     pub fn set_current_weapon(&mut self, i: usize) {
         if i < self.weapons.len() {
-            self.request_current_weapon_visible(false);
+            let from = self.current_weapon();
 
             self.current_weapon = i as u32;
 
-            self.request_current_weapon_visible(true);
+            self.sender.send(Message::SwitchWeapon {
+                from,
+                to: self.weapons[i],
+            });
         }
     }
 
@@ -202,3 +338,64 @@ impl Character {
         scene.remove_node(self.body);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wearing(armor: f32, armor_tier: ArmorTier) -> Character {
+        Character {
+            armor,
+            armor_tier,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn armor_absorbs_share_of_damage() {
+        let mut character = wearing(100.0, ArmorTier::Green);
+        assert_eq!(character.damage(40.0), 20.0);
+        assert_eq!(character.armor, 80.0);
+        assert_eq!(character.health, 80.0);
+    }
+
+    #[test]
+    fn damage_above_armor_goes_to_health() {
+        let mut character = wearing(10.0, ArmorTier::Green);
+        assert_eq!(character.damage(40.0), 10.0);
+        assert_eq!(character.armor, 0.0);
+        assert_eq!(character.health, 70.0);
+    }
+
+    #[test]
+    fn no_armor_absorbs_nothing() {
+        let mut character = wearing(0.0, ArmorTier::Red);
+        assert_eq!(character.damage(40.0), 0.0);
+        assert_eq!(character.armor, 0.0);
+        assert_eq!(character.health, 60.0);
+    }
+
+    #[test]
+    fn pickup_does_not_charge_above_cap() {
+        let mut character = wearing(100.0, ArmorTier::Green);
+        character.add_armor(ArmorTier::Green);
+        assert_eq!(character.armor, ArmorTier::Green.cap());
+
+        character.add_armor(ArmorTier::Red);
+        assert_eq!(character.armor_tier, ArmorTier::Red);
+        assert_eq!(character.armor, ArmorTier::Red.cap());
+    }
+
+    #[test]
+    fn lower_tier_only_tops_up_to_its_cap() {
+        let mut character = wearing(120.0, ArmorTier::Red);
+        character.add_armor(ArmorTier::Green);
+        assert_eq!(character.armor_tier, ArmorTier::Red);
+        assert_eq!(character.armor, 120.0);
+
+        let mut character = wearing(30.0, ArmorTier::Red);
+        character.add_armor(ArmorTier::Green);
+        assert_eq!(character.armor_tier, ArmorTier::Red);
+        assert_eq!(character.armor, 80.0);
+    }
+}
@@ -0,0 +1,280 @@
+//! Single line text chat on HUD. Typed lines are sent as `Message::ChatMessage` like any other
+//! event, so bots (and network players later) can take part in a conversation.
+
+use crate::{control_scheme::ControlButton, message::Message, player::PLAYER_NAME};
+use fyrox::{
+    core::{color::Color, pool::Handle},
+    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
+    gui::{
+        brush::Brush,
+        message::{MessageDirection, UiMessage},
+        stack_panel::StackPanelBuilder,
+        text::{TextBuilder, TextMessage},
+        text_box::TextBoxBuilder,
+        widget::{WidgetBuilder, WidgetMessage},
+        BuildContext, HorizontalAlignment, Thickness, UiNode, UserInterface, VerticalAlignment,
+    },
+};
+use std::{collections::VecDeque, sync::mpsc::Sender};
+
+/// Amount of messages shown at once.
+const MAX_ENTRIES: usize = 6;
+/// Time in seconds for which a message stays on screen.
+const ENTRY_LIFETIME: f32 = 8.0;
+/// A message fades out during this last part of its lifetime, in seconds.
+const FADE_TIME: f32 = 1.0;
+const TEXT_COLOR: Color = Color::opaque(230, 230, 230);
+
+struct Entry {
+    text: String,
+    time_left: f32,
+}
+
+impl Entry {
+    fn alpha(&self) -> u8 {
+        ((self.time_left / FADE_TIME).max(0.0).min(1.0) * 255.0) as u8
+    }
+}
+
+pub struct Chat {
+    root: Handle<UiNode>,
+    /// A text for every shown message, so each of them fades out on its own.
+    lines: Vec<Handle<UiNode>>,
+    input: Handle<UiNode>,
+    input_text: String,
+    active: bool,
+    /// Character of the key that opened chat arrives right after the key press, it must not
+    /// get into input.
+    swallow_char: bool,
+    entries: VecDeque<Entry>,
+    dirty: bool,
+}
+
+impl Chat {
+    pub fn new(ctx: &mut BuildContext) -> Self {
+        let lines = (0..MAX_ENTRIES)
+            .map(|_| {
+                TextBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_foreground(Brush::Solid(TEXT_COLOR)),
+                )
+                .build(ctx)
+            })
+            .collect::<Vec<_>>();
+        let input;
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_vertical_alignment(VerticalAlignment::Center)
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_margin(Thickness {
+                    left: 45.0,
+                    top: 0.0,
+                    right: 0.0,
+                    bottom: 60.0,
+                })
+                .with_width(400.0)
+                .with_child(
+                    StackPanelBuilder::new(
+                        WidgetBuilder::new().with_children(lines.iter().copied()),
+                    )
+                    .build(ctx),
+                )
+                .with_child({
+                    input = TextBoxBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .with_height(24.0)
+                            .with_margin(Thickness::top(2.0))
+                            .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 160))),
+                    )
+                    .build(ctx);
+                    input
+                }),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            lines,
+            input,
+            input_text: Default::default(),
+            active: false,
+            swallow_char: false,
+            entries: Default::default(),
+            dirty: false,
+        }
+    }
+
+    pub fn root(&self) -> Handle<UiNode> {
+        self.root
+    }
+
+    /// Returns true while player is typing, game must not react to keyboard in this case.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn set_active(&mut self, ui: &mut UserInterface, active: bool) {
+        self.active = active;
+        self.input_text.clear();
+        ui.send_message(TextMessage::text(
+            self.input,
+            MessageDirection::ToWidget,
+            Default::default(),
+        ));
+        ui.send_message(WidgetMessage::visibility(
+            self.input,
+            MessageDirection::ToWidget,
+            active,
+        ));
+        if active {
+            ui.send_message(WidgetMessage::focus(self.input, MessageDirection::ToWidget));
+        } else {
+            ui.send_message(WidgetMessage::unfocus(
+                self.input,
+                MessageDirection::ToWidget,
+            ));
+        }
+    }
+
+    pub fn add(&mut self, sender_name: &str, text: &str) {
+        self.entries.push_back(Entry {
+            text: format!("{}: {}", sender_name, text),
+            time_left: ENTRY_LIFETIME,
+        });
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.dirty = true;
+    }
+
+    pub fn handle_ui_message(&mut self, message: &UiMessage) {
+        if message.destination() == self.input
+            && message.direction() == MessageDirection::FromWidget
+        {
+            if let Some(TextMessage::Text(text)) = message.data() {
+                self.input_text = text.clone();
+            }
+        }
+    }
+
+    /// Returns true if event was consumed by chat, such events must not be passed further
+    /// to game.
+    pub fn process_input_event(
+        &mut self,
+        ui: &mut UserInterface,
+        event: &Event<()>,
+        open_button: ControlButton,
+        sender: &Sender<Message>,
+    ) -> bool {
+        if let Event::WindowEvent { event, .. } = event {
+            match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let (ElementState::Pressed, Some(key)) = (input.state, input.virtual_keycode)
+                    {
+                        match key {
+                            VirtualKeyCode::Escape if self.active => {
+                                self.set_active(ui, false);
+                                return true;
+                            }
+                            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter if self.active => {
+                                let text = self.input_text.trim().to_owned();
+                                if !text.is_empty() {
+                                    sender
+                                        .send(Message::ChatMessage {
+                                            sender_name: PLAYER_NAME.to_owned(),
+                                            text,
+                                        })
+                                        .unwrap();
+                                }
+                                self.set_active(ui, false);
+                                return true;
+                            }
+                            _ if !self.active && ControlButton::Key(key) == open_button => {
+                                self.set_active(ui, true);
+                                self.swallow_char = true;
+                                return true;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                WindowEvent::ReceivedCharacter(_) if self.swallow_char => {
+                    self.swallow_char = false;
+                    return true;
+                }
+                _ => (),
+            }
+        }
+        false
+    }
+
+    pub fn update(&mut self, ui: &mut UserInterface, dt: f32) {
+        for entry in self.entries.iter_mut() {
+            entry.time_left -= dt;
+        }
+        while self
+            .entries
+            .front()
+            .map_or(false, |entry| entry.time_left <= 0.0)
+        {
+            self.entries.pop_front();
+            self.dirty = true;
+        }
+
+        for (i, &line) in self.lines.iter().enumerate() {
+            let entry = self.entries.get(i);
+            if self.dirty {
+                ui.send_message(WidgetMessage::visibility(
+                    line,
+                    MessageDirection::ToWidget,
+                    entry.is_some(),
+                ));
+                if let Some(entry) = entry {
+                    ui.send_message(TextMessage::text(
+                        line,
+                        MessageDirection::ToWidget,
+                        entry.text.clone(),
+                    ));
+                }
+            }
+            if let Some(entry) = entry {
+                let alpha = entry.alpha();
+                // Lines shift up when the oldest one is gone, so every line gets its color back.
+                if self.dirty || alpha < 255 {
+                    let mut color = TEXT_COLOR;
+                    color.a = alpha;
+                    ui.send_message(WidgetMessage::foreground(
+                        line,
+                        MessageDirection::ToWidget,
+                        Brush::Solid(color),
+                    ));
+                }
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alpha(time_left: f32) -> u8 {
+        Entry {
+            text: Default::default(),
+            time_left,
+        }
+        .alpha()
+    }
+
+    #[test]
+    fn line_fades_out_during_last_second() {
+        assert_eq!(alpha(ENTRY_LIFETIME), 255);
+        assert_eq!(alpha(FADE_TIME), 255);
+        assert_eq!(alpha(FADE_TIME * 0.5), 127);
+        assert_eq!(alpha(0.0), 0);
+        assert_eq!(alpha(-0.1), 0);
+    }
+}
@@ -0,0 +1,153 @@
+//! Command line arguments, mostly useful for testing to skip menu and launch straight into
+//! a match.
+
+use crate::{
+    mutators::Mutators, CaptureTheFlag, DeathMatch, MatchOptions, Practice, TeamDeathMatch,
+};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: rusty-shooter [OPTIONS]
+
+Options:
+    --map <path>          Path to a map for new matches.
+    --mode <mode>         Match mode: dm, tdm, ctf or practice.
+    --frag-limit <n>      Frag limit (team frag limit for tdm, flag limit for ctf).
+    --time-limit <min>    Time limit in minutes.
+    --grapple             Enable grappling hook.
+    --bot-senses          Give every bot kind its own view distance and hearing.
+    --bots <n>            Amount of bots to spawn.
+    --seed <n>            Seed for gameplay random number generator.
+    --skip-menu           Start a match immediately.
+    --load <slot>         Load a save from given slot on startup.
+    --max-fps <n>         Limit frame rate, simulation rate is not affected.
+    --vsync               Synchronize frames with display refresh rate.
+    --host <port>         Start a match and accept a network client on given port.
+    --connect <addr>      Join a match of a host at given address (host:port).
+    --verbose             Write informational messages to log too.
+    --event-log           Append match events as JSON lines to events.log in user data folder.
+    --help                Print this message.";
+
+pub struct CommandLineArgs {
+    pub map: Option<PathBuf>,
+    pub options: MatchOptions,
+    pub bots: Option<usize>,
+    pub seed: Option<u64>,
+    pub skip_menu: bool,
+    pub load: Option<String>,
+    pub max_fps: Option<f32>,
+    pub vsync: bool,
+    pub host: Option<u16>,
+    pub connect: Option<String>,
+    pub verbose: bool,
+    pub event_log: bool,
+    pub help: bool,
+}
+
+impl Default for CommandLineArgs {
+    fn default() -> Self {
+        Self {
+            map: None,
+            options: Default::default(),
+            bots: None,
+            seed: None,
+            skip_menu: false,
+            load: None,
+            max_fps: None,
+            vsync: false,
+            host: None,
+            connect: None,
+            verbose: false,
+            event_log: false,
+            help: false,
+        }
+    }
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, name: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", name))
+}
+
+fn number<T: std::str::FromStr, I: Iterator<Item = String>>(
+    args: &mut I,
+    name: &str,
+) -> Result<T, String> {
+    let value = value(args, name)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}", value, name))
+}
+
+impl CommandLineArgs {
+    /// Parses arguments, program name must not be included.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut result = Self::default();
+
+        let mut mode = "dm".to_owned();
+        let mut frag_limit = 30;
+        let mut time_limit_minutes = 10.0;
+        let mut mutators = Mutators::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--map" => result.map = Some(value(&mut args, &arg)?.into()),
+                "--mode" => mode = value(&mut args, &arg)?,
+                "--frag-limit" => frag_limit = number(&mut args, &arg)?,
+                "--time-limit" => time_limit_minutes = number::<f32, _>(&mut args, &arg)?,
+                "--bots" => result.bots = Some(number(&mut args, &arg)?),
+                "--seed" => result.seed = Some(number(&mut args, &arg)?),
+                "--skip-menu" => result.skip_menu = true,
+                "--grapple" => mutators.grapple = true,
+                "--bot-senses" => mutators.bot_senses = true,
+                "--load" => result.load = Some(value(&mut args, &arg)?),
+                "--max-fps" => result.max_fps = Some(number(&mut args, &arg)?),
+                "--vsync" => result.vsync = true,
+                "--host" => result.host = Some(number(&mut args, &arg)?),
+                "--connect" => result.connect = Some(value(&mut args, &arg)?),
+                "--verbose" => result.verbose = true,
+                "--event-log" => result.event_log = true,
+                "--help" | "-h" => result.help = true,
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+
+        let time_limit_secs = time_limit_minutes * 60.0;
+        result.options = match mode.as_str() {
+            "dm" => MatchOptions::DeathMatch(DeathMatch {
+                time_limit_secs,
+                frag_limit,
+                mutators,
+            }),
+            "tdm" => MatchOptions::TeamDeathMatch(TeamDeathMatch {
+                time_limit_secs,
+                team_frag_limit: frag_limit,
+                mutators,
+            }),
+            "ctf" => MatchOptions::CaptureTheFlag(CaptureTheFlag {
+                time_limit_secs,
+                flag_limit: frag_limit,
+                mutators,
+            }),
+            "practice" => MatchOptions::Practice(Practice { mutators }),
+            _ => return Err(format!("Unknown match mode {}", mode)),
+        };
+
+        if let Some(max_fps) = result.max_fps {
+            if max_fps <= 0.0 {
+                return Err(format!("Invalid value {} for --max-fps", max_fps));
+            }
+        }
+
+        if result.host.is_some() && result.connect.is_some() {
+            return Err("--host and --connect can't be used together".to_owned());
+        }
+
+        if let Some(map) = result.map.as_ref() {
+            if !map.exists() {
+                return Err(format!("Map {} does not exist", map.display()));
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,226 @@
+//! Small menu with match commands that is shown while its key is held: restart the match, add or
+//! kick bots, shuffle teams and switch player's team. Player can't shoot while the menu is open,
+//! so clicks on its buttons do not fire a weapon.
+
+use crate::{
+    character::Team,
+    control_scheme::ControlButton,
+    level::Level,
+    message::{Message, MessageSender},
+};
+use fyrox::{
+    core::{algebra::Vector2, pool::Handle},
+    event::{DeviceEvent, ElementState, Event, WindowEvent},
+    gui::{
+        button::{Button, ButtonBuilder, ButtonMessage},
+        message::{MessageDirection, UiMessage},
+        stack_panel::StackPanelBuilder,
+        text::TextMessage,
+        widget::{WidgetBuilder, WidgetMessage},
+        window::{WindowBuilder, WindowMessage, WindowTitle},
+        BuildContext, Thickness, UiNode, UserInterface,
+    },
+};
+
+const RESTART_TEXT: &str = "Restart Match";
+const RESTART_CONFIRM_TEXT: &str = "Click Again To Restart";
+
+pub struct CommandMenu {
+    sender: MessageSender,
+    window: Handle<UiNode>,
+    restart: Handle<UiNode>,
+    add_bot: Handle<UiNode>,
+    remove_bot: Handle<UiNode>,
+    shuffle_teams: Handle<UiNode>,
+    join_red: Handle<UiNode>,
+    join_blue: Handle<UiNode>,
+    visible: bool,
+    /// Restart needs a second click, first one only asks for confirmation.
+    restart_requested: bool,
+}
+
+fn make_button(ctx: &mut BuildContext, text: &str) -> Handle<UiNode> {
+    ButtonBuilder::new(
+        WidgetBuilder::new()
+            .with_height(36.0)
+            .with_margin(Thickness::uniform(2.0)),
+    )
+    .with_text(text)
+    .build(ctx)
+}
+
+impl CommandMenu {
+    pub fn new(ui: &mut UserInterface, sender: MessageSender) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let restart = make_button(ctx, RESTART_TEXT);
+        let add_bot = make_button(ctx, "Add Bot");
+        let remove_bot = make_button(ctx, "Remove Last Bot");
+        let shuffle_teams = make_button(ctx, "Shuffle Teams");
+        let join_red = make_button(ctx, "Join Red Team");
+        let join_blue = make_button(ctx, "Join Blue Team");
+        let window = WindowBuilder::new(
+            WidgetBuilder::new()
+                .with_width(250.0)
+                .with_desired_position(Vector2::new(20.0, 200.0)),
+        )
+        .with_title(WindowTitle::text("Match Commands"))
+        .can_resize(false)
+        .can_minimize(false)
+        .can_close(false)
+        .open(false)
+        .with_content(
+            StackPanelBuilder::new(
+                WidgetBuilder::new()
+                    .with_child(restart)
+                    .with_child(add_bot)
+                    .with_child(remove_bot)
+                    .with_child(shuffle_teams)
+                    .with_child(join_red)
+                    .with_child(join_blue),
+            )
+            .build(ctx),
+        )
+        .build(ctx);
+
+        Self {
+            sender,
+            window,
+            restart,
+            add_bot,
+            remove_bot,
+            shuffle_teams,
+            join_red,
+            join_blue,
+            visible: false,
+            restart_requested: false,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, ui: &mut UserInterface, visible: bool, level: Option<&Level>) {
+        if self.visible == visible {
+            return;
+        }
+        self.visible = visible;
+
+        if visible {
+            let team_mode = level.map_or(false, |level| level.options.is_team_mode());
+            for button in [self.shuffle_teams, self.join_red, self.join_blue].iter() {
+                ui.send_message(WidgetMessage::visibility(
+                    *button,
+                    MessageDirection::ToWidget,
+                    team_mode,
+                ));
+            }
+            ui.send_message(WindowMessage::open(
+                self.window,
+                MessageDirection::ToWidget,
+                false,
+            ));
+        } else {
+            self.set_restart_requested(ui, false);
+            ui.send_message(WindowMessage::close(
+                self.window,
+                MessageDirection::ToWidget,
+            ));
+        }
+    }
+
+    fn set_restart_requested(&mut self, ui: &mut UserInterface, requested: bool) {
+        if self.restart_requested == requested {
+            return;
+        }
+        self.restart_requested = requested;
+        if let Some(button) = ui.node(self.restart).cast::<Button>() {
+            ui.send_message(TextMessage::text(
+                button.content(),
+                MessageDirection::ToWidget,
+                if requested {
+                    RESTART_CONFIRM_TEXT
+                } else {
+                    RESTART_TEXT
+                }
+                .to_owned(),
+            ));
+        }
+    }
+
+    /// Shows menu while given button is held, mouse buttons can be bound too.
+    pub fn process_input_event(
+        &mut self,
+        ui: &mut UserInterface,
+        event: &Event<()>,
+        open_button: ControlButton,
+        level: &Level,
+    ) {
+        let state = match event {
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } if input.virtual_keycode.map(ControlButton::Key) == Some(open_button) => {
+                Some(input.state)
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::Button { button, state },
+                ..
+            } if ControlButton::Mouse(*button as u16) == open_button => Some(*state),
+            _ => None,
+        };
+        if let Some(state) = state {
+            self.set_visible(ui, state == ElementState::Pressed, Some(level));
+        }
+    }
+
+    pub fn handle_ui_event(
+        &mut self,
+        ui: &mut UserInterface,
+        message: &UiMessage,
+        level: Option<&Level>,
+    ) {
+        let level = match level {
+            Some(level) if self.visible => level,
+            _ => return,
+        };
+
+        if let Some(ButtonMessage::Click) = message.data() {
+            if message.destination() == self.restart {
+                if self.restart_requested {
+                    self.set_visible(ui, false, None);
+                    self.sender.send(Message::StartNewGame {
+                        options: level.options,
+                    });
+                } else {
+                    self.set_restart_requested(ui, true);
+                }
+                return;
+            }
+
+            if message.destination() == self.add_bot {
+                let (kind, name) = level.next_bot();
+                self.sender.send(Message::SpawnBot { kind, name });
+            } else if message.destination() == self.remove_bot {
+                if let Some(name) = level.bot_names().last() {
+                    self.sender.send(Message::RemoveBot { name: name.clone() });
+                }
+            } else if message.destination() == self.shuffle_teams {
+                self.sender.send(Message::ShuffleTeams);
+            } else if message.destination() == self.join_red
+                || message.destination() == self.join_blue
+            {
+                let team = if message.destination() == self.join_red {
+                    Team::Red
+                } else {
+                    Team::Blue
+                };
+                self.sender.send(Message::RequestTeamChange {
+                    actor: level.get_player(),
+                    team,
+                });
+            }
+            self.set_restart_requested(ui, false);
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Drop-down developer console. Console does not modify game state directly, instead each
+//! command is translated into one or more messages which then handled by appropriate systems
+//! like any other message.
+
+use crate::{
+    actor::Actor, bot::BotKind, item::ItemKind, message::Message, weapon::WeaponKind,
+    MAX_TIME_SCALE,
+};
+use fyrox::{
+    core::{algebra::Vector2, color::Color, pool::Handle},
+    engine::Engine,
+    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        grid::{Column, GridBuilder, Row},
+        message::{MessageDirection, UiMessage},
+        text::{TextBuilder, TextMessage},
+        text_box::TextBoxBuilder,
+        widget::{WidgetBuilder, WidgetMessage},
+        Thickness, UiNode, UserInterface, VerticalAlignment,
+    },
+};
+use std::{collections::VecDeque, path::PathBuf, sync::mpsc::Sender};
+
+/// Amount of lines kept in scrollback.
+const MAX_LINES: usize = 14;
+
+/// Everything command can use to do its job.
+pub struct CommandContext<'a> {
+    pub sender: &'a Sender<Message>,
+    /// Handle of player's actor, can be NONE if there is no level or player is dead.
+    pub player: Handle<Actor>,
+}
+
+impl<'a> CommandContext<'a> {
+    fn player(&self) -> Result<Handle<Actor>, String> {
+        if self.player.is_some() {
+            Ok(self.player)
+        } else {
+            Err("There is no player on level.".to_owned())
+        }
+    }
+
+    fn send(&self, message: Message) {
+        self.sender.send(message).unwrap();
+    }
+}
+
+/// Command handler gets arguments (without command name) and returns text that will be
+/// echoed into scrollback.
+type CommandHandler = fn(&[&str], &CommandContext) -> Result<String, String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    handler: CommandHandler,
+}
+
+pub struct Console {
+    root: Handle<UiNode>,
+    scrollback: Handle<UiNode>,
+    input: Handle<UiNode>,
+    lines: VecDeque<String>,
+    input_text: String,
+    commands: Vec<Command>,
+    visible: bool,
+    sender: Sender<Message>,
+}
+
+fn parse_bot_kind(name: &str) -> Option<BotKind> {
+    match name.to_lowercase().as_str() {
+        "mutant" => Some(BotKind::Mutant),
+        "parasite" => Some(BotKind::Parasite),
+        "maw" => Some(BotKind::Maw),
+        _ => None,
+    }
+}
+
+fn parse_weapon_kind(name: &str) -> Option<WeaponKind> {
+    match name.to_lowercase().as_str() {
+        "m4" => Some(WeaponKind::M4),
+        "ak47" => Some(WeaponKind::Ak47),
+        "plasma" => Some(WeaponKind::PlasmaRifle),
+        "rocket" => Some(WeaponKind::RocketLauncher),
+        "mines" => Some(WeaponKind::MineLayer),
+        "grenade" => Some(WeaponKind::Grenade),
+        _ => None,
+    }
+}
+
+fn parse_item_kind(name: &str) -> Option<ItemKind> {
+    match name.to_lowercase().as_str() {
+        "medkit" => Some(ItemKind::Medkit),
+        "megahealth" => Some(ItemKind::MegaHealth),
+        "armor" | "green_armor" => Some(ItemKind::Armor),
+        "yellow_armor" => Some(ItemKind::YellowArmor),
+        "red_armor" => Some(ItemKind::RedArmor),
+        "plasma_ammo" => Some(ItemKind::Plasma),
+        "ak47_ammo" => Some(ItemKind::Ak47Ammo),
+        "m4_ammo" => Some(ItemKind::M4Ammo),
+        "rockets" => Some(ItemKind::Rockets),
+        _ => None,
+    }
+}
+
+fn cmd_map(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    let name = args.first().ok_or("Map name expected.")?;
+    let path = PathBuf::from(format!("data/levels/{}.rgs", name));
+    if !path.exists() {
+        return Err(format!("There is no map {}", path.display()));
+    }
+    ctx.send(Message::ChangeMap { path });
+    Ok(format!("Loading map {}...", name))
+}
+
+fn cmd_add_bot(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    let kind_name = args.first().ok_or("Bot kind expected.")?;
+    let kind =
+        parse_bot_kind(kind_name).ok_or_else(|| format!("Unknown bot kind {}", kind_name))?;
+    let name = args
+        .get(1)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| kind.description().to_owned());
+    ctx.send(Message::SpawnBot {
+        kind,
+        name: name.clone(),
+    });
+    Ok(format!("Bot {} added.", name))
+}
+
+fn cmd_give(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    let name = args.first().ok_or("Weapon or item name expected.")?;
+    let actor = ctx.player()?;
+    if let Some(kind) = parse_weapon_kind(name) {
+        ctx.send(Message::GiveNewWeapon { actor, kind });
+    } else if let Some(kind) = parse_item_kind(name) {
+        ctx.send(Message::GiveItem { actor, kind });
+    } else {
+        return Err(format!("Unknown weapon or item {}", name));
+    }
+    Ok(format!("Given {}.", name))
+}
+
+fn cmd_god(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::ToggleGodMode {
+        actor: ctx.player()?,
+    });
+    Ok("God mode toggled.".to_owned())
+}
+
+fn cmd_noclip(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::ToggleNoclip {
+        actor: ctx.player()?,
+    });
+    Ok("Noclip toggled.".to_owned())
+}
+
+fn cmd_free_camera(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::ToggleFreeCamera);
+    Ok("Free camera toggled.".to_owned())
+}
+
+fn cmd_pause(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::TogglePause);
+    Ok("Pause toggled.".to_owned())
+}
+
+fn cmd_map_issues(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::ShowMapIssues);
+    Ok("Issues of current map:".to_owned())
+}
+
+fn cmd_kill(_: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    ctx.send(Message::RespawnActor {
+        actor: ctx.player()?,
+    });
+    Ok("Player killed.".to_owned())
+}
+
+fn cmd_time_limit(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    let minutes = args
+        .first()
+        .ok_or("Time limit in minutes expected.")?
+        .parse::<f32>()
+        .map_err(|e| format!("Invalid time limit: {}", e))?;
+    ctx.send(Message::SetTimeLimit {
+        time_limit_secs: minutes * 60.0,
+    });
+    Ok(format!("Time limit set to {} min.", minutes))
+}
+
+fn cmd_time_scale(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    let time_scale = args
+        .first()
+        .ok_or("Time scale expected, for example 0.25, 0.5 or 1.")?
+        .parse::<f32>()
+        .map_err(|e| format!("Invalid time scale: {}", e))?;
+    if !(0.0..=MAX_TIME_SCALE).contains(&time_scale) {
+        return Err(format!(
+            "Time scale must be in [0; {}] range.",
+            MAX_TIME_SCALE
+        ));
+    }
+    ctx.send(Message::SetTimeScale { time_scale });
+    Ok(format!("Time scale set to {}x.", time_scale))
+}
+
+fn cmd_heatmap(args: &[&str], ctx: &CommandContext) -> Result<String, String> {
+    match args.first().copied() {
+        Some("on") => {
+            ctx.send(Message::SetHeatmapRecording { enabled: true });
+            Ok("Heatmap recording started.".to_owned())
+        }
+        Some("off") => {
+            ctx.send(Message::SetHeatmapRecording { enabled: false });
+            Ok("Heatmap recording stopped.".to_owned())
+        }
+        Some("clear") => {
+            ctx.send(Message::ClearHeatmap);
+            Ok("Heatmap cleared.".to_owned())
+        }
+        Some("dump") => {
+            ctx.send(Message::DumpHeatmap);
+            Ok("Writing heatmap...".to_owned())
+        }
+        _ => Err("Expected on, off, clear or dump.".to_owned()),
+    }
+}
+
+impl Console {
+    pub fn new(engine: &mut Engine, sender: Sender<Message>) -> Self {
+        let frame_size = engine.renderer.get_frame_size();
+        let ctx = &mut engine.user_interface.build_ctx();
+
+        let scrollback;
+        let input;
+        let root = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(frame_size.0 as f32)
+                .with_height(300.0)
+                .with_visibility(false)
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 200)))
+                .with_child(
+                    GridBuilder::new(
+                        WidgetBuilder::new()
+                            .with_child({
+                                scrollback = TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(0)
+                                        .with_margin(Thickness::uniform(4.0))
+                                        .with_vertical_alignment(VerticalAlignment::Bottom),
+                                )
+                                .build(ctx);
+                                scrollback
+                            })
+                            .with_child({
+                                input = TextBoxBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(1)
+                                        .with_margin(Thickness::uniform(2.0)),
+                                )
+                                .build(ctx);
+                                input
+                            }),
+                    )
+                    .add_row(Row::stretch())
+                    .add_row(Row::strict(26.0))
+                    .add_column(Column::stretch())
+                    .build(ctx),
+                ),
+        )
+        .build(ctx);
+
+        let mut console = Self {
+            root,
+            scrollback,
+            input,
+            lines: Default::default(),
+            input_text: Default::default(),
+            commands: Default::default(),
+            visible: false,
+            sender,
+        };
+
+        console.register("map", "map <name>", "Starts new match on a map.", cmd_map);
+        console.register(
+            "addbot",
+            "addbot <mutant|parasite|maw> [name]",
+            "Spawns a new bot.",
+            cmd_add_bot,
+        );
+        console.register(
+            "give",
+            "give <m4|ak47|plasma|rocket|mines|grenade|medkit|megahealth|armor|plasma_ammo|ak47_ammo|m4_ammo|rockets>",
+            "Gives a weapon or an item to player.",
+            cmd_give,
+        );
+        console.register("god", "god", "Toggles player invulnerability.", cmd_god);
+        console.register(
+            "noclip",
+            "noclip",
+            "Toggles free flight through walls.",
+            cmd_noclip,
+        );
+        console.register(
+            "freecam",
+            "freecam",
+            "Toggles free-fly debug camera, player stands still.",
+            cmd_free_camera,
+        );
+        console.register(
+            "pause",
+            "pause",
+            "Freezes or resumes the match, free camera keeps flying.",
+            cmd_pause,
+        );
+        console.register("kill", "kill", "Kills and respawns player.", cmd_kill);
+        console.register(
+            "map_issues",
+            "map_issues",
+            "Prints problems found in current map when it was loaded.",
+            cmd_map_issues,
+        );
+        console.register(
+            "timelimit",
+            "timelimit <minutes>",
+            "Changes time limit of current match.",
+            cmd_time_limit,
+        );
+        console.register(
+            "timescale",
+            "timescale <scale>",
+            "Scales game time, for example 0.25 or 2. 1 is normal speed, 0 freezes the game.",
+            cmd_time_scale,
+        );
+        console.register(
+            "heatmap",
+            "heatmap <on|off|clear|dump>",
+            "Records actor positions once per second, they're shown in debug drawing.",
+            cmd_heatmap,
+        );
+
+        console
+    }
+
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        handler: CommandHandler,
+    ) {
+        self.commands.push(Command {
+            name,
+            usage,
+            help,
+            handler,
+        });
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, ui: &mut UserInterface, visible: bool) {
+        self.visible = visible;
+        ui.send_message(WidgetMessage::visibility(
+            self.root,
+            MessageDirection::ToWidget,
+            visible,
+        ));
+        if visible {
+            ui.send_message(WidgetMessage::focus(self.input, MessageDirection::ToWidget));
+        } else {
+            ui.send_message(WidgetMessage::unfocus(
+                self.input,
+                MessageDirection::ToWidget,
+            ));
+        }
+    }
+
+    pub fn print<S: Into<String>>(&mut self, ui: &mut UserInterface, line: S) {
+        self.lines.push_back(line.into());
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+
+        let text = self.lines.iter().fold(String::new(), |mut text, line| {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(line);
+            text
+        });
+        ui.send_message(TextMessage::text(
+            self.scrollback,
+            MessageDirection::ToWidget,
+            text,
+        ));
+    }
+
+    pub fn execute(&mut self, ui: &mut UserInterface, line: &str, player: Handle<Actor>) {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return,
+        };
+        let args = words.collect::<Vec<_>>();
+
+        self.print(ui, format!("> {}", line));
+
+        if name == "help" {
+            let help = self
+                .commands
+                .iter()
+                .map(|command| format!("{} - {}", command.usage, command.help))
+                .collect::<Vec<_>>();
+            for line in help {
+                self.print(ui, line);
+            }
+            return;
+        }
+
+        let result = match self.commands.iter().find(|command| command.name == name) {
+            Some(command) => {
+                let ctx = CommandContext {
+                    sender: &self.sender,
+                    player,
+                };
+                (command.handler)(&args, &ctx)
+            }
+            None => Err(format!("Unknown command {}. Type help for a list.", name)),
+        };
+
+        match result {
+            Ok(text) => self.print(ui, text),
+            Err(text) => self.print(ui, format!("Error: {}", text)),
+        }
+    }
+
+    pub fn handle_ui_message(&mut self, message: &UiMessage) {
+        if message.destination() == self.input
+            && message.direction() == MessageDirection::FromWidget
+        {
+            if let Some(TextMessage::Text(text)) = message.data() {
+                self.input_text = text.clone();
+            }
+        }
+    }
+
+    /// Console spans the whole width of the window, its height is fixed.
+    pub fn resize(&mut self, ui: &mut UserInterface, size: Vector2<f32>) {
+        ui.send_message(WidgetMessage::width(
+            self.root,
+            MessageDirection::ToWidget,
+            size.x,
+        ));
+    }
+
+    /// Returns true if event was consumed by console, such events must not be passed
+    /// further to game.
+    pub fn process_input_event(
+        &mut self,
+        engine: &mut Engine,
+        event: &Event<()>,
+        player: Handle<Actor>,
+    ) -> bool {
+        if let Event::WindowEvent { event, .. } = event {
+            match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let (ElementState::Pressed, Some(key)) = (input.state, input.virtual_keycode)
+                    {
+                        match key {
+                            VirtualKeyCode::Grave => {
+                                self.set_visible(&mut engine.user_interface, !self.visible);
+                                return true;
+                            }
+                            VirtualKeyCode::Escape if self.visible => {
+                                self.set_visible(&mut engine.user_interface, false);
+                                return true;
+                            }
+                            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter
+                                if self.visible =>
+                            {
+                                let line = std::mem::take(&mut self.input_text);
+                                self.execute(&mut engine.user_interface, line.trim(), player);
+                                engine.user_interface.send_message(TextMessage::text(
+                                    self.input,
+                                    MessageDirection::ToWidget,
+                                    Default::default(),
+                                ));
+                                return true;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                // Backquote is used to toggle console, it should never appear in input.
+                WindowEvent::ReceivedCharacter('`') => return true,
+                _ => (),
+            }
+        }
+
+        false
+    }
+}
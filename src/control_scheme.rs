@@ -1,6 +1,117 @@
-use fyrox::event::VirtualKeyCode;
+//! Bindings of controls. Player may have several named control profiles, one of them is
+//! active and shared with everything that reads input.
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+use fyrox::{
+    core::visitor::{Visit, VisitResult, Visitor},
+    event::VirtualKeyCode,
+};
+
+/// Built-in profiles go first in [`ControlProfiles`] and can't be deleted.
+pub const BUILTIN_PROFILE_COUNT: usize = 2;
+
+/// Keys that can be restored from settings file, keys are stored by name. Binding of any other
+/// key is reset to default on next start.
+const BINDABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::Insert,
+    VirtualKeyCode::Home,
+    VirtualKeyCode::Delete,
+    VirtualKeyCode::End,
+    VirtualKeyCode::PageDown,
+    VirtualKeyCode::PageUp,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Capital,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::RShift,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::RControl,
+    VirtualKeyCode::LAlt,
+    VirtualKeyCode::RAlt,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::NumpadAdd,
+    VirtualKeyCode::NumpadSubtract,
+    VirtualKeyCode::NumpadMultiply,
+    VirtualKeyCode::NumpadDivide,
+    VirtualKeyCode::NumpadDecimal,
+    VirtualKeyCode::NumpadEnter,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::LBracket,
+    VirtualKeyCode::RBracket,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Apostrophe,
+    VirtualKeyCode::Grave,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Slash,
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ControlButton {
     Mouse(u16),
     Key(VirtualKeyCode),
@@ -26,12 +137,54 @@ impl ControlButton {
     }
 }
 
+impl Visit for ControlButton {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let (mut kind, mut index, mut key) = match *self {
+            ControlButton::Mouse(index) => (0u32, index, String::new()),
+            ControlButton::Key(code) => {
+                (1, 0, fyrox::utils::virtual_key_code_name(code).to_owned())
+            }
+            ControlButton::WheelUp => (2, 0, String::new()),
+            ControlButton::WheelDown => (3, 0, String::new()),
+        };
+        kind.visit("kind", visitor)?;
+        index.visit("index", visitor)?;
+        key.visit("key", visitor)?;
+
+        if visitor.is_reading() {
+            let button = match kind {
+                0 => Some(ControlButton::Mouse(index)),
+                1 => BINDABLE_KEYS
+                    .iter()
+                    .find(|&&code| fyrox::utils::virtual_key_code_name(code) == key)
+                    .map(|&code| ControlButton::Key(code)),
+                2 => Some(ControlButton::WheelUp),
+                3 => Some(ControlButton::WheelDown),
+                _ => None,
+            };
+            // Unknown button keeps default binding.
+            if let Some(button) = button {
+                *self = button;
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug, Visit)]
 pub struct ControlButtonDefinition {
+    #[visit(skip)]
     pub description: String,
     pub button: ControlButton,
 }
 
+#[derive(Clone, Debug)]
 pub struct ControlScheme {
+    /// Name of control profile, see [`ControlProfiles`].
+    pub name: String,
     pub move_forward: ControlButtonDefinition,
     pub move_backward: ControlButtonDefinition,
     pub move_left: ControlButtonDefinition,
@@ -42,6 +195,19 @@ pub struct ControlScheme {
     pub next_weapon: ControlButtonDefinition,
     pub prev_weapon: ControlButtonDefinition,
     pub run: ControlButtonDefinition,
+    pub zoom: ControlButtonDefinition,
+    pub flashlight: ControlButtonDefinition,
+    pub grapple: ControlButtonDefinition,
+    pub use_item: ControlButtonDefinition,
+    pub toggle_laser: ControlButtonDefinition,
+    pub toggle_debug_draw: ControlButtonDefinition,
+    pub cycle_debug_overlay: ControlButtonDefinition,
+    pub toggle_free_camera: ControlButtonDefinition,
+    pub cycle_time_scale: ControlButtonDefinition,
+    pub toggle_pause: ControlButtonDefinition,
+    pub chat: ControlButtonDefinition,
+    pub show_scoreboard: ControlButtonDefinition,
+    pub command_menu: ControlButtonDefinition,
     pub mouse_sens: f32,
     pub mouse_y_inverse: bool,
     pub smooth_mouse: bool,
@@ -51,6 +217,7 @@ pub struct ControlScheme {
 impl Default for ControlScheme {
     fn default() -> Self {
         Self {
+            name: "Default".to_owned(),
             move_forward: ControlButtonDefinition {
                 description: "Move Forward".to_string(),
                 button: ControlButton::Key(VirtualKeyCode::W),
@@ -91,6 +258,58 @@ impl Default for ControlScheme {
                 description: "Run".to_string(),
                 button: ControlButton::Key(VirtualKeyCode::LShift),
             },
+            zoom: ControlButtonDefinition {
+                description: "Zoom".to_string(),
+                button: ControlButton::Mouse(2),
+            },
+            flashlight: ControlButtonDefinition {
+                description: "Flashlight".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F),
+            },
+            grapple: ControlButtonDefinition {
+                description: "Grappling Hook".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::Q),
+            },
+            use_item: ControlButtonDefinition {
+                description: "Use".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::E),
+            },
+            toggle_laser: ControlButtonDefinition {
+                description: "Toggle Laser Sight".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::L),
+            },
+            toggle_debug_draw: ControlButtonDefinition {
+                description: "Toggle Debug Draw".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F3),
+            },
+            cycle_debug_overlay: ControlButtonDefinition {
+                description: "Cycle Debug Overlay".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F2),
+            },
+            toggle_free_camera: ControlButtonDefinition {
+                description: "Toggle Free Camera".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F4),
+            },
+            cycle_time_scale: ControlButtonDefinition {
+                description: "Cycle Time Scale".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F6),
+            },
+            toggle_pause: ControlButtonDefinition {
+                description: "Toggle Pause".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F7),
+            },
+            chat: ControlButtonDefinition {
+                description: "Chat".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::T),
+            },
+            show_scoreboard: ControlButtonDefinition {
+                description: "Show Scoreboard".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::Tab),
+            },
+            command_menu: ControlButtonDefinition {
+                description: "Match Commands".to_string(),
+                button: ControlButton::Key(VirtualKeyCode::F1),
+            },
             mouse_sens: 0.3,
             mouse_y_inverse: false,
             smooth_mouse: false,
@@ -99,8 +318,49 @@ impl Default for ControlScheme {
     }
 }
 
+impl Visit for ControlScheme {
+    // Fields are read one by one, so a profile saved before a control was added keeps its
+    // bindings and gets default binding of the new control.
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let _ = self.name.visit("name", visitor);
+        let _ = self.move_forward.visit("move_forward", visitor);
+        let _ = self.move_backward.visit("move_backward", visitor);
+        let _ = self.move_left.visit("move_left", visitor);
+        let _ = self.move_right.visit("move_right", visitor);
+        let _ = self.jump.visit("jump", visitor);
+        let _ = self.crouch.visit("crouch", visitor);
+        let _ = self.shoot.visit("shoot", visitor);
+        let _ = self.next_weapon.visit("next_weapon", visitor);
+        let _ = self.prev_weapon.visit("prev_weapon", visitor);
+        let _ = self.run.visit("run", visitor);
+        let _ = self.zoom.visit("zoom", visitor);
+        let _ = self.flashlight.visit("flashlight", visitor);
+        let _ = self.grapple.visit("grapple", visitor);
+        let _ = self.use_item.visit("use_item", visitor);
+        let _ = self.toggle_laser.visit("toggle_laser", visitor);
+        let _ = self.toggle_debug_draw.visit("toggle_debug_draw", visitor);
+        let _ = self
+            .cycle_debug_overlay
+            .visit("cycle_debug_overlay", visitor);
+        let _ = self.toggle_free_camera.visit("toggle_free_camera", visitor);
+        let _ = self.cycle_time_scale.visit("cycle_time_scale", visitor);
+        let _ = self.toggle_pause.visit("toggle_pause", visitor);
+        let _ = self.chat.visit("chat", visitor);
+        let _ = self.show_scoreboard.visit("show_scoreboard", visitor);
+        let _ = self.command_menu.visit("command_menu", visitor);
+        let _ = self.mouse_sens.visit("mouse_sens", visitor);
+        let _ = self.mouse_y_inverse.visit("mouse_y_inverse", visitor);
+        let _ = self.smooth_mouse.visit("smooth_mouse", visitor);
+        let _ = self.shake_camera.visit("shake_camera", visitor);
+
+        visitor.leave_region()
+    }
+}
+
 impl ControlScheme {
-    pub fn buttons_mut(&mut self) -> [&mut ControlButtonDefinition; 10] {
+    pub fn buttons_mut(&mut self) -> [&mut ControlButtonDefinition; 23] {
         [
             &mut self.move_forward,
             &mut self.move_backward,
@@ -112,10 +372,23 @@ impl ControlScheme {
             &mut self.next_weapon,
             &mut self.prev_weapon,
             &mut self.run,
+            &mut self.zoom,
+            &mut self.flashlight,
+            &mut self.grapple,
+            &mut self.use_item,
+            &mut self.toggle_laser,
+            &mut self.toggle_debug_draw,
+            &mut self.cycle_debug_overlay,
+            &mut self.toggle_free_camera,
+            &mut self.cycle_time_scale,
+            &mut self.toggle_pause,
+            &mut self.chat,
+            &mut self.show_scoreboard,
+            &mut self.command_menu,
         ]
     }
 
-    pub fn buttons(&self) -> [&ControlButtonDefinition; 10] {
+    pub fn buttons(&self) -> [&ControlButtonDefinition; 23] {
         [
             &self.move_forward,
             &self.move_backward,
@@ -127,10 +400,141 @@ impl ControlScheme {
             &self.next_weapon,
             &self.prev_weapon,
             &self.run,
+            &self.zoom,
+            &self.flashlight,
+            &self.grapple,
+            &self.use_item,
+            &self.toggle_laser,
+            &self.toggle_debug_draw,
+            &self.cycle_debug_overlay,
+            &self.toggle_free_camera,
+            &self.cycle_time_scale,
+            &self.toggle_pause,
+            &self.chat,
+            &self.show_scoreboard,
+            &self.command_menu,
         ]
     }
 
+    /// Same as default scheme, but movement is on ESDF, so left hand reaches more keys.
+    pub fn esdf() -> Self {
+        let mut scheme = Self {
+            name: "ESDF".to_owned(),
+            ..Default::default()
+        };
+        scheme.move_forward.button = ControlButton::Key(VirtualKeyCode::E);
+        scheme.move_backward.button = ControlButton::Key(VirtualKeyCode::D);
+        scheme.move_left.button = ControlButton::Key(VirtualKeyCode::S);
+        scheme.move_right.button = ControlButton::Key(VirtualKeyCode::F);
+        scheme.grapple.button = ControlButton::Key(VirtualKeyCode::W);
+        scheme.use_item.button = ControlButton::Key(VirtualKeyCode::R);
+        scheme.flashlight.button = ControlButton::Key(VirtualKeyCode::G);
+        scheme
+    }
+
+    /// Returns [`BUILTIN_PROFILE_COUNT`] profiles that are always available.
+    pub fn builtin_profiles() -> Vec<ControlScheme> {
+        vec![Self::default(), Self::esdf()]
+    }
+
+    /// Restores bindings of built-in profile with the same name, or default bindings for
+    /// profiles made by player. Name is kept.
     pub fn reset(&mut self) {
-        *self = Default::default();
+        let name = std::mem::take(&mut self.name);
+        *self = Self::builtin_profiles()
+            .into_iter()
+            .find(|profile| profile.name == name)
+            .unwrap_or_default();
+        self.name = name;
+    }
+}
+
+/// Named control schemes, active one is copied to the shared [`ControlScheme`] that is read by
+/// player and menus, so switching profile is picked up live.
+#[derive(Clone, Debug, Visit)]
+pub struct ControlProfiles {
+    profiles: Vec<ControlScheme>,
+    active: u32,
+}
+
+impl Default for ControlProfiles {
+    fn default() -> Self {
+        Self {
+            profiles: ControlScheme::builtin_profiles(),
+            active: 0,
+        }
+    }
+}
+
+impl ControlProfiles {
+    /// Puts missing built-in profiles back in front of the list and fixes active index, used
+    /// after profiles were loaded.
+    pub fn validate(&mut self) {
+        for (i, builtin) in ControlScheme::builtin_profiles().into_iter().enumerate() {
+            if self.profiles.get(i).map(|profile| &profile.name) != Some(&builtin.name) {
+                self.profiles.retain(|profile| profile.name != builtin.name);
+                self.profiles.insert(i, builtin);
+            }
+        }
+        if self.active as usize >= self.profiles.len() {
+            self.active = 0;
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.iter().map(|profile| profile.name.as_str())
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active as usize
+    }
+
+    pub fn active(&self) -> &ControlScheme {
+        &self.profiles[self.active as usize]
+    }
+
+    pub fn is_builtin(&self, index: usize) -> bool {
+        index < BUILTIN_PROFILE_COUNT
+    }
+
+    /// Keeps edits made to the shared scheme in the active profile.
+    pub fn store(&mut self, current: &ControlScheme) {
+        self.profiles[self.active as usize] = current.clone();
+    }
+
+    /// Makes profile with given index active and puts it into the shared scheme.
+    pub fn select(&mut self, index: usize, current: &mut ControlScheme) {
+        if index >= self.profiles.len() {
+            return;
+        }
+        self.store(current);
+        self.active = index as u32;
+        *current = self.profiles[index].clone();
+    }
+
+    /// Adds copy of active profile and makes it active.
+    pub fn duplicate(&mut self, current: &mut ControlScheme) {
+        let mut copy = current.clone();
+        let mut n = 1;
+        copy.name = format!("{} Copy", current.name);
+        while self.names().any(|name| name == copy.name) {
+            n += 1;
+            copy.name = format!("{} Copy {}", current.name, n);
+        }
+        self.profiles.push(copy);
+        self.select(self.profiles.len() - 1, current);
+    }
+
+    /// Deletes active profile, previous one becomes active. Returns false for built-in
+    /// profiles, they can't be deleted.
+    pub fn delete_active(&mut self, current: &mut ControlScheme) -> bool {
+        let index = self.active as usize;
+        if self.is_builtin(index) {
+            return false;
+        }
+        self.profiles.remove(index);
+        self.active = (index - 1) as u32;
+        *current = self.profiles[index - 1].clone();
+        true
     }
 }
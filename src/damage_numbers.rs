@@ -0,0 +1,214 @@
+//! Arcade-style damage readout: numbers that pop up at the place where local player hit
+//! someone, drift upwards and fade out. Rapid hits on the same victim are summed up into one
+//! number.
+
+use crate::{actor::Actor, gui};
+use fyrox::{
+    core::{
+        algebra::{Vector2, Vector3},
+        color::Color,
+        pool::Handle,
+    },
+    gui::{
+        brush::Brush,
+        canvas::CanvasBuilder,
+        message::MessageDirection,
+        text::{TextBuilder, TextMessage},
+        widget::{WidgetBuilder, WidgetMessage},
+        BuildContext, HorizontalAlignment, UiNode, UserInterface,
+    },
+    scene::camera::Camera,
+};
+
+/// Amount of numbers shown at once, the oldest one is replaced by a new one.
+const MAX_NUMBERS: usize = 20;
+/// Time in seconds for which a number stays on screen.
+const LIFETIME: f32 = 0.7;
+/// Hits on the same victim that come faster than this are merged into one number.
+const MERGE_TIME: f32 = 0.1;
+/// Speed of upward drift, meters per second.
+const DRIFT_SPEED: f32 = 0.8;
+const NUMBER_WIDTH: f32 = 80.0;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum DamageNumberKind {
+    Normal,
+    /// Part of the damage was absorbed by armor.
+    Armor,
+    KillingBlow,
+}
+
+impl DamageNumberKind {
+    fn color(self) -> Color {
+        match self {
+            DamageNumberKind::Normal => Color::opaque(255, 255, 255),
+            DamageNumberKind::Armor => Color::opaque(90, 170, 255),
+            DamageNumberKind::KillingBlow => Color::opaque(255, 60, 30),
+        }
+    }
+}
+
+struct Entry {
+    victim: Handle<Actor>,
+    /// World position where number appeared.
+    position: Vector3<f32>,
+    amount: f32,
+    kind: DamageNumberKind,
+    age: f32,
+}
+
+/// Text widget of a single number and the last values sent to it.
+struct Slot {
+    text: Handle<UiNode>,
+    shown: Option<(u32, DamageNumberKind)>,
+}
+
+pub struct DamageNumbers {
+    root: Handle<UiNode>,
+    slots: Vec<Slot>,
+    entries: Vec<Entry>,
+    enabled: bool,
+}
+
+impl DamageNumbers {
+    pub fn new(ctx: &mut BuildContext, frame_size: (u32, u32)) -> Self {
+        let slots = (0..MAX_NUMBERS)
+            .map(|_| Slot {
+                text: TextBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_width(NUMBER_WIDTH),
+                )
+                .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                .build(ctx),
+                shown: None,
+            })
+            .collect::<Vec<_>>();
+
+        let root = CanvasBuilder::new(
+            WidgetBuilder::new()
+                .with_width(frame_size.0 as f32)
+                .with_height(frame_size.1 as f32)
+                .with_hit_test_visibility(false)
+                .with_children(slots.iter().map(|slot| slot.text)),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            slots,
+            entries: Default::default(),
+            enabled: false,
+        }
+    }
+
+    pub fn root(&self) -> Handle<UiNode> {
+        self.root
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.entries.clear();
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        victim: Handle<Actor>,
+        position: Vector3<f32>,
+        amount: f32,
+        kind: DamageNumberKind,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.victim == victim && entry.age < MERGE_TIME)
+        {
+            entry.amount += amount;
+            entry.kind = entry.kind.max(kind);
+            entry.age = 0.0;
+            return;
+        }
+
+        if self.entries.len() == MAX_NUMBERS {
+            self.entries.remove(0);
+        }
+        self.entries.push(Entry {
+            victim,
+            position,
+            amount,
+            kind,
+            age: 0.0,
+        });
+    }
+
+    /// Advances numbers and places them on screen. Numbers are hidden while there is no
+    /// camera to project them with.
+    pub fn update(
+        &mut self,
+        ui: &mut UserInterface,
+        camera: Option<&Camera>,
+        screen_size: Vector2<f32>,
+        dt: f32,
+    ) {
+        for entry in self.entries.iter_mut() {
+            entry.age += dt;
+        }
+        self.entries.retain(|entry| entry.age < LIFETIME);
+
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let placement = self.entries.get(i).and_then(|entry| {
+                let position = entry.position + Vector3::new(0.0, DRIFT_SPEED * entry.age, 0.0);
+                camera
+                    .and_then(|camera| gui::world_to_screen(camera, position, screen_size))
+                    .map(|screen_position| (entry, screen_position))
+            });
+
+            let (entry, screen_position) = match placement {
+                Some(placement) => placement,
+                None => {
+                    if slot.shown.take().is_some() {
+                        ui.send_message(WidgetMessage::visibility(
+                            slot.text,
+                            MessageDirection::ToWidget,
+                            false,
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            let shown = (entry.amount.round() as u32, entry.kind);
+            if slot.shown.replace(shown) != Some(shown) {
+                ui.send_message(TextMessage::text(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    shown.0.to_string(),
+                ));
+                ui.send_message(WidgetMessage::visibility(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    true,
+                ));
+            }
+
+            let mut color = entry.kind.color();
+            color.a = ((1.0 - entry.age / LIFETIME) * 255.0) as u8;
+            ui.send_message(WidgetMessage::foreground(
+                slot.text,
+                MessageDirection::ToWidget,
+                Brush::Solid(color),
+            ));
+            ui.send_message(WidgetMessage::desired_position(
+                slot.text,
+                MessageDirection::ToWidget,
+                screen_position - Vector2::new(NUMBER_WIDTH * 0.5, 0.0),
+            ));
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Balance tables. Weapons and projectiles are described in `data/config/weapons.ron` and
+//! `data/config/projectiles.ron`, so they can be tweaked without recompilation. Compiled-in
+//! definitions are used for a table if its file is missing or invalid.
+//!
+//! Tables are loaded on first access. Files are watched while a match runs, see
+//! [`DefinitionsWatcher`], and a table is replaced when its file is saved with valid content.
+//! Replaced tables are leaked on purpose: definitions are handed out as `&'static` references
+//! and some of them may still be in use. It is a few kilobytes per edit of a file.
+
+use crate::{
+    logger::Logger,
+    projectile::{Projectile, ProjectileDefinition, ProjectileKind},
+    weapon::{Weapon, WeaponDefinition, WeaponKind},
+};
+use fyrox::utils::log::MessageKind;
+use once_cell::sync::OnceCell;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    io::ErrorKind,
+    sync::RwLock,
+    time::{Duration, Instant, SystemTime},
+};
+
+pub const WEAPONS_PATH: &str = "data/config/weapons.ron";
+pub const PROJECTILES_PATH: &str = "data/config/projectiles.ron";
+
+/// Files are checked for changes this often.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+static DEFINITIONS: OnceCell<RwLock<&'static Definitions>> = OnceCell::new();
+
+pub struct Definitions {
+    weapons: &'static HashMap<WeaponKind, WeaponDefinition>,
+    projectiles: &'static HashMap<ProjectileKind, ProjectileDefinition>,
+}
+
+impl Definitions {
+    pub fn get() -> &'static Definitions {
+        *Self::current().read().unwrap()
+    }
+
+    fn current() -> &'static RwLock<&'static Definitions> {
+        DEFINITIONS.get_or_init(|| {
+            RwLock::new(&*Box::leak(Box::new(Definitions {
+                weapons: Box::leak(Box::new(load_table(
+                    WEAPONS_PATH,
+                    "weapon",
+                    Self::parse_weapons,
+                    Weapon::builtin_definition,
+                    &WeaponKind::ALL,
+                ))),
+                projectiles: Box::leak(Box::new(load_table(
+                    PROJECTILES_PATH,
+                    "projectile",
+                    Self::parse_projectiles,
+                    Projectile::builtin_definition,
+                    &ProjectileKind::ALL,
+                ))),
+            })))
+        })
+    }
+
+    /// Replaces current definitions, tables that are not given stay as they are.
+    fn replace(
+        weapons: Option<HashMap<WeaponKind, WeaponDefinition>>,
+        projectiles: Option<HashMap<ProjectileKind, ProjectileDefinition>>,
+    ) {
+        let mut current = Self::current().write().unwrap();
+        *current = Box::leak(Box::new(Definitions {
+            weapons: weapons.map_or(current.weapons, |table| &*Box::leak(Box::new(table))),
+            projectiles: projectiles
+                .map_or(current.projectiles, |table| &*Box::leak(Box::new(table))),
+        }));
+    }
+
+    pub fn weapon(&self, kind: WeaponKind) -> &WeaponDefinition {
+        &self.weapons[&kind]
+    }
+
+    pub fn projectile(&self, kind: ProjectileKind) -> &ProjectileDefinition {
+        &self.projectiles[&kind]
+    }
+
+    fn parse_weapons(source: &str) -> Result<HashMap<WeaponKind, WeaponDefinition>, String> {
+        parse_table(source, &WeaponKind::ALL, WeaponDefinition::validate)
+    }
+
+    fn parse_projectiles(
+        source: &str,
+    ) -> Result<HashMap<ProjectileKind, ProjectileDefinition>, String> {
+        parse_table(source, &ProjectileKind::ALL, ProjectileDefinition::validate)
+    }
+}
+
+/// Parses a map of definitions and checks that every kind is described and every definition
+/// is valid. Unknown kinds, including ones that are referenced from a definition, are
+/// rejected by the parser.
+fn parse_table<K, D>(
+    source: &str,
+    kinds: &[K],
+    validate: fn(&D) -> Result<(), String>,
+) -> Result<HashMap<K, D>, String>
+where
+    K: DeserializeOwned + Eq + Hash + Debug,
+    D: DeserializeOwned,
+{
+    let table: HashMap<K, D> = ron::from_str(source).map_err(|e| e.to_string())?;
+    for kind in kinds {
+        match table.get(kind) {
+            Some(definition) => validate(definition).map_err(|e| format!("{:?}: {}", kind, e))?,
+            None => return Err(format!("{:?} is not described", kind)),
+        }
+    }
+    Ok(table)
+}
+
+/// Reads changed file of a table and logs what has changed. Current table stays in use if
+/// the file can't be read or parsed, error describes why.
+fn reload_table<K, D>(
+    path: &str,
+    name: &str,
+    parse: fn(&str) -> Result<HashMap<K, D>, String>,
+    current: &HashMap<K, D>,
+    kinds: &[K],
+) -> Result<HashMap<K, D>, String>
+where
+    K: Eq + Hash + Debug,
+    D: Debug,
+{
+    let table = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| parse(&source))
+        .map_err(|e| format!("Unable to reload {}: {}", path, e))?;
+
+    let mut changes = 0;
+    for kind in kinds {
+        for change in diff_definitions(&current[kind], &table[kind]) {
+            Logger::writeln(
+                MessageKind::Information,
+                format!("{} {:?}: {}", name, kind, change),
+            );
+            changes += 1;
+        }
+    }
+    Logger::writeln(
+        MessageKind::Information,
+        format!(
+            "Reloaded {} definitions from {}, {} change(s).",
+            name, path, changes
+        ),
+    );
+    Ok(table)
+}
+
+/// Describes differences between two definitions field by field, as `field: old -> new`.
+/// Fields are compared by their debug output, nested values are compared line by line.
+fn diff_definitions<D: Debug>(old: &D, new: &D) -> Vec<String> {
+    let old = format!("{:#?}", old);
+    let new = format!("{:#?}", new);
+    if old == new {
+        return Vec::new();
+    }
+
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    if old_lines.len() != new_lines.len() {
+        // Optional part was added or removed, there is no line to line match.
+        let compact = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+        return vec![format!("{} -> {}", compact(&old), compact(&new))];
+    }
+
+    old_lines
+        .iter()
+        .zip(new_lines.iter())
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| {
+            let old = old.trim().trim_end_matches(',');
+            let new = new.trim().trim_end_matches(',');
+            match (old.split_once(": "), new.split_once(": ")) {
+                (Some((field, old_value)), Some((new_field, new_value))) if field == new_field => {
+                    format!("{}: {} -> {}", field, old_value, new_value)
+                }
+                _ => format!("{} -> {}", old, new),
+            }
+        })
+        .collect()
+}
+
+/// Polls modification times of definition files and reloads changed ones, so balance can be
+/// tweaked while the game runs.
+pub struct DefinitionsWatcher {
+    last_poll: Instant,
+    weapons_modified: Option<SystemTime>,
+    projectiles_modified: Option<SystemTime>,
+}
+
+impl Default for DefinitionsWatcher {
+    fn default() -> Self {
+        Self {
+            last_poll: Instant::now(),
+            weapons_modified: modification_time(WEAPONS_PATH),
+            projectiles_modified: modification_time(PROJECTILES_PATH),
+        }
+    }
+}
+
+fn modification_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Result of [`DefinitionsWatcher::poll`].
+#[derive(Default)]
+pub struct ReloadOutcome {
+    /// At least one table was replaced.
+    pub reloaded: bool,
+    /// Files that were changed but could not be loaded, previous tables are kept for them.
+    pub errors: Vec<String>,
+}
+
+impl DefinitionsWatcher {
+    /// Reloads files that were modified since last poll. Does nothing until
+    /// [`WATCH_INTERVAL`] has passed since previous check.
+    pub fn poll(&mut self) -> ReloadOutcome {
+        let mut outcome = ReloadOutcome::default();
+        if self.last_poll.elapsed() < WATCH_INTERVAL {
+            return outcome;
+        }
+        self.last_poll = Instant::now();
+
+        let current = Definitions::get();
+        let mut weapons = None;
+        let modified = modification_time(WEAPONS_PATH);
+        if modified != self.weapons_modified {
+            self.weapons_modified = modified;
+            match reload_table(
+                WEAPONS_PATH,
+                "weapon",
+                Definitions::parse_weapons,
+                current.weapons,
+                &WeaponKind::ALL,
+            ) {
+                Ok(table) => weapons = Some(table),
+                Err(e) => outcome.errors.push(e),
+            }
+        }
+        let mut projectiles = None;
+        let modified = modification_time(PROJECTILES_PATH);
+        if modified != self.projectiles_modified {
+            self.projectiles_modified = modified;
+            match reload_table(
+                PROJECTILES_PATH,
+                "projectile",
+                Definitions::parse_projectiles,
+                current.projectiles,
+                &ProjectileKind::ALL,
+            ) {
+                Ok(table) => projectiles = Some(table),
+                Err(e) => outcome.errors.push(e),
+            }
+        }
+
+        for error in outcome.errors.iter() {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!("{}. Previous definitions are kept.", error),
+            );
+        }
+
+        if weapons.is_some() || projectiles.is_some() {
+            Definitions::replace(weapons, projectiles);
+            outcome.reloaded = true;
+        }
+        outcome
+    }
+}
+
+fn load_table<K, D>(
+    path: &str,
+    name: &str,
+    parse: fn(&str) -> Result<HashMap<K, D>, String>,
+    builtin: fn(K) -> D,
+    kinds: &[K],
+) -> HashMap<K, D>
+where
+    K: Copy + Eq + Hash,
+{
+    let error = match std::fs::read_to_string(path) {
+        Ok(source) => match parse(&source) {
+            Ok(table) => {
+                Logger::writeln(
+                    MessageKind::Information,
+                    format!("Loaded {} definitions from {}", name, path),
+                );
+                return table;
+            }
+            Err(e) => e,
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            Logger::writeln(
+                MessageKind::Information,
+                format!("{} not found, using built-in {} definitions", path, name),
+            );
+            return kinds.iter().map(|&kind| (kind, builtin(kind))).collect();
+        }
+        Err(e) => e.to_string(),
+    };
+    Logger::writeln(
+        MessageKind::Warning,
+        format!(
+            "Unable to load {} definitions from {}: {}. Built-in definitions are used.",
+            name, path, error
+        ),
+    );
+    kinds.iter().map(|&kind| (kind, builtin(kind))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_weapons_are_valid() {
+        let source = std::fs::read_to_string(WEAPONS_PATH).unwrap();
+        let table = Definitions::parse_weapons(&source).unwrap();
+        assert_eq!(table.len(), WeaponKind::ALL.len());
+    }
+
+    #[test]
+    fn shipped_projectiles_are_valid() {
+        let source = std::fs::read_to_string(PROJECTILES_PATH).unwrap();
+        let table = Definitions::parse_projectiles(&source).unwrap();
+        assert_eq!(table.len(), ProjectileKind::ALL.len());
+    }
+
+    #[test]
+    fn table_without_every_kind_is_rejected() {
+        let source = r#"{
+            Plasma: (
+                damage: 30.0,
+                speed: 0.15,
+                lifetime: 10.0,
+                is_kinematic: true,
+                impact_sound: "data/sounds/bullet_impact_concrete.ogg",
+                visual: Sprite("data/particles/light_01.png"),
+            ),
+        }"#;
+        assert!(Definitions::parse_projectiles(source)
+            .unwrap_err()
+            .contains("is not described"));
+    }
+}
@@ -26,6 +26,23 @@ pub enum EffectKind {
     Steam,
 }
 
+impl EffectKind {
+    pub const ALL: [EffectKind; 4] = [
+        EffectKind::BulletImpact,
+        EffectKind::ItemAppear,
+        EffectKind::Smoke,
+        EffectKind::Steam,
+    ];
+
+    pub fn texture(self) -> &'static str {
+        match self {
+            EffectKind::BulletImpact => "data/particles/circle_05.png",
+            EffectKind::ItemAppear => "data/particles/star_09.png",
+            EffectKind::Smoke | EffectKind::Steam => "data/particles/smoke_04.tga",
+        }
+    }
+}
+
 pub fn create(
     kind: EffectKind,
     graph: &mut Graph,
@@ -68,7 +85,7 @@ fn create_steam(
         .with_height(0.2)
         .with_radius(0.2)
         .build()])
-    .with_texture(resource_manager.request_texture("data/particles/smoke_04.tga"))
+    .with_texture(resource_manager.request_texture(EffectKind::Steam.texture()))
     .build(graph)
 }
 
@@ -104,7 +121,7 @@ fn create_bullet_impact(
     )
     .with_radius(0.01)
     .build()])
-    .with_texture(resource_manager.request_texture("data/particles/circle_05.png"))
+    .with_texture(resource_manager.request_texture(EffectKind::BulletImpact.texture()))
     .build(graph)
 }
 
@@ -143,7 +160,7 @@ fn create_smoke(
     )
     .with_radius(0.01)
     .build()])
-    .with_texture(resource_manager.request_texture("data/particles/smoke_04.tga"))
+    .with_texture(resource_manager.request_texture(EffectKind::Smoke.texture()))
     .build(graph)
 }
 
@@ -179,6 +196,6 @@ fn create_item_appear(
     )
     .with_radius(0.01)
     .build()])
-    .with_texture(resource_manager.request_texture("data/particles/star_09.png"))
+    .with_texture(resource_manager.request_texture(EffectKind::ItemAppear.texture()))
     .build(graph)
 }
@@ -0,0 +1,139 @@
+//! Optional per-map settings. They're stored next to the map in a sidecar file with the same
+//! name and `.env.ron` extension, for example `data/levels/dm6.env.ron` for
+//! `data/levels/dm6.rgs`. Every field is optional, missing ones have default values.
+
+use crate::logger::Logger;
+use fyrox::{
+    core::{algebra::Vector3, color::Color},
+    scene::Scene,
+    utils::log::MessageKind,
+};
+use serde::Deserialize;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Ambient brightness (brightest channel) below which a map is dark and bots see less far.
+const DARK_AMBIENT_BRIGHTNESS: u8 = 40;
+/// View distance of bots in pitch black relative to a lit map.
+const DARKNESS_VIEW_DISTANCE_SCALE: f32 = 0.4;
+
+/// How far bots see with given ambient lighting relative to a lit map. Maps at or above
+/// [`DARK_AMBIENT_BRIGHTNESS`] are not affected, darker ones scale view distance down to
+/// [`DARKNESS_VIEW_DISTANCE_SCALE`]. Targets that shine a flashlight are seen at full distance.
+pub fn view_distance_scale(ambient: Color) -> f32 {
+    let brightness = ambient.r.max(ambient.g).max(ambient.b);
+    if brightness >= DARK_AMBIENT_BRIGHTNESS {
+        1.0
+    } else {
+        let k = brightness as f32 / DARK_AMBIENT_BRIGHTNESS as f32;
+        DARKNESS_VIEW_DISTANCE_SCALE + (1.0 - DARKNESS_VIEW_DISTANCE_SCALE) * k
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MapEnvironment {
+    /// RGB color of ambient lighting. Dark colors make bots see less far, see
+    /// [`view_distance_scale`].
+    pub ambient_color: [u8; 3],
+    /// Sound file that is played in a loop during the match.
+    pub music: Option<PathBuf>,
+    pub music_volume: f32,
+    /// Multiplier of default gravity.
+    pub gravity_scale: f32,
+}
+
+impl Default for MapEnvironment {
+    fn default() -> Self {
+        Self {
+            ambient_color: [60, 60, 60],
+            music: None,
+            music_volume: 0.25,
+            gravity_scale: 1.0,
+        }
+    }
+}
+
+impl MapEnvironment {
+    pub fn sidecar_path(map: &Path) -> PathBuf {
+        map.with_extension("env.ron")
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        ron::from_str(source).map_err(|e| e.to_string())
+    }
+
+    /// Loads settings of given map. Defaults are used if map has no sidecar file, malformed
+    /// file is reported to the log and never prevents map from loading.
+    pub fn load(map: &Path) -> Self {
+        let path = Self::sidecar_path(map);
+        let error = match std::fs::read_to_string(&path) {
+            Ok(source) => match Self::parse(&source) {
+                Ok(environment) => return environment,
+                Err(e) => e,
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::default(),
+            Err(e) => e.to_string(),
+        };
+        Logger::writeln(
+            MessageKind::Warning,
+            format!(
+                "Unable to load environment settings from {}: {}. Defaults are used.",
+                path.display(),
+                error
+            ),
+        );
+        Self::default()
+    }
+
+    pub fn apply(&self, scene: &mut Scene) {
+        let [r, g, b] = self.ambient_color;
+        scene.ambient_lighting_color = Color::opaque(r, g, b);
+        scene.graph.physics.gravity = Vector3::new(0.0, -9.81 * self.gravity_scale, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_have_defaults() {
+        assert_eq!(MapEnvironment::parse("()"), Ok(MapEnvironment::default()));
+        assert_eq!(
+            MapEnvironment::parse("(gravity_scale: 0.5)"),
+            Ok(MapEnvironment {
+                gravity_scale: 0.5,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        assert!(MapEnvironment::parse("(ambient_color: (300, 0, 0))").is_err());
+        assert!(MapEnvironment::parse("(ambient_color: (1, 2))").is_err());
+        assert!(MapEnvironment::parse("(music_volume: \"loud\")").is_err());
+        assert!(MapEnvironment::parse("(gravity_scale: 1.0").is_err());
+    }
+
+    #[test]
+    fn missing_sidecar_gives_defaults() {
+        assert_eq!(
+            MapEnvironment::load(Path::new("data/levels/no_such_map.rgs")),
+            MapEnvironment::default()
+        );
+    }
+
+    #[test]
+    fn bundled_sidecar_is_valid() {
+        let source = std::fs::read_to_string("data/levels/dm6.env.ron").unwrap();
+        let environment = MapEnvironment::parse(&source).unwrap();
+        assert_eq!(
+            environment.music,
+            Some(PathBuf::from("data/sounds/Antonio_Bizarro_Berzerker.ogg"))
+        );
+    }
+}
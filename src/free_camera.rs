@@ -0,0 +1,153 @@
+//! Free-fly debug camera. It flies through level geometry and lets you observe what is going
+//! on in the level while the player stands still. Debug only, so it is never saved.
+
+use crate::control_scheme::{ControlButton, ControlScheme};
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        pool::Handle,
+    },
+    event::{DeviceEvent, ElementState, Event, MouseScrollDelta, WindowEvent},
+    scene::{
+        base::BaseBuilder, camera::CameraBuilder, graph::Graph, node::Node,
+        transform::TransformBuilder,
+    },
+};
+
+const MIN_SPEED: f32 = 1.0;
+const MAX_SPEED: f32 = 50.0;
+
+#[derive(Default)]
+struct Controller {
+    move_forward: bool,
+    move_backward: bool,
+    move_left: bool,
+    move_right: bool,
+}
+
+pub struct FreeCamera {
+    camera: Handle<Node>,
+    controller: Controller,
+    yaw: f32,
+    pitch: f32,
+    /// Speed in units per second, adjusted by mouse wheel.
+    speed: f32,
+}
+
+impl FreeCamera {
+    /// Creates new camera at given position and orientation, usually it is taken from
+    /// currently active camera so there is no visible "jump".
+    pub fn new(graph: &mut Graph, position: Vector3<f32>, yaw: f32, pitch: f32) -> Self {
+        let camera = CameraBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            ),
+        )
+        .build(graph);
+
+        Self {
+            camera,
+            controller: Default::default(),
+            yaw,
+            pitch,
+            speed: 5.0,
+        }
+    }
+
+    pub fn camera(&self) -> Handle<Node> {
+        self.camera
+    }
+
+    pub fn process_input_event(&mut self, event: &Event<()>, control_scheme: &ControlScheme) {
+        let mut control_button = None;
+        let mut control_button_state = ElementState::Released;
+
+        if let Event::DeviceEvent { event, .. } = event {
+            match event {
+                DeviceEvent::MouseMotion { delta } => {
+                    self.yaw -= delta.0 as f32 * control_scheme.mouse_sens;
+
+                    let sens = if control_scheme.mouse_y_inverse {
+                        -control_scheme.mouse_sens
+                    } else {
+                        control_scheme.mouse_sens
+                    };
+
+                    self.pitch = (self.pitch + delta.1 as f32 * sens).max(-90.0).min(90.0);
+                }
+                DeviceEvent::Button { button, state } => {
+                    control_button = Some(ControlButton::Mouse(*button as u16));
+                    control_button_state = *state;
+                }
+                DeviceEvent::MouseWheel {
+                    delta: MouseScrollDelta::LineDelta(_, y),
+                } => {
+                    self.speed = (self.speed * (1.0 + 0.1 * *y))
+                        .max(MIN_SPEED)
+                        .min(MAX_SPEED);
+                }
+                _ => (),
+            }
+        }
+
+        if let Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { input, .. },
+            ..
+        } = event
+        {
+            if let Some(code) = input.virtual_keycode {
+                control_button = Some(ControlButton::Key(code));
+                control_button_state = input.state;
+            }
+        }
+
+        if let Some(control_button) = control_button {
+            let state = control_button_state == ElementState::Pressed;
+            if control_button == control_scheme.move_forward.button {
+                self.controller.move_forward = state;
+            } else if control_button == control_scheme.move_backward.button {
+                self.controller.move_backward = state;
+            } else if control_button == control_scheme.move_left.button {
+                self.controller.move_left = state;
+            } else if control_button == control_scheme.move_right.button {
+                self.controller.move_right = state;
+            }
+        }
+    }
+
+    pub fn update(&mut self, graph: &mut Graph, dt: f32) {
+        let node = &mut graph[self.camera];
+
+        let look = node.look_vector();
+        let side = node.side_vector();
+
+        let mut velocity = Vector3::default();
+        if self.controller.move_forward {
+            velocity += look;
+        }
+        if self.controller.move_backward {
+            velocity -= look;
+        }
+        if self.controller.move_left {
+            velocity += side;
+        }
+        if self.controller.move_right {
+            velocity -= side;
+        }
+
+        let transform = node.local_transform_mut();
+        if let Some(direction) = velocity.try_normalize(std::f32::EPSILON) {
+            transform.offset(direction.scale(self.speed * dt));
+        }
+        transform.set_rotation(
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw.to_radians())
+                * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.pitch.to_radians()),
+        );
+    }
+
+    pub fn clean_up(&mut self, graph: &mut Graph) {
+        graph.remove_node(self.camera);
+    }
+}
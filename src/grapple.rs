@@ -0,0 +1,202 @@
+//! Grappling hook. It attaches to level geometry in front of the player and pulls player
+//! towards the anchor until the key is released or the player gets close enough. Velocity
+//! gained from the pull is kept after release, until the player lands.
+
+use crate::query_buffer::QueryBufferPool;
+use fyrox::{
+    core::{
+        algebra::{Matrix4, Point3, UnitQuaternion, Vector3},
+        math::ray::Ray,
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        base::BaseBuilder,
+        collider::{ColliderShape, InteractionGroups},
+        graph::{physics::RayCastOptions, Graph},
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+            MeshBuilder,
+        },
+        node::Node,
+        Scene,
+    },
+};
+
+/// Max distance at which hook can attach, in meters.
+pub const GRAPPLE_RANGE: f32 = 30.0;
+/// Hook lets go when player gets this close to the anchor.
+const RELEASE_DISTANCE: f32 = 1.5;
+/// Pull acceleration per meter of cable, makes the cable behave like a stiff spring.
+const STIFFNESS: f32 = 3.0;
+const MAX_PULL_ACCELERATION: f32 = 35.0;
+const MAX_PULL_SPEED: f32 = 16.0;
+const CABLE_THICKNESS: f32 = 0.015;
+
+#[derive(Visit)]
+pub struct Grapple {
+    cable: Handle<Node>,
+    attached: bool,
+    anchor: Vector3<f32>,
+    /// Collider hook is attached to, hook lets go if it disappears.
+    anchor_collider: Handle<Node>,
+    /// Set when player let go mid-air, movement input does not override velocity until
+    /// player lands.
+    flying: bool,
+}
+
+impl Default for Grapple {
+    fn default() -> Self {
+        Self {
+            cable: Handle::NONE,
+            attached: false,
+            anchor: Vector3::default(),
+            anchor_collider: Handle::NONE,
+            flying: false,
+        }
+    }
+}
+
+impl Grapple {
+    pub fn new(scene: &mut Scene) -> Self {
+        // Unit cylinder along Y axis, it is stretched between player and anchor every frame.
+        let cable = MeshBuilder::new(BaseBuilder::new().with_visibility(false))
+            .with_surfaces(vec![SurfaceBuilder::new(SurfaceSharedData::new(
+                SurfaceData::make_cylinder(6, 1.0, 1.0, false, &Matrix4::identity()),
+            ))
+            .build()])
+            .with_cast_shadows(false)
+            .build(&mut scene.graph);
+
+        Self {
+            cable,
+            ..Default::default()
+        }
+    }
+
+    /// Saves made before grappling hook have no cable, hook is created again in such case.
+    /// Returns true if hook was created.
+    pub fn restore_missing_nodes(&mut self, scene: &mut Scene) -> bool {
+        if scene.graph.is_valid_handle(self.cable) {
+            return false;
+        }
+        *self = Self::new(scene);
+        true
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    /// Returns true if movement input must not override velocity of player.
+    pub fn controls_velocity(&self) -> bool {
+        self.attached || self.flying
+    }
+
+    /// Casts a ray from `origin` along `direction` and attaches hook to the first piece of
+    /// static level geometry it hits. `owner_collider` is ignored by the ray.
+    pub fn fire(
+        &mut self,
+        graph: &mut Graph,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        owner_collider: Handle<Node>,
+    ) -> bool {
+        let ray = Ray::new(origin, direction.scale(GRAPPLE_RANGE));
+        let hit = QueryBufferPool::with(|query_buffer| {
+            graph.physics.cast_ray(
+                RayCastOptions {
+                    ray_origin: Point3::from(ray.origin),
+                    ray_direction: ray.dir,
+                    max_len: GRAPPLE_RANGE,
+                    groups: InteractionGroups::default(),
+                    sort_results: true,
+                },
+                query_buffer,
+            );
+            query_buffer
+                .iter()
+                .find(|hit| hit.collider != owner_collider)
+                .map(|hit| (hit.collider, hit.position.coords))
+        });
+
+        match hit {
+            // Only level geometry is good as an anchor, actors and items move or vanish.
+            Some((collider, position))
+                if matches!(
+                    graph[collider].as_collider().shape(),
+                    ColliderShape::Trimesh(_)
+                ) =>
+            {
+                self.attached = true;
+                self.anchor = position;
+                self.anchor_collider = collider;
+                graph[self.cable].set_visibility(true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lets go of the anchor, velocity of player is kept as is.
+    pub fn release(&mut self, graph: &mut Graph, has_ground_contact: bool) {
+        if self.attached {
+            self.attached = false;
+            self.flying = !has_ground_contact;
+            graph[self.cable].set_visibility(false);
+        }
+    }
+
+    /// Pulls player body towards the anchor and stretches the cable from `hand` to it.
+    pub fn update(
+        &mut self,
+        graph: &mut Graph,
+        body: Handle<Node>,
+        hand: Vector3<f32>,
+        has_ground_contact: bool,
+        dt: f32,
+    ) {
+        if has_ground_contact {
+            self.flying = false;
+        }
+
+        if !self.attached {
+            return;
+        }
+
+        // Anchor surface could be removed, a hook hanging in the air is not an option.
+        if !graph.is_valid_handle(self.anchor_collider) {
+            self.release(graph, has_ground_contact);
+            return;
+        }
+
+        let body = graph[body].as_rigid_body_mut();
+        let to_anchor = self.anchor - body.global_position();
+        let distance = to_anchor.norm();
+        if distance <= RELEASE_DISTANCE {
+            self.release(graph, has_ground_contact);
+            return;
+        }
+
+        let acceleration = (distance * STIFFNESS).min(MAX_PULL_ACCELERATION);
+        let mut velocity = body.lin_vel() + to_anchor.scale(acceleration * dt / distance);
+        if velocity.norm() > MAX_PULL_SPEED {
+            velocity = velocity.normalize().scale(MAX_PULL_SPEED);
+        }
+        body.set_lin_vel(velocity);
+
+        let cable = self.anchor - hand;
+        let length = cable.norm();
+        let rotation = UnitQuaternion::rotation_between(&Vector3::y(), &cable)
+            .unwrap_or_else(UnitQuaternion::identity);
+        graph[self.cable]
+            .local_transform_mut()
+            .set_position(hand)
+            .set_rotation(rotation)
+            .set_scale(Vector3::new(CABLE_THICKNESS, length, CABLE_THICKNESS));
+    }
+
+    pub fn clean_up(&mut self, scene: &mut Scene) {
+        scene.remove_node(self.cable);
+    }
+}
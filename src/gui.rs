@@ -2,12 +2,49 @@
 //! However most of the styles are used from dark theme of fyrox-ui library so there
 //! is not much.
 
-use fyrox::core::pool::Handle;
+use crate::logger::Logger;
+use fyrox::core::{
+    algebra::{Vector2, Vector3, Vector4},
+    futures::executor::block_on,
+    parking_lot::Mutex,
+    pool::Handle,
+};
+use fyrox::event::{Event, WindowEvent};
 use fyrox::gui::{
-    check_box::CheckBoxBuilder, scroll_bar::ScrollBarBuilder, scroll_viewer::ScrollViewerBuilder,
-    widget::WidgetBuilder, BuildContext, HorizontalAlignment, Orientation, Thickness, UiNode,
+    check_box::CheckBoxBuilder,
+    message::MessageDirection,
+    scroll_bar::ScrollBarBuilder,
+    scroll_viewer::ScrollViewerBuilder,
+    ttf::{Font, SharedFont},
+    widget::{WidgetBuilder, WidgetMessage},
+    BuildContext, HorizontalAlignment, Orientation, Thickness, UiNode, UserInterface,
     VerticalAlignment,
 };
+use fyrox::scene::camera::Camera;
+use fyrox::utils::log::MessageKind;
+use std::{path::Path, sync::Arc};
+
+/// Font of game menus and HUD.
+pub const FONT_PATH: &str = "data/ui/SquaresBold.ttf";
+
+/// Loads a font of given height, built-in font of the UI is used if the file is missing or
+/// broken, so the game stays usable without its data files.
+pub fn load_font(ctx: &BuildContext, path: &str, height: f32) -> SharedFont {
+    match block_on(Font::from_file(
+        Path::new(path),
+        height,
+        Font::default_char_set(),
+    )) {
+        Ok(font) => SharedFont(Arc::new(Mutex::new(font))),
+        Err(_) => {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!("Unable to load font {}, default font is used.", path),
+            );
+            ctx.default_font()
+        }
+    }
+}
 
 pub struct ScrollBarData {
     pub min: f32,
@@ -21,6 +58,64 @@ pub struct ScrollBarData {
     pub orientation: Orientation,
 }
 
+/// Size of full screen UI. Window resizes are tracked in this single place, before any screen
+/// gets a chance to consume the event (console and chat swallow input while active), then the
+/// size is pushed to every screen by its `resize` method.
+pub struct UiLayout {
+    size: Vector2<f32>,
+}
+
+impl UiLayout {
+    pub fn new(frame_size: (u32, u32)) -> Self {
+        Self {
+            size: Vector2::new(frame_size.0 as f32, frame_size.1 as f32),
+        }
+    }
+
+    pub fn size(&self) -> Vector2<f32> {
+        self.size
+    }
+
+    /// Returns true if the event changed size of the window, screens must be resized then.
+    pub fn process_event(&mut self, event: &Event<()>) -> bool {
+        if let Event::WindowEvent {
+            event: WindowEvent::Resized(new_size),
+            ..
+        } = event
+        {
+            self.resize(new_size.width, new_size.height)
+        } else {
+            false
+        }
+    }
+
+    /// Takes new size of the window, returns true if layout has changed.
+    fn resize(&mut self, width: u32, height: u32) -> bool {
+        // Minimized window reports zero size, layout of zero size is useless.
+        let size = Vector2::new(width.max(1) as f32, height.max(1) as f32);
+        if size != self.size {
+            self.size = size;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Stretches full screen widget over the window after the window was resized.
+pub fn fit_to_window(ui: &mut UserInterface, widget: Handle<UiNode>, size: Vector2<f32>) {
+    ui.send_message(WidgetMessage::width(
+        widget,
+        MessageDirection::ToWidget,
+        size.x,
+    ));
+    ui.send_message(WidgetMessage::height(
+        widget,
+        MessageDirection::ToWidget,
+        size.y,
+    ));
+}
+
 pub fn create_scroll_bar(ctx: &mut BuildContext, data: ScrollBarData) -> Handle<UiNode> {
     let mut wb = WidgetBuilder::new();
     match data.orientation {
@@ -94,3 +189,53 @@ pub fn create_scroll_viewer(ctx: &mut BuildContext) -> Handle<UiNode> {
         ))
         .build(ctx)
 }
+
+/// Projects a point in the world into screen coordinates of user interface, origin is in the
+/// top left corner. Returns `None` for points behind the camera. Anything that marks a place
+/// in the world on HUD is positioned with it.
+pub fn world_to_screen(
+    camera: &Camera,
+    point: Vector3<f32>,
+    screen_size: Vector2<f32>,
+) -> Option<Vector2<f32>> {
+    let clip = camera.view_projection_matrix() * Vector4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.xy() / clip.w;
+    Some(Vector2::new(
+        (ndc.x + 1.0) * 0.5 * screen_size.x,
+        (1.0 - ndc.y) * 0.5 * screen_size.y,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_follows_window_size() {
+        let mut layout = UiLayout::new((1280, 720));
+        assert_eq!(layout.size(), Vector2::new(1280.0, 720.0));
+
+        // 4K and ultrawide.
+        assert!(layout.resize(3840, 2160));
+        assert_eq!(layout.size(), Vector2::new(3840.0, 2160.0));
+        assert!(layout.resize(3440, 1440));
+        assert_eq!(layout.size(), Vector2::new(3440.0, 1440.0));
+
+        // Same size again does not resize screens.
+        assert!(!layout.resize(3440, 1440));
+    }
+
+    #[test]
+    fn minimized_window_keeps_layout_usable() {
+        let mut layout = UiLayout::new((800, 600));
+        assert!(layout.resize(0, 0));
+        assert_eq!(layout.size(), Vector2::new(1.0, 1.0));
+        assert!(!layout.resize(0, 0));
+
+        assert!(layout.resize(320, 0));
+        assert_eq!(layout.size(), Vector2::new(320.0, 1.0));
+    }
+}
@@ -0,0 +1,172 @@
+//! Small health bars that hang above bots which were damaged recently or are under crosshair,
+//! so state of a fight can be read at a glance. Bars are ordinary widgets that follow
+//! projected positions of bots' heads.
+
+use crate::{character::Team, gui};
+use fyrox::{
+    core::{
+        algebra::{Vector2, Vector3},
+        color::Color,
+        pool::Handle,
+    },
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        canvas::CanvasBuilder,
+        message::MessageDirection,
+        widget::{WidgetBuilder, WidgetMessage},
+        BuildContext, HorizontalAlignment, UiNode, UserInterface,
+    },
+    scene::camera::Camera,
+};
+
+/// Amount of bars shown at once, only the nearest bots get them.
+pub const MAX_BARS: usize = 8;
+/// Time in seconds for which bar stays visible after bot was damaged.
+pub const SHOW_TIME: f64 = 4.0;
+const BAR_WIDTH: f32 = 50.0;
+const BAR_HEIGHT: f32 = 5.0;
+
+/// Bot that needs a health bar, see [`crate::level::Level::health_bars`].
+pub struct HealthBar {
+    /// Point in the world right above the head.
+    pub position: Vector3<f32>,
+    /// Health in 0.0..=1.0 range.
+    pub health: f32,
+    /// Team of the bot, `Team::None` in modes without teams.
+    pub team: Team,
+}
+
+fn bar_color(team: Team) -> Color {
+    match team {
+        Team::None => Color::opaque(240, 200, 40),
+        Team::Red => Color::opaque(230, 30, 30),
+        Team::Blue => Color::opaque(40, 110, 240),
+    }
+}
+
+/// Widgets of a single bar and the last values sent to them.
+struct Slot {
+    background: Handle<UiNode>,
+    fill: Handle<UiNode>,
+    shown: Option<(u32, Team)>,
+}
+
+pub struct HealthBars {
+    root: Handle<UiNode>,
+    slots: Vec<Slot>,
+    enabled: bool,
+}
+
+impl HealthBars {
+    pub fn new(ctx: &mut BuildContext, frame_size: (u32, u32)) -> Self {
+        let slots = (0..MAX_BARS)
+            .map(|_| {
+                let fill = BorderBuilder::new(
+                    WidgetBuilder::new()
+                        .with_width(BAR_WIDTH)
+                        .with_horizontal_alignment(HorizontalAlignment::Left)
+                        .with_background(Brush::Solid(bar_color(Team::None))),
+                )
+                .build(ctx);
+                let background = BorderBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_width(BAR_WIDTH)
+                        .with_height(BAR_HEIGHT)
+                        .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 140)))
+                        .with_child(fill),
+                )
+                .build(ctx);
+                Slot {
+                    background,
+                    fill,
+                    shown: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let root = CanvasBuilder::new(
+            WidgetBuilder::new()
+                .with_width(frame_size.0 as f32)
+                .with_height(frame_size.1 as f32)
+                .with_hit_test_visibility(false)
+                .with_children(slots.iter().map(|slot| slot.background)),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            slots,
+            enabled: true,
+        }
+    }
+
+    pub fn root(&self) -> Handle<UiNode> {
+        self.root
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Places bars on screen, bars are hidden while there is no camera to project them with.
+    pub fn update(
+        &mut self,
+        ui: &mut UserInterface,
+        camera: Option<&Camera>,
+        screen_size: Vector2<f32>,
+        bars: &[HealthBar],
+    ) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let placement = bars.get(i).filter(|_| self.enabled).and_then(|bar| {
+                camera
+                    .and_then(|camera| gui::world_to_screen(camera, bar.position, screen_size))
+                    .map(|screen_position| (bar, screen_position))
+            });
+
+            let (bar, screen_position) = match placement {
+                Some(placement) => placement,
+                None => {
+                    if slot.shown.take().is_some() {
+                        ui.send_message(WidgetMessage::visibility(
+                            slot.background,
+                            MessageDirection::ToWidget,
+                            false,
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            let health = bar.health.clamp(0.0, 1.0);
+            let shown = ((health * BAR_WIDTH).round() as u32, bar.team);
+            let previous = slot.shown.replace(shown);
+            if previous.is_none() {
+                ui.send_message(WidgetMessage::visibility(
+                    slot.background,
+                    MessageDirection::ToWidget,
+                    true,
+                ));
+            }
+            if previous != Some(shown) {
+                ui.send_message(WidgetMessage::width(
+                    slot.fill,
+                    MessageDirection::ToWidget,
+                    BAR_WIDTH * health,
+                ));
+                ui.send_message(WidgetMessage::background(
+                    slot.fill,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(bar_color(bar.team)),
+                ));
+            }
+
+            ui.send_message(WidgetMessage::desired_position(
+                slot.background,
+                MessageDirection::ToWidget,
+                screen_position - Vector2::new(BAR_WIDTH * 0.5, BAR_HEIGHT),
+            ));
+        }
+    }
+}
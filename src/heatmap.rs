@@ -0,0 +1,181 @@
+//! Heatmap of actor positions for map design. While recording, position of every actor is
+//! sampled once per second into a grid over world bounds of the map. Grid resolution is
+//! fixed, so memory does not depend on match length or map size. Visited cells are shown in
+//! debug drawing, colored from blue (rarely visited) to red (most visited); cells that stay
+//! empty are dead zones of the map.
+
+use fyrox::{
+    core::{algebra::Vector3, color::Color, math::aabb::AxisAlignedBoundingBox},
+    scene::debug::{Line, SceneDrawingContext},
+};
+use std::{fmt::Write as _, path::Path};
+
+/// Amount of cells along the longest horizontal side of world bounds.
+const RESOLUTION: usize = 128;
+/// Cells are never smaller than this, small maps get less cells.
+const MIN_CELL_SIZE: f32 = 1.0;
+/// Time between samples in seconds.
+const SAMPLE_INTERVAL: f32 = 1.0;
+/// Cells are drawn a bit above average height of samples, so floor does not hide them.
+const DRAW_OFFSET: f32 = 0.1;
+
+pub struct Heatmap {
+    /// World position of the corner of the first cell.
+    origin: Vector3<f32>,
+    cell_size: f32,
+    width: usize,
+    depth: usize,
+    counts: Vec<u32>,
+    /// Sum of heights of samples of every cell, cells are drawn at average height.
+    heights: Vec<f32>,
+    recording: bool,
+    sample_timer: f32,
+}
+
+impl Heatmap {
+    pub fn new(world_bounds: &AxisAlignedBoundingBox) -> Self {
+        let size = world_bounds.max - world_bounds.min;
+        let cell_size = (size.x.max(size.z) / RESOLUTION as f32).max(MIN_CELL_SIZE);
+        let cells = |length: f32| ((length / cell_size).ceil() as usize).clamp(1, RESOLUTION);
+        let (width, depth) = (cells(size.x), cells(size.z));
+        Self {
+            origin: world_bounds.min,
+            cell_size,
+            width,
+            depth,
+            counts: vec![0; width * depth],
+            heights: vec![0.0; width * depth],
+            recording: false,
+            sample_timer: 0.0,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+        // First sample is taken right away.
+        self.sample_timer = 0.0;
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.heights.iter_mut().for_each(|height| *height = 0.0);
+    }
+
+    /// Total amount of samples in all cells.
+    pub fn sample_count(&self) -> u64 {
+        self.counts.iter().map(|&count| count as u64).sum()
+    }
+
+    fn cell_index(&self, position: Vector3<f32>) -> Option<usize> {
+        let local = (position - self.origin).scale(1.0 / self.cell_size);
+        if local.x < 0.0 || local.z < 0.0 {
+            return None;
+        }
+        let (x, z) = (local.x as usize, local.z as usize);
+        if x < self.width && z < self.depth {
+            Some(z * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Center of the cell at floor level of the grid.
+    fn cell_center(&self, index: usize) -> Vector3<f32> {
+        let (x, z) = (index % self.width, index / self.width);
+        self.origin
+            + Vector3::new(
+                (x as f32 + 0.5) * self.cell_size,
+                0.0,
+                (z as f32 + 0.5) * self.cell_size,
+            )
+    }
+
+    /// Samples given positions once per [`SAMPLE_INTERVAL`] while recording.
+    pub fn update<I: IntoIterator<Item = Vector3<f32>>>(&mut self, positions: I, delta: f32) {
+        if !self.recording {
+            return;
+        }
+        self.sample_timer -= delta;
+        if self.sample_timer > 0.0 {
+            return;
+        }
+        self.sample_timer += SAMPLE_INTERVAL;
+
+        for position in positions {
+            if let Some(index) = self.cell_index(position) {
+                self.counts[index] += 1;
+                self.heights[index] += position.y;
+            }
+        }
+    }
+
+    pub fn debug_draw(&self, context: &mut SceneDrawingContext) {
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return;
+        }
+
+        let half = self.cell_size * 0.5;
+        for (index, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let mut center = self.cell_center(index);
+            center.y = self.heights[index] / count as f32 + DRAW_OFFSET;
+            let color = heat_color(count as f32 / max as f32);
+            let corners = [
+                center + Vector3::new(-half, 0.0, -half),
+                center + Vector3::new(half, 0.0, -half),
+                center + Vector3::new(half, 0.0, half),
+                center + Vector3::new(-half, 0.0, half),
+            ];
+            for (&begin, &end) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+                context.add_line(Line { begin, end, color });
+            }
+            // Diagonal makes hot cells stand out in a dense grid.
+            context.add_line(Line {
+                begin: corners[0],
+                end: corners[2],
+                color,
+            });
+        }
+    }
+
+    /// Writes visited cells as CSV with `x,y,z,samples` columns, where x and z are center of
+    /// the cell and y is average height of samples in it.
+    pub fn write_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut csv = String::from("x,y,z,samples\n");
+        for (index, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let center = self.cell_center(index);
+            let _ = writeln!(
+                csv,
+                "{:.2},{:.2},{:.2},{}",
+                center.x,
+                self.heights[index] / count as f32,
+                center.z,
+                count
+            );
+        }
+        std::fs::write(path, csv)
+    }
+}
+
+/// Blue for cold values, through green to red for hot ones, `heat` is in 0..=1 range.
+fn heat_color(heat: f32) -> Color {
+    let heat = heat.clamp(0.0, 1.0);
+    let (r, g, b) = if heat < 0.5 {
+        let t = heat * 2.0;
+        (0.0, t, 1.0 - t)
+    } else {
+        let t = (heat - 0.5) * 2.0;
+        (t, 1.0 - t, 0.0)
+    };
+    Color::opaque((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
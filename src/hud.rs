@@ -1,44 +1,336 @@
 use crate::{
+    character::{ArmorTier, BASE_HEALTH},
+    chat::Chat,
+    control_scheme::ControlButton,
+    damage_numbers::DamageNumbers,
+    definitions::Definitions,
+    gui,
+    health_bars::{HealthBar, HealthBars},
+    item_timers::{ItemTimer, ItemTimers},
     leader_board::{LeaderBoard, LeaderBoardUI},
+    level::{AimTarget, LevelStatus, MatchPhase},
     message::Message,
+    notifications::{NotificationSeverity, Notifications},
+    weapon::{WeaponCrosshair, WeaponKind, DEFAULT_CROSSHAIR},
     GameTime, MatchOptions,
 };
-use fyrox::core::parking_lot::Mutex;
 use fyrox::{
-    core::{color::Color, pool::Handle},
-    engine::Engine,
-    event::{Event, WindowEvent},
+    core::{algebra::Vector2, color::Color, pool::Handle},
+    engine::{resource_manager::ResourceManager, Engine},
+    event::Event,
     gui::{
         border::BorderBuilder,
         brush::Brush,
+        canvas::CanvasBuilder,
         grid::{Column, GridBuilder, Row},
-        image::ImageBuilder,
-        message::MessageDirection,
+        image::{ImageBuilder, ImageMessage},
+        message::{MessageDirection, UiMessage},
         stack_panel::StackPanelBuilder,
         text::{TextBuilder, TextMessage},
-        ttf::{Font, SharedFont},
         widget::{WidgetBuilder, WidgetMessage},
         HorizontalAlignment, Orientation, Thickness, VerticalAlignment,
     },
-    gui::{UiNode, UserInterface},
+    gui::{BuildContext, UiNode, UserInterface},
+    scene::camera::Camera,
     utils,
 };
-use std::{collections::VecDeque, path::Path, sync::Arc};
+use std::{path::PathBuf, sync::mpsc::Sender};
+
+const WEAPON_SLOT_WIDTH: f32 = 70.0;
+const WEAPON_SLOT_HEIGHT: f32 = 30.0;
+/// Active weapon's slot is enlarged by this factor.
+const ACTIVE_WEAPON_SLOT_SCALE: f32 = 1.3;
+/// How long a banner stays on screen, in seconds.
+const BANNER_TIME: f32 = 2.0;
+/// How long name of newly selected weapon stays on screen, in seconds.
+const WEAPON_NAME_TIME: f32 = 1.5;
+/// Width of health bar of teammate under crosshair.
+const AIM_HEALTH_BAR_WIDTH: f32 = 80.0;
+const VALUE_BAR_WIDTH: f32 = 170.0;
+const VALUE_BAR_HEIGHT: f32 = 35.0;
+/// Time in seconds for trailing layer of a value bar to reach new value.
+const VALUE_BAR_TRAIL_TIME: f32 = 0.5;
+const CROSSHAIR_COLOR: Color = Color::opaque(255, 255, 255);
+const CROSSHAIR_ENEMY_COLOR: Color = Color::opaque(230, 30, 30);
+const CROSSHAIR_TEAMMATE_COLOR: Color = Color::opaque(40, 220, 60);
+/// Amount of segments in the ring around crosshair that shows how much of grenade fuse has
+/// burnt down.
+const CHARGE_SEGMENTS: usize = 12;
+const CHARGE_RING_RADIUS: f32 = 26.0;
+const CHARGE_SEGMENT_SIZE: f32 = 6.0;
+const CHARGE_COLOR: Color = Color::opaque(255, 160, 0);
+const CHARGE_EMPTY_COLOR: Color = Color::from_rgba(0, 0, 0, 120);
+const AMMO_COLOR: Color = Color::opaque(79, 79, 255);
+const AMMO_LOW_COLOR: Color = Color::opaque(255, 210, 0);
+const AMMO_CRITICAL_COLOR: Color = Color::opaque(220, 30, 30);
+/// Ammo counter turns yellow below this fraction of max ammo of current weapon.
+const AMMO_LOW_FRACTION: f32 = 0.25;
+/// Ammo counter turns red below this fraction of max ammo of current weapon.
+const AMMO_CRITICAL_FRACTION: f32 = 0.1;
+/// How many times per second counter of empty weapon pulses.
+const AMMO_PULSE_FREQUENCY: f32 = 2.0;
+/// Amount of lines of bot intent feed shown at once.
+const INTENT_FEED_ENTRIES: usize = 6;
+const INTENT_FEED_COLOR: Color = Color::opaque(140, 200, 255);
+
+/// Amount of ammo in current weapon relative to its max ammo.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum AmmoLevel {
+    Normal,
+    Low,
+    Critical,
+    Empty,
+}
+
+impl AmmoLevel {
+    fn new(ammo: u32, max_ammo: u32) -> Self {
+        let fraction = ammo as f32 / max_ammo.max(1) as f32;
+        if ammo == 0 {
+            AmmoLevel::Empty
+        } else if fraction < AMMO_CRITICAL_FRACTION {
+            AmmoLevel::Critical
+        } else if fraction < AMMO_LOW_FRACTION {
+            AmmoLevel::Low
+        } else {
+            AmmoLevel::Normal
+        }
+    }
+
+    /// Amount of warning thresholds ammo is below. Empty weapon is below the same thresholds
+    /// as critical one, it only pulses.
+    fn thresholds_crossed(self) -> u32 {
+        match self {
+            AmmoLevel::Normal => 0,
+            AmmoLevel::Low => 1,
+            AmmoLevel::Critical | AmmoLevel::Empty => 2,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            AmmoLevel::Normal => AMMO_COLOR,
+            AmmoLevel::Low => AMMO_LOW_COLOR,
+            AmmoLevel::Critical | AmmoLevel::Empty => AMMO_CRITICAL_COLOR,
+        }
+    }
+}
+
+/// Slot of weapon bar. Slots are created once and reused, [`Hud::sync_weapons`] only changes
+/// their contents and hides unused ones.
+struct WeaponSlot {
+    border: Handle<UiNode>,
+    text: Handle<UiNode>,
+    last: Option<(WeaponKind, u32, bool)>,
+}
+
+/// Bar behind a number on HUD that is filled proportionally to the number. The layer that
+/// shows new value snaps to it right away, the other one follows over
+/// [`VALUE_BAR_TRAIL_TIME`], so size of a hit or a heal stays visible for a moment.
+struct ValueBar {
+    front: Handle<UiNode>,
+    back: Handle<UiNode>,
+    /// Current value in 0.0..=1.0 range.
+    value: f32,
+    /// Value shown by trailing layer, it follows `value`.
+    trail: f32,
+    /// Speed of trailing layer, in fractions of full bar per second.
+    trail_speed: f32,
+    dirty: bool,
+}
+
+impl ValueBar {
+    /// Builds bar with given text on top of it, returns the bar and its root widget.
+    fn build(ctx: &mut BuildContext, text: Handle<UiNode>, color: Color) -> (Self, Handle<UiNode>) {
+        let back = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(VALUE_BAR_WIDTH)
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_background(Brush::Solid(Color::from_rgba(255, 255, 255, 90))),
+        )
+        .build(ctx);
+        let front = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(VALUE_BAR_WIDTH)
+                .with_horizontal_alignment(HorizontalAlignment::Left)
+                .with_background(Brush::Solid(color)),
+        )
+        .build(ctx);
+        let root = GridBuilder::new(
+            WidgetBuilder::new()
+                .with_width(VALUE_BAR_WIDTH)
+                .with_height(VALUE_BAR_HEIGHT)
+                .with_child(back)
+                .with_child(front)
+                .with_child(text),
+        )
+        .build(ctx);
+        let bar = Self {
+            front,
+            back,
+            value: 1.0,
+            trail: 1.0,
+            trail_speed: 0.0,
+            dirty: false,
+        };
+        (bar, root)
+    }
+
+    fn set_value(&mut self, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        if value == self.value {
+            return;
+        }
+        self.value = value;
+        self.trail_speed = (self.trail - value).abs() / VALUE_BAR_TRAIL_TIME;
+        self.dirty = true;
+    }
+
+    fn set_color(&self, ui: &mut UserInterface, color: Color) {
+        ui.send_message(WidgetMessage::background(
+            self.front,
+            MessageDirection::ToWidget,
+            Brush::Solid(color),
+        ));
+    }
+
+    fn update(&mut self, ui: &mut UserInterface, dt: f32) {
+        if self.trail != self.value {
+            let step = self.trail_speed * dt;
+            self.trail = if self.trail > self.value {
+                (self.trail - step).max(self.value)
+            } else {
+                (self.trail + step).min(self.value)
+            };
+            self.dirty = true;
+        }
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        // Loss is shown by trailing layer above current value, gain by current value above
+        // trailing layer.
+        ui.send_message(WidgetMessage::width(
+            self.front,
+            MessageDirection::ToWidget,
+            VALUE_BAR_WIDTH * self.value.min(self.trail),
+        ));
+        ui.send_message(WidgetMessage::width(
+            self.back,
+            MessageDirection::ToWidget,
+            VALUE_BAR_WIDTH * self.value.max(self.trail),
+        ));
+    }
+}
+
+/// Color of health bar, it shifts from green to red as health drops.
+fn health_bar_color(fraction: f32) -> Color {
+    let fraction = fraction.clamp(0.0, 1.0);
+    Color::from_rgba(
+        (220.0 - 180.0 * fraction) as u8,
+        (30.0 + 170.0 * fraction) as u8,
+        40,
+        150,
+    )
+}
+
+fn armor_bar_color(tier: ArmorTier) -> Color {
+    match tier {
+        ArmorTier::Green => Color::from_rgba(60, 200, 60, 150),
+        ArmorTier::Yellow => Color::from_rgba(255, 210, 0, 150),
+        ArmorTier::Red => Color::from_rgba(220, 30, 30, 150),
+    }
+}
 
 pub struct Hud {
     root: Handle<UiNode>,
     health: Handle<UiNode>,
+    health_bar: ValueBar,
     armor: Handle<UiNode>,
+    armor_bar: ValueBar,
     ammo: Handle<UiNode>,
     time: Handle<UiNode>,
-    message: Handle<UiNode>,
-    message_queue: VecDeque<String>,
-    message_timeout: f32,
+    notifications: Notifications,
+    /// What bots are busy with, see [`crate::bot::BotIntent`]. Hidden unless turned on in
+    /// options.
+    intent_feed: Notifications,
+    intent_panel: Handle<UiNode>,
+    show_bot_intents: bool,
+    damage_numbers: DamageNumbers,
+    health_bars: HealthBars,
+    item_timers: ItemTimers,
+    banner: Handle<UiNode>,
+    /// Banner that will be shown on next update.
+    pending_banner: Option<String>,
+    banner_timeout: f32,
+    /// Name of current weapon above ammo counter, shown for a while after weapon switch.
+    weapon_name: Handle<UiNode>,
+    weapon_name_timeout: f32,
     leader_board: LeaderBoardUI,
+    chat: Chat,
     match_limit: Handle<UiNode>,
     first_score: Handle<UiNode>,
     second_score: Handle<UiNode>,
+    /// Name of the leader under the scores.
+    leader: Handle<UiNode>,
     died: Handle<UiNode>,
+    debug_watermark: Handle<UiNode>,
+    /// Shown while match is frozen with pause command.
+    paused: Handle<UiNode>,
+    /// Fullscreen tint that is shown while player stands in lava.
+    burning: Handle<UiNode>,
+    /// Image of crosshair depends on current weapon, see [`Hud::set_crosshair`].
+    crosshair: Handle<UiNode>,
+    /// Name and health of teammate under crosshair.
+    aim_info: Handle<UiNode>,
+    aim_name: Handle<UiNode>,
+    aim_health_bar: Handle<UiNode>,
+    /// Sight ring that replaces crosshair while player is zoomed in.
+    scope: Handle<UiNode>,
+    /// Ring of segments around crosshair that fills up while grenade is cooked.
+    charge_ring: Handle<UiNode>,
+    charge_segments: Vec<Handle<UiNode>>,
+    /// Warning that is shown when flashlight battery is almost empty.
+    low_battery: Handle<UiNode>,
+    /// Hint about what Use key does, for example weapon swap.
+    prompt: Handle<UiNode>,
+    weapon_slots: Vec<WeaponSlot>,
+    resource_manager: ResourceManager,
+    // Last values sent to widgets, text is changed only when value changes. Otherwise every
+    // frame would format new strings and invalidate layout of widgets.
+    last_health: Option<f32>,
+    last_armor: Option<f32>,
+    last_armor_tier: Option<ArmorTier>,
+    last_ammo: Option<u32>,
+    /// Current weapon and its ammo level. Click is played when ammo of the same weapon drops
+    /// below one more threshold, switching weapons only changes color.
+    ammo_level: Option<(WeaponKind, AmmoLevel)>,
+    /// Time since ammo counter started pulsing, in seconds.
+    ammo_pulse: f32,
+    /// Low ammo click must be played, see [`Hud::take_low_ammo_click`].
+    low_ammo_click: bool,
+    last_time: Option<u32>,
+    last_is_died: Option<bool>,
+    /// Whole seconds left until respawn.
+    /// Seconds until respawn and AFK flag.
+    last_respawn_seconds: Option<(Option<u32>, bool)>,
+    /// Numbers in the first and second score boxes.
+    last_scores: Option<(u32, u32)>,
+    last_leader: Option<String>,
+    last_is_burning: Option<bool>,
+    last_is_zoomed: Option<bool>,
+    last_crosshair_visible: Option<bool>,
+    /// Weapon whose crosshair is shown, `Some(None)` is default crosshair.
+    last_crosshair: Option<Option<WeaponKind>>,
+    last_weapon: Option<Option<WeaponKind>>,
+    /// Flashlight battery charge in percents.
+    last_flashlight_battery: Option<u32>,
+    last_prompt: Option<Option<String>>,
+    /// Relation, name and rounded health of last actor under crosshair.
+    last_aim_target: Option<Option<(bool, String, u32)>>,
+    /// Amount of lit segments of charge ring, `Some(None)` if ring is hidden.
+    last_charge: Option<Option<usize>>,
+    last_paused: Option<bool>,
+    overview_dirty: bool,
 }
 
 impl Hud {
@@ -49,30 +341,149 @@ impl Hud {
         let ctx = &mut engine.user_interface.build_ctx();
         let resource_manager = engine.resource_manager.clone();
 
-        let font = fyrox::core::futures::executor::block_on(Font::from_file(
-            Path::new("data/ui/SquaresBold.ttf"),
-            35.0,
-            Font::default_char_set(),
-        ))
-        .unwrap();
-        let font = SharedFont(Arc::new(Mutex::new(font)));
+        let font = gui::load_font(ctx, gui::FONT_PATH, 35.0);
 
         let health;
+        let health_bar;
         let armor;
+        let armor_bar;
         let ammo;
-        let message;
         let time;
         let first_score;
         let second_score;
         let match_limit;
+        let leader;
         let died;
+        let banner;
+        let weapon_name;
+        let low_battery;
+        let prompt;
+        let debug_watermark;
+        let paused;
+        // Built before root, so the rest of HUD is drawn on top of it.
+        let burning = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(frame_size.0 as f32)
+                .with_height(frame_size.1 as f32)
+                .with_visibility(false)
+                .with_hit_test_visibility(false)
+                .with_background(Brush::Solid(Color::from_rgba(255, 80, 0, 70))),
+        )
+        .build(ctx);
+        let damage_numbers = DamageNumbers::new(ctx, frame_size);
+        let health_bars = HealthBars::new(ctx, frame_size);
+        let item_timers = ItemTimers::new(ctx, resource_manager.clone(), frame_size);
+        let chat = Chat::new(ctx);
+        let notifications = Notifications::new(ctx, resource_manager.clone());
+        let intent_feed = Notifications::with_placement(
+            ctx,
+            resource_manager.clone(),
+            INTENT_FEED_ENTRIES,
+            HorizontalAlignment::Right,
+            Thickness {
+                left: 0.0,
+                top: 200.0,
+                right: 20.0,
+                bottom: 0.0,
+            },
+            Some(INTENT_FEED_COLOR),
+        );
+        let intent_panel = GridBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .on_row(0)
+                .on_column(2)
+                .with_child(intent_feed.root()),
+        )
+        .build(ctx);
+        let crosshair;
+        let aim_info;
+        let aim_name;
+        let aim_health_bar;
+        let scope;
+        let charge_segments = (0..CHARGE_SEGMENTS)
+            .map(|i| {
+                // Segments go clockwise from the top.
+                let angle = i as f32 / CHARGE_SEGMENTS as f32 * std::f32::consts::TAU;
+                let center = Vector2::new(angle.sin(), -angle.cos()).scale(CHARGE_RING_RADIUS)
+                    + Vector2::repeat(CHARGE_RING_RADIUS);
+                BorderBuilder::new(
+                    WidgetBuilder::new()
+                        .with_width(CHARGE_SEGMENT_SIZE)
+                        .with_height(CHARGE_SEGMENT_SIZE)
+                        .with_desired_position(center - Vector2::repeat(CHARGE_SEGMENT_SIZE * 0.5))
+                        .with_background(Brush::Solid(CHARGE_EMPTY_COLOR)),
+                )
+                .build(ctx)
+            })
+            .collect::<Vec<_>>();
+        let charge_ring = CanvasBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_hit_test_visibility(false)
+                .with_width(CHARGE_RING_RADIUS * 2.0)
+                .with_height(CHARGE_RING_RADIUS * 2.0)
+                .with_horizontal_alignment(HorizontalAlignment::Center)
+                .with_vertical_alignment(VerticalAlignment::Center)
+                .on_row(0)
+                .on_column(1)
+                .with_children(charge_segments.iter().cloned()),
+        )
+        .build(ctx);
+        let weapon_slots = WeaponKind::ALL
+            .iter()
+            .map(|_| {
+                let text = TextBuilder::new(
+                    WidgetBuilder::new()
+                        .with_horizontal_alignment(HorizontalAlignment::Center)
+                        .with_vertical_alignment(VerticalAlignment::Center),
+                )
+                .build(ctx);
+                let border = BorderBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_margin(Thickness::uniform(2.0))
+                        .with_vertical_alignment(VerticalAlignment::Bottom)
+                        .with_width(WEAPON_SLOT_WIDTH)
+                        .with_height(WEAPON_SLOT_HEIGHT)
+                        .with_background(Brush::Solid(Color::from_rgba(0, 0, 0, 120)))
+                        .with_child(text),
+                )
+                .with_stroke_thickness(Thickness::uniform(2.0))
+                .build(ctx);
+                WeaponSlot {
+                    border,
+                    text,
+                    last: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let weapon_bar = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .on_row(0)
+                .on_column(2)
+                .with_vertical_alignment(VerticalAlignment::Bottom)
+                .with_horizontal_alignment(HorizontalAlignment::Right)
+                .with_margin(Thickness {
+                    left: 0.0,
+                    top: 0.0,
+                    right: 20.0,
+                    bottom: 60.0,
+                })
+                .with_children(weapon_slots.iter().map(|slot| slot.border)),
+        )
+        .with_orientation(Orientation::Horizontal)
+        .build(ctx);
         let root = GridBuilder::new(
             WidgetBuilder::new()
                 .with_width(frame_size.0 as f32)
                 .with_height(frame_size.1 as f32)
                 .with_visibility(false)
-                .with_child(
-                    ImageBuilder::new(
+                .with_child(chat.root())
+                .with_child(weapon_bar)
+                .with_child(charge_ring)
+                .with_child({
+                    crosshair = ImageBuilder::new(
                         WidgetBuilder::new()
                             .with_horizontal_alignment(HorizontalAlignment::Center)
                             .with_vertical_alignment(VerticalAlignment::Center)
@@ -82,10 +493,78 @@ impl Hud {
                             .on_column(1),
                     )
                     .with_texture(utils::into_gui_texture(
-                        resource_manager.request_texture("data/ui/crosshair.tga"),
+                        resource_manager.request_texture(DEFAULT_CROSSHAIR),
                     ))
-                    .build(ctx),
-                )
+                    .build(ctx);
+                    crosshair
+                })
+                .with_child({
+                    aim_info = StackPanelBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .with_hit_test_visibility(false)
+                            .on_row(0)
+                            .on_column(1)
+                            .with_margin(Thickness::top(70.0))
+                            .with_horizontal_alignment(HorizontalAlignment::Center)
+                            .with_vertical_alignment(VerticalAlignment::Center)
+                            .with_child({
+                                aim_name = TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .with_horizontal_alignment(HorizontalAlignment::Center)
+                                        .with_foreground(Brush::Solid(CROSSHAIR_TEAMMATE_COLOR)),
+                                )
+                                .build(ctx);
+                                aim_name
+                            })
+                            .with_child(
+                                BorderBuilder::new(
+                                    WidgetBuilder::new()
+                                        .with_width(AIM_HEALTH_BAR_WIDTH)
+                                        .with_height(6.0)
+                                        .with_margin(Thickness::top(2.0))
+                                        .with_background(Brush::Solid(Color::from_rgba(
+                                            0, 0, 0, 120,
+                                        )))
+                                        .with_child({
+                                            aim_health_bar = BorderBuilder::new(
+                                                WidgetBuilder::new()
+                                                    .with_width(AIM_HEALTH_BAR_WIDTH)
+                                                    .with_horizontal_alignment(
+                                                        HorizontalAlignment::Left,
+                                                    )
+                                                    .with_background(Brush::Solid(
+                                                        CROSSHAIR_TEAMMATE_COLOR,
+                                                    )),
+                                            )
+                                            .build(ctx);
+                                            aim_health_bar
+                                        }),
+                                )
+                                .build(ctx),
+                            ),
+                    )
+                    .build(ctx);
+                    aim_info
+                })
+                .with_child({
+                    scope = ImageBuilder::new(
+                        WidgetBuilder::new()
+                            .with_horizontal_alignment(HorizontalAlignment::Center)
+                            .with_vertical_alignment(VerticalAlignment::Center)
+                            .with_width(96.0)
+                            .with_height(96.0)
+                            .with_visibility(false)
+                            .with_hit_test_visibility(false)
+                            .on_row(0)
+                            .on_column(1),
+                    )
+                    .with_texture(utils::into_gui_texture(
+                        resource_manager.request_texture("data/ui/circle.png"),
+                    ))
+                    .build(ctx);
+                    scope
+                })
                 .with_child({
                     time = TextBuilder::new(
                         WidgetBuilder::new()
@@ -99,6 +578,22 @@ impl Hud {
                     .build(ctx);
                     time
                 })
+                .with_child({
+                    leader = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .on_column(0)
+                            .on_row(0)
+                            .with_vertical_alignment(VerticalAlignment::Bottom)
+                            .with_margin(Thickness {
+                                left: 50.0,
+                                top: 0.0,
+                                right: 0.0,
+                                bottom: 125.0,
+                            }),
+                    )
+                    .build(ctx);
+                    leader
+                })
                 .with_child(
                     GridBuilder::new(
                         WidgetBuilder::new()
@@ -220,19 +715,33 @@ impl Hud {
                             .with_child({
                                 health = TextBuilder::new(
                                     WidgetBuilder::new()
-                                        .with_foreground(Brush::Solid(Color::opaque(180, 14, 22)))
-                                        .with_width(170.0)
-                                        .with_height(35.0),
+                                        .with_foreground(Brush::Solid(Color::opaque(180, 14, 22))),
                                 )
                                 .with_text("100")
                                 .with_font(font.clone())
                                 .build(ctx);
-                                health
+                                let (bar, root) =
+                                    ValueBar::build(ctx, health, health_bar_color(1.0));
+                                health_bar = bar;
+                                root
                             }),
                     )
                     .with_orientation(Orientation::Horizontal)
                     .build(ctx),
                 )
+                .with_child({
+                    weapon_name = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .with_margin(Thickness::bottom(55.0))
+                            .on_column(1)
+                            .with_vertical_alignment(VerticalAlignment::Bottom)
+                            .with_horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .with_font(font.clone())
+                    .build(ctx);
+                    weapon_name
+                })
                 .with_child(
                     StackPanelBuilder::new(
                         WidgetBuilder::new()
@@ -260,7 +769,7 @@ impl Hud {
                             .with_child({
                                 ammo = TextBuilder::new(
                                     WidgetBuilder::new()
-                                        .with_foreground(Brush::Solid(Color::opaque(79, 79, 255)))
+                                        .with_foreground(Brush::Solid(AMMO_COLOR))
                                         .with_width(170.0)
                                         .with_height(35.0),
                                 )
@@ -300,37 +809,96 @@ impl Hud {
                             .with_child({
                                 armor = TextBuilder::new(
                                     WidgetBuilder::new()
-                                        .with_foreground(Brush::Solid(Color::opaque(255, 100, 26)))
-                                        .with_width(170.0)
-                                        .with_height(35.0),
+                                        .with_foreground(Brush::Solid(Color::opaque(255, 100, 26))),
                                 )
                                 .with_font(font.clone())
                                 .with_text("100")
                                 .build(ctx);
-                                armor
+                                let (bar, root) =
+                                    ValueBar::build(ctx, armor, armor_bar_color(ArmorTier::Green));
+                                armor_bar = bar;
+                                root
                             }),
                     )
                     .with_orientation(Orientation::Horizontal)
                     .build(ctx),
                 )
+                .with_child(notifications.root())
+                .with_child(intent_panel)
                 .with_child({
-                    message = TextBuilder::new(
+                    debug_watermark = TextBuilder::new(
                         WidgetBuilder::new()
+                            .with_visibility(false)
                             .on_row(0)
-                            .on_column(0)
-                            .with_vertical_alignment(VerticalAlignment::Center)
-                            .with_horizontal_alignment(HorizontalAlignment::Left)
-                            .with_margin(Thickness {
-                                left: 45.0,
-                                top: 30.0,
-                                right: 0.0,
-                                bottom: 0.0,
-                            })
-                            .with_height(40.0)
-                            .with_width(400.0),
+                            .on_column(2)
+                            .with_margin(Thickness::uniform(10.0))
+                            .with_foreground(Brush::Solid(Color::opaque(255, 0, 0)))
+                            .with_vertical_alignment(VerticalAlignment::Top)
+                            .with_horizontal_alignment(HorizontalAlignment::Right),
+                    )
+                    .with_font(font.clone())
+                    .with_text("DEBUG")
+                    .build(ctx);
+                    debug_watermark
+                })
+                .with_child({
+                    paused = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .on_row(0)
+                            .on_column(1)
+                            .with_margin(Thickness::top(80.0))
+                            .with_foreground(Brush::Solid(Color::WHITE))
+                            .with_vertical_alignment(VerticalAlignment::Top)
+                            .with_horizontal_alignment(HorizontalAlignment::Center),
                     )
+                    .with_font(font.clone())
+                    .with_text("PAUSED")
                     .build(ctx);
-                    message
+                    paused
+                })
+                .with_child({
+                    banner = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .on_row(0)
+                            .on_column(1)
+                            .with_margin(Thickness::top(150.0))
+                            .with_foreground(Brush::Solid(Color::opaque(255, 200, 0)))
+                            .with_vertical_alignment(VerticalAlignment::Top)
+                            .with_horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .with_font(font.clone())
+                    .build(ctx);
+                    banner
+                })
+                .with_child({
+                    low_battery = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .on_row(0)
+                            .on_column(1)
+                            .with_margin(Thickness::bottom(150.0))
+                            .with_foreground(Brush::Solid(Color::opaque(255, 200, 0)))
+                            .with_vertical_alignment(VerticalAlignment::Bottom)
+                            .with_horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .build(ctx);
+                    low_battery
+                })
+                .with_child({
+                    prompt = TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_visibility(false)
+                            .on_row(0)
+                            .on_column(1)
+                            .with_margin(Thickness::bottom(200.0))
+                            .with_foreground(Brush::Solid(Color::opaque(255, 255, 255)))
+                            .with_vertical_alignment(VerticalAlignment::Bottom)
+                            .with_horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .build(ctx);
+                    prompt
                 })
                 .with_child({
                     died = TextBuilder::new(
@@ -356,22 +924,361 @@ impl Hud {
 
         Self {
             leader_board,
+            chat,
             root,
             health,
+            health_bar,
             armor,
+            armor_bar,
             ammo,
-            message,
+            notifications,
+            intent_feed,
+            intent_panel,
+            show_bot_intents: false,
+            damage_numbers,
+            health_bars,
+            item_timers,
             time,
             first_score,
             second_score,
             match_limit,
+            leader,
             died,
-            message_timeout: 0.0,
-            message_queue: Default::default(),
+            debug_watermark,
+            paused,
+            burning,
+            crosshair,
+            aim_info,
+            aim_name,
+            aim_health_bar,
+            scope,
+            charge_ring,
+            charge_segments,
+            low_battery,
+            prompt,
+            weapon_slots,
+            resource_manager,
+            banner,
+            pending_banner: None,
+            banner_timeout: 0.0,
+            weapon_name,
+            weapon_name_timeout: 0.0,
+            last_health: None,
+            last_armor: None,
+            last_armor_tier: None,
+            last_ammo: None,
+            ammo_level: None,
+            ammo_pulse: 0.0,
+            low_ammo_click: false,
+            last_time: None,
+            last_is_died: None,
+            last_respawn_seconds: None,
+            last_scores: None,
+            last_leader: None,
+            last_is_burning: None,
+            last_is_zoomed: None,
+            last_crosshair_visible: None,
+            last_crosshair: None,
+            last_weapon: None,
+            last_flashlight_battery: None,
+            last_prompt: None,
+            last_aim_target: None,
+            last_charge: None,
+            last_paused: None,
+            overview_dirty: false,
+        }
+    }
+
+    /// Syncs HUD with the state of the level, must be called once per tick.
+    pub fn sync(&mut self, ui: &mut UserInterface, status: &LevelStatus) {
+        // Countdown is shown if match has time limit.
+        self.set_time(ui, status.time_remaining.unwrap_or(status.time));
+
+        if let Some(player) = status.player.as_ref() {
+            self.set_health(ui, player.health, player.max_health);
+            self.set_armor(ui, player.armor, player.max_armor);
+            self.set_armor_tier(ui, player.armor_tier);
+            match (player.current_weapon(), player.ammo) {
+                (Some(kind), Some(ammo)) => self.set_ammo(ui, kind, ammo),
+                _ => self.ammo_level = None,
+            }
+            self.sync_weapons(ui, &player.weapons);
+            self.set_current_weapon(ui, player.current_weapon());
+            self.set_is_died(ui, false);
+            self.set_is_burning(ui, player.is_burning);
+            self.set_is_zoomed(ui, player.is_zoomed);
+            self.set_flashlight_battery(ui, player.flashlight_battery);
+            self.set_prompt(ui, player.interaction_prompt.as_deref());
+            self.set_aim_target(ui, player.aim_target.as_ref());
+            self.set_charge(ui, player.charge);
+        } else {
+            self.set_is_died(ui, true);
+            self.ammo_level = None;
+            self.set_is_burning(ui, false);
+            self.set_is_zoomed(ui, false);
+            self.set_flashlight_battery(ui, 1.0);
+            self.set_prompt(ui, None);
+            self.set_aim_target(ui, None);
+            self.set_charge(ui, None);
+            self.sync_weapons(ui, &[]);
+            self.set_current_weapon(ui, None);
+        }
+        // Nobody respawns after the match is over.
+        let respawn_time_left = match status.phase {
+            MatchPhase::InProgress => status.respawn_time_left,
+            MatchPhase::Over => None,
+        };
+        self.set_respawn_time(ui, respawn_time_left, status.is_afk);
+
+        // Crosshair is useless while dead, zoomed in or looking at final results.
+        self.set_crosshair_visible(
+            ui,
+            status.phase == MatchPhase::InProgress
+                && status
+                    .player
+                    .as_ref()
+                    .map_or(false, |player| !player.is_zoomed),
+        );
+
+        self.set_scores(ui, status);
+    }
+
+    /// Shows crosshair of given weapon, default crosshair is shown if there is no weapon.
+    pub fn set_crosshair(&mut self, ui: &mut UserInterface, weapon: Option<WeaponKind>) {
+        if self.last_crosshair.replace(weapon) == Some(weapon) {
+            return;
+        }
+
+        let (texture, size) = match weapon {
+            Some(kind) => {
+                let crosshair = &Definitions::get().weapon(kind).crosshair;
+                (crosshair.texture.as_str(), crosshair.size)
+            }
+            None => (DEFAULT_CROSSHAIR, WeaponCrosshair::default().size),
+        };
+        ui.send_message(ImageMessage::texture(
+            self.crosshair,
+            MessageDirection::ToWidget,
+            Some(utils::into_gui_texture(
+                self.resource_manager.request_texture(texture),
+            )),
+        ));
+        ui.send_message(WidgetMessage::width(
+            self.crosshair,
+            MessageDirection::ToWidget,
+            size,
+        ));
+        ui.send_message(WidgetMessage::height(
+            self.crosshair,
+            MessageDirection::ToWidget,
+            size,
+        ));
+    }
+
+    /// Shows name of the weapon for a while when player switches to it.
+    fn set_current_weapon(&mut self, ui: &mut UserInterface, weapon: Option<WeaponKind>) {
+        if self.last_weapon.replace(weapon) == Some(weapon) {
+            return;
+        }
+
+        if let Some(kind) = weapon {
+            ui.send_message(TextMessage::text(
+                self.weapon_name,
+                MessageDirection::ToWidget,
+                kind.display_name().to_owned(),
+            ));
+            self.weapon_name_timeout = WEAPON_NAME_TIME;
+        } else {
+            self.weapon_name_timeout = 0.0;
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.weapon_name,
+            MessageDirection::ToWidget,
+            weapon.is_some(),
+        ));
+    }
+
+    /// Fills charge ring around crosshair, `charge` is in 0.0..=1.0 range. Ring is hidden if
+    /// there is no charge.
+    fn set_charge(&mut self, ui: &mut UserInterface, charge: Option<f32>) {
+        let lit = charge.map(|charge| (charge * CHARGE_SEGMENTS as f32).ceil() as usize);
+        let previous = match self.last_charge.replace(lit) {
+            Some(previous) if previous == lit => return,
+            previous => previous.flatten(),
+        };
+
+        if previous.is_some() != lit.is_some() {
+            ui.send_message(WidgetMessage::visibility(
+                self.charge_ring,
+                MessageDirection::ToWidget,
+                lit.is_some(),
+            ));
+        }
+        let lit = lit.unwrap_or(0);
+        for (i, &segment) in self.charge_segments.iter().enumerate() {
+            let color = if i < lit {
+                CHARGE_COLOR
+            } else {
+                CHARGE_EMPTY_COLOR
+            };
+            ui.send_message(WidgetMessage::background(
+                segment,
+                MessageDirection::ToWidget,
+                Brush::Solid(color),
+            ));
+        }
+    }
+
+    fn set_crosshair_visible(&mut self, ui: &mut UserInterface, visible: bool) {
+        if self.last_crosshair_visible.replace(visible) == Some(visible) {
+            return;
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.crosshair,
+            MessageDirection::ToWidget,
+            visible,
+        ));
+    }
+
+    fn set_prompt(&mut self, ui: &mut UserInterface, prompt: Option<&str>) {
+        if self.last_prompt.as_ref().map(|last| last.as_deref()) == Some(prompt) {
+            return;
+        }
+        self.last_prompt = Some(prompt.map(ToOwned::to_owned));
+        ui.send_message(WidgetMessage::visibility(
+            self.prompt,
+            MessageDirection::ToWidget,
+            prompt.is_some(),
+        ));
+        if let Some(prompt) = prompt {
+            ui.send_message(TextMessage::text(
+                self.prompt,
+                MessageDirection::ToWidget,
+                prompt.to_owned(),
+            ));
+        }
+    }
+
+    /// Tints crosshair by relation to the actor under it, teammates also get their name and
+    /// health shown below crosshair.
+    fn set_aim_target(&mut self, ui: &mut UserInterface, target: Option<&AimTarget>) {
+        let state = target.map(|target| {
+            (
+                target.is_teammate,
+                target.name.clone(),
+                target.health.max(0.0).ceil() as u32,
+            )
+        });
+        if self.last_aim_target.as_ref() == Some(&state) {
+            return;
+        }
+        self.last_aim_target = Some(state);
+
+        let color = match target {
+            Some(target) if target.is_teammate => CROSSHAIR_TEAMMATE_COLOR,
+            Some(_) => CROSSHAIR_ENEMY_COLOR,
+            None => CROSSHAIR_COLOR,
+        };
+        ui.send_message(WidgetMessage::background(
+            self.crosshair,
+            MessageDirection::ToWidget,
+            Brush::Solid(color),
+        ));
+
+        let teammate = target.filter(|target| target.is_teammate);
+        ui.send_message(WidgetMessage::visibility(
+            self.aim_info,
+            MessageDirection::ToWidget,
+            teammate.is_some(),
+        ));
+        if let Some(teammate) = teammate {
+            ui.send_message(TextMessage::text(
+                self.aim_name,
+                MessageDirection::ToWidget,
+                teammate.name.clone(),
+            ));
+            ui.send_message(WidgetMessage::width(
+                self.aim_health_bar,
+                MessageDirection::ToWidget,
+                AIM_HEALTH_BAR_WIDTH * (teammate.health / BASE_HEALTH).clamp(0.0, 1.0),
+            ));
+        }
+    }
+
+    fn set_respawn_time(&mut self, ui: &mut UserInterface, time_left: Option<f32>, is_afk: bool) {
+        let seconds = time_left.map(|time_left| time_left.ceil() as u32);
+        if self.last_respawn_seconds.replace((seconds, is_afk)) == Some((seconds, is_afk)) {
+            return;
+        }
+        ui.send_message(TextMessage::text(
+            self.died,
+            MessageDirection::ToWidget,
+            match seconds {
+                _ if is_afk => "AFK\nPress any key to rejoin".to_owned(),
+                Some(seconds) => format!("You Died\nRespawn in {}", seconds),
+                None => "You Died".to_owned(),
+            },
+        ));
+    }
+
+    /// Shows scores of red and blue teams in team modes, or scores of the first two places.
+    fn set_scores(&mut self, ui: &mut UserInterface, status: &LevelStatus) {
+        // TODO: This is probably not correct way of showing leader and second place on HUD
+        //  it is better to show player's score and leader/second score of some bot.
+        let (scores, leader) = match status.team_scores {
+            Some((red, blue)) => (
+                (red, blue),
+                if red > blue {
+                    "Red team leads".to_owned()
+                } else if blue > red {
+                    "Blue team leads".to_owned()
+                } else {
+                    String::new()
+                },
+            ),
+            None => match status.leader.as_ref() {
+                Some((name, score)) => (
+                    (*score, status.runner_up_score.unwrap_or(0)),
+                    if *score > 0 {
+                        format!("Leader: {}", name)
+                    } else {
+                        String::new()
+                    },
+                ),
+                None => ((0, 0), String::new()),
+            },
+        };
+
+        if self.last_scores.replace(scores) != Some(scores) {
+            ui.send_message(TextMessage::text(
+                self.first_score,
+                MessageDirection::ToWidget,
+                format!("{}", scores.0),
+            ));
+            ui.send_message(TextMessage::text(
+                self.second_score,
+                MessageDirection::ToWidget,
+                format!("{}", scores.1),
+            ));
+        }
+
+        if self.last_leader.as_ref() != Some(&leader) {
+            ui.send_message(TextMessage::text(
+                self.leader,
+                MessageDirection::ToWidget,
+                leader.clone(),
+            ));
+            self.last_leader = Some(leader);
         }
     }
 
-    pub fn set_health(&mut self, ui: &mut UserInterface, health: f32) {
+    fn set_health(&mut self, ui: &mut UserInterface, health: f32, max_health: f32) {
+        let fraction = health / max_health;
+        self.health_bar.set_value(fraction);
+        if self.last_health.replace(health) == Some(health) {
+            return;
+        }
+        self.health_bar.set_color(ui, health_bar_color(fraction));
         ui.send_message(TextMessage::text(
             self.health,
             MessageDirection::ToWidget,
@@ -379,7 +1286,11 @@ impl Hud {
         ));
     }
 
-    pub fn set_armor(&mut self, ui: &mut UserInterface, armor: f32) {
+    fn set_armor(&mut self, ui: &mut UserInterface, armor: f32, max_armor: f32) {
+        self.armor_bar.set_value(armor / max_armor);
+        if self.last_armor.replace(armor) == Some(armor) {
+            return;
+        }
         ui.send_message(TextMessage::text(
             self.armor,
             MessageDirection::ToWidget,
@@ -387,7 +1298,43 @@ impl Hud {
         ));
     }
 
-    pub fn set_ammo(&mut self, ui: &mut UserInterface, ammo: u32) {
+    fn set_armor_tier(&mut self, ui: &mut UserInterface, tier: ArmorTier) {
+        if self.last_armor_tier.replace(tier) == Some(tier) {
+            return;
+        }
+        let color = match tier {
+            ArmorTier::Green => Color::opaque(60, 200, 60),
+            ArmorTier::Yellow => Color::opaque(255, 210, 0),
+            ArmorTier::Red => Color::opaque(220, 30, 30),
+        };
+        ui.send_message(WidgetMessage::foreground(
+            self.armor,
+            MessageDirection::ToWidget,
+            Brush::Solid(color),
+        ));
+        self.armor_bar.set_color(ui, armor_bar_color(tier));
+    }
+
+    fn set_ammo(&mut self, ui: &mut UserInterface, kind: WeaponKind, ammo: u32) {
+        let level = AmmoLevel::new(ammo, Definitions::get().weapon(kind).max_ammo);
+        let last_level = match self.ammo_level.replace((kind, level)) {
+            Some((last_kind, last_level)) if last_kind == kind => Some(last_level),
+            _ => None,
+        };
+        if let Some(last_level) = last_level {
+            // Thresholds that ammo rises above are armed again.
+            if level.thresholds_crossed() > last_level.thresholds_crossed() {
+                self.low_ammo_click = true;
+            }
+        }
+        if last_level != Some(level) {
+            self.ammo_pulse = 0.0;
+            self.set_ammo_color(ui, level.color());
+        }
+
+        if self.last_ammo.replace(ammo) == Some(ammo) {
+            return;
+        }
         ui.send_message(TextMessage::text(
             self.ammo,
             MessageDirection::ToWidget,
@@ -395,15 +1342,53 @@ impl Hud {
         ));
     }
 
+    fn set_ammo_color(&self, ui: &mut UserInterface, color: Color) {
+        ui.send_message(WidgetMessage::foreground(
+            self.ammo,
+            MessageDirection::ToWidget,
+            Brush::Solid(color),
+        ));
+    }
+
+    /// Returns true once after ammo of current weapon dropped below a warning threshold.
+    pub fn take_low_ammo_click(&mut self) -> bool {
+        std::mem::take(&mut self.low_ammo_click)
+    }
+
     pub fn set_visible(&mut self, ui: &mut UserInterface, visible: bool) {
         ui.send_message(WidgetMessage::visibility(
             self.root,
             MessageDirection::ToWidget,
             visible,
         ));
+        self.leader_board.set_hud_visible(ui, visible);
+        for &widget in [
+            self.damage_numbers.root(),
+            self.health_bars.root(),
+            self.item_timers.root(),
+        ]
+        .iter()
+        {
+            ui.send_message(WidgetMessage::visibility(
+                widget,
+                MessageDirection::ToWidget,
+                visible,
+            ));
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.burning,
+            MessageDirection::ToWidget,
+            visible && self.last_is_burning == Some(true),
+        ));
     }
 
-    pub fn set_time(&mut self, ui: &mut UserInterface, time: f32) {
+    fn set_time(&mut self, ui: &mut UserInterface, time: f32) {
+        // Only whole seconds are shown.
+        let total_seconds = time as u32;
+        if self.last_time.replace(total_seconds) == Some(total_seconds) {
+            return;
+        }
+
         let seconds = (time % 60.0) as u32;
         let minutes = (time / 60.0) as u32;
         let hours = (time / 3600.0) as u32;
@@ -415,119 +1400,385 @@ impl Hud {
         ));
     }
 
+    /// Scoreboard is shown while player waits for respawn.
     pub fn set_is_died(&mut self, ui: &mut UserInterface, is_died: bool) {
+        if self.last_is_died.replace(is_died) == Some(is_died) {
+            return;
+        }
         ui.send_message(WidgetMessage::visibility(
             self.died,
             MessageDirection::ToWidget,
             is_died,
         ));
+        self.leader_board.set_player_dead(ui, is_died);
     }
 
-    pub fn add_message<P: AsRef<str>>(&mut self, message: P) {
-        self.message_queue.push_back(message.as_ref().to_owned())
+    /// Shows a line about personal best under final results, `None` hides it.
+    pub fn set_personal_record(&mut self, record: Option<String>) {
+        self.leader_board.set_personal_record(record);
     }
 
-    pub fn process_event(&mut self, engine: &mut Engine, event: &Event<()>) {
-        if let Event::WindowEvent { event, .. } = event {
-            if let WindowEvent::Resized(new_size) = event {
-                engine.user_interface.send_message(WidgetMessage::width(
-                    self.root,
-                    MessageDirection::ToWidget,
-                    new_size.width as f32,
-                ));
-                engine.user_interface.send_message(WidgetMessage::height(
-                    self.root,
-                    MessageDirection::ToWidget,
-                    new_size.height as f32,
-                ));
-            }
+    /// Keeps scoreboard with final results on screen until next match.
+    pub fn set_match_over(&mut self, ui: &mut UserInterface, match_over: bool) {
+        self.leader_board.set_match_over(ui, match_over);
+    }
+
+    /// Shows warning when flashlight battery drops below 25%. `battery` is in 0.0..=1.0 range.
+    fn set_flashlight_battery(&mut self, ui: &mut UserInterface, battery: f32) {
+        let percent = (battery * 100.0).ceil() as u32;
+        if self.last_flashlight_battery.replace(percent) == Some(percent) {
+            return;
+        }
+        let low = percent < 25;
+        ui.send_message(WidgetMessage::visibility(
+            self.low_battery,
+            MessageDirection::ToWidget,
+            low,
+        ));
+        if low {
+            ui.send_message(TextMessage::text(
+                self.low_battery,
+                MessageDirection::ToWidget,
+                format!("Flashlight battery: {}%", percent),
+            ));
         }
+    }
 
-        self.leader_board.process_input_event(engine, event);
+    fn set_is_burning(&mut self, ui: &mut UserInterface, is_burning: bool) {
+        if self.last_is_burning.replace(is_burning) == Some(is_burning) {
+            return;
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.burning,
+            MessageDirection::ToWidget,
+            is_burning,
+        ));
     }
 
-    pub fn leader_board(&self) -> &LeaderBoardUI {
-        &self.leader_board
+    fn set_is_zoomed(&mut self, ui: &mut UserInterface, is_zoomed: bool) {
+        if self.last_is_zoomed.replace(is_zoomed) == Some(is_zoomed) {
+            return;
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.scope,
+            MessageDirection::ToWidget,
+            is_zoomed,
+        ));
     }
 
-    pub fn update(&mut self, ui: &mut UserInterface, time: &GameTime) {
-        self.message_timeout -= time.delta;
+    /// Shows owned weapons as a list of `(kind, ammo, is_current)`, in the order they were
+    /// picked up. Only slots whose contents have changed are updated.
+    fn sync_weapons(&mut self, ui: &mut UserInterface, weapons: &[(WeaponKind, u32, bool)]) {
+        for (i, slot) in self.weapon_slots.iter_mut().enumerate() {
+            let entry = weapons.get(i).copied();
+            if slot.last == entry {
+                continue;
+            }
 
-        if self.message_timeout <= 0.0 {
-            if let Some(message) = self.message_queue.pop_front() {
-                ui.send_message(TextMessage::text(
-                    self.message,
+            if slot.last.is_some() != entry.is_some() {
+                ui.send_message(WidgetMessage::visibility(
+                    slot.border,
                     MessageDirection::ToWidget,
-                    message,
+                    entry.is_some(),
                 ));
-                self.message_timeout = 1.25;
-            } else {
+            }
+
+            if let Some((kind, ammo, is_current)) = entry {
+                let color = if ammo == 0 {
+                    Color::opaque(90, 90, 90)
+                } else if is_current {
+                    Color::opaque(255, 200, 0)
+                } else {
+                    Color::opaque(200, 200, 200)
+                };
+                let scale = if is_current {
+                    ACTIVE_WEAPON_SLOT_SCALE
+                } else {
+                    1.0
+                };
+
                 ui.send_message(TextMessage::text(
-                    self.message,
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    format!("{} {}", kind.short_name(), ammo),
+                ));
+                ui.send_message(WidgetMessage::foreground(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(color),
+                ));
+                ui.send_message(WidgetMessage::foreground(
+                    slot.border,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(if is_current {
+                        color
+                    } else {
+                        Color::TRANSPARENT
+                    }),
+                ));
+                ui.send_message(WidgetMessage::width(
+                    slot.border,
                     MessageDirection::ToWidget,
-                    Default::default(),
+                    WEAPON_SLOT_WIDTH * scale,
+                ));
+                ui.send_message(WidgetMessage::height(
+                    slot.border,
+                    MessageDirection::ToWidget,
+                    WEAPON_SLOT_HEIGHT * scale,
                 ));
             }
+
+            slot.last = entry;
         }
     }
 
-    fn update_leader_board_overview(
+    pub fn set_debug_watermark_visible(&mut self, ui: &mut UserInterface, visible: bool) {
+        ui.send_message(WidgetMessage::visibility(
+            self.debug_watermark,
+            MessageDirection::ToWidget,
+            visible,
+        ));
+    }
+
+    pub fn set_paused(&mut self, ui: &mut UserInterface, paused: bool) {
+        if self.last_paused != Some(paused) {
+            self.last_paused = Some(paused);
+            ui.send_message(WidgetMessage::visibility(
+                self.paused,
+                MessageDirection::ToWidget,
+                paused,
+            ));
+        }
+    }
+
+    pub fn add_message<P: AsRef<str>>(
+        &mut self,
+        message: P,
+        severity: NotificationSeverity,
+        icon: Option<PathBuf>,
+    ) {
+        self.notifications.add(message.as_ref(), severity, icon);
+    }
+
+    /// Pickup notifications are frequent and can be turned off in options.
+    pub fn set_show_pickup_notifications(&mut self, show: bool) {
+        self.notifications.set_show_pickups(show);
+    }
+
+    /// Damage numbers are off by default and can be turned on in options.
+    pub fn set_show_damage_numbers(&mut self, show: bool) {
+        self.damage_numbers.set_enabled(show);
+    }
+
+    /// Moves damage numbers, `camera` is the one through which the world is seen.
+    pub fn update_damage_numbers(
         &mut self,
         ui: &mut UserInterface,
-        leader_board: &LeaderBoard,
-        match_options: &MatchOptions,
+        camera: Option<&Camera>,
+        frame_size: (u32, u32),
+        dt: f32,
     ) {
-        // TODO: This is probably not correct way of showing leader and second place on HUD
-        //  it is better to show player's score and leader/second score of some bot.
-        if let Some((leader_name, leader_score)) = leader_board.highest_personal_score(None) {
+        let screen_size = Vector2::new(frame_size.0 as f32, frame_size.1 as f32);
+        self.damage_numbers.update(ui, camera, screen_size, dt);
+    }
+
+    /// Intent feed is off by default and can be turned on in options.
+    pub fn set_show_bot_intents(&mut self, ui: &mut UserInterface, show: bool) {
+        if self.show_bot_intents == show {
+            return;
+        }
+        self.show_bot_intents = show;
+        ui.send_message(WidgetMessage::visibility(
+            self.intent_panel,
+            MessageDirection::ToWidget,
+            show,
+        ));
+    }
+
+    /// Health bars of bots are on by default and can be turned off in options.
+    pub fn set_show_health_bars(&mut self, show: bool) {
+        self.health_bars.set_enabled(show);
+    }
+
+    /// Places health bars above bots, see [`crate::level::Level::health_bars`].
+    pub fn update_health_bars(
+        &mut self,
+        ui: &mut UserInterface,
+        camera: Option<&Camera>,
+        frame_size: (u32, u32),
+        bars: &[HealthBar],
+    ) {
+        let screen_size = Vector2::new(frame_size.0 as f32, frame_size.1 as f32);
+        self.health_bars.update(ui, camera, screen_size, bars);
+    }
+
+    /// Places respawn timers above items, see [`crate::level::Level::item_timers`].
+    pub fn update_item_timers(
+        &mut self,
+        ui: &mut UserInterface,
+        camera: Option<&Camera>,
+        frame_size: (u32, u32),
+        timers: &[ItemTimer],
+    ) {
+        let screen_size = Vector2::new(frame_size.0 as f32, frame_size.1 as f32);
+        self.item_timers.update(ui, camera, screen_size, timers);
+    }
+
+    /// Shows large text in the middle of the screen, replacing previous one if any.
+    pub fn show_banner<P: AsRef<str>>(&mut self, text: P) {
+        self.pending_banner = Some(text.as_ref().to_owned());
+    }
+
+    /// Stretches HUD and leader board over the window, see [`gui::UiLayout`].
+    pub fn resize(&mut self, ui: &mut UserInterface, size: Vector2<f32>) {
+        for &widget in [
+            self.root,
+            self.burning,
+            self.damage_numbers.root(),
+            self.health_bars.root(),
+            self.item_timers.root(),
+        ]
+        .iter()
+        {
+            gui::fit_to_window(ui, widget, size);
+        }
+
+        self.leader_board.resize(ui, size);
+    }
+
+    pub fn is_chat_active(&self) -> bool {
+        self.chat.is_active()
+    }
+
+    /// Returns true if event was consumed by chat.
+    pub fn process_chat_input(
+        &mut self,
+        ui: &mut UserInterface,
+        event: &Event<()>,
+        open_button: ControlButton,
+        sender: &Sender<Message>,
+    ) -> bool {
+        self.chat
+            .process_input_event(ui, event, open_button, sender)
+    }
+
+    pub fn handle_ui_message(&mut self, message: &UiMessage) {
+        self.chat.handle_ui_message(message);
+    }
+
+    pub fn update(&mut self, ui: &mut UserInterface, time: &GameTime) {
+        self.chat.update(ui, time.delta);
+        self.notifications.update(ui, time.delta);
+        self.intent_feed.update(ui, time.delta);
+        self.health_bar.update(ui, time.delta);
+        self.armor_bar.update(ui, time.delta);
+
+        if let Some(text) = self.pending_banner.take() {
             ui.send_message(TextMessage::text(
-                self.first_score,
+                self.banner,
+                MessageDirection::ToWidget,
+                text,
+            ));
+            ui.send_message(WidgetMessage::visibility(
+                self.banner,
                 MessageDirection::ToWidget,
-                format!("{}", leader_score),
+                true,
             ));
+            self.banner_timeout = BANNER_TIME;
+        } else if self.banner_timeout > 0.0 {
+            self.banner_timeout -= time.delta;
+            if self.banner_timeout <= 0.0 {
+                ui.send_message(WidgetMessage::visibility(
+                    self.banner,
+                    MessageDirection::ToWidget,
+                    false,
+                ));
+            }
+        }
 
-            if let Some((_, second_score)) = leader_board.highest_personal_score(Some(leader_name))
-            {
-                ui.send_message(TextMessage::text(
-                    self.second_score,
+        if let Some((_, AmmoLevel::Empty)) = self.ammo_level {
+            self.ammo_pulse += time.delta;
+            let phase = (self.ammo_pulse * AMMO_PULSE_FREQUENCY * std::f32::consts::TAU).cos();
+            let alpha = 0.5 + 0.5 * phase;
+            self.set_ammo_color(
+                ui,
+                Color::from_rgba(
+                    AMMO_CRITICAL_COLOR.r,
+                    AMMO_CRITICAL_COLOR.g,
+                    AMMO_CRITICAL_COLOR.b,
+                    (55.0 + 200.0 * alpha) as u8,
+                ),
+            );
+        }
+
+        if self.weapon_name_timeout > 0.0 {
+            self.weapon_name_timeout -= time.delta;
+            if self.weapon_name_timeout <= 0.0 {
+                ui.send_message(WidgetMessage::visibility(
+                    self.weapon_name,
                     MessageDirection::ToWidget,
-                    format!("{}", second_score),
+                    false,
                 ));
             }
         }
+    }
 
-        let limit = match match_options {
-            MatchOptions::DeathMatch(dm) => dm.frag_limit,
-            MatchOptions::TeamDeathMatch(tdm) => tdm.team_frag_limit,
-            MatchOptions::CaptureTheFlag(ctf) => ctf.flag_limit,
-        };
+    fn update_match_limit(&mut self, ui: &mut UserInterface, match_options: &MatchOptions) {
         ui.send_message(TextMessage::text(
             self.match_limit,
             MessageDirection::ToWidget,
-            format!("{}", limit),
+            if match_options.is_practice() {
+                "-".to_owned()
+            } else {
+                format!("{}", match_options.score_limit())
+            },
         ));
     }
 
-    pub fn handle_message(
-        &mut self,
-        message: &Message,
-        ui: &mut UserInterface,
-        leader_board: &LeaderBoard,
-        match_options: &MatchOptions,
-    ) {
+    pub fn handle_message(&mut self, message: &Message) {
         match message {
-            Message::AddNotification { text } => self.add_message(text),
+            Message::AddNotification {
+                text,
+                severity,
+                icon,
+            } => self.add_message(text, *severity, icon.clone()),
+            Message::ShowBanner { text } => self.show_banner(text),
+            Message::ShowBotIntent { text } => {
+                if self.show_bot_intents {
+                    self.intent_feed.add(text, NotificationSeverity::Info, None);
+                }
+            }
+            &Message::ShowDamageNumber {
+                victim,
+                position,
+                amount,
+                kind,
+            } => self.damage_numbers.add(victim, position, amount, kind),
+            Message::ChatMessage { sender_name, text } => self.chat.add(sender_name, text),
             Message::AddBot { .. }
             | Message::RemoveActor { .. }
+            | Message::RemoveBot { .. }
             | Message::RespawnActor { .. }
             | Message::SpawnBot { .. }
-            | Message::SpawnPlayer => {
-                self.update_leader_board_overview(ui, leader_board, match_options)
-            }
+            | Message::SpawnPlayer => self.overview_dirty = true,
             _ => (),
         }
 
-        self.leader_board
-            .handle_message(message, ui, leader_board, match_options);
+        self.leader_board.handle_message(message);
+    }
+
+    /// Syncs leader board widgets with the model, must be called once per frame after all
+    /// messages were handled.
+    pub fn flush_leader_board(
+        &mut self,
+        ui: &mut UserInterface,
+        leader_board: &LeaderBoard,
+        match_options: &MatchOptions,
+    ) {
+        if self.overview_dirty {
+            self.overview_dirty = false;
+            self.update_match_limit(ui, match_options);
+        }
+        self.leader_board.flush(ui, leader_board, match_options);
     }
 }
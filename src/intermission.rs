@@ -0,0 +1,94 @@
+//! Cinematic camera that is shown behind final results when match is over. It slowly flies
+//! along closed spline through given waypoints and always looks at the middle of the map.
+//! Intermission lasts only until level is destroyed, so it is never saved.
+
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        pool::Handle,
+    },
+    scene::{
+        base::BaseBuilder, camera::CameraBuilder, graph::Graph, node::Node,
+        transform::TransformBuilder,
+    },
+};
+
+/// Time in seconds to fly from one waypoint to the next one.
+const SEGMENT_TIME: f32 = 6.0;
+/// Radius of the circle that is used when there is too few waypoints to make a path.
+const ORBIT_RADIUS: f32 = 8.0;
+
+pub struct IntermissionCamera {
+    camera: Handle<Node>,
+    waypoints: Vec<Vector3<f32>>,
+    /// Point at which camera looks all the time.
+    target: Vector3<f32>,
+    /// Position on the path, integer part is index of current segment.
+    progress: f32,
+}
+
+impl IntermissionCamera {
+    pub fn new(graph: &mut Graph, mut waypoints: Vec<Vector3<f32>>, target: Vector3<f32>) -> Self {
+        if waypoints.len() < 2 {
+            let center = waypoints.first().cloned().unwrap_or(target);
+            waypoints = (0..4)
+                .map(|i| {
+                    let angle = i as f32 * std::f32::consts::FRAC_PI_2;
+                    center + Vector3::new(angle.cos(), 0.0, angle.sin()).scale(ORBIT_RADIUS)
+                })
+                .collect();
+        }
+
+        let camera = CameraBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(waypoints[0])
+                    .build(),
+            ),
+        )
+        .build(graph);
+
+        Self {
+            camera,
+            waypoints,
+            target,
+            progress: 0.0,
+        }
+    }
+
+    pub fn camera(&self) -> Handle<Node> {
+        self.camera
+    }
+
+    /// Returns point on closed Catmull-Rom spline through waypoints.
+    fn sample(&self, progress: f32) -> Vector3<f32> {
+        let count = self.waypoints.len();
+        let segment = progress.floor() as usize;
+        let t = progress.fract();
+        let point = |offset: usize| self.waypoints[(segment + count - 1 + offset) % count];
+        let (p0, p1, p2, p3) = (point(0), point(1), point(2), point(3));
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (p1.scale(2.0)
+            + (p2 - p0).scale(t)
+            + (p0.scale(2.0) - p1.scale(5.0) + p2.scale(4.0) - p3).scale(t2)
+            + (p1.scale(3.0) - p0 - p2.scale(3.0) + p3).scale(t3))
+        .scale(0.5)
+    }
+
+    pub fn update(&mut self, graph: &mut Graph, dt: f32) {
+        self.progress = (self.progress + dt / SEGMENT_TIME) % self.waypoints.len() as f32;
+
+        let position = self.sample(self.progress);
+        let transform = graph[self.camera].local_transform_mut();
+        transform.set_position(position);
+        if let Some(direction) = (self.target - position).try_normalize(std::f32::EPSILON) {
+            transform.set_rotation(UnitQuaternion::face_towards(&direction, &Vector3::y()));
+        }
+    }
+
+    pub fn clean_up(&mut self, graph: &mut Graph) {
+        graph.remove_node(self.camera);
+    }
+}
@@ -0,0 +1,102 @@
+//! Simulation runs at fixed rate, but frames are rendered as fast as display allows. To make
+//! use of these extra frames, moving nodes are rendered at a blended pose between two last
+//! simulation steps, and moved back to the simulated pose right after rendering so simulation
+//! itself never sees interpolated values.
+
+use fyrox::{
+    core::{
+        algebra::{UnitQuaternion, Vector3},
+        pool::Handle,
+    },
+    scene::{graph::Graph, node::Node},
+};
+use std::collections::HashMap;
+
+/// Nodes that moved more than this distance in one step were teleported (respawn, jump pad
+/// and so on), there is nothing to interpolate for them.
+const TELEPORT_DISTANCE: f32 = 2.0;
+
+#[derive(Copy, Clone)]
+struct Pose {
+    position: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+}
+
+impl Pose {
+    fn of(node: &Node) -> Self {
+        let transform = node.local_transform();
+        Self {
+            position: **transform.position(),
+            rotation: **transform.rotation(),
+        }
+    }
+
+    fn apply(&self, node: &mut Node) {
+        node.local_transform_mut()
+            .set_position(self.position)
+            .set_rotation(self.rotation);
+    }
+}
+
+struct NodeState {
+    previous: Pose,
+    current: Pose,
+}
+
+#[derive(Default)]
+pub struct Interpolator {
+    states: HashMap<Handle<Node>, NodeState>,
+}
+
+impl Interpolator {
+    /// Remembers simulated poses of given nodes, must be called at the end of every fixed
+    /// step. Nodes that are not in the list anymore are forgotten.
+    pub fn capture<I: Iterator<Item = Handle<Node>>>(&mut self, graph: &Graph, nodes: I) {
+        let mut states = HashMap::with_capacity(self.states.len());
+        for handle in nodes {
+            if !graph.is_valid_handle(handle) {
+                continue;
+            }
+            let current = Pose::of(&graph[handle]);
+            let previous = match self.states.get(&handle) {
+                Some(state)
+                    if (state.current.position - current.position).norm() < TELEPORT_DISTANCE =>
+                {
+                    state.current
+                }
+                _ => current,
+            };
+            states.insert(handle, NodeState { previous, current });
+        }
+        self.states = states;
+    }
+
+    /// Moves nodes to a pose between two last simulation steps. `alpha` is a fraction of
+    /// fixed step that passed since last simulation step.
+    pub fn apply(&self, graph: &mut Graph, alpha: f32) {
+        let alpha = alpha.max(0.0).min(1.0);
+        for (&handle, state) in self.states.iter() {
+            if graph.is_valid_handle(handle) {
+                Pose {
+                    position: state.previous.position.lerp(&state.current.position, alpha),
+                    rotation: state
+                        .previous
+                        .rotation
+                        .slerp(&state.current.rotation, alpha),
+                }
+                .apply(&mut graph[handle]);
+            }
+        }
+        graph.update_hierarchical_data();
+    }
+
+    /// Moves nodes back to their simulated poses.
+    pub fn restore(&self, graph: &mut Graph) {
+        for (&handle, state) in self.states.iter() {
+            if graph.is_valid_handle(handle) {
+                state.current.apply(&mut graph[handle]);
+            }
+        }
+        graph.update_hierarchical_data();
+    }
+}
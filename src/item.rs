@@ -1,17 +1,48 @@
-use crate::{effects::EffectKind, fyrox::core::math::Vector3Ext, message::Message, GameTime};
+use crate::{
+    character::ArmorTier,
+    effects::EffectKind,
+    fyrox::core::math::Vector3Ext,
+    message::{Message, MessageSender},
+    weapon::WeaponKind,
+    GameTime,
+};
 use fyrox::{
     core::{
         algebra::Vector3,
+        color::Color,
+        math::aabb::AxisAlignedBoundingBox,
         pool::{Handle, Pool},
         visitor::{Visit, VisitResult, Visitor},
     },
     engine::resource_manager::ResourceManager,
     scene::{
-        base::BaseBuilder, graph::Graph, node::Node, pivot::PivotBuilder,
-        transform::TransformBuilder, Scene,
+        base::BaseBuilder,
+        graph::Graph,
+        light::{
+            point::{PointLight, PointLightBuilder},
+            BaseLightBuilder,
+        },
+        node::Node,
+        pivot::PivotBuilder,
+        transform::TransformBuilder,
+        Scene,
     },
 };
-use std::{path::Path, sync::mpsc::Sender};
+use std::{cmp::Ordering, path::Path};
+
+/// Actor picks up an item when it is closer than this distance.
+pub const PICKUP_RADIUS: f32 = 1.25;
+/// Items closer than this to player glow. It is a bit beyond pickup range, so the glow shows
+/// up before an item is taken.
+pub const HIGHLIGHT_RADIUS: f32 = 2.5;
+const HIGHLIGHT_LIGHT_RADIUS: f32 = 1.0;
+/// Magnetic items start drifting toward an actor that is closer than this distance, it is a
+/// bit beyond pickup range.
+pub const MAGNET_RADIUS: f32 = PICKUP_RADIUS + 1.2;
+/// Drift speed grows by this much per second, so the item accelerates toward the actor.
+const MAGNET_ACCELERATION: f32 = 12.0;
+/// Speed at which an item that lost its magnet goes back to its place.
+const MAGNET_RETURN_SPEED: f32 = 1.0;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Visit)]
 pub enum ItemKind {
@@ -27,21 +58,209 @@ pub enum ItemKind {
     Ak47,
     M4,
     RocketLauncher,
+
+    // Items that were added later go last, so ids of saved items stay the same.
+    MegaHealth,
+    /// Green armor.
+    Armor,
+    YellowArmor,
+    RedArmor,
+    Rockets,
+}
+
+impl ItemKind {
+    /// Parses kind from a name used in maps, name is case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "medkit" => Some(ItemKind::Medkit),
+            "mega" | "megahealth" => Some(ItemKind::MegaHealth),
+            "armor" | "armor_green" => Some(ItemKind::Armor),
+            "armor_yellow" => Some(ItemKind::YellowArmor),
+            "armor_red" => Some(ItemKind::RedArmor),
+            "plasma" | "ammo_plasma" => Some(ItemKind::Plasma),
+            "ak47ammo" | "ammo_ak47" => Some(ItemKind::Ak47Ammo),
+            "m4ammo" | "ammo_m4" => Some(ItemKind::M4Ammo),
+            "rockets" | "ammo_rocket" | "ammo_rockets" => Some(ItemKind::Rockets),
+            "plasmagun" | "plasmarifle" => Some(ItemKind::PlasmaGun),
+            "ak47" => Some(ItemKind::Ak47),
+            "m4" => Some(ItemKind::M4),
+            "rocketlauncher" => Some(ItemKind::RocketLauncher),
+            _ => None,
+        }
+    }
+
+    /// Name that is shown to players, weapon items are called after their weapons.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ItemKind::Medkit => "Medkit",
+            ItemKind::MegaHealth => "Mega Health",
+            ItemKind::Armor => "Green Armor",
+            ItemKind::YellowArmor => "Yellow Armor",
+            ItemKind::RedArmor => "Red Armor",
+            ItemKind::Plasma => "Plasma Cells",
+            ItemKind::Ak47Ammo => "AK-47 Ammo",
+            ItemKind::M4Ammo => "M4 Ammo",
+            ItemKind::Rockets => "Rockets",
+            ItemKind::PlasmaGun | ItemKind::Ak47 | ItemKind::M4 | ItemKind::RocketLauncher => {
+                self.weapon_kind().unwrap().display_name()
+            }
+        }
+    }
+
+    /// Icon that is shown next to notifications about this item.
+    pub fn icon(self) -> &'static str {
+        match self {
+            ItemKind::Medkit | ItemKind::MegaHealth => "data/ui/health_icon.png",
+            ItemKind::Armor | ItemKind::YellowArmor | ItemKind::RedArmor => {
+                "data/ui/shield_icon.png"
+            }
+            _ => "data/ui/ammo_icon.png",
+        }
+    }
+
+    /// Items that drift toward nearby actors that need them. Weapons and powerups are not
+    /// magnetic, they have to be taken deliberately.
+    pub const MAGNETIC: [ItemKind; 5] = [
+        ItemKind::Medkit,
+        ItemKind::Plasma,
+        ItemKind::Ak47Ammo,
+        ItemKind::M4Ammo,
+        ItemKind::Rockets,
+    ];
+
+    pub fn is_magnetic(self) -> bool {
+        Self::MAGNETIC.contains(&self)
+    }
+
+    /// Returns tier of armor that is given by this item.
+    pub fn armor_tier(self) -> Option<ArmorTier> {
+        match self {
+            ItemKind::Armor => Some(ArmorTier::Green),
+            ItemKind::YellowArmor => Some(ArmorTier::Yellow),
+            ItemKind::RedArmor => Some(ArmorTier::Red),
+            _ => None,
+        }
+    }
+
+    pub fn is_weapon(self) -> bool {
+        self.weapon_kind().is_some()
+    }
+
+    /// Items worth planning around: weapons, mega health and heavy armor. Spectators see
+    /// respawn timers of such items.
+    pub fn is_major(self) -> bool {
+        self.is_weapon()
+            || matches!(
+                self,
+                ItemKind::MegaHealth | ItemKind::YellowArmor | ItemKind::RedArmor
+            )
+    }
+
+    /// Returns item which gives weapon of given kind, if weapon can be dropped as an item.
+    pub fn from_weapon_kind(kind: WeaponKind) -> Option<Self> {
+        match kind {
+            WeaponKind::M4 => Some(ItemKind::M4),
+            WeaponKind::Ak47 => Some(ItemKind::Ak47),
+            WeaponKind::PlasmaRifle => Some(ItemKind::PlasmaGun),
+            WeaponKind::RocketLauncher => Some(ItemKind::RocketLauncher),
+            WeaponKind::MineLayer | WeaponKind::Grenade => None,
+        }
+    }
+
+    /// Returns kind of weapon that is given by this item.
+    pub fn weapon_kind(self) -> Option<WeaponKind> {
+        match self {
+            ItemKind::Ak47 => Some(WeaponKind::Ak47),
+            ItemKind::PlasmaGun => Some(WeaponKind::PlasmaRifle),
+            ItemKind::M4 => Some(WeaponKind::M4),
+            ItemKind::RocketLauncher => Some(WeaponKind::RocketLauncher),
+            _ => None,
+        }
+    }
+
+    /// Returns kind of weapon which ammo is given by this item.
+    pub fn ammo_weapon_kind(self) -> Option<WeaponKind> {
+        match self {
+            ItemKind::Plasma => Some(WeaponKind::PlasmaRifle),
+            ItemKind::Ak47Ammo => Some(WeaponKind::Ak47),
+            ItemKind::M4Ammo => Some(WeaponKind::M4),
+            ItemKind::Rockets => Some(WeaponKind::RocketLauncher),
+            _ => None,
+        }
+    }
+}
+
+/// Actor that attracts magnetic items, see [`ItemKind::is_magnetic`].
+pub struct ItemMagnet {
+    pub position: Vector3<f32>,
+    /// Kinds of items that would not be wasted on the actor.
+    pub wanted: Vec<ItemKind>,
 }
 
-#[derive(Visit)]
 pub struct Item {
     kind: ItemKind,
     pivot: Handle<Node>,
+    /// Place of the item on level, magnetic item returns there when it respawns.
+    home: Vector3<f32>,
+    /// Current speed of magnetic drift.
+    drift_speed: f32,
     model: Handle<Node>,
     offset: Vector3<f32>,
     dest_offset: Vector3<f32>,
     offset_factor: f32,
     reactivation_timer: f32,
     active: bool,
-    #[visit(skip)]
-    pub sender: Option<Sender<Message>>,
+    pub sender: MessageSender,
     lifetime: Option<f32>,
+    /// Overrides reactivation interval from definition, can be set per item in a map.
+    respawn_time: Option<f32>,
+    /// Light that makes item glow while player is close to it.
+    highlight: Handle<Node>,
+    highlighted: bool,
+    /// Time left until item can be picked up, dropped weapons are not taken back instantly.
+    pickup_delay: f32,
+}
+
+impl Visit for Item {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.kind.visit("kind", visitor)?;
+        self.pivot.visit("pivot", visitor)?;
+        self.model.visit("model", visitor)?;
+        self.offset.visit("offset", visitor)?;
+        self.dest_offset.visit("dest_offset", visitor)?;
+        self.offset_factor.visit("offset_factor", visitor)?;
+        self.reactivation_timer
+            .visit("reactivation_timer", visitor)?;
+        self.active.visit("active", visitor)?;
+        self.lifetime.visit("lifetime", visitor)?;
+
+        // Saves made before item highlighting and magnets have no such fields, highlight and home
+        // are restored after load, see `restore_missing_nodes`.
+        let _ = self.home.visit("home", visitor);
+        let _ = self.highlight.visit("highlight", visitor);
+
+        // Saves made before per-item respawn time and dropped weapons have no such fields.
+        let _ = self.respawn_time.visit("respawn_time", visitor);
+        let _ = self.pickup_delay.visit("pickup_delay", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+/// Creates light that makes item glow, it is hidden until player comes close.
+fn build_highlight(graph: &mut Graph, pivot: Handle<Node>) -> Handle<Node> {
+    let highlight = PointLightBuilder::new(
+        BaseLightBuilder::new(BaseBuilder::new().with_visibility(false))
+            .with_color(Color::opaque(255, 240, 200))
+            .with_scatter_enabled(false)
+            .cast_shadows(false),
+    )
+    .with_radius(HIGHLIGHT_LIGHT_RADIUS)
+    .build(graph);
+    graph.link_nodes(highlight, pivot);
+    highlight
 }
 
 impl Default for Item {
@@ -49,14 +268,20 @@ impl Default for Item {
         Self {
             kind: ItemKind::Medkit,
             pivot: Default::default(),
+            home: Default::default(),
+            drift_speed: 0.0,
             model: Default::default(),
             offset: Default::default(),
             dest_offset: Default::default(),
             offset_factor: 0.0,
             reactivation_timer: 0.0,
             active: true,
-            sender: None,
+            sender: Default::default(),
             lifetime: None,
+            respawn_time: None,
+            highlight: Handle::NONE,
+            highlighted: false,
+            pickup_delay: 0.0,
         }
     }
 }
@@ -78,6 +303,38 @@ impl Item {
                 };
                 &DEFINITION
             }
+            ItemKind::MegaHealth => {
+                static DEFINITION: ItemDefinition = ItemDefinition {
+                    model: "data/models/medkit.fbx",
+                    scale: 1.5,
+                    reactivation_interval: 60.0,
+                };
+                &DEFINITION
+            }
+            ItemKind::Armor => {
+                static DEFINITION: ItemDefinition = ItemDefinition {
+                    model: "data/models/box_large.FBX",
+                    scale: 0.30,
+                    reactivation_interval: 25.0,
+                };
+                &DEFINITION
+            }
+            ItemKind::YellowArmor => {
+                static DEFINITION: ItemDefinition = ItemDefinition {
+                    model: "data/models/box_large.FBX",
+                    scale: 0.35,
+                    reactivation_interval: 30.0,
+                };
+                &DEFINITION
+            }
+            ItemKind::RedArmor => {
+                static DEFINITION: ItemDefinition = ItemDefinition {
+                    model: "data/models/box_large.FBX",
+                    scale: 0.40,
+                    reactivation_interval: 40.0,
+                };
+                &DEFINITION
+            }
             ItemKind::Plasma => {
                 static DEFINITION: ItemDefinition = ItemDefinition {
                     model: "data/models/yellow_box.FBX",
@@ -102,6 +359,14 @@ impl Item {
                 };
                 &DEFINITION
             }
+            ItemKind::Rockets => {
+                static DEFINITION: ItemDefinition = ItemDefinition {
+                    model: "data/models/box_small.FBX",
+                    scale: 0.25,
+                    reactivation_interval: 20.0,
+                };
+                &DEFINITION
+            }
             ItemKind::PlasmaGun => {
                 static DEFINITION: ItemDefinition = ItemDefinition {
                     model: "data/models/plasma_rifle.FBX",
@@ -142,7 +407,7 @@ impl Item {
         position: Vector3<f32>,
         scene: &mut Scene,
         resource_manager: ResourceManager,
-        sender: Sender<Message>,
+        sender: MessageSender,
     ) -> Self {
         let definition = Self::get_definition(kind);
 
@@ -168,11 +433,15 @@ impl Item {
 
         scene.graph.link_nodes(model, pivot);
 
+        let highlight = build_highlight(&mut scene.graph, pivot);
+
         Self {
             pivot,
+            home: position,
             kind,
             model,
-            sender: Some(sender),
+            sender,
+            highlight,
             ..Default::default()
         }
     }
@@ -181,10 +450,70 @@ impl Item {
         self.pivot
     }
 
+    /// Model bobs up and down under the pivot.
+    pub fn get_model(&self) -> Handle<Node> {
+        self.model
+    }
+
+    /// Saves made before item highlighting and magnets have neither highlight light nor home,
+    /// light is created again and home is where the item is. Returns true if something was
+    /// missing. Global transforms of `graph` must be up to date.
+    pub fn restore_missing_nodes(&mut self, graph: &mut Graph) -> bool {
+        if graph.is_valid_handle(self.highlight) {
+            return false;
+        }
+        self.highlight = build_highlight(graph, self.pivot);
+        self.home = graph[self.pivot].global_position();
+        true
+    }
+
     pub fn position(&self, graph: &Graph) -> Vector3<f32> {
         graph[self.pivot].global_position()
     }
 
+    /// Moves magnetic item toward the closest magnet that wants it, or back to its place if
+    /// there is none. Item is taken on contact as usual.
+    fn update_drift(&mut self, graph: &mut Graph, magnets: &[ItemMagnet], dt: f32) {
+        let position = graph[self.pivot].global_position();
+        if !self.is_available() || !self.kind.is_magnetic() {
+            if position != self.home {
+                graph[self.pivot]
+                    .local_transform_mut()
+                    .set_position(self.home);
+            }
+            self.drift_speed = 0.0;
+            return;
+        }
+
+        let kind = self.kind;
+        let magnet = magnets
+            .iter()
+            .filter(|magnet| magnet.wanted.contains(&kind))
+            .map(|magnet| (magnet.position, magnet.position.metric_distance(&position)))
+            .filter(|(_, distance)| *distance < MAGNET_RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+
+        let (target, speed) = match magnet {
+            Some((target, _)) => {
+                self.drift_speed += MAGNET_ACCELERATION * dt;
+                (target, self.drift_speed)
+            }
+            None => {
+                self.drift_speed = 0.0;
+                (self.home, MAGNET_RETURN_SPEED)
+            }
+        };
+
+        let to_target = target - position;
+        let distance = to_target.norm();
+        if distance > f32::EPSILON {
+            let step = to_target.scale((speed * dt).min(distance) / distance);
+            graph[self.pivot]
+                .local_transform_mut()
+                .set_position(position + step);
+        }
+    }
+
     pub fn update(&mut self, graph: &mut Graph, time: GameTime) {
         self.offset_factor += 1.2 * time.delta;
 
@@ -198,19 +527,25 @@ impl Item {
         model.set_visibility(!self.is_picked_up());
         model.local_transform_mut().set_position(self.offset);
 
+        self.pickup_delay = (self.pickup_delay - time.delta).max(0.0);
+
+        let glow = self.highlighted && !self.is_picked_up();
+        let highlight = &mut graph[self.highlight];
+        highlight.set_visibility(glow);
+        if let Some(light) = highlight.cast_mut::<PointLight>() {
+            let pulse = 0.8 + 0.2 * (4.0 * self.offset_factor).sin();
+            light.set_radius(HIGHLIGHT_LIGHT_RADIUS * pulse);
+        }
+
         if !self.active {
             self.reactivation_timer -= time.delta;
             if self.reactivation_timer <= 0.0 {
                 self.active = true;
 
-                self.sender
-                    .as_ref()
-                    .unwrap()
-                    .send(Message::CreateEffect {
-                        kind: EffectKind::ItemAppear,
-                        position,
-                    })
-                    .unwrap();
+                self.sender.send(Message::CreateEffect {
+                    kind: EffectKind::ItemAppear,
+                    position,
+                });
             }
         }
     }
@@ -224,7 +559,9 @@ impl Item {
     }
 
     pub fn pick_up(&mut self) {
-        self.reactivation_timer = self.definition().reactivation_interval;
+        self.reactivation_timer = self
+            .respawn_time
+            .unwrap_or(self.definition().reactivation_interval);
         self.active = false;
     }
 
@@ -232,6 +569,29 @@ impl Item {
         !self.active
     }
 
+    /// Seconds until picked up item appears again, `None` if item is in place or will not
+    /// come back (dropped weapons disappear for good).
+    pub fn respawn_remaining(&self) -> Option<f32> {
+        if self.active || self.lifetime.is_some() {
+            None
+        } else {
+            Some(self.reactivation_timer.max(0.0))
+        }
+    }
+
+    /// Returns true if actors can take the item right now.
+    pub fn is_available(&self) -> bool {
+        self.active && self.pickup_delay <= 0.0
+    }
+
+    pub fn set_pickup_delay(&mut self, delay: f32) {
+        self.pickup_delay = delay;
+    }
+
+    pub fn set_highlighted(&mut self, highlighted: bool) {
+        self.highlighted = highlighted;
+    }
+
     fn cleanup(&self, graph: &mut Graph) {
         graph.remove_node(self.pivot)
     }
@@ -243,9 +603,26 @@ impl Item {
         }
     }
 
+    /// Called when item ended up outside of world bounds: temporary item is removed, constant
+    /// one goes back to its place.
+    fn leave_world(&mut self, graph: &mut Graph) {
+        if self.lifetime.is_some() {
+            self.lifetime = Some(0.0);
+        } else {
+            graph[self.pivot]
+                .local_transform_mut()
+                .set_position(self.home);
+            self.drift_speed = 0.0;
+        }
+    }
+
     pub fn set_lifetime(&mut self, lifetime: Option<f32>) {
         self.lifetime = lifetime;
     }
+
+    pub fn set_respawn_time(&mut self, respawn_time: Option<f32>) {
+        self.respawn_time = respawn_time;
+    }
 }
 
 #[derive(Visit)]
@@ -268,6 +645,10 @@ impl ItemContainer {
         self.pool.spawn(item)
     }
 
+    pub fn get(&self, item: Handle<Item>) -> &Item {
+        self.pool.borrow(item)
+    }
+
     pub fn get_mut(&mut self, item: Handle<Item>) -> &mut Item {
         self.pool.borrow_mut(item)
     }
@@ -288,9 +669,21 @@ impl ItemContainer {
         self.pool.iter_mut()
     }
 
-    pub fn update(&mut self, scene: &mut Scene, time: GameTime) {
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        magnets: &[ItemMagnet],
+        world_bounds: Option<&AxisAlignedBoundingBox>,
+        time: GameTime,
+    ) {
         for item in self.pool.iter_mut() {
+            item.update_drift(&mut scene.graph, magnets, time.delta);
             item.update(&mut scene.graph, time);
+            if let Some(world_bounds) = world_bounds {
+                if !world_bounds.is_contains_point(item.position(&scene.graph)) {
+                    item.leave_world(&mut scene.graph);
+                }
+            }
         }
 
         // Remove temporary items.
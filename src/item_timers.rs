@@ -0,0 +1,191 @@
+//! Respawn timers above major items that were picked up, shown while player spectates (is
+//! dead or flies free camera) so the next life can be planned. Like health bars, timers are
+//! ordinary widgets that follow projected positions of items.
+
+use crate::gui;
+use fyrox::{
+    core::{
+        algebra::{Vector2, Vector3},
+        color::Color,
+        pool::Handle,
+    },
+    engine::resource_manager::ResourceManager,
+    gui::{
+        brush::Brush,
+        canvas::CanvasBuilder,
+        image::{ImageBuilder, ImageMessage},
+        message::MessageDirection,
+        stack_panel::StackPanelBuilder,
+        text::{TextBuilder, TextMessage},
+        widget::{WidgetBuilder, WidgetMessage},
+        BuildContext, Orientation, Thickness, UiNode, UserInterface, VerticalAlignment,
+    },
+    scene::camera::Camera,
+    utils,
+};
+
+/// Amount of timers shown at once, only the nearest items get them.
+pub const MAX_TIMERS: usize = 6;
+const ICON_SIZE: f32 = 20.0;
+const TIMER_WIDTH: f32 = 60.0;
+const TIMER_COLOR: Color = Color::opaque(255, 255, 255);
+/// Timers of items behind walls are faded, they are still useful but less urgent.
+const OCCLUDED_TIMER_COLOR: Color = Color::from_rgba(255, 255, 255, 90);
+
+/// Item that needs a timer, see [`crate::level::Level::item_timers`].
+pub struct ItemTimer {
+    /// Point in the world right above the item.
+    pub position: Vector3<f32>,
+    pub icon: &'static str,
+    /// Seconds until the item appears again.
+    pub remaining: f32,
+    /// Level geometry is between the camera and the item.
+    pub occluded: bool,
+}
+
+/// Widgets of a single timer and the last values sent to them.
+struct Slot {
+    root: Handle<UiNode>,
+    icon: Handle<UiNode>,
+    text: Handle<UiNode>,
+    shown: Option<(&'static str, u32, bool)>,
+}
+
+pub struct ItemTimers {
+    root: Handle<UiNode>,
+    slots: Vec<Slot>,
+    resource_manager: ResourceManager,
+}
+
+impl ItemTimers {
+    pub fn new(
+        ctx: &mut BuildContext,
+        resource_manager: ResourceManager,
+        frame_size: (u32, u32),
+    ) -> Self {
+        let slots = (0..MAX_TIMERS)
+            .map(|_| {
+                let icon = ImageBuilder::new(
+                    WidgetBuilder::new()
+                        .with_width(ICON_SIZE)
+                        .with_height(ICON_SIZE)
+                        .with_margin(Thickness::right(3.0)),
+                )
+                .build(ctx);
+                let text = TextBuilder::new(
+                    WidgetBuilder::new()
+                        .with_vertical_alignment(VerticalAlignment::Center)
+                        .with_foreground(Brush::Solid(TIMER_COLOR)),
+                )
+                .build(ctx);
+                let root = StackPanelBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_width(TIMER_WIDTH)
+                        .with_child(icon)
+                        .with_child(text),
+                )
+                .with_orientation(Orientation::Horizontal)
+                .build(ctx);
+                Slot {
+                    root,
+                    icon,
+                    text,
+                    shown: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let root = CanvasBuilder::new(
+            WidgetBuilder::new()
+                .with_width(frame_size.0 as f32)
+                .with_height(frame_size.1 as f32)
+                .with_hit_test_visibility(false)
+                .with_children(slots.iter().map(|slot| slot.root)),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            slots,
+            resource_manager,
+        }
+    }
+
+    pub fn root(&self) -> Handle<UiNode> {
+        self.root
+    }
+
+    /// Places timers on screen, timers are hidden while there is no camera to project them
+    /// with.
+    pub fn update(
+        &mut self,
+        ui: &mut UserInterface,
+        camera: Option<&Camera>,
+        screen_size: Vector2<f32>,
+        timers: &[ItemTimer],
+    ) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let placement = timers.get(i).and_then(|timer| {
+                camera
+                    .and_then(|camera| gui::world_to_screen(camera, timer.position, screen_size))
+                    .map(|screen_position| (timer, screen_position))
+            });
+
+            let (timer, screen_position) = match placement {
+                Some(placement) => placement,
+                None => {
+                    if slot.shown.take().is_some() {
+                        ui.send_message(WidgetMessage::visibility(
+                            slot.root,
+                            MessageDirection::ToWidget,
+                            false,
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            let shown = (timer.icon, timer.remaining.ceil() as u32, timer.occluded);
+            let previous = slot.shown.replace(shown);
+            if previous.is_none() {
+                ui.send_message(WidgetMessage::visibility(
+                    slot.root,
+                    MessageDirection::ToWidget,
+                    true,
+                ));
+            }
+            if previous.map(|(icon, _, _)| icon) != Some(timer.icon) {
+                ui.send_message(ImageMessage::texture(
+                    slot.icon,
+                    MessageDirection::ToWidget,
+                    Some(utils::into_gui_texture(
+                        self.resource_manager.request_texture(timer.icon),
+                    )),
+                ));
+            }
+            if previous != Some(shown) {
+                ui.send_message(TextMessage::text(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    format!("{}s", shown.1),
+                ));
+                ui.send_message(WidgetMessage::foreground(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(if timer.occluded {
+                        OCCLUDED_TIMER_COLOR
+                    } else {
+                        TIMER_COLOR
+                    }),
+                ));
+            }
+
+            ui.send_message(WidgetMessage::desired_position(
+                slot.root,
+                MessageDirection::ToWidget,
+                screen_position - Vector2::new(TIMER_WIDTH * 0.5, ICON_SIZE),
+            ));
+        }
+    }
+}
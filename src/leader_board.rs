@@ -1,12 +1,14 @@
-use crate::{character::Team, message::Message, MatchOptions};
+use crate::{
+    character::Team, gui, message::Message, player::PLAYER_NAME, weapon::WeaponKind, MatchOptions,
+};
 use fyrox::{
     core::{
+        algebra::Vector2,
         color::Color,
         pool::Handle,
         visitor::{Visit, VisitResult, Visitor},
     },
     engine::Engine,
-    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
     gui::{
         brush::Brush,
         grid::{Column, GridBuilder, Row},
@@ -18,10 +20,14 @@ use fyrox::{
 };
 use std::collections::HashMap;
 
-#[derive(Copy, Clone, Visit)]
+#[derive(Copy, Clone)]
 pub struct PersonalScore {
     pub kills: u32,
     pub deaths: u32,
+    pub is_bot: bool,
+    /// Player is AFK, such entry stays in the table but can't lead. It is not saved, AFK
+    /// player comes back on load.
+    pub is_afk: bool,
 }
 
 impl Default for PersonalScore {
@@ -29,14 +35,114 @@ impl Default for PersonalScore {
         Self {
             kills: 0,
             deaths: 0,
+            is_bot: false,
+            is_afk: false,
         }
     }
 }
 
-#[derive(Visit)]
+impl Visit for PersonalScore {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.kills.visit("kills", visitor)?;
+        self.deaths.visit("deaths", visitor)?;
+        // Saves made before bots were tagged have no such field, every actor in them
+        // will be shown as a human.
+        let _ = self.is_bot.visit("is_bot", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+/// Damage and kills of a single weapon.
+#[derive(Copy, Clone, Default, Debug, Visit)]
+pub struct WeaponScore {
+    pub damage: f32,
+    pub kills: u32,
+}
+
+/// Damage and kills of an actor broken down by weapon.
+#[derive(Clone, Default, Debug, Visit)]
+pub struct WeaponStats {
+    weapons: HashMap<WeaponKind, WeaponScore>,
+}
+
+impl WeaponStats {
+    pub fn add_damage(&mut self, weapon: WeaponKind, amount: f32) {
+        self.weapons.entry(weapon).or_default().damage += amount;
+    }
+
+    pub fn add_kill(&mut self, weapon: WeaponKind) {
+        self.weapons.entry(weapon).or_default().kills += 1;
+    }
+
+    pub fn get(&self, weapon: WeaponKind) -> WeaponScore {
+        self.weapons.get(&weapon).copied().unwrap_or_default()
+    }
+
+    /// Weapon that dealt the most damage, the first one in [`WeaponKind::ALL`] wins between
+    /// equal values so the result does not depend on hash map order.
+    pub fn most_used(&self) -> Option<WeaponKind> {
+        self.best_by(|score| score.damage)
+    }
+
+    /// Weapon with the most kills.
+    pub fn most_lethal(&self) -> Option<WeaponKind> {
+        self.best_by(|score| score.kills as f32)
+    }
+
+    fn best_by<F: Fn(&WeaponScore) -> f32>(&self, value: F) -> Option<WeaponKind> {
+        let mut best = None;
+        for kind in WeaponKind::ALL.iter() {
+            let value = value(&self.get(*kind));
+            match best {
+                Some((_, best_value)) if value <= best_value => (),
+                _ if value > 0.0 => best = Some((*kind, value)),
+                _ => (),
+            }
+        }
+        best.map(|(kind, _)| kind)
+    }
+
+    /// Adds values of other stats to these ones.
+    pub fn merge(&mut self, other: &WeaponStats) {
+        for (kind, score) in other.weapons.iter() {
+            let entry = self.weapons.entry(*kind).or_default();
+            entry.damage += score.damage;
+            entry.kills += score.kills;
+        }
+    }
+}
+
+/// Who holds first place, see [`LeaderBoard::lead`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Lead {
+    /// Nobody has scored yet.
+    Nobody,
+    Single(String),
+    /// At least two actors share the highest score.
+    Tied,
+}
+
 pub struct LeaderBoard {
     personal_score: HashMap<String, PersonalScore>,
     team_score: HashMap<Team, u32>,
+    weapon_stats: HashMap<String, WeaponStats>,
+}
+
+impl Visit for LeaderBoard {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.personal_score.visit("personal_score", visitor)?;
+        self.team_score.visit("team_score", visitor)?;
+
+        // Saves made before weapon stats have no such field, stats are counted from the load.
+        let _ = self.weapon_stats.visit("weapon_stats", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 impl LeaderBoard {
@@ -46,6 +152,11 @@ impl LeaderBoard {
             .or_insert_with(Default::default)
     }
 
+    pub fn remove_actor<P: AsRef<str>>(&mut self, actor_name: P) {
+        self.personal_score.remove(actor_name.as_ref());
+        self.weapon_stats.remove(actor_name.as_ref());
+    }
+
     pub fn add_frag<P: AsRef<str>>(&mut self, actor_name: P) {
         self.get_or_add_actor(actor_name).kills += 1;
     }
@@ -54,6 +165,29 @@ impl LeaderBoard {
         self.get_or_add_actor(actor_name).deaths += 1;
     }
 
+    pub fn add_weapon_damage<P: AsRef<str>>(
+        &mut self,
+        actor_name: P,
+        weapon: WeaponKind,
+        amount: f32,
+    ) {
+        self.weapon_stats
+            .entry(actor_name.as_ref().to_owned())
+            .or_default()
+            .add_damage(weapon, amount);
+    }
+
+    pub fn add_weapon_kill<P: AsRef<str>>(&mut self, actor_name: P, weapon: WeaponKind) {
+        self.weapon_stats
+            .entry(actor_name.as_ref().to_owned())
+            .or_default()
+            .add_kill(weapon);
+    }
+
+    pub fn weapon_stats<P: AsRef<str>>(&self, actor_name: P) -> Option<&WeaponStats> {
+        self.weapon_stats.get(actor_name.as_ref())
+    }
+
     pub fn team_score(&self, team: Team) -> u32 {
         match self.team_score.get(&team) {
             None => 0,
@@ -63,11 +197,14 @@ impl LeaderBoard {
 
     /// Returns record about leader as a pair of character name and its score.
     /// `except` parameter can be used to exclude already found leader and search
-    /// for a character at second place.
+    /// for a character at second place. AFK players are skipped.
     pub fn highest_personal_score(&self, except: Option<&str>) -> Option<(&str, u32)> {
         let mut pair = None;
 
         for (name, score) in self.personal_score.iter() {
+            if score.is_afk {
+                continue;
+            }
             if let Some(except) = except {
                 if name == except {
                     continue;
@@ -86,10 +223,54 @@ impl LeaderBoard {
         pair
     }
 
+    /// Returns names and kills of first and second places.
+    pub fn top_two(&self) -> (Option<(&str, u32)>, Option<(&str, u32)>) {
+        let mut values = self.sorted_values().into_iter();
+        let mut next = || values.next().map(|(name, score)| (name, score.kills));
+        let first = next();
+        (first, next())
+    }
+
+    pub fn lead(&self) -> Lead {
+        match self.top_two() {
+            (None, _) | (Some((_, 0)), _) => Lead::Nobody,
+            (Some((_, first)), Some((_, second))) if first == second => Lead::Tied,
+            (Some((name, _)), _) => Lead::Single(name.to_owned()),
+        }
+    }
+
+    /// Returns true if given actor has the highest score, alone or tied with others.
+    pub fn is_leading<P: AsRef<str>>(&self, actor_name: P) -> bool {
+        match (
+            self.top_two().0,
+            self.personal_score.get(actor_name.as_ref()),
+        ) {
+            (Some((_, top)), Some(score)) => top > 0 && score.kills == top,
+            _ => false,
+        }
+    }
+
     pub fn values(&self) -> &HashMap<String, PersonalScore> {
         &self.personal_score
     }
 
+    /// Returns scores in the order they are shown in the table: by kills, humans above bots
+    /// at equal kills, then by name so the order is stable between rebuilds.
+    pub fn sorted_values(&self) -> Vec<(&str, &PersonalScore)> {
+        let mut values = self
+            .personal_score
+            .iter()
+            .map(|(name, score)| (name.as_str(), score))
+            .collect::<Vec<_>>();
+        values.sort_by(|(a_name, a), (b_name, b)| {
+            b.kills
+                .cmp(&a.kills)
+                .then(a.is_bot.cmp(&b.is_bot))
+                .then(a_name.cmp(b_name))
+        });
+        values
+    }
+
     pub fn is_match_over(&self, options: &MatchOptions) -> bool {
         match options {
             MatchOptions::DeathMatch(dm) => {
@@ -115,6 +296,7 @@ impl LeaderBoard {
                 }
                 false
             }
+            MatchOptions::Practice(_) => false,
         }
     }
 }
@@ -124,12 +306,24 @@ impl Default for LeaderBoard {
         Self {
             personal_score: Default::default(),
             team_score: Default::default(),
+            weapon_stats: Default::default(),
         }
     }
 }
 
 pub struct LeaderBoardUI {
     root: Handle<UiNode>,
+    /// Table is rebuilt at most once per frame, no matter how many actors were spawned.
+    dirty: bool,
+    // Reasons to show the table, see `sync_visibility`.
+    key_held: bool,
+    /// Scoreboard key state comes with a message, widget is updated on next flush.
+    visibility_dirty: bool,
+    player_dead: bool,
+    match_over: bool,
+    hud_visible: bool,
+    /// Line about personal best of local player, it is shown under final results.
+    personal_record: Option<String>,
 }
 
 impl LeaderBoardUI {
@@ -148,10 +342,19 @@ impl LeaderBoardUI {
         .add_row(Row::strict(600.0))
         .add_row(Row::stretch())
         .add_column(Column::stretch())
-        .add_column(Column::strict(500.0))
+        .add_column(Column::strict(600.0))
         .add_column(Column::stretch())
         .build(&mut ui.build_ctx());
-        Self { root }
+        Self {
+            root,
+            dirty: false,
+            key_held: false,
+            visibility_dirty: false,
+            player_dead: false,
+            match_over: false,
+            hud_visible: false,
+            personal_record: None,
+        }
     }
 
     fn sync_to_model(
@@ -171,41 +374,15 @@ impl LeaderBoardUI {
 
         let mut children = Vec::new();
 
-        for (i, (name, score)) in leader_board.values().iter().enumerate() {
+        for (i, (name, score)) in leader_board.sorted_values().into_iter().enumerate() {
             let row = i + 1;
 
-            children.push(
-                TextBuilder::new(
-                    WidgetBuilder::new()
-                        .with_margin(Thickness::uniform(3.0))
-                        .on_row(row)
-                        .on_column(0),
-                )
-                .with_text(name)
-                .build(ctx),
-            );
-
-            children.push(
-                TextBuilder::new(
-                    WidgetBuilder::new()
-                        .with_margin(Thickness::uniform(3.0))
-                        .on_row(row)
-                        .on_column(1),
-                )
-                .with_text(format!("{}", score.kills))
-                .build(ctx),
-            );
-
-            children.push(
-                TextBuilder::new(
-                    WidgetBuilder::new()
-                        .with_margin(Thickness::uniform(3.0))
-                        .on_row(row)
-                        .on_column(2),
-                )
-                .with_text(format!("{}", score.deaths))
-                .build(ctx),
-            );
+            // Local player's row is highlighted so it can be found at a glance.
+            let foreground = if name == PLAYER_NAME {
+                Brush::Solid(Color::opaque(255, 200, 0))
+            } else {
+                Brush::Solid(Color::WHITE)
+            };
 
             let kd = if score.deaths != 0 {
                 format!("{}", score.kills as f32 / score.deaths as f32)
@@ -213,16 +390,47 @@ impl LeaderBoardUI {
                 "N/A".to_owned()
             };
 
-            children.push(
-                TextBuilder::new(
-                    WidgetBuilder::new()
-                        .with_margin(Thickness::uniform(3.0))
-                        .on_row(row)
-                        .on_column(3),
-                )
-                .with_text(kd)
-                .build(ctx),
-            );
+            // Most used and most lethal weapons, the latter is missing until the first kill.
+            let weapons = match leader_board.weapon_stats(name) {
+                Some(stats) => match (stats.most_used(), stats.most_lethal()) {
+                    (Some(used), Some(lethal)) => {
+                        format!("{} / {}", used.short_name(), lethal.short_name())
+                    }
+                    (Some(used), None) => format!("{} / -", used.short_name()),
+                    _ => "-".to_owned(),
+                },
+                None => "-".to_owned(),
+            };
+
+            let cells = [
+                name.to_owned(),
+                format!("{}", score.kills),
+                format!("{}", score.deaths),
+                kd,
+                weapons,
+                if score.is_bot {
+                    "BOT"
+                } else if score.is_afk {
+                    "AFK"
+                } else {
+                    ""
+                }
+                .to_owned(),
+            ];
+
+            for (column, text) in cells.iter().enumerate() {
+                children.push(
+                    TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_margin(Thickness::uniform(3.0))
+                            .with_foreground(foreground.clone())
+                            .on_row(row)
+                            .on_column(column),
+                    )
+                    .with_text(text)
+                    .build(ctx),
+                );
+            }
         }
 
         let table = GridBuilder::new(
@@ -238,17 +446,13 @@ impl LeaderBoardUI {
                             .with_horizontal_alignment(HorizontalAlignment::Center),
                     )
                     .with_text({
-                        let time_limit_secs = match match_options {
-                            MatchOptions::DeathMatch(dm) => dm.time_limit_secs,
-                            MatchOptions::TeamDeathMatch(tdm) => tdm.time_limit_secs,
-                            MatchOptions::CaptureTheFlag(ctf) => ctf.time_limit_secs,
-                        };
+                        let time_limit_secs = match_options.time_limit_secs();
 
                         let seconds = (time_limit_secs % 60.0) as u32;
                         let minutes = (time_limit_secs / 60.0) as u32;
                         let hours = (time_limit_secs / 3600.0) as u32;
 
-                        match match_options {
+                        let title = match match_options {
                             MatchOptions::DeathMatch(_) => format!(
                                 "Death Match - Time Limit {:02}:{:02}:{:02}",
                                 hours, minutes, seconds
@@ -261,6 +465,11 @@ impl LeaderBoardUI {
                                 "Capture The Flag - Time Limit {:02}:{:02}:{:02}",
                                 hours, minutes, seconds
                             ),
+                            MatchOptions::Practice(_) => "Practice - No Time Limit".to_owned(),
+                        };
+                        match match_options.mutators().describe() {
+                            Some(mutators) => format!("{}\nMutators: {}", title, mutators),
+                            None => title,
                         }
                     })
                     .build(ctx),
@@ -327,6 +536,18 @@ impl LeaderBoardUI {
                             ))
                             .build(ctx)
                         }
+                        MatchOptions::Practice(_) => TextBuilder::new(
+                            WidgetBuilder::new()
+                                .with_margin(Thickness::uniform(5.0))
+                                .with_horizontal_alignment(HorizontalAlignment::Center)
+                                .on_column(0)
+                                .on_row(1),
+                        )
+                        .with_text(
+                            "Shooting at target dummies
+No score limit",
+                        )
+                        .build(ctx),
                     }
                 })
                 .with_child(
@@ -379,6 +600,28 @@ impl LeaderBoardUI {
                                 .with_text("K/D")
                                 .build(ctx),
                             )
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .with_horizontal_alignment(HorizontalAlignment::Center)
+                                        .with_vertical_alignment(VerticalAlignment::Center)
+                                        .on_column(4)
+                                        .on_row(0),
+                                )
+                                .with_text("Weapons")
+                                .build(ctx),
+                            )
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .with_horizontal_alignment(HorizontalAlignment::Center)
+                                        .with_vertical_alignment(VerticalAlignment::Center)
+                                        .on_column(5)
+                                        .on_row(0),
+                                )
+                                .with_text("Type")
+                                .build(ctx),
+                            )
                             .with_children(children),
                     )
                     .with_border_thickness(2.0)
@@ -393,14 +636,30 @@ impl LeaderBoardUI {
                     .add_column(Column::stretch())
                     .add_column(Column::stretch())
                     .add_column(Column::stretch())
+                    .add_column(Column::strict(100.0))
+                    .add_column(Column::strict(60.0))
                     .draw_border(true)
                     .build(ctx),
+                )
+                .with_child(
+                    TextBuilder::new(
+                        WidgetBuilder::new()
+                            .with_margin(Thickness::uniform(5.0))
+                            .with_horizontal_alignment(HorizontalAlignment::Center)
+                            .with_foreground(Brush::Solid(Color::opaque(255, 200, 0)))
+                            .with_visibility(self.personal_record.is_some())
+                            .on_column(0)
+                            .on_row(3),
+                    )
+                    .with_text(self.personal_record.clone().unwrap_or_default())
+                    .build(ctx),
                 ),
         )
         .add_column(Column::auto())
         .add_row(Row::auto())
         .add_row(Row::auto())
         .add_row(Row::stretch())
+        .add_row(Row::auto())
         .build(ctx);
 
         if let Some(table) = ctx[self.root].children().first() {
@@ -414,60 +673,172 @@ impl LeaderBoardUI {
         ));
     }
 
-    pub fn set_visible(&self, visible: bool, ui: &mut UserInterface) {
+    /// Table is shown while scoreboard key is held or while player waits for respawn, but only
+    /// in game. Final results stay visible after match end even without HUD. Releasing the key
+    /// while player is still dead keeps the table.
+    fn sync_visibility(&self, ui: &mut UserInterface) {
         ui.send_message(WidgetMessage::visibility(
             self.root,
             MessageDirection::ToWidget,
-            visible,
+            self.match_over || self.hud_visible && (self.key_held || self.player_dead),
         ));
     }
 
-    pub fn process_input_event(&mut self, engine: &mut Engine, event: &Event<()>) {
-        if let Event::WindowEvent { event, .. } = event {
-            match event {
-                WindowEvent::Resized(new_size) => {
-                    engine.user_interface.send_message(WidgetMessage::width(
-                        self.root,
-                        MessageDirection::ToWidget,
-                        new_size.width as f32,
-                    ));
-                    engine.user_interface.send_message(WidgetMessage::height(
-                        self.root,
-                        MessageDirection::ToWidget,
-                        new_size.height as f32,
-                    ));
-                }
-                WindowEvent::KeyboardInput { input, .. } => {
-                    if let Some(vk) = input.virtual_keycode {
-                        if vk == VirtualKeyCode::Tab {
-                            let visible = match input.state {
-                                ElementState::Pressed => true,
-                                ElementState::Released => false,
-                            };
+    pub fn set_player_dead(&mut self, ui: &mut UserInterface, dead: bool) {
+        self.player_dead = dead;
+        self.sync_visibility(ui);
+    }
 
-                            self.set_visible(visible, &mut engine.user_interface);
-                        }
-                    }
-                }
-                _ => {}
+    pub fn set_match_over(&mut self, ui: &mut UserInterface, match_over: bool) {
+        self.match_over = match_over;
+        self.sync_visibility(ui);
+    }
+
+    /// Sets line about personal best that is shown under final results, `None` hides it.
+    pub fn set_personal_record(&mut self, record: Option<String>) {
+        self.personal_record = record;
+        self.dirty = true;
+    }
+
+    pub fn set_hud_visible(&mut self, ui: &mut UserInterface, visible: bool) {
+        self.hud_visible = visible;
+        self.sync_visibility(ui);
+    }
+
+    pub fn resize(&mut self, ui: &mut UserInterface, size: Vector2<f32>) {
+        gui::fit_to_window(ui, self.root, size);
+    }
+
+    pub fn handle_message(&mut self, message: &Message) {
+        match message {
+            Message::AddBot { .. }
+            | Message::RemoveActor { .. }
+            | Message::RemoveBot { .. }
+            | Message::SpawnBot { .. }
+            | Message::SpawnPlayer
+            | Message::PlayerAfk
+            | Message::PlayerReturned
+            | Message::RespawnActor { .. } => self.dirty = true,
+            &Message::SetScoreboardVisible { visible } => {
+                self.key_held = visible;
+                self.visibility_dirty = true;
             }
+            _ => (),
         }
     }
 
-    pub fn handle_message(
+    /// Rebuilds table if something has changed since last sync.
+    pub fn flush(
         &mut self,
-        message: &Message,
         ui: &mut UserInterface,
         leader_board: &LeaderBoard,
         match_options: &MatchOptions,
     ) {
-        match message {
-            Message::AddBot { .. } => self.sync_to_model(ui, leader_board, match_options),
-            Message::RemoveActor { .. } => self.sync_to_model(ui, leader_board, match_options),
-            Message::SpawnBot { .. } => self.sync_to_model(ui, leader_board, match_options),
-            Message::SpawnPlayer => self.sync_to_model(ui, leader_board, match_options),
-            Message::RespawnActor { .. } => self.sync_to_model(ui, leader_board, match_options),
-            _ => (),
+        if self.dirty {
+            self.dirty = false;
+            self.sync_to_model(ui, leader_board, match_options);
         }
+        if self.visibility_dirty {
+            self.visibility_dirty = false;
+            self.sync_visibility(ui);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_back(name: &str, leader_board: &mut LeaderBoard) -> LeaderBoard {
+        let path = crate::temp_path(name);
+        let mut visitor = Visitor::new();
+        leader_board.visit("LeaderBoard", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let mut visitor =
+            fyrox::core::futures::executor::block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut loaded = LeaderBoard::default();
+        loaded.visit("LeaderBoard", &mut visitor).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn weapon_stats_are_kept_per_actor_and_weapon() {
+        let mut leader_board = LeaderBoard::default();
+        leader_board.add_weapon_damage("Player", WeaponKind::Ak47, 10.0);
+        leader_board.add_weapon_damage("Player", WeaponKind::Ak47, 15.0);
+        leader_board.add_weapon_damage("Player", WeaponKind::RocketLauncher, 100.0);
+        leader_board.add_weapon_kill("Player", WeaponKind::Ak47);
+        leader_board.add_weapon_kill("Player", WeaponKind::Ak47);
+        leader_board.add_weapon_damage("Bot 1", WeaponKind::M4, 5.0);
+
+        let player = leader_board.weapon_stats("Player").unwrap();
+        assert_eq!(player.get(WeaponKind::Ak47).damage, 25.0);
+        assert_eq!(player.get(WeaponKind::Ak47).kills, 2);
+        assert_eq!(player.get(WeaponKind::RocketLauncher).kills, 0);
+        assert_eq!(player.get(WeaponKind::M4).damage, 0.0);
+        assert_eq!(player.most_used(), Some(WeaponKind::RocketLauncher));
+        assert_eq!(player.most_lethal(), Some(WeaponKind::Ak47));
+
+        let bot = leader_board.weapon_stats("Bot 1").unwrap();
+        assert_eq!(bot.get(WeaponKind::M4).damage, 5.0);
+        assert_eq!(bot.most_lethal(), None);
+
+        // Actor that leaves the match takes its stats along.
+        leader_board.remove_actor("Bot 1");
+        assert!(leader_board.weapon_stats("Bot 1").is_none());
+        assert!(leader_board.weapon_stats("Nobody").is_none());
+    }
+
+    #[test]
+    fn equal_weapons_resolve_in_weapon_order() {
+        let mut stats = WeaponStats::default();
+        stats.add_damage(WeaponKind::PlasmaRifle, 20.0);
+        stats.add_damage(WeaponKind::Ak47, 20.0);
+        stats.add_kill(WeaponKind::Grenade);
+        stats.add_kill(WeaponKind::M4);
+        assert_eq!(stats.most_used(), Some(WeaponKind::Ak47));
+        assert_eq!(stats.most_lethal(), Some(WeaponKind::M4));
+    }
+
+    #[test]
+    fn merge_adds_up_stats() {
+        let mut total = WeaponStats::default();
+        total.add_damage(WeaponKind::Ak47, 10.0);
+        let mut other = WeaponStats::default();
+        other.add_damage(WeaponKind::Ak47, 5.0);
+        other.add_kill(WeaponKind::MineLayer);
+        total.merge(&other);
+        assert_eq!(total.get(WeaponKind::Ak47).damage, 15.0);
+        assert_eq!(total.get(WeaponKind::MineLayer).kills, 1);
+    }
+
+    #[test]
+    fn scores_and_weapon_stats_survive_save() {
+        let mut leader_board = LeaderBoard::default();
+        leader_board.get_or_add_actor("Bot 1").is_bot = true;
+        leader_board.add_frag("Player");
+        leader_board.add_death("Bot 1");
+        leader_board.add_weapon_damage("Player", WeaponKind::PlasmaRifle, 42.5);
+        leader_board.add_weapon_kill("Player", WeaponKind::PlasmaRifle);
+        leader_board.add_weapon_damage("Bot 1", WeaponKind::M4, 7.0);
+
+        let loaded = read_back("leader_board", &mut leader_board);
+
+        let scores = loaded.values();
+        assert_eq!(scores["Player"].kills, 1);
+        assert_eq!(scores["Bot 1"].deaths, 1);
+        assert!(scores["Bot 1"].is_bot);
+        let player = loaded.weapon_stats("Player").unwrap();
+        assert_eq!(player.get(WeaponKind::PlasmaRifle).damage, 42.5);
+        assert_eq!(player.get(WeaponKind::PlasmaRifle).kills, 1);
+        assert_eq!(
+            loaded
+                .weapon_stats("Bot 1")
+                .unwrap()
+                .get(WeaponKind::M4)
+                .damage,
+            7.0
+        );
     }
 }
@@ -1,59 +1,165 @@
 use crate::{
     actor::{Actor, ActorContainer},
     bot::{Bot, BotKind},
+    breakable_light::{self, BreakableLight, BreakableLightContainer},
+    character::{ArmorTier, Team, BASE_HEALTH, FOOTSTEP_SOUNDS, HEAL_LIMIT},
     control_scheme::ControlScheme,
-    effects,
-    item::{Item, ItemContainer, ItemKind},
+    damage_numbers::DamageNumberKind,
+    effects::{self, EffectKind},
+    environment::{self, MapEnvironment},
+    free_camera::FreeCamera,
+    health_bars::{self, HealthBar},
+    heatmap::Heatmap,
+    intermission::IntermissionCamera,
+    interpolation::Interpolator,
+    item::{Item, ItemContainer, ItemKind, ItemMagnet, HIGHLIGHT_RADIUS, PICKUP_RADIUS},
+    item_timers::{self, ItemTimer},
     jump_pad::{JumpPad, JumpPadContainer},
-    leader_board::LeaderBoard,
-    message::Message,
-    player::Player,
-    projectile::{Projectile, ProjectileContainer, ProjectileKind},
-    weapon::{Weapon, WeaponContainer, WeaponKind},
+    leader_board::{Lead, LeaderBoard},
+    logger::Logger,
+    message::{Message, MessageSender},
+    mutators::Mutators,
+    net::{self, ActorRole, ActorState, RemoteInput, GUEST_NAME, HOST_NAME},
+    notifications::NotificationSeverity,
+    observer::GameEvent,
+    player::{self, Player, PLAYER_NAME},
+    profiler::Profiler,
+    projectile::{Projectile, ProjectileContainer, ProjectileKind, ProjectileVisual, Shooter},
+    prop::{Prop, PropContainer},
+    query_buffer::QueryBufferPool,
+    rng::GameRng,
+    settings::Preferences,
+    spatial_grid::SpatialGrid,
+    turret::{self, Turret, TurretContainer, TurretTarget},
+    weapon::{self, ShooterState, Weapon, WeaponContainer, WeaponKind, WeaponState},
     GameTime, MatchOptions,
 };
-use fyrox::core::algebra::Vector2;
 use fyrox::{
     core::{
         algebra::Point3,
-        algebra::{Matrix3, Vector3},
+        algebra::{Matrix3, UnitQuaternion, Vector2, Vector3},
         color::Color,
-        math::Vector3Ext,
+        futures::future::join_all,
         math::{aabb::AxisAlignedBoundingBox, ray::Ray, PositionProvider},
         pool::Handle,
-        rand::Rng,
+        rand::{self, seq::SliceRandom, Rng},
         visitor::{Visit, VisitResult, Visitor},
     },
     engine::{resource_manager::ResourceManager, Engine},
     event::Event,
-    rand,
+    resource::{model::Model, texture::Texture},
     scene::{
         self,
         base::BaseBuilder,
         camera::{Camera, CameraBuilder},
-        collider::InteractionGroups,
+        collider::{Collider, ColliderShape, InteractionGroups},
         graph::{physics::RayCastOptions, Graph},
+        mesh::Mesh,
         node::Node,
         sound::{
             context::SoundContext,
             effect::{BaseEffectBuilder, Effect, EffectInput, ReverbEffectBuilder},
-            HrirSphere, HrtfRenderer, Renderer, SoundBuilder, Status, SAMPLE_RATE,
+            HrirSphere, HrtfRenderer, Renderer, SoundBufferResource, SoundBuilder, Status,
+            SAMPLE_RATE,
         },
         transform::TransformBuilder,
         Scene,
     },
-    utils::log::{Log, MessageKind},
+    utils::log::MessageKind,
 };
 use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
     path::{Path, PathBuf},
-    sync::{mpsc::Sender, Arc, RwLock},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 
 pub const RESPAWN_TIME: f32 = 4.0;
+pub const DEFAULT_BOT_COUNT: usize = 3;
+pub const DEFAULT_MAP: &str = "data/levels/dm6.rgs";
+pub const ITEM_PICKUP_SOUND: &str = "data/sounds/item_pickup.ogg";
+pub const HRIR_SPHERE_PATH: &str = "data/sounds/IRC_1040_C.bin";
+/// Spectator camera stops this high above the floor.
+const SPECTATOR_FLOOR_OFFSET: f32 = 0.3;
+/// Spectator camera looks for the floor only this far below, it stops falling if there is
+/// nothing (a pit for example).
+const SPECTATOR_PROBE_DISTANCE: f32 = 3.0;
+/// Maximum speed of spectator camera descent, in m/s.
+const SPECTATOR_MAX_FALL_SPEED: f32 = 4.0;
+/// Spectator camera tilts down to this pitch (in degrees) during first second after death.
+const SPECTATOR_PITCH: f32 = 35.0;
+const SPECTATOR_PITCH_TIME: f32 = 1.0;
+/// Ammo given by a weapon item.
+const WEAPON_ITEM_AMMO: u32 = 200;
+/// Ammo given by an ammo item.
+const AMMO_ITEM_AMMO: u32 = 200;
+/// Rockets are scarce, a pack of them gives only a few.
+const ROCKETS_ITEM_AMMO: u32 = 5;
+/// Intermission camera flies this high above spawn points, unless ceiling is lower.
+const INTERMISSION_HEIGHT: f32 = 3.0;
+/// At most this many of the highest spawn points are used as intermission waypoints.
+const INTERMISSION_WAYPOINTS: usize = 8;
+/// "Ammo full" is shown to player at most once per this time in seconds, player that stands
+/// on an ammo item would get it every frame otherwise.
+const AMMO_FULL_NOTICE_INTERVAL: f64 = 2.0;
+/// Weapons dropped by actors disappear after this time, in seconds.
+const DROPPED_WEAPON_LIFETIME: f32 = 20.0;
+/// Weapon dropped by a swap can't be picked up for this time, otherwise the actor that
+/// dropped it would take it back right away.
+const SWAP_PICKUP_DELAY: f32 = 2.0;
+/// Size of cells of spatial grids that are used for proximity queries.
+const GRID_CELL_SIZE: f32 = 8.0;
+/// Damaging death zones hurt actors with this interval while they stay inside.
+const DEATH_ZONE_DAMAGE_INTERVAL: f32 = 0.5;
+const LAVA_DAMAGE_PER_SECOND: f32 = 40.0;
+const HURT_DAMAGE_PER_SECOND: f32 = 10.0;
+/// Distance between map geometry and world bounds, anything beyond bounds is lost for good.
+const WORLD_BOUNDS_MARGIN: f32 = 10.0;
+pub const LAVA_SOUND: &str = "data/sounds/plasma_shot.ogg";
+pub const LEAD_TAKEN_SOUND: &str = "data/sounds/lead_taken.wav";
+pub const LEAD_LOST_SOUND: &str = "data/sounds/lead_lost.wav";
+pub const PERSONAL_BEST_SOUND: &str = "data/sounds/item_pickup.ogg";
+/// Played when ammo of current weapon of the player drops below a warning threshold.
+pub const LOW_AMMO_SOUND: &str = "data/sounds/bullet_impact_metal.ogg";
+/// Damage numbers appear this high above position of a victim.
+const DAMAGE_NUMBER_HEIGHT: f32 = 1.0;
+/// Health bars hang this high above heads of bots.
+const HEALTH_BAR_HEIGHT: f32 = 0.35;
+/// Respawn timers hang this high above items.
+const ITEM_TIMER_HEIGHT: f32 = 0.8;
+/// Chance that some bot answers to a chat message of player.
+const BOT_CHAT_REPLY_CHANCE: f32 = 0.5;
+const BOT_CHAT_REPLIES: [&str; 6] = [
+    "Talk is cheap.",
+    "Come and get me!",
+    "You'll need more than words.",
+    "Is that all you've got?",
+    "I'm right behind you.",
+    "Nice try.",
+];
 
-#[derive(Default, Visit)]
+#[derive(Default)]
 pub struct SoundManager {
     reverb: Handle<Effect>,
+    music: Handle<Node>,
+    /// Loaded sound buffers, it is filled before match starts so playing a sound is just
+    /// a lookup and never waits for resource manager.
+    buffers: HashMap<PathBuf, SoundBufferResource>,
+}
+
+impl Visit for SoundManager {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.reverb.visit("reverb", visitor)?;
+
+        // Saves made before map music have no such field, music is not played after load.
+        let _ = self.music.visit("music", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 impl SoundManager {
@@ -64,17 +170,119 @@ impl SoundManager {
             .with_decay_time(3.0)
             .build(context);
 
-        let hrir_sphere = HrirSphere::from_file("data/sounds/IRC_1040_C.bin", SAMPLE_RATE).unwrap();
-
-        context.set_renderer(Renderer::HrtfRenderer(HrtfRenderer::new(hrir_sphere)));
+        match HrirSphere::from_file(HRIR_SPHERE_PATH, SAMPLE_RATE) {
+            Ok(hrir_sphere) => {
+                context.set_renderer(Renderer::HrtfRenderer(HrtfRenderer::new(hrir_sphere)))
+            }
+            Err(e) => Logger::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Unable to load HRIR sphere {}: {:?}. Default sound renderer is used.",
+                    HRIR_SPHERE_PATH, e
+                ),
+            ),
+        }
 
-        Self { reverb }
+        Self {
+            reverb,
+            music: Handle::NONE,
+            buffers: Default::default(),
+        }
     }
 
-    pub async fn handle_message(
+    /// Starts looping music track, it is not affected by reverb and position of listener.
+    pub async fn play_music(
         &mut self,
         graph: &mut Graph,
         resource_manager: ResourceManager,
+        path: &Path,
+        gain: f32,
+    ) {
+        match resource_manager.request_sound_buffer(path).await {
+            Ok(buffer) => {
+                self.music = SoundBuilder::new(BaseBuilder::new())
+                    .with_buffer(Some(buffer))
+                    .with_looping(true)
+                    .with_status(Status::Playing)
+                    .with_gain(gain)
+                    .with_spatial_blend_factor(0.0)
+                    .build(graph);
+            }
+            Err(_) => Logger::writeln(
+                MessageKind::Error,
+                format!("Unable to load music {}", path.display()),
+            ),
+        }
+    }
+
+    /// Returns paths of every sound that can be played during a match.
+    fn gameplay_sounds() -> Vec<&'static str> {
+        let mut sounds = vec![
+            ITEM_PICKUP_SOUND,
+            LAVA_SOUND,
+            LEAD_TAKEN_SOUND,
+            LEAD_LOST_SOUND,
+            LOW_AMMO_SOUND,
+            breakable_light::BREAK_SOUND,
+            turret::SHOT_SOUND,
+            turret::DESTROY_SOUND,
+        ];
+        sounds.extend_from_slice(&FOOTSTEP_SOUNDS);
+        for kind in WeaponKind::ALL.iter() {
+            sounds.push(Weapon::get_definition(*kind).shot_sound.as_str());
+        }
+        for kind in ProjectileKind::ALL.iter() {
+            sounds.push(Projectile::get_definition(*kind).impact_sound.as_str());
+        }
+        sounds.sort_unstable();
+        sounds.dedup();
+        sounds
+    }
+
+    pub async fn preload(&mut self, resource_manager: ResourceManager) {
+        for path in Self::gameplay_sounds() {
+            let path = PathBuf::from(path);
+            if self.buffers.contains_key(&path) {
+                continue;
+            }
+            match resource_manager.request_sound_buffer(&path).await {
+                Ok(buffer) => {
+                    self.buffers.insert(path, buffer);
+                }
+                Err(_) => Logger::writeln(
+                    MessageKind::Error,
+                    format!("Unable to preload sound {:?}", path),
+                ),
+            }
+        }
+    }
+
+    fn buffer(
+        &mut self,
+        resource_manager: &ResourceManager,
+        path: &Path,
+    ) -> Option<SoundBufferResource> {
+        if let Some(buffer) = self.buffers.get(path) {
+            return Some(buffer.clone());
+        }
+
+        // Every sound should be preloaded, if we got here then list of gameplay sounds
+        // is missing something. Load it anyway, but only once.
+        Logger::writeln(
+            MessageKind::Warning,
+            format!("Sound {:?} was not preloaded, loading it now.", path),
+        );
+        let buffer =
+            fyrox::core::futures::executor::block_on(resource_manager.request_sound_buffer(path))
+                .ok()?;
+        self.buffers.insert(path.to_path_buf(), buffer.clone());
+        Some(buffer)
+    }
+
+    pub fn handle_message(
+        &mut self,
+        graph: &mut Graph,
+        resource_manager: &ResourceManager,
         message: &Message,
     ) {
         match message {
@@ -85,7 +293,7 @@ impl SoundManager {
                 rolloff_factor,
                 radius,
             } => {
-                if let Ok(buffer) = resource_manager.request_sound_buffer(path).await {
+                if let Some(buffer) = self.buffer(resource_manager, path) {
                     let sound = SoundBuilder::new(
                         BaseBuilder::new().with_local_transform(
                             TransformBuilder::new()
@@ -93,7 +301,7 @@ impl SoundManager {
                                 .build(),
                         ),
                     )
-                    .with_buffer(buffer.into())
+                    .with_buffer(Some(buffer))
                     .with_status(Status::Playing)
                     .with_play_once(true)
                     .with_gain(*gain)
@@ -110,7 +318,7 @@ impl SoundManager {
                             filter: None,
                         });
                 } else {
-                    Log::writeln(
+                    Logger::writeln(
                         MessageKind::Error,
                         format!("Unable to play sound {:?}", path),
                     );
@@ -121,7 +329,73 @@ impl SoundManager {
     }
 }
 
-#[derive(Visit)]
+/// Strong references to resources that are instantiated during a match (weapon models,
+/// projectiles, effects). They're loaded before actors spawn, so first shot of a weapon does
+/// not hitch, and kept alive while level exists, so resource manager won't unload them.
+#[derive(Default)]
+pub struct PreloadedResources {
+    models: Vec<Model>,
+    textures: Vec<Texture>,
+}
+
+impl PreloadedResources {
+    pub async fn load(resource_manager: ResourceManager) -> Self {
+        let mut model_paths = Vec::new();
+        let mut texture_paths = Vec::new();
+        for kind in WeaponKind::ALL.iter() {
+            model_paths.push(Weapon::get_definition(*kind).model.as_str());
+        }
+        for kind in ProjectileKind::ALL.iter() {
+            match &Projectile::get_definition(*kind).visual {
+                ProjectileVisual::Sprite(texture) => texture_paths.push(texture.as_str()),
+                ProjectileVisual::Model(model) => model_paths.push(model.as_str()),
+            }
+        }
+        for kind in EffectKind::ALL.iter() {
+            texture_paths.push(kind.texture());
+        }
+        model_paths.sort_unstable();
+        model_paths.dedup();
+        texture_paths.sort_unstable();
+        texture_paths.dedup();
+
+        // Request everything at once, resource manager loads them in parallel.
+        let (models, textures) = fyrox::core::futures::join!(
+            join_all(
+                model_paths
+                    .iter()
+                    .map(|path| resource_manager.request_model(path))
+            ),
+            join_all(
+                texture_paths
+                    .iter()
+                    .map(|path| resource_manager.request_texture(path))
+            )
+        );
+
+        let mut resources = Self::default();
+        for (path, model) in model_paths.iter().zip(models) {
+            match model {
+                Ok(model) => resources.models.push(model),
+                Err(_) => Logger::writeln(
+                    MessageKind::Error,
+                    format!("Unable to preload model {}", path),
+                ),
+            }
+        }
+        for (path, texture) in texture_paths.iter().zip(textures) {
+            match texture {
+                Ok(texture) => resources.textures.push(texture),
+                Err(_) => Logger::writeln(
+                    MessageKind::Error,
+                    format!("Unable to preload texture {}", path),
+                ),
+            }
+        }
+        resources
+    }
+}
+
 pub struct Level {
     map_root: Handle<Node>,
     pub scene: Handle<Scene>,
@@ -132,18 +406,205 @@ pub struct Level {
     jump_pads: JumpPadContainer,
     items: ItemContainer,
     spawn_points: Vec<SpawnPoint>,
-    #[visit(skip)]
-    sender: Option<Sender<Message>>,
-    #[visit(skip)]
+    breakable_lights: BreakableLightContainer,
+    props: PropContainer,
+    turrets: TurretContainer,
+    sender: MessageSender,
     pub control_scheme: Option<Arc<RwLock<ControlScheme>>>,
+    pub preferences: Option<Arc<RwLock<Preferences>>>,
     death_zones: Vec<DeathZone>,
+    /// Box around map geometry, projectiles are killed and actors respawned outside of it.
+    /// `None` if map has no geometry.
+    world_bounds: Option<AxisAlignedBoundingBox>,
     pub options: MatchOptions,
     time: f32,
     pub leader_board: LeaderBoard,
+    /// Shots of local player during the match and how many of them damaged someone, they
+    /// give accuracy for personal records.
+    player_shots: u32,
+    player_hits: u32,
     respawn_list: Vec<RespawnEntry>,
+    /// Names of bots in the order they joined the match, respawns do not change the order.
+    bot_names: Vec<String>,
+    /// Team of every actor by name, actors get their team back when they respawn.
+    teams: HashMap<String, Team>,
     spectator_camera: Handle<Node>,
     target_spectator_position: Vector3<f32>,
+    spectator_fall: SpectatorFall,
     sound_manager: SoundManager,
+    rng: GameRng,
+    free_camera: Option<FreeCamera>,
+    /// Camera that flies around the map when match is over, actors are frozen meanwhile.
+    intermission: Option<IntermissionCamera>,
+    interpolator: Interpolator,
+    /// Items are spawned and removed during the match, so grid is rebuilt every tick.
+    item_grid: SpatialGrid<Handle<Item>>,
+    /// Death zones never change, grid is built on first use.
+    death_zone_grid: Option<SpatialGrid<usize>>,
+    preloaded_resources: PreloadedResources,
+    /// Index of spawn point used by previous spawn, it is penalized for next spawn.
+    last_spawn_point: Option<usize>,
+    /// Actors that are inside of damaging death zones.
+    zone_contacts: HashMap<Handle<Actor>, ZoneContact>,
+    /// True while some UI element (chat for example) takes keyboard input, player must not
+    /// move or shoot in this case.
+    ui_has_keyboard_focus: bool,
+    /// Level on client side of network game, it only shows what host tells it.
+    mirror: bool,
+    /// Actor of network client on host side.
+    guest: Handle<Actor>,
+    guest_connected: bool,
+    /// Stand-in actor for host's player is requested once, it appears a few ticks later.
+    host_proxy_requested: bool,
+    /// Weapon item next to player which can't be picked up because player has this weapon
+    /// already, player may swap current weapon for it.
+    swap_offer: Option<(Handle<Item>, WeaponKind)>,
+    /// Time when player was told last time that ammo item is left because ammo is full.
+    last_ammo_full_notice: Option<f64>,
+    /// Actor on which laser sight of player's weapon rests.
+    aim_target: Handle<Actor>,
+    /// Player is out of the match because of inactivity, see [`Message::PlayerAfk`].
+    afk: bool,
+    /// Non-fatal problems of the map found on level creation, a loaded save has none.
+    map_issues: Vec<MapIssue>,
+    /// Positions of actors for map design, created when recording is turned on first time.
+    heatmap: Option<Heatmap>,
+}
+
+impl Visit for Level {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.map_root.visit("map_root", visitor)?;
+        self.scene.visit("scene", visitor)?;
+        self.player.visit("player", visitor)?;
+        self.projectiles.visit("projectiles", visitor)?;
+        self.actors.visit("actors", visitor)?;
+        self.weapons.visit("weapons", visitor)?;
+        self.jump_pads.visit("jump_pads", visitor)?;
+        self.items.visit("items", visitor)?;
+        self.spawn_points.visit("spawn_points", visitor)?;
+        self.death_zones.visit("death_zones", visitor)?;
+        self.options.visit("options", visitor)?;
+        self.time.visit("time", visitor)?;
+        self.leader_board.visit("leader_board", visitor)?;
+        self.respawn_list.visit("respawn_list", visitor)?;
+        self.spectator_camera.visit("spectator_camera", visitor)?;
+        self.target_spectator_position
+            .visit("target_spectator_position", visitor)?;
+        self.sound_manager.visit("sound_manager", visitor)?;
+
+        // Saves made before these entities were added have none of them.
+        let _ = self.breakable_lights.visit("breakable_lights", visitor);
+        let _ = self.props.visit("props", visitor);
+        let _ = self.turrets.visit("turrets", visitor);
+
+        // Saves made before world bounds have no such field, leaving the map is not punished.
+        let _ = self.world_bounds.visit("world_bounds", visitor);
+
+        // Saves made before personal records have no such fields, accuracy is counted from
+        // the load.
+        let _ = self.player_shots.visit("player_shots", visitor);
+        let _ = self.player_hits.visit("player_hits", visitor);
+
+        // Saves made before seeded random generator have no such field, it gets default seed.
+        let _ = self.rng.visit("rng", visitor);
+
+        // Saves made before bots and teams were tracked by name have no such fields, they are
+        // taken from actors.
+        if self.bot_names.visit("bot_names", visitor).is_err() {
+            self.bot_names = self
+                .actors
+                .iter()
+                .filter(|actor| matches!(actor, Actor::Bot(_)))
+                .map(|actor| actor.name.clone())
+                .collect();
+        }
+        if self.teams.visit("teams", visitor).is_err() {
+            self.teams = self
+                .actors
+                .iter()
+                .map(|actor| (actor.name.clone(), actor.team))
+                .collect();
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Phase of current match, see [`LevelStatus`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MatchPhase {
+    InProgress,
+    /// Limit of the match is reached, level is going to be destroyed.
+    Over,
+}
+
+/// State of local player, see [`LevelStatus`].
+#[derive(Clone, Debug)]
+pub struct PlayerStatus {
+    pub health: f32,
+    /// Health at which health bar is full, health above it is shown as full bar.
+    pub max_health: f32,
+    pub armor: f32,
+    /// Armor cap of current armor tier.
+    pub max_armor: f32,
+    pub armor_tier: ArmorTier,
+    /// Ammo of current weapon, if player has one.
+    pub ammo: Option<u32>,
+    /// Kind, ammo and "is current" flag of every weapon player has.
+    pub weapons: Vec<(WeaponKind, u32, bool)>,
+    /// How much of the fuse of charged weapon has burnt down in 0.0..=1.0 range, `None` if
+    /// current weapon is not charged.
+    pub charge: Option<f32>,
+    pub is_burning: bool,
+    pub is_zoomed: bool,
+    /// Charge of flashlight in 0.0..=1.0 range.
+    pub flashlight_battery: f32,
+    /// Hint about what Use key does right now, for example "Press E to swap for M4".
+    pub interaction_prompt: Option<String>,
+    /// Living actor under crosshair.
+    pub aim_target: Option<AimTarget>,
+}
+
+impl PlayerStatus {
+    pub fn current_weapon(&self) -> Option<WeaponKind> {
+        self.weapons
+            .iter()
+            .find(|(_, _, is_current)| *is_current)
+            .map(|(kind, _, _)| *kind)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AimTarget {
+    /// Always false in modes without teams.
+    pub is_teammate: bool,
+    pub name: String,
+    pub health: f32,
+}
+
+/// Plain snapshot of everything HUD shows about the match, it is built once per tick by
+/// [`Level::status`], so HUD does not need to look into level internals.
+#[derive(Clone, Debug)]
+pub struct LevelStatus {
+    pub phase: MatchPhase,
+    /// Time since start of the match, in seconds.
+    pub time: f32,
+    /// Time left until time limit, `None` if match has no time limit.
+    pub time_remaining: Option<f32>,
+    /// `None` while local player is dead or spectates.
+    pub player: Option<PlayerStatus>,
+    /// Time left until local player respawns, `None` if player is not waiting for respawn.
+    pub respawn_time_left: Option<f32>,
+    /// Local player is out of the match because of inactivity.
+    pub is_afk: bool,
+    /// Name and frags of actor at first place.
+    pub leader: Option<(String, u32)>,
+    /// Frags of actor at second place.
+    pub runner_up_score: Option<u32>,
+    /// Scores of red and blue teams, `None` in modes without teams.
+    pub team_scores: Option<(u32, u32)>,
 }
 
 impl Default for Level {
@@ -158,39 +619,137 @@ impl Default for Level {
             jump_pads: JumpPadContainer::new(),
             items: ItemContainer::new(),
             spawn_points: Default::default(),
-            sender: None,
+            breakable_lights: Default::default(),
+            props: Default::default(),
+            turrets: Default::default(),
+            sender: Default::default(),
             control_scheme: None,
+            preferences: None,
             death_zones: Default::default(),
+            world_bounds: None,
             options: Default::default(),
             time: 0.0,
             leader_board: Default::default(),
+            player_shots: 0,
+            player_hits: 0,
             respawn_list: Default::default(),
+            bot_names: Default::default(),
+            teams: Default::default(),
             spectator_camera: Default::default(),
             target_spectator_position: Default::default(),
+            spectator_fall: Default::default(),
             sound_manager: Default::default(),
+            rng: Default::default(),
+            free_camera: None,
+            intermission: None,
+            interpolator: Default::default(),
+            item_grid: SpatialGrid::new(GRID_CELL_SIZE),
+            death_zone_grid: None,
+            preloaded_resources: Default::default(),
+            last_spawn_point: None,
+            zone_contacts: Default::default(),
+            ui_has_keyboard_focus: false,
+            mirror: false,
+            guest: Handle::NONE,
+            guest_connected: false,
+            host_proxy_requested: false,
+            swap_offer: None,
+            last_ammo_full_notice: None,
+            aim_target: Handle::NONE,
+            afk: false,
+            map_issues: Default::default(),
+            heatmap: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Visit)]
+pub enum DeathZoneKind {
+    /// Actor is respawned as soon as it touches the zone.
+    Kill,
+    /// Actor burns while it is inside, quick actors can escape.
+    Lava,
+    /// Actor takes damage while it is inside.
+    Hurt,
+}
+
+impl DeathZoneKind {
+    /// Parses kind from the rest of death zone name, "DeathZone" and "DeathZone_Kill" are
+    /// kill zones, "DeathZone_Lava" is lava and so on.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.strip_prefix('_').unwrap_or(name) {
+            "" | "Kill" => Some(Self::Kill),
+            "Lava" => Some(Self::Lava),
+            "Hurt" => Some(Self::Hurt),
+            _ => None,
+        }
+    }
+
+    fn default_damage_per_second(self) -> f32 {
+        match self {
+            DeathZoneKind::Kill => 0.0,
+            DeathZoneKind::Lava => LAVA_DAMAGE_PER_SECOND,
+            DeathZoneKind::Hurt => HURT_DAMAGE_PER_SECOND,
         }
     }
 }
 
-#[derive(Visit)]
 pub struct DeathZone {
+    kind: DeathZoneKind,
     bounds: AxisAlignedBoundingBox,
+    damage_per_second: f32,
+}
+
+impl Visit for DeathZone {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.bounds.visit("bounds", visitor)?;
+
+        // Saves made before hurt zones have only kill zones.
+        let _ = self.kind.visit("kind", visitor);
+        let _ = self.damage_per_second.visit("damage_per_second", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 impl Default for DeathZone {
     fn default() -> Self {
         Self {
+            kind: DeathZoneKind::Kill,
             bounds: Default::default(),
+            damage_per_second: 0.0,
         }
     }
 }
 
+/// Orientation of player at the moment of death and time since then, they drive "falling
+/// body" motion of spectator camera.
+#[derive(Default)]
+struct SpectatorFall {
+    yaw: f32,
+    pitch: f32,
+    time: f32,
+}
+
+struct ZoneContact {
+    kind: DeathZoneKind,
+    /// Time left until next damage tick.
+    next_tick: f32,
+}
+
 pub struct UpdateContext<'a> {
     pub time: GameTime,
     pub scene: &'a mut Scene,
     pub items: &'a ItemContainer,
+    pub item_grid: &'a SpatialGrid<Handle<Item>>,
     pub jump_pads: &'a JumpPadContainer,
     pub weapons: &'a WeaponContainer,
+    pub options: &'a MatchOptions,
+    /// Multiplier of bot view distance from map lighting, see
+    /// [`crate::environment::view_distance_scale`].
+    pub view_distance_scale: f32,
 }
 
 #[derive(Visit)]
@@ -225,6 +784,7 @@ impl Default for BotRespawnEntry {
 enum RespawnEntry {
     Bot(BotRespawnEntry),
     Player(PlayerRespawnEntry),
+    Guest(PlayerRespawnEntry),
 }
 
 impl Default for RespawnEntry {
@@ -233,27 +793,112 @@ impl Default for RespawnEntry {
     }
 }
 
+/// Problem of a map found by [`analyze`], map author has to fix it.
+#[derive(Clone, Debug)]
+pub enum MapIssue {
+    /// Jump pad has no "Begin" or "End" child node, it does nothing.
+    JumpPadWithoutTarget { node: String },
+    /// Death zone node is not a mesh, so it has no bounds and is ignored.
+    DeathZoneNotMesh { node: String },
+    /// Actors would spawn at the origin, most likely inside geometry.
+    NoSpawnPoints,
+    /// Bots can't move without navigation mesh.
+    NoNavmesh,
+    /// Map has no trimesh collider, there is nothing to stand on.
+    NoCollisionGeometry,
+}
+
+impl MapIssue {
+    /// Map with such issue can't be played at all.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            MapIssue::NoSpawnPoints | MapIssue::NoCollisionGeometry
+        )
+    }
+}
+
+impl fmt::Display for MapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapIssue::JumpPadWithoutTarget { node } => write!(
+                f,
+                "jump pad {} has no Begin or End child node, it is ignored",
+                node
+            ),
+            MapIssue::DeathZoneNotMesh { node } => {
+                write!(f, "death zone {} is not a mesh, it is ignored", node)
+            }
+            MapIssue::NoSpawnPoints => write!(f, "map has no SpawnPoint nodes"),
+            MapIssue::NoNavmesh => write!(f, "map has no navigation mesh, bots won't move"),
+            MapIssue::NoCollisionGeometry => write!(f, "map has no collision geometry"),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AnalysisResult {
     jump_pads: JumpPadContainer,
     items: ItemContainer,
     death_zones: Vec<DeathZone>,
+    world_bounds: Option<AxisAlignedBoundingBox>,
     spawn_points: Vec<SpawnPoint>,
+    breakable_lights: BreakableLightContainer,
+    props: PropContainer,
+    turrets: TurretContainer,
+    issues: Vec<MapIssue>,
+}
+
+/// Multiplier of jump pad launch speed. Height and length of a ballistic flight are
+/// proportional to squared speed divided by gravity, so in low gravity pads launch slower and
+/// actors land where map author intended instead of overshooting into the sky.
+fn jump_pad_gravity_factor(mutators: &Mutators) -> f32 {
+    if mutators.low_gravity {
+        LOW_GRAVITY_SCALE.sqrt()
+    } else {
+        1.0
+    }
 }
 
 pub async fn analyze(
     scene: &mut Scene,
     resource_manager: ResourceManager,
-    sender: Sender<Message>,
+    sender: MessageSender,
+    mutators: &Mutators,
 ) -> AnalysisResult {
     let mut result = AnalysisResult::default();
 
     let mut items = Vec::new();
     let mut spawn_points = Vec::new();
     let mut death_zones = Vec::new();
+    let mut props = Vec::new();
     for (handle, node) in scene.graph.pair_iter() {
         let position = node.global_position();
-        let name = node.name();
+        // Name can have parameter after @, for example "Medkit@30" is a medkit that respawns
+        // every 30 seconds.
+        let (name, parameter) = match node.name().split_once('@') {
+            Some((name, parameter)) => match parameter.parse::<f32>() {
+                Ok(value) => (name, Some(value)),
+                Err(_) => {
+                    Logger::writeln(
+                        MessageKind::Warning,
+                        format!(
+                            "Invalid parameter of node {}, a number expected after @.",
+                            node.name()
+                        ),
+                    );
+                    (name, None)
+                }
+            },
+            None => (node.name(), None),
+        };
+        let mut add_item = |kind: Option<ItemKind>| match kind {
+            Some(kind) => items.push((kind, position, parameter)),
+            None => Logger::writeln(
+                MessageKind::Warning,
+                format!("Node {} has unknown item kind, it is ignored.", node.name()),
+            ),
+        };
         if name.starts_with("JumpPad") {
             let begin = scene.graph.find_by_name(handle, "Begin");
             let end = scene.graph.find_by_name(handle, "End");
@@ -263,67 +908,200 @@ pub async fn analyze(
                 let d = end - begin;
                 let len = d.norm();
                 let force = d.try_normalize(std::f32::EPSILON);
-                let force = force.unwrap_or(Vector3::y()).scale(len * 2.0);
+                let force = force
+                    .unwrap_or(Vector3::y())
+                    .scale(len * 2.0 * jump_pad_gravity_factor(mutators));
                 let collider = scene.graph.find(handle, &mut |n| n.is_collider());
                 result.jump_pads.add(JumpPad::new(collider, force));
-            };
+            } else {
+                result.issues.push(MapIssue::JumpPadWithoutTarget {
+                    node: node.name().to_owned(),
+                });
+            }
         } else if name.starts_with("Medkit") {
-            items.push((ItemKind::Medkit, position));
+            add_item(Some(ItemKind::Medkit));
+        } else if name.starts_with("Mega") {
+            add_item(Some(ItemKind::MegaHealth));
+        } else if name.starts_with("Armor_Yellow") {
+            add_item(Some(ItemKind::YellowArmor));
+        } else if name.starts_with("Armor_Red") {
+            add_item(Some(ItemKind::RedArmor));
+        } else if name.starts_with("Armor") {
+            // Plain "Armor" nodes of older maps are green armor.
+            add_item(Some(ItemKind::Armor));
         } else if name.starts_with("Ammo_Ak47") {
-            items.push((ItemKind::Ak47Ammo, position));
+            add_item(Some(ItemKind::Ak47Ammo));
         } else if name.starts_with("Ammo_M4") {
-            items.push((ItemKind::M4Ammo, position));
+            add_item(Some(ItemKind::M4Ammo));
         } else if name.starts_with("Ammo_Plasma") {
-            items.push((ItemKind::Plasma, position));
+            add_item(Some(ItemKind::Plasma));
+        } else if name.starts_with("Ammo_Rocket") {
+            add_item(Some(ItemKind::Rockets));
+        } else if let Some(suffix) = name.strip_prefix("Powerup_") {
+            add_item(ItemKind::from_name(suffix));
+        } else if let Some(suffix) = name.strip_prefix("Weapon_") {
+            add_item(ItemKind::from_name(suffix).filter(|kind| kind.is_weapon()));
         } else if name.starts_with("SpawnPoint") {
-            spawn_points.push(node.global_position())
-        } else if name.starts_with("DeathZone") {
-            death_zones.push(handle);
+            let team = if name.starts_with("SpawnPoint_Red") {
+                Team::Red
+            } else if name.starts_with("SpawnPoint_Blue") {
+                Team::Blue
+            } else {
+                Team::None
+            };
+            spawn_points.push(SpawnPoint {
+                position,
+                team,
+                cooldown: 0.0,
+            })
+        } else if let Some(suffix) = name.strip_prefix("DeathZone") {
+            let kind = DeathZoneKind::from_name(suffix).unwrap_or_else(|| {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Node {} has unknown death zone kind, kill zone is used.",
+                        node.name()
+                    ),
+                );
+                DeathZoneKind::Kill
+            });
+            if node.cast::<Mesh>().is_some() {
+                death_zones.push((handle, kind, parameter));
+            } else {
+                result.issues.push(MapIssue::DeathZoneNotMesh {
+                    node: node.name().to_owned(),
+                });
+            }
+        } else if name.starts_with("Breakable_Light") {
+            result.breakable_lights.add(BreakableLight::new(handle));
+        } else if name.starts_with("Turret") {
+            let team = if name.starts_with("Turret_Red") {
+                Team::Red
+            } else if name.starts_with("Turret_Blue") {
+                Team::Blue
+            } else {
+                Team::None
+            };
+            result.turrets.add(Turret::new(handle, team, &scene.graph));
+        } else if name.starts_with("Prop_") {
+            props.push(handle);
+        } else if parameter.is_some() {
+            // Parameters make sense only for known entities, most likely there is a typo.
+            Logger::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Node {} has a parameter, but its kind is unknown.",
+                    node.name()
+                ),
+            );
         }
     }
 
-    for (kind, position) in items {
-        result.items.add(
-            Item::new(
-                kind,
-                position,
-                scene,
-                resource_manager.clone(),
-                sender.clone(),
-            )
-            .await,
-        );
+    // Only map itself is in the scene at this point, items and actors are added later.
+    for (_, node) in scene.graph.pair_iter() {
+        if node.cast::<Mesh>().is_none() {
+            continue;
+        }
+        let bounds = node.world_bounding_box();
+        match result.world_bounds.as_mut() {
+            Some(total) => {
+                total.add_point(bounds.min);
+                total.add_point(bounds.max);
+            }
+            None => result.world_bounds = Some(bounds),
+        }
+    }
+    if let Some(bounds) = result.world_bounds.as_mut() {
+        let margin = Vector3::repeat(WORLD_BOUNDS_MARGIN);
+        *bounds = AxisAlignedBoundingBox::from_min_max(bounds.min - margin, bounds.max + margin);
+    }
+
+    if mutators.no_pickups {
+        items.clear();
+    }
+    for (kind, position, respawn_time) in items {
+        let mut item = Item::new(
+            kind,
+            position,
+            scene,
+            resource_manager.clone(),
+            sender.clone(),
+        )
+        .await;
+        item.set_respawn_time(respawn_time);
+        result.items.add(item);
     }
-    for handle in death_zones {
+    for (handle, kind, damage_per_second) in death_zones {
         let node = &mut scene.graph[handle];
         node.set_visibility(false);
         result.death_zones.push(DeathZone {
+            kind,
             bounds: node.world_bounding_box(),
+            damage_per_second: damage_per_second
+                .unwrap_or_else(|| kind.default_damage_per_second()),
         });
     }
-    result.spawn_points = spawn_points
+    let props = props
         .into_iter()
-        .map(|p| SpawnPoint { position: p })
-        .collect();
+        .map(|model| Prop::new(model, &mut scene.graph))
+        .collect::<Vec<_>>();
+    // Bodies must have valid global transforms before models are attached to them.
+    scene.graph.update_hierarchical_data();
+    for prop in props {
+        prop.attach(&mut scene.graph);
+        result.props.add(prop);
+    }
+    result.spawn_points = spawn_points;
+
+    if result.spawn_points.is_empty() {
+        result.issues.push(MapIssue::NoSpawnPoints);
+    }
+    if scene.navmeshes.at(0).is_none() {
+        result.issues.push(MapIssue::NoNavmesh);
+    }
+    let has_collision_geometry = scene.graph.linear_iter().any(|node| {
+        node.cast::<Collider>().map_or(false, |collider| {
+            matches!(collider.shape(), ColliderShape::Trimesh(_))
+        })
+    });
+    if !has_collision_geometry {
+        result.issues.push(MapIssue::NoCollisionGeometry);
+    }
+    for issue in result.issues.iter() {
+        Logger::writeln(
+            if issue.is_fatal() {
+                MessageKind::Error
+            } else {
+                MessageKind::Warning
+            },
+            format!("Map issue: {}.", issue),
+        );
+    }
 
     result
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn spawn_player(
-    spawn_points: &[SpawnPoint],
+    spawn_points: &mut [SpawnPoint],
     actors: &mut ActorContainer,
     weapons: &mut WeaponContainer,
-    sender: Sender<Message>,
+    sender: MessageSender,
     resource_manager: ResourceManager,
     control_scheme: Arc<RwLock<ControlScheme>>,
+    preferences: Arc<RwLock<Preferences>>,
     scene: &mut Scene,
+    team: Team,
+    last_spawn_point: &mut Option<usize>,
+    rng: &mut GameRng,
 ) -> Handle<Actor> {
-    let index = find_suitable_spawn_point(spawn_points, actors, scene);
+    let index = find_suitable_spawn_point(spawn_points, actors, scene, team, last_spawn_point, rng);
     let spawn_position = spawn_points.get(index).map_or(Vector3::default(), |pt| {
         pt.position + Vector3::new(0.0, 1.5, 0.0)
     });
     let mut player = Player::new(scene, sender.clone());
     player.set_control_scheme(control_scheme);
+    player.set_preferences(preferences);
     let player = actors.add(Actor::Player(player));
     actors
         .get_mut(player)
@@ -333,6 +1111,8 @@ async fn spawn_player(
         WeaponKind::M4,
         WeaponKind::Ak47,
         WeaponKind::PlasmaRifle,
+        WeaponKind::MineLayer,
+        WeaponKind::Grenade,
         WeaponKind::RocketLauncher,
     ];
     for (i, &weapon) in weapons_to_give.iter().enumerate() {
@@ -352,68 +1132,266 @@ async fn spawn_player(
     player
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn give_new_weapon(
     kind: WeaponKind,
     actor: Handle<Actor>,
-    sender: Sender<Message>,
+    sender: MessageSender,
     resource_manager: ResourceManager,
-    visible: bool,
+    switch: bool,
     weapons: &mut WeaponContainer,
     actors: &mut ActorContainer,
     scene: &mut Scene,
 ) {
     if actors.contains(actor) {
-        let mut weapon = Weapon::new(kind, resource_manager, scene, sender.clone()).await;
+        // Bots pick a random look, so two bots with the same weapon can be told apart.
+        let skin = match actors.get(actor) {
+            Actor::Player(player) => player.weapon_skin(kind),
+            Actor::Bot(_) => rand::thread_rng()
+                .gen_range(0..=Weapon::get_definition(kind).skins.len())
+                .checked_sub(1),
+        };
+        let mut weapon = Weapon::new(kind, resource_manager, scene, sender.clone(), skin).await;
         weapon.set_owner(actor);
+        // Bots always show their lasers, so player can see where they aim.
+        if let Actor::Player(player) = actors.get(actor) {
+            weapon.set_laser_enabled(player.laser_sight_default());
+        }
         let weapon_model = weapon.get_model();
-        scene.graph[weapon_model].set_visibility(visible);
+        // Shown by `SwitchWeapon` message if weapon becomes current.
+        weapon.set_visibility(false, &mut scene.graph);
         let actor = actors.get_mut(actor);
         let weapon_handle = weapons.add(weapon);
-        actor.add_weapon(weapon_handle);
+        actor.add_weapon(weapon_handle, switch);
         scene.graph.link_nodes(weapon_model, actor.weapon_pivot());
 
-        sender
-            .send(Message::AddNotification {
-                text: format!("Actor picked up weapon {:?}", kind),
-            })
-            .unwrap();
+        sender.send(Message::AddNotification {
+            text: format!("{} got {}", actor.name, kind.display_name()),
+            severity: NotificationSeverity::Info,
+            icon: Some(PathBuf::from("data/ui/ammo_icon.png")),
+        });
+    }
+}
+
+/// Spawn points closer than this distance to an enemy are never used.
+const SPAWN_SAFE_DISTANCE: f32 = 8.0;
+/// Score penalty for every enemy that can see a spawn point.
+const SPAWN_VISIBILITY_PENALTY: f32 = 100.0;
+/// Score penalty for a spawn point that was used by previous spawn.
+const SPAWN_REUSE_PENALTY: f32 = 50.0;
+/// Spawn point can't be used again for this many seconds, unless every point is cooling down.
+const SPAWN_COOLDOWN: f32 = 2.0;
+/// Bots join a new match one by one with this interval, so they do not spawn next to each
+/// other and their models are not loaded all in the same frame.
+const INITIAL_BOT_SPAWN_INTERVAL: f32 = 0.75;
+/// Score bonus for a spawn point that belongs to actor's team.
+const SPAWN_TEAM_BONUS: f32 = 100.0;
+/// Amount of target dummies in practice mode, one spawn point is always left for player.
+const PRACTICE_DUMMY_COUNT: usize = 4;
+/// Health restored per second by regeneration mutator.
+const REGEN_HEALTH_PER_SECOND: f32 = 2.0;
+/// Multiplier of world gravity with low gravity mutator.
+pub const LOW_GRAVITY_SCALE: f32 = 0.4;
+
+/// Living actor as seen from a spawn point.
+struct SpawnNeighbour {
+    distance: f32,
+    /// Only enemies are checked for line of sight, it is always false for teammates.
+    visible: bool,
+    team: Team,
+}
+
+/// Whether an actor of `other` team is a threat for an actor of `team`. Everyone is an enemy
+/// in modes without teams.
+fn is_spawn_enemy(team: Team, other: Team) -> bool {
+    team == Team::None || other != team
+}
+
+/// Rates a spawn point for an actor of given team, higher is better. Returns `None` if the
+/// point must not be used at all. Teammates around the point do not matter.
+fn score_spawn_point(
+    point_team: Team,
+    team: Team,
+    neighbours: &[SpawnNeighbour],
+    recently_used: bool,
+) -> Option<f32> {
+    // Team spawn points are used only by members of the team.
+    if point_team != Team::None && team != Team::None && point_team != team {
+        return None;
+    }
+
+    let mut score = 0.0;
+    for threat in neighbours
+        .iter()
+        .filter(|neighbour| is_spawn_enemy(team, neighbour.team))
+    {
+        if threat.distance < SPAWN_SAFE_DISTANCE {
+            return None;
+        }
+        score += threat.distance;
+        if threat.visible {
+            score -= SPAWN_VISIBILITY_PENALTY;
+        }
+    }
+    if recently_used {
+        score -= SPAWN_REUSE_PENALTY;
+    }
+    if point_team != Team::None && point_team == team {
+        score += SPAWN_TEAM_BONUS;
+    }
+    Some(score)
+}
+
+/// Fallback for the case when every spawn point is contested: index of the point with the
+/// largest sum of distances to all actors. `crowding` has such sum for every point, `None`
+/// for points that can't be used.
+fn least_contested_spawn_point(crowding: &[Option<f32>]) -> Option<usize> {
+    crowding
+        .iter()
+        .enumerate()
+        .filter_map(|(i, sum)| sum.map(|sum| (i, sum)))
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map(|(i, _)| i)
+}
+
+/// Handle and name of actor credited for damage. Damage dealer may be gone already, for example
+/// its rocket was still in flight when it died and respawned. Damage is still credited to the
+/// name captured at the shot, as long as the dealer is still on the leader board.
+fn damage_dealer(
+    actors: &ActorContainer,
+    leader_board: &LeaderBoard,
+    who: Handle<Actor>,
+    captured_who_name: Option<&str>,
+) -> (Handle<Actor>, Option<String>) {
+    match actors.try_get(who) {
+        Some(dealer) => (who, Some(dealer.name.clone())),
+        None => (
+            Handle::NONE,
+            captured_who_name
+                .filter(|name| leader_board.values().contains_key(*name))
+                .map(str::to_owned),
+        ),
     }
 }
 
+/// Tells whether level geometry is not in the way between two points.
+pub fn is_visible(scene: &Scene, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+    let ray = Ray::from_two_points(from, to);
+    QueryBufferPool::with(|query_buffer| {
+        scene.graph.physics.cast_ray(
+            RayCastOptions {
+                ray_origin: Point3::from(ray.origin),
+                ray_direction: ray.dir,
+                max_len: ray.dir.norm(),
+                groups: InteractionGroups::default(),
+                sort_results: false,
+            },
+            query_buffer,
+        );
+        // Only level geometry blocks line of sight.
+        !query_buffer.iter().any(|hit| {
+            matches!(
+                scene.graph[hit.collider].as_collider().shape(),
+                ColliderShape::Trimesh(_)
+            )
+        })
+    })
+}
+
 fn find_suitable_spawn_point(
-    spawn_points: &[SpawnPoint],
+    spawn_points: &mut [SpawnPoint],
     actors: &ActorContainer,
     scene: &Scene,
+    team: Team,
+    last_spawn_point: &mut Option<usize>,
+    rng: &mut GameRng,
 ) -> usize {
-    // Find spawn point with least amount of enemies nearby.
-    let mut index = rand::thread_rng().gen_range(0..spawn_points.len());
-    let mut max_distance = -std::f32::MAX;
+    let living = actors
+        .iter()
+        .filter(|actor| !actor.is_dead())
+        .map(|actor| (actor.position(&scene.graph), actor.team()))
+        .collect::<Vec<_>>();
+
+    // Points used moments ago are skipped, unless every point was used.
+    let all_cooling = spawn_points.iter().all(|pt| pt.cooldown > 0.0);
+    let is_ready = |pt: &SpawnPoint| all_cooling || pt.cooldown <= 0.0;
+
+    let mut best = None;
+    let mut best_score = -std::f32::MAX;
+    let mut neighbours = Vec::with_capacity(living.len());
     for (i, pt) in spawn_points.iter().enumerate() {
-        let mut sum_distance = 0.0;
-        for actor in actors.iter() {
-            let position = actor.position(&scene.graph);
-            sum_distance += pt.position.metric_distance(&position);
+        if !is_ready(pt) {
+            continue;
         }
-        if sum_distance > max_distance {
-            max_distance = sum_distance;
-            index = i;
+        let eye_position = pt.position + Vector3::new(0.0, 1.5, 0.0);
+        neighbours.clear();
+        neighbours.extend(living.iter().map(|(position, other)| SpawnNeighbour {
+            distance: pt.position.metric_distance(position),
+            visible: is_spawn_enemy(team, *other) && is_visible(scene, eye_position, *position),
+            team: *other,
+        }));
+        if let Some(score) =
+            score_spawn_point(pt.team, team, &neighbours, *last_spawn_point == Some(i))
+        {
+            if score > best_score {
+                best_score = score;
+                best = Some(i);
+            }
         }
     }
+
+    // Every point is contested, fall back to the point with least amount of enemies nearby.
+    let index = best.unwrap_or_else(|| {
+        let crowding = spawn_points
+            .iter()
+            .map(|pt| {
+                is_ready(pt).then(|| {
+                    actors
+                        .iter()
+                        .map(|actor| pt.position.metric_distance(&actor.position(&scene.graph)))
+                        .sum::<f32>()
+                })
+            })
+            .collect::<Vec<_>>();
+        least_contested_spawn_point(&crowding)
+            .unwrap_or_else(|| rng.gen_range(0..spawn_points.len()))
+    });
+    *last_spawn_point = Some(index);
+    if let Some(pt) = spawn_points.get_mut(index) {
+        pt.cooldown = SPAWN_COOLDOWN;
+    }
     index
 }
 
+/// Returns kind and name of `index`-th bot of a match, kinds go in turns. Names must be unique,
+/// because leader board uses them as keys.
+fn bot_kind_and_name(index: usize) -> (BotKind, String) {
+    let kinds = [BotKind::Maw, BotKind::Mutant, BotKind::Parasite];
+    let kind = kinds[index % kinds.len()];
+    let name = match index / kinds.len() {
+        0 => kind.description().to_owned(),
+        n => format!("{} {}", kind.description(), n + 1),
+    };
+    (kind, name)
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn spawn_bot(
     kind: BotKind,
     name: Option<String>,
-    spawn_points: &[SpawnPoint],
+    spawn_points: &mut [SpawnPoint],
     actors: &mut ActorContainer,
     weapons: &mut WeaponContainer,
     resource_manager: ResourceManager,
-    sender: Sender<Message>,
+    sender: MessageSender,
     leader_board: &mut LeaderBoard,
     scene: &mut Scene,
+    team: Team,
+    last_spawn_point: &mut Option<usize>,
+    rng: &mut GameRng,
+    dummy: bool,
 ) -> Handle<Actor> {
-    let index = find_suitable_spawn_point(spawn_points, actors, scene);
+    let index = find_suitable_spawn_point(spawn_points, actors, scene, team, last_spawn_point, rng);
     let spawn_position = spawn_points
         .get(index)
         .map_or(Vector3::default(), |pt| pt.position);
@@ -428,12 +1406,15 @@ async fn spawn_bot(
         sender,
         leader_board,
         scene,
+        dummy,
     )
     .await;
 
     bot
 }
 
+/// Adds a bot with a rifle, or an unarmed target dummy if `dummy` is set.
+#[allow(clippy::too_many_arguments)]
 async fn add_bot(
     kind: BotKind,
     position: Vector3<f32>,
@@ -441,11 +1422,12 @@ async fn add_bot(
     actors: &mut ActorContainer,
     weapons: &mut WeaponContainer,
     resource_manager: ResourceManager,
-    sender: Sender<Message>,
+    sender: MessageSender,
     leader_board: &mut LeaderBoard,
     scene: &mut Scene,
+    dummy: bool,
 ) -> Handle<Actor> {
-    let bot = Bot::new(
+    let mut bot = Bot::new(
         kind,
         resource_manager.clone(),
         scene,
@@ -453,9 +1435,13 @@ async fn add_bot(
         sender.clone(),
     )
     .await;
+    bot.set_inert(dummy);
     let name = name.unwrap_or_else(|| format!("Bot {:?} {}", kind, actors.count()));
-    leader_board.get_or_add_actor(&name);
+    leader_board.get_or_add_actor(&name).is_bot = true;
     let bot = actors.add(Actor::Bot(bot));
+    if dummy {
+        return bot;
+    }
     give_new_weapon(
         WeaponKind::Ak47,
         bot,
@@ -471,17 +1457,38 @@ async fn add_bot(
 }
 
 impl Level {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
+        map: PathBuf,
+        bot_count: usize,
+        seed: u64,
         resource_manager: ResourceManager,
         control_scheme: Arc<RwLock<ControlScheme>>,
-        sender: Sender<Message>,
+        preferences: Arc<RwLock<Preferences>>,
+        sender: MessageSender,
         options: MatchOptions,
-    ) -> (Level, Scene) {
+    ) -> Result<(Level, Scene), String> {
         let mut scene = Scene::new();
 
-        scene.ambient_lighting_color = Color::opaque(60, 60, 60);
+        let environment = MapEnvironment::load(&map);
+        environment.apply(&mut scene);
+        Self::apply_mutators(&mut scene, options.mutators());
 
-        let sound_manager = SoundManager::new(&mut scene.graph.sound_context);
+        let mut sound_manager = SoundManager::new(&mut scene.graph.sound_context);
+        let (_, preloaded_resources) = fyrox::core::futures::join!(
+            sound_manager.preload(resource_manager.clone()),
+            PreloadedResources::load(resource_manager.clone())
+        );
+        if let Some(music) = environment.music.as_ref() {
+            sound_manager
+                .play_music(
+                    &mut scene.graph,
+                    resource_manager.clone(),
+                    music,
+                    environment.music_volume,
+                )
+                .await;
+        }
 
         // Spectator camera is used when there is no player on level.
         // This includes situation when player is dead - all dead actors are removed
@@ -492,9 +1499,9 @@ impl Level {
 
         // Instantiate map
         let map_root = resource_manager
-            .request_model(Path::new("data/levels/dm6.rgs"))
+            .request_model(&map)
             .await
-            .unwrap()
+            .map_err(|e| format!("Unable to load map {}: {:?}", map.display(), e))?
             .instantiate_geometry(&mut scene);
 
         // Make sure global coordinates are calculated.
@@ -504,36 +1511,78 @@ impl Level {
             jump_pads,
             items,
             death_zones,
-            spawn_points,
-        } = analyze(&mut scene, resource_manager.clone(), sender.clone()).await;
+            world_bounds,
+            mut spawn_points,
+            breakable_lights,
+            props,
+            turrets,
+            issues,
+        } = analyze(
+            &mut scene,
+            resource_manager.clone(),
+            sender.clone(),
+            options.mutators(),
+        )
+        .await;
+        // Actors would be spawned inside geometry or would fall forever, better to tell
+        // map author right away.
+        let fatal_issues = issues
+            .iter()
+            .filter(|issue| issue.is_fatal())
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+        if !fatal_issues.is_empty() {
+            return Err(format!(
+                "Map {} can't be played: {}.",
+                map.display(),
+                fatal_issues.join(", ")
+            ));
+        }
         let mut actors = ActorContainer::new();
         let mut weapons = WeaponContainer::new();
         let mut leader_board = LeaderBoard::default();
+        let mut rng = GameRng::new(seed);
+        let mut last_spawn_point = None;
 
-        for &kind in &[BotKind::Maw, BotKind::Mutant, BotKind::Parasite] {
-            spawn_bot(
+        // Bots are spawned by respawn machinery a bit later, one by one. They are shown on
+        // leader board right away.
+        let mut bot_names = Vec::new();
+        let mut respawn_list = Vec::new();
+        // Practice has no hostile bots, only target dummies standing at spawn points.
+        let bot_count = if options.is_practice() {
+            PRACTICE_DUMMY_COUNT.min(spawn_points.len().saturating_sub(1))
+        } else {
+            bot_count
+        };
+        for i in 0..bot_count {
+            let (kind, name) = if options.is_practice() {
+                (BotKind::Mutant, format!("Dummy {}", i + 1))
+            } else {
+                bot_kind_and_name(i)
+            };
+            leader_board.get_or_add_actor(&name).is_bot = true;
+            bot_names.push(name.clone());
+            respawn_list.push(RespawnEntry::Bot(BotRespawnEntry {
+                name,
                 kind,
-                Some(kind.description().to_owned()),
-                &spawn_points,
-                &mut actors,
-                &mut weapons,
-                resource_manager.clone(),
-                sender.clone(),
-                &mut leader_board,
-                &mut scene,
-            )
-            .await;
+                time_left: INITIAL_BOT_SPAWN_INTERVAL * (i + 1) as f32,
+            }));
         }
 
-        let level = Level {
+        let mut level = Level {
             player: spawn_player(
-                &spawn_points,
+                &mut spawn_points,
                 &mut actors,
                 &mut weapons,
                 sender.clone(),
                 resource_manager.clone(),
                 control_scheme.clone(),
+                preferences.clone(),
                 &mut scene,
+                // Player picks a team after the level is loaded, any spawn point will do.
+                Team::None,
+                &mut last_spawn_point,
+                &mut rng,
             )
             .await,
             map_root,
@@ -544,57 +1593,298 @@ impl Level {
             jump_pads,
             items,
             death_zones,
+            world_bounds,
             spawn_points,
+            breakable_lights,
+            props,
+            turrets,
             leader_board,
             scene: Handle::NONE, // Filled when scene will be moved to engine.
-            sender: Some(sender),
+            sender,
             control_scheme: Some(control_scheme),
+            preferences: Some(preferences),
             time: 0.0,
-            respawn_list: Default::default(),
+            player_shots: 0,
+            player_hits: 0,
+            respawn_list,
+            bot_names,
+            teams: Default::default(),
             projectiles: ProjectileContainer::new(),
             target_spectator_position: Default::default(),
+            spectator_fall: Default::default(),
             sound_manager,
+            rng,
+            free_camera: None,
+            intermission: None,
+            interpolator: Default::default(),
+            item_grid: SpatialGrid::new(GRID_CELL_SIZE),
+            death_zone_grid: None,
+            preloaded_resources,
+            last_spawn_point,
+            zone_contacts: Default::default(),
+            ui_has_keyboard_focus: false,
+            mirror: false,
+            guest: Handle::NONE,
+            guest_connected: false,
+            host_proxy_requested: false,
+            swap_offer: None,
+            last_ammo_full_notice: None,
+            aim_target: Handle::NONE,
+            afk: false,
+            map_issues: issues,
         };
 
-        (level, scene)
+        // Actors were spawned before level existed, they join teams now.
+        let actors = level
+            .actors
+            .pair_iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        for actor in actors {
+            level.assign_team(actor);
+        }
+
+        Ok((level, scene))
     }
 
     pub fn destroy(&mut self, engine: &mut Engine) {
         engine.scenes.remove(self.scene);
     }
 
-    async fn give_new_weapon(
-        &mut self,
-        engine: &mut Engine,
-        actor: Handle<Actor>,
-        kind: WeaponKind,
-    ) {
-        give_new_weapon(
-            kind,
-            actor,
-            self.sender.clone().unwrap(),
-            engine.resource_manager.clone(),
-            true,
-            &mut self.weapons,
-            &mut self.actors,
-            &mut engine.scenes[self.scene],
-        )
-        .await;
+    /// Removes everything from scene that must not get into a save.
+    pub fn prepare_for_save(&mut self, engine: &mut Engine) {
+        self.projectiles
+            .release_free(&mut engine.scenes[self.scene]);
     }
 
-    async fn spawn_player(&mut self, engine: &mut Engine) -> Handle<Actor> {
+    /// Older saves have no scene nodes for parts of entities that were added later, such nodes
+    /// are created again.
+    fn restore_missing_nodes(&mut self, engine: &mut Engine) {
         let scene = &mut engine.scenes[self.scene];
-
-        let player = spawn_player(
-            &self.spawn_points,
+        let mut restored = 0;
+        for actor in self.actors.iter_mut() {
+            if let Actor::Player(player) = actor {
+                if player.restore_missing_nodes(scene) {
+                    restored += 1;
+                }
+            }
+        }
+        for weapon in self.weapons.iter_mut() {
+            if weapon.restore_missing_nodes(&mut scene.graph, &engine.resource_manager) {
+                restored += 1;
+            }
+        }
+        // Items take their home from global position, the scene was not updated after load yet.
+        scene.graph.update_hierarchical_data();
+        for item in self.items.iter_mut() {
+            if item.restore_missing_nodes(&mut scene.graph) {
+                restored += 1;
+            }
+        }
+        if restored > 0 {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Save was made by an older version, restored {} missing part(s).",
+                    restored
+                ),
+            );
+        }
+    }
+
+    /// Checks handles that were restored from a save. Handles that point nowhere are repaired
+    /// with a warning; problems that can't be repaired are returned, and such a save must not
+    /// be played.
+    pub fn validate_after_load(&mut self, engine: &mut Engine) -> Result<(), Vec<String>> {
+        self.check_scene(&engine.scenes[self.scene])?;
+        self.restore_missing_nodes(engine);
+        self.repair_handles(&mut engine.scenes[self.scene]);
+        Ok(())
+    }
+
+    /// Finds problems of a loaded level that can't be repaired.
+    fn check_scene(&self, scene: &Scene) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if !scene.graph.is_valid_handle(self.map_root) {
+            errors.push("Map root is missing from the scene.".to_owned());
+        }
+        if !scene.graph.is_valid_handle(self.spectator_camera) {
+            errors.push("Spectator camera is missing from the scene.".to_owned());
+        }
+        for (handle, actor) in self.actors.pair_iter() {
+            if !scene.graph.is_valid_handle(actor.body) {
+                errors.push(format!(
+                    "Body of actor {} ({:?}) is missing from the scene.",
+                    actor.name, handle
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if scene.navmeshes.at(0).is_none()
+            && self
+                .actors
+                .iter()
+                .any(|actor| matches!(actor, Actor::Bot(_)))
+        {
+            Logger::writeln(
+                MessageKind::Warning,
+                "Level has bots, but no navigation mesh. Bots won't be able to move.".to_owned(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Repairs handles between actors, weapons and projectiles that point nowhere.
+    fn repair_handles(&mut self, scene: &mut Scene) {
+        if self.player.is_some()
+            && !matches!(self.actors.try_get(self.player), Some(Actor::Player(_)))
+        {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Player {:?} is not a player actor, it will respawn.",
+                    self.player
+                ),
+            );
+            self.player = Handle::NONE;
+        }
+        // Player without pending respawn was saved while AFK, AFK state is not saved.
+        if self.player.is_none()
+            && !self
+                .respawn_list
+                .iter()
+                .any(|entry| matches!(entry, RespawnEntry::Player(_)))
+        {
+            self.respawn_list
+                .push(RespawnEntry::Player(PlayerRespawnEntry {
+                    time_left: RESPAWN_TIME,
+                }));
+        }
+
+        for actor in self.actors.iter_mut() {
+            let weapons = &self.weapons;
+            let count = actor.weapons.len();
+            actor.weapons.retain(|&weapon| weapons.contains(weapon));
+            if actor.weapons.len() != count {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Removed {} missing weapon(s) from actor {}.",
+                        count - actor.weapons.len(),
+                        actor.name
+                    ),
+                );
+            }
+            if actor.current_weapon as usize >= actor.weapons.len().max(1) {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Current weapon {} of actor {} is out of range.",
+                        actor.current_weapon, actor.name
+                    ),
+                );
+                actor.current_weapon = 0;
+            }
+        }
+
+        // Weapon without an owner would hang in the air forever.
+        let orphans = self
+            .weapons
+            .pair_iter()
+            .filter(|(handle, weapon)| {
+                self.actors
+                    .try_get(weapon.owner())
+                    .map_or(true, |actor| !actor.weapons.contains(handle))
+            })
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        for weapon in orphans {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!("Removed weapon {:?} that has no owner.", weapon),
+            );
+            self.remove_weapon(scene, weapon);
+        }
+
+        // Weapons are fixed at this point, so owners of projectiles can be checked.
+        for projectile in self.projectiles.iter_mut() {
+            if projectile.owner.is_some() && !self.weapons.contains(projectile.owner) {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Projectile owner {:?} is missing, owner reset.",
+                        projectile.owner
+                    ),
+                );
+                projectile.owner = Handle::NONE;
+            }
+        }
+    }
+
+    async fn give_new_weapon(
+        &mut self,
+        engine: &mut Engine,
+        actor: Handle<Actor>,
+        kind: WeaponKind,
+    ) {
+        let switch = self.actors.contains(actor) && self.should_auto_switch(actor, kind);
+        give_new_weapon(
+            kind,
+            actor,
+            self.sender.clone(),
+            engine.resource_manager.clone(),
+            switch,
+            &mut self.weapons,
+            &mut self.actors,
+            &mut engine.scenes[self.scene],
+        )
+        .await;
+    }
+
+    /// Decides whether actor takes out picked up weapon. Bots always take better weapons, player
+    /// does it only if it is enabled in settings and never while firing.
+    fn should_auto_switch(&self, actor: Handle<Actor>, kind: WeaponKind) -> bool {
+        let actor = self.actors.get(actor);
+        let current = actor.current_weapon();
+        let is_better = !self.weapons.contains(current)
+            || kind.priority() > self.weapons[current].get_kind().priority();
+        match actor {
+            Actor::Bot(_) => is_better,
+            Actor::Player(player) => {
+                is_better
+                    && !player.is_shooting()
+                    && self.preferences.as_ref().map_or(true, |preferences| {
+                        preferences.read().unwrap().auto_switch_weapon
+                    })
+            }
+        }
+    }
+
+    async fn spawn_player(&mut self, engine: &mut Engine) -> Handle<Actor> {
+        let team = self.spawn_team(PLAYER_NAME);
+        let scene = &mut engine.scenes[self.scene];
+
+        let player = spawn_player(
+            &mut self.spawn_points,
             &mut self.actors,
             &mut self.weapons,
-            self.sender.clone().unwrap(),
+            self.sender.clone(),
             engine.resource_manager.clone(),
             self.control_scheme.clone().unwrap(),
+            self.preferences.clone().unwrap(),
             scene,
+            team,
+            &mut self.last_spawn_point,
+            &mut self.rng,
         )
         .await;
+        self.leader_board.get_or_add_actor(PLAYER_NAME).is_bot = false;
+        self.assign_team(player);
 
         if let Some(spectator_camera) = scene.graph[self.spectator_camera].cast_mut::<Camera>() {
             spectator_camera.set_enabled(false);
@@ -607,7 +1897,342 @@ impl Level {
         self.player
     }
 
+    /// Turns level into a mirror of host's level, see [`crate::net`].
+    pub fn set_mirror(&mut self, mirror: bool) {
+        self.mirror = mirror;
+        if mirror {
+            // Mirror does not run respawns, bots that are going to join are added right away
+            // and then moved by snapshots of host.
+            let position = self
+                .spawn_points
+                .first()
+                .map_or(Vector3::default(), |pt| pt.position);
+            for entry in self.respawn_list.drain(..) {
+                if let RespawnEntry::Bot(bot) = entry {
+                    self.sender.send(Message::AddBot {
+                        kind: bot.kind,
+                        position,
+                        name: Some(bot.name),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Spawns actor of network client, it is controlled by input received from network.
+    pub async fn connect_guest(&mut self, engine: &mut Engine) {
+        self.guest_connected = true;
+        self.spawn_guest(engine).await;
+    }
+
+    pub fn disconnect_guest(&mut self) {
+        self.guest_connected = false;
+        if self.actors.contains(self.guest) {
+            self.sender.send(Message::RemoveActor { actor: self.guest });
+        }
+        self.guest = Handle::NONE;
+    }
+
+    async fn spawn_guest(&mut self, engine: &mut Engine) {
+        // Client may leave while its actor waits for respawn.
+        if !self.guest_connected {
+            return;
+        }
+
+        let team = self.spawn_team(GUEST_NAME);
+        let scene = &mut engine.scenes[self.scene];
+        let guest = spawn_player(
+            &mut self.spawn_points,
+            &mut self.actors,
+            &mut self.weapons,
+            self.sender.clone(),
+            engine.resource_manager.clone(),
+            self.control_scheme.clone().unwrap(),
+            self.preferences.clone().unwrap(),
+            scene,
+            team,
+            &mut self.last_spawn_point,
+            &mut self.rng,
+        )
+        .await;
+        let actor = self.actors.get_mut(guest);
+        actor.name = GUEST_NAME.to_owned();
+        self.leader_board.get_or_add_actor(GUEST_NAME).is_bot = false;
+        if let Actor::Player(player) = actor {
+            // Host sees the world through its own camera only.
+            Self::set_camera_enabled(&mut scene.graph, player.camera(), false);
+        }
+        self.guest = guest;
+        self.assign_team(guest);
+    }
+
+    pub fn apply_guest_input(&mut self, input: &RemoteInput) {
+        if let Some(Actor::Player(guest)) = self.actors.try_get_mut(self.guest) {
+            guest.apply_remote_input(input);
+        }
+    }
+
+    /// Returns input of local player, client sends it to host every tick.
+    pub fn local_input(&self) -> Option<RemoteInput> {
+        match self.actors.try_get(self.player) {
+            Some(Actor::Player(player)) => Some(player.remote_input()),
+            _ => None,
+        }
+    }
+
+    /// Collects state of every actor, host sends it to client every tick.
+    pub fn snapshot(&self, engine: &Engine) -> Vec<ActorState> {
+        let graph = &engine.scenes[self.scene].graph;
+        self.actors
+            .pair_iter()
+            .map(|(handle, actor)| ActorState {
+                role: if handle == self.player {
+                    ActorRole::Host
+                } else if handle == self.guest {
+                    ActorRole::Guest
+                } else {
+                    ActorRole::Bot
+                },
+                name: actor.name.clone(),
+                position: actor.position(graph),
+                rotation: **graph[actor.get_body()].local_transform().rotation(),
+                health: actor.health,
+                armor: actor.armor,
+                alive: !actor.is_dead(),
+            })
+            .collect()
+    }
+
+    /// Moves actors of mirrored level to positions received from host and gives them health
+    /// and armor of their counterparts. Local player keeps its own orientation, it follows
+    /// local input. Host's player has no counterpart on client, so a stand-in bot is spawned
+    /// for it.
+    pub fn apply_snapshot(&mut self, engine: &mut Engine, states: &[ActorState]) {
+        let graph = &mut engine.scenes[self.scene].graph;
+        for state in states {
+            let handle = match state.role {
+                ActorRole::Guest => self.player,
+                ActorRole::Host => self.find_actor_by_name(HOST_NAME),
+                ActorRole::Bot => self.find_actor_by_name(&state.name),
+            };
+            if let Some(actor) = self.actors.try_get_mut(handle) {
+                actor.set_position(graph, state.position);
+                if state.role != ActorRole::Guest {
+                    graph[actor.get_body()]
+                        .local_transform_mut()
+                        .set_rotation(state.rotation);
+                }
+                actor.health = if state.alive {
+                    state.health
+                } else {
+                    state.health.min(0.0)
+                };
+                actor.armor = state.armor;
+            } else if state.role == ActorRole::Host && !self.host_proxy_requested {
+                self.host_proxy_requested = true;
+                self.sender.send(Message::AddBot {
+                    kind: BotKind::Maw,
+                    position: state.position,
+                    name: Some(HOST_NAME.to_owned()),
+                });
+            }
+        }
+    }
+
+    fn find_actor_by_name(&self, name: &str) -> Handle<Actor> {
+        self.actors
+            .pair_iter()
+            .find(|(_, actor)| actor.name == name)
+            .map_or(Handle::NONE, |(handle, _)| handle)
+    }
+
+    /// Names of bots in the order they joined the match, including bots waiting for respawn.
+    pub fn bot_names(&self) -> &[String] {
+        &self.bot_names
+    }
+
+    /// Returns kind and unique name for a bot that is going to join the match.
+    pub fn next_bot(&self) -> (BotKind, String) {
+        (self.bot_names.len()..)
+            .map(bot_kind_and_name)
+            .find(|(_, name)| !self.bot_names.contains(name))
+            .unwrap()
+    }
+
+    /// Gives actor its team back after respawn, newcomers join the smaller team.
+    fn assign_team(&mut self, actor: Handle<Actor>) {
+        if !self.options.is_team_mode() {
+            return;
+        }
+        let name = self.actors.get(actor).name.clone();
+        let team = self.spawn_team(&name);
+        self.actors.get_mut(actor).set_team(team);
+    }
+
+    /// Team of actor with given name, it is known before the actor spawns, so spawn point
+    /// can be picked for the team. Newcomers join the smaller team. `Team::None` in modes
+    /// without teams.
+    fn spawn_team(&mut self, name: &str) -> Team {
+        if !self.options.is_team_mode() {
+            return Team::None;
+        }
+        match self.teams.get(name) {
+            Some(team) => *team,
+            None => {
+                let team = self.balanced_team();
+                self.teams.insert(name.to_owned(), team);
+                team
+            }
+        }
+    }
+
+    /// Amount of members of a team, including ones waiting for respawn.
+    fn team_size(&self, team: Team) -> usize {
+        self.teams
+            .values()
+            .filter(|member| **member == team)
+            .count()
+    }
+
+    /// Returns team with fewer members, ties are broken in favor of a team with lower score.
+    fn balanced_team(&self) -> Team {
+        let rank = |team| (self.team_size(team), self.leader_board.team_score(team));
+        if rank(Team::Blue) < rank(Team::Red) {
+            Team::Blue
+        } else {
+            Team::Red
+        }
+    }
+
+    /// Moves actor to another team and respawns it there. Request is rejected if new team
+    /// would have two or more members more than the other one.
+    async fn change_team(&mut self, engine: &mut Engine, actor: Handle<Actor>, team: Team) {
+        if !self.options.is_team_mode() || team == Team::None {
+            return;
+        }
+        let (name, current) = match self.actors.try_get(actor) {
+            Some(character) => (character.name.clone(), character.team()),
+            None => return,
+        };
+        if current == team {
+            return;
+        }
+
+        let new_size = self.team_size(team) + 1;
+        let mut other_size = self.team_size(team.opponent());
+        if current == team.opponent() {
+            other_size -= 1;
+        }
+        if new_size > other_size + 1 {
+            self.sender.send(Message::AddNotification {
+                text: format!(
+                    "{} can't join {} team, teams would be unbalanced.",
+                    name,
+                    team.name()
+                ),
+                severity: NotificationSeverity::Important,
+                icon: None,
+            });
+            return;
+        }
+
+        self.teams.insert(name.clone(), team);
+        self.sender.send(Message::AddNotification {
+            text: format!("{} joined {} team.", name, team.name()),
+            severity: NotificationSeverity::Info,
+            icon: None,
+        });
+        // Switching team is not a death, so it is not counted on leader board.
+        self.schedule_respawn(engine, actor).await;
+    }
+
+    async fn remove_bot(&mut self, engine: &mut Engine, name: &str) {
+        if !self.bot_names.iter().any(|bot_name| bot_name == name) {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!("Unable to remove bot {}, there is no such bot.", name),
+            );
+            return;
+        }
+
+        let actor = self.find_actor_by_name(name);
+        if let Some(Actor::Bot(_)) = self.actors.try_get(actor) {
+            self.remove_mines(engine, actor);
+            self.remove_actor(engine, actor).await;
+        }
+        // Dead bot has no actor, only an entry that would bring it back.
+        self.respawn_list
+            .retain(|entry| !matches!(entry, RespawnEntry::Bot(bot) if bot.name == name));
+        self.leader_board.remove_actor(name);
+        self.bot_names.retain(|bot_name| bot_name != name);
+        self.teams.remove(name);
+
+        self.sender.send(Message::AddNotification {
+            text: format!("Bot {} left the match.", name),
+            severity: NotificationSeverity::Info,
+            icon: None,
+        });
+    }
+
+    fn shuffle_teams(&mut self) {
+        if !self.options.is_team_mode() {
+            return;
+        }
+
+        // Leader board knows everyone in the match, including actors waiting for respawn.
+        // Names are sorted first, so the result depends only on the seed of the match.
+        let mut names = self
+            .leader_board
+            .values()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names.shuffle(&mut self.rng);
+
+        for (i, name) in names.into_iter().enumerate() {
+            let team = if i % 2 == 0 { Team::Red } else { Team::Blue };
+            let actor = self.find_actor_by_name(&name);
+            if let Some(character) = self.actors.try_get_mut(actor) {
+                character.set_team(team);
+            }
+            self.teams.insert(name, team);
+        }
+
+        self.sender.send(Message::AddNotification {
+            text: "Teams were shuffled.".to_owned(),
+            severity: NotificationSeverity::Info,
+            icon: None,
+        });
+    }
+
+    pub fn set_ui_has_keyboard_focus(&mut self, focus: bool) {
+        if focus && !self.ui_has_keyboard_focus && self.player.is_some() {
+            // Keys that are held when UI takes focus will be released while UI has it.
+            if let Actor::Player(player) = self.actors.get_mut(self.player) {
+                player.stop();
+            }
+        }
+        self.ui_has_keyboard_focus = focus;
+    }
+
     pub fn process_input_event(&mut self, event: &Event<()>) -> bool {
+        if self.ui_has_keyboard_focus || self.intermission.is_some() {
+            return false;
+        }
+
+        if let Some(free_camera) = self.free_camera.as_mut() {
+            if let Some(control_scheme) = self.control_scheme.as_ref() {
+                free_camera.process_input_event(event, &control_scheme.read().unwrap());
+            }
+            return false;
+        }
+
+        if self.afk && player::is_press(event) {
+            self.sender.send(Message::PlayerReturned);
+            return false;
+        }
+
         if self.player.is_some() {
             if let Actor::Player(player) = self.actors.get_mut(self.player) {
                 return player.process_input_event(event);
@@ -624,10 +2249,6 @@ impl Level {
         &mut self.actors
     }
 
-    pub fn weapons(&self) -> &WeaponContainer {
-        &self.weapons
-    }
-
     fn pick(&self, engine: &mut Engine, from: Vector3<f32>, to: Vector3<f32>) -> Vector3<f32> {
         let scene = &mut engine.scenes[self.scene];
         let ray = Ray::from_two_points(from, to);
@@ -638,23 +2259,35 @@ impl Level {
             groups: InteractionGroups::default(),
             sort_results: true,
         };
-        let mut query_buffer = Vec::default();
-        scene.graph.physics.cast_ray(options, &mut query_buffer);
-        if let Some(pt) = query_buffer.first() {
-            pt.position.coords
-        } else {
-            from
+        QueryBufferPool::with(|query_buffer| {
+            scene.graph.physics.cast_ray(options, query_buffer);
+            if let Some(pt) = query_buffer.first() {
+                pt.position.coords
+            } else {
+                from
+            }
+        })
+    }
+
+    /// Removes mines of an actor that leaves the match. Mines of killed actors stay, so they
+    /// are not removed together with weapons.
+    fn remove_mines(&mut self, engine: &mut Engine, actor: Handle<Actor>) {
+        if self.actors.contains(actor) {
+            let scene = &mut engine.scenes[self.scene];
+            for &weapon in self.actors.get(actor).weapons() {
+                self.projectiles.remove_mines(weapon, scene);
+            }
         }
     }
 
-    fn remove_weapon(&mut self, engine: &mut Engine, weapon: Handle<Weapon>) {
+    fn remove_weapon(&mut self, scene: &mut Scene, weapon: Handle<Weapon>) {
         for projectile in self.projectiles.iter_mut() {
             if projectile.owner == weapon {
                 // Reset owner because handle to weapon will be invalid after weapon freed.
                 projectile.owner = Handle::NONE;
             }
         }
-        self.weapons[weapon].clean_up(&mut engine.scenes[self.scene]);
+        self.weapons[weapon].clean_up(scene);
         self.weapons.free(weapon);
     }
 
@@ -672,9 +2305,10 @@ impl Level {
             &mut self.actors,
             &mut self.weapons,
             engine.resource_manager.clone(),
-            self.sender.clone().unwrap(),
+            self.sender.clone(),
             &mut self.leader_board,
             &mut engine.scenes[self.scene],
+            false,
         )
         .await
     }
@@ -692,15 +2326,32 @@ impl Level {
                 .copied()
                 .collect::<Vec<Handle<Weapon>>>();
             for weapon in weapons {
-                let item_kind = match self.weapons[weapon].get_kind() {
-                    WeaponKind::M4 => ItemKind::M4,
-                    WeaponKind::Ak47 => ItemKind::Ak47,
-                    WeaponKind::PlasmaRifle => ItemKind::PlasmaGun,
-                    WeaponKind::RocketLauncher => ItemKind::RocketLauncher,
-                };
-                self.spawn_item(engine, item_kind, drop_position, true, Some(20.0))
+                // Rocket launcher with rockets left also leaves a pack of rockets, otherwise
+                // rockets would only come from map pickups.
+                if self.weapons[weapon].get_kind() == WeaponKind::RocketLauncher
+                    && self.weapons[weapon].ammo() > 0
+                {
+                    self.spawn_item(
+                        engine,
+                        ItemKind::Rockets,
+                        drop_position,
+                        true,
+                        Some(DROPPED_WEAPON_LIFETIME),
+                    )
+                    .await;
+                }
+                if let Some(item_kind) = ItemKind::from_weapon_kind(self.weapons[weapon].get_kind())
+                {
+                    self.spawn_item(
+                        engine,
+                        item_kind,
+                        drop_position,
+                        true,
+                        Some(DROPPED_WEAPON_LIFETIME),
+                    )
                     .await;
-                self.remove_weapon(engine, weapon);
+                }
+                self.remove_weapon(&mut engine.scenes[self.scene], weapon);
             }
 
             let scene = &mut engine.scenes[self.scene];
@@ -713,129 +2364,512 @@ impl Level {
         }
     }
 
-    async fn give_item(&mut self, engine: &mut Engine, actor: Handle<Actor>, kind: ItemKind) {
-        if self.actors.contains(actor) {
-            let character = self.actors.get_mut(actor);
-            match kind {
-                ItemKind::Medkit => character.heal(20.0),
-                ItemKind::Ak47 | ItemKind::PlasmaGun | ItemKind::M4 | ItemKind::RocketLauncher => {
-                    let weapon_kind = match kind {
-                        ItemKind::Ak47 => WeaponKind::Ak47,
-                        ItemKind::PlasmaGun => WeaponKind::PlasmaRifle,
-                        ItemKind::M4 => WeaponKind::M4,
-                        ItemKind::RocketLauncher => WeaponKind::RocketLauncher,
-                        _ => unreachable!(),
-                    };
+    /// Gives item of given kind to the actor, returns false if the item gave nothing because
+    /// ammo is full already.
+    async fn give_item(
+        &mut self,
+        engine: &mut Engine,
+        actor: Handle<Actor>,
+        kind: ItemKind,
+    ) -> bool {
+        if !self.actors.contains(actor) {
+            return false;
+        }
+        let character = self.actors.get_mut(actor);
+        match kind {
+            ItemKind::Medkit => character.heal(20.0),
+            ItemKind::MegaHealth => character.heal_up_to(100.0, 200.0),
+            ItemKind::Armor | ItemKind::YellowArmor | ItemKind::RedArmor => {
+                character.add_armor(kind.armor_tier().unwrap())
+            }
+            ItemKind::Ak47 | ItemKind::PlasmaGun | ItemKind::M4 | ItemKind::RocketLauncher => {
+                let weapon_kind = kind.weapon_kind().unwrap();
 
-                    let mut found = false;
-                    for weapon_handle in character.weapons() {
-                        let weapon = &mut self.weapons[*weapon_handle];
-                        // If actor already has weapon of given kind, then just add ammo to it.
-                        if weapon.get_kind() == weapon_kind {
-                            found = true;
-                            weapon.add_ammo(200);
-                            break;
-                        }
-                    }
-                    // Finally if actor does not have such weapon, give new one to him.
-                    if !found {
-                        self.give_new_weapon(engine, actor, weapon_kind).await;
+                let mut found = None;
+                for (i, weapon_handle) in character.weapons().iter().enumerate() {
+                    let weapon = &mut self.weapons[*weapon_handle];
+                    // If actor already has weapon of given kind, then just add ammo to it.
+                    if weapon.get_kind() == weapon_kind {
+                        found = Some((i, weapon.add_ammo(WEAPON_ITEM_AMMO)));
+                        break;
                     }
                 }
-                ItemKind::Plasma | ItemKind::Ak47Ammo | ItemKind::M4Ammo => {
-                    for weapon in character.weapons() {
-                        let weapon = &mut self.weapons[*weapon];
-                        let (weapon_kind, ammo) = match kind {
-                            ItemKind::Plasma => (WeaponKind::PlasmaRifle, 200),
-                            ItemKind::Ak47Ammo => (WeaponKind::Ak47, 200),
-                            ItemKind::M4Ammo => (WeaponKind::M4, 200),
-                            _ => continue,
-                        };
-                        if weapon.get_kind() == weapon_kind {
-                            weapon.add_ammo(ammo);
-                            break;
+                match found {
+                    Some((_, 0)) => return false,
+                    Some((i, _)) => {
+                        if self.should_auto_switch(actor, weapon_kind) {
+                            self.actors.get_mut(actor).set_current_weapon(i);
                         }
                     }
+                    // Finally if actor does not have such weapon, give new one to him.
+                    None => self.give_new_weapon(engine, actor, weapon_kind).await,
                 }
             }
+            ItemKind::Plasma | ItemKind::Ak47Ammo | ItemKind::M4Ammo | ItemKind::Rockets => {
+                let weapon_kind = kind.ammo_weapon_kind().unwrap();
+                let amount = if kind == ItemKind::Rockets {
+                    ROCKETS_ITEM_AMMO
+                } else {
+                    AMMO_ITEM_AMMO
+                };
+                let weapons = &mut self.weapons;
+                let added = character
+                    .weapons()
+                    .iter()
+                    .find(|&&weapon| weapons[weapon].get_kind() == weapon_kind)
+                    .map_or(0, |&weapon| weapons[weapon].add_ammo(amount));
+                return added > 0;
+            }
         }
+        true
     }
 
-    async fn pickup_item(&mut self, engine: &mut Engine, actor: Handle<Actor>, item: Handle<Item>) {
-        if self.actors.contains(actor) && self.items.contains(item) {
-            let item = self.items.get_mut(item);
+    /// Returns true if item would give nothing useful to the actor, such items stay on level.
+    /// Weapon is wasted if actor has it already with at least as much ammo as the item gives,
+    /// or with full ammo.
+    fn is_pickup_wasted(&self, actor: Handle<Actor>, kind: ItemKind) -> bool {
+        match kind.weapon_kind() {
+            Some(weapon_kind) => self.actors.get(actor).weapons().iter().any(|&weapon| {
+                let weapon = &self.weapons[weapon];
+                weapon.get_kind() == weapon_kind
+                    && (weapon.ammo() >= WEAPON_ITEM_AMMO || weapon.is_ammo_full())
+            }),
+            None => false,
+        }
+    }
 
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::AddNotification {
-                    text: format!("Actor picked up item {:?}", item.get_kind()),
-                })
-                .unwrap();
+    /// Returns true if weapon item of given kind can be taken by the actor: actor has no such
+    /// weapon, or the weapon can take more ammo.
+    fn can_take_weapon(&self, actor: Handle<Actor>, kind: WeaponKind) -> bool {
+        self.actors.get(actor).weapons().iter().all(|&weapon| {
+            let weapon = &self.weapons[weapon];
+            weapon.get_kind() != kind || !weapon.is_ammo_full()
+        })
+    }
 
-            let scene = &mut engine.scenes[self.scene];
-            let position = item.position(&scene.graph);
-            item.pick_up();
-            let kind = item.get_kind();
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::PlaySound {
-                    path: PathBuf::from("data/sounds/item_pickup.ogg"),
-                    position,
-                    gain: 1.0,
-                    rolloff_factor: 3.0,
-                    radius: 2.0,
-                })
-                .unwrap();
-            self.give_item(engine, actor, kind).await;
+    /// Returns true if magnetic item of given kind would give something to the actor.
+    fn wants_item(&self, actor: Handle<Actor>, kind: ItemKind) -> bool {
+        let character = self.actors.get(actor);
+        match kind.ammo_weapon_kind() {
+            Some(weapon_kind) => character.weapons().iter().any(|&weapon| {
+                let weapon = &self.weapons[weapon];
+                weapon.get_kind() == weapon_kind && !weapon.is_ammo_full()
+            }),
+            None => character.get_health() < HEAL_LIMIT,
         }
     }
 
-    async fn create_projectile(
-        &mut self,
-        engine: &mut Engine,
-        kind: ProjectileKind,
-        position: Vector3<f32>,
-        direction: Vector3<f32>,
-        initial_velocity: Vector3<f32>,
-        owner: Handle<Weapon>,
-        basis: Matrix3<f32>,
-    ) {
-        let scene = &mut engine.scenes[self.scene];
-        let projectile = Projectile::new(
-            kind,
-            engine.resource_manager.clone(),
-            scene,
-            direction,
-            position,
-            owner,
-            initial_velocity,
-            self.sender.as_ref().unwrap().clone(),
-            basis,
-        )
-        .await;
-        self.projectiles.add(projectile);
+    /// Living actors together with magnetic items they would take.
+    fn item_magnets(&self, graph: &Graph) -> Vec<ItemMagnet> {
+        self.actors
+            .pair_iter()
+            .filter(|(_, actor)| !actor.is_dead())
+            .map(|(handle, actor)| ItemMagnet {
+                position: actor.position(graph),
+                wanted: ItemKind::MAGNETIC
+                    .iter()
+                    .copied()
+                    .filter(|&kind| self.wants_item(handle, kind))
+                    .collect(),
+            })
+            .collect()
     }
 
-    async fn shoot_weapon(
+    async fn pickup_item(
         &mut self,
         engine: &mut Engine,
-        weapon_handle: Handle<Weapon>,
-        initial_velocity: Vector3<f32>,
+        actor: Handle<Actor>,
+        item: Handle<Item>,
         time: GameTime,
-        direction: Option<Vector3<f32>>,
     ) {
-        if self.weapons.contains(weapon_handle) {
-            let scene = &mut engine.scenes[self.scene];
+        if self.actors.contains(actor)
+            && self.items.contains(item)
+            && !self.is_pickup_wasted(actor, self.items.get(item).get_kind())
+            && !self.take_item(engine, actor, item).await
+            && actor == self.player
+        {
+            self.notify_ammo_full(time);
+        }
+    }
+
+    fn notify_ammo_full(&mut self, time: GameTime) {
+        if self.last_ammo_full_notice.map_or(true, |last| {
+            time.elapsed - last >= AMMO_FULL_NOTICE_INTERVAL
+        }) {
+            self.last_ammo_full_notice = Some(time.elapsed);
+            self.sender.send(Message::AddNotification {
+                text: "Ammo full".to_owned(),
+                severity: NotificationSeverity::Info,
+                icon: None,
+            });
+        }
+    }
+
+    /// Gives item to the actor and removes it from level, returns false if item was left on
+    /// level because it gave nothing.
+    async fn take_item(
+        &mut self,
+        engine: &mut Engine,
+        actor: Handle<Actor>,
+        item: Handle<Item>,
+    ) -> bool {
+        if self.actors.contains(actor) && self.items.contains(item) {
+            let kind = self.items.get(item).get_kind();
+            if !self.give_item(engine, actor, kind).await {
+                return false;
+            }
+
+            let item = self.items.get_mut(item);
+
+            self.sender.send(Message::ReportEvent {
+                event: GameEvent::Pickup {
+                    actor: self.actors.get(actor).name.clone(),
+                    item: kind,
+                },
+            });
+            self.sender.send(Message::AddNotification {
+                text: format!(
+                    "{} picked up {}",
+                    self.actors.get(actor).name,
+                    item.get_kind().display_name()
+                ),
+                severity: NotificationSeverity::Pickup,
+                icon: Some(PathBuf::from(item.get_kind().icon())),
+            });
+
+            let scene = &mut engine.scenes[self.scene];
+            let position = item.position(&scene.graph);
+            item.pick_up();
+            self.sender.send(Message::PlaySound {
+                path: PathBuf::from(ITEM_PICKUP_SOUND),
+                position,
+                gain: 1.0,
+                rolloff_factor: 3.0,
+                radius: 2.0,
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drops current weapon of the actor and takes weapon from given item instead.
+    async fn swap_weapon(&mut self, engine: &mut Engine, actor: Handle<Actor>, item: Handle<Item>) {
+        if !self.actors.contains(actor) || !self.items.contains(item) {
+            return;
+        }
+        let new_kind = match self.items.get(item) {
+            item if item.is_available() => match item.get_kind().weapon_kind() {
+                Some(kind) => kind,
+                None => return,
+            },
+            _ => return,
+        };
+        let current = self.actors.get(actor).current_weapon();
+        if !self.weapons.contains(current) || self.weapons[current].get_kind() == new_kind {
+            return;
+        }
+        // Item would stay on the ground, current weapon must not be dropped for nothing.
+        if !self.can_take_weapon(actor, new_kind) {
+            return;
+        }
+        let dropped_kind = match ItemKind::from_weapon_kind(self.weapons[current].get_kind()) {
+            Some(kind) => kind,
+            None => return,
+        };
+
+        let position = self
+            .actors
+            .get(actor)
+            .position(&engine.scenes[self.scene].graph);
+        let dropped = self
+            .spawn_item(
+                engine,
+                dropped_kind,
+                position,
+                true,
+                Some(DROPPED_WEAPON_LIFETIME),
+            )
+            .await;
+        self.items
+            .get_mut(dropped)
+            .set_pickup_delay(SWAP_PICKUP_DELAY);
+        self.actors.get_mut(actor).remove_weapon(current);
+        self.remove_weapon(&mut engine.scenes[self.scene], current);
+
+        self.take_item(engine, actor, item).await;
+        let weapons = &self.weapons;
+        let character = self.actors.get_mut(actor);
+        if let Some(i) = character
+            .weapons()
+            .iter()
+            .position(|&weapon| weapons[weapon].get_kind() == new_kind)
+        {
+            character.set_current_weapon(i);
+        }
+    }
+
+    /// Bots that show health bars: recently damaged ones and the one under crosshair. Only
+    /// the nearest ones to the player are returned.
+    pub fn health_bars(&self, engine: &Engine, time: &GameTime) -> Vec<HealthBar> {
+        let graph = &engine.scenes[self.scene].graph;
+        let eye = match self.actors.try_get(self.player) {
+            Some(Actor::Player(player)) => graph[player.camera()].global_position(),
+            _ => return Vec::new(),
+        };
+        let is_team_mode = self.options.is_team_mode();
+
+        let mut bars = self
+            .actors
+            .pair_iter()
+            .filter_map(|(handle, actor)| match actor {
+                Actor::Bot(bot)
+                    if !bot.is_dead()
+                        && (handle == self.aim_target
+                            || bot.last_damage_time.map_or(false, |last| {
+                                time.elapsed - last < health_bars::SHOW_TIME
+                            })) =>
+                {
+                    Some(HealthBar {
+                        position: bot.head_position(graph)
+                            + Vector3::new(0.0, HEALTH_BAR_HEIGHT, 0.0),
+                        health: bot.get_health() / BASE_HEALTH,
+                        team: if is_team_mode { bot.team() } else { Team::None },
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        bars.sort_by(|a, b| {
+            let a = (a.position - eye).norm_squared();
+            let b = (b.position - eye).norm_squared();
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        });
+        bars.truncate(health_bars::MAX_BARS);
+        bars
+    }
+
+    /// Camera of local player while it watches the match instead of playing: free camera, or
+    /// spectator camera while player is dead. `None` while player plays.
+    pub fn spectator_view(&self) -> Option<Handle<Node>> {
+        match self.free_camera.as_ref() {
+            Some(free_camera) => Some(free_camera.camera()),
+            None if self.player.is_none() && self.intermission.is_none() => {
+                Some(self.spectator_camera)
+            }
+            None => None,
+        }
+    }
+
+    /// Respawn timers of major items that were picked up, only for spectators. Only the
+    /// nearest items to the camera are returned.
+    pub fn item_timers(&self, engine: &Engine) -> Vec<ItemTimer> {
+        let scene = &engine.scenes[self.scene];
+        let eye = match self.spectator_view() {
+            Some(camera) if scene.graph.is_valid_handle(camera) => {
+                scene.graph[camera].global_position()
+            }
+            _ => return Vec::new(),
+        };
+
+        let mut timers = self
+            .items
+            .iter()
+            .filter(|item| item.get_kind().is_major())
+            .filter_map(|item| {
+                item.respawn_remaining().map(|remaining| ItemTimer {
+                    position: item.position(&scene.graph)
+                        + Vector3::new(0.0, ITEM_TIMER_HEIGHT, 0.0),
+                    icon: item.get_kind().icon(),
+                    remaining,
+                    occluded: false,
+                })
+            })
+            .collect::<Vec<_>>();
+        timers.sort_by(|a, b| {
+            let a = (a.position - eye).norm_squared();
+            let b = (b.position - eye).norm_squared();
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        });
+        timers.truncate(item_timers::MAX_TIMERS);
+        // Rays are cast only for timers that are going to be shown.
+        for timer in timers.iter_mut() {
+            timer.occluded = !is_visible(scene, eye, timer.position);
+        }
+        timers
+    }
+
+    /// Finds living actor on which laser sight of player's current weapon rests, laser sight
+    /// is updated by weapon, so no extra ray cast is needed.
+    fn update_aim_target(&mut self) {
+        self.aim_target = match self.actors.try_get(self.player) {
+            Some(player) if !player.is_dead() && self.weapons.contains(player.current_weapon()) => {
+                let target = self.weapons[player.current_weapon()].aimed_actor();
+                match self.actors.try_get(target) {
+                    Some(actor) if !actor.is_dead() => target,
+                    _ => Handle::NONE,
+                }
+            }
+            _ => Handle::NONE,
+        };
+    }
+
+    /// Makes items next to player glow and finds weapon player may swap for.
+    fn update_item_highlights(&mut self, graph: &Graph) {
+        for item in self.items.iter_mut() {
+            item.set_highlighted(false);
+        }
+        self.swap_offer = None;
+
+        let player = match self.actors.try_get(self.player) {
+            Some(player) if !player.is_dead() => player,
+            _ => return,
+        };
+        let position = player.position(graph);
+        let current = player.current_weapon();
+        let current_kind = if self.weapons.contains(current) {
+            Some(self.weapons[current].get_kind())
+        } else {
+            None
+        };
+        // Mine layer can't be dropped, so it can't be swapped either.
+        let can_swap =
+            current_kind.map_or(false, |kind| ItemKind::from_weapon_kind(kind).is_some());
+
+        let mut nearby_items = Vec::new();
+        self.item_grid
+            .query_sphere(position, HIGHLIGHT_RADIUS, &mut nearby_items);
+        let mut closest_offer = None;
+        for item_handle in nearby_items {
+            let item = self.items.get(item_handle);
+            let distance = (item.position(graph) - position).norm();
+            if distance >= HIGHLIGHT_RADIUS || !item.is_available() {
+                continue;
+            }
+
+            if distance < PICKUP_RADIUS && can_swap {
+                if let Some(kind) = item.get_kind().weapon_kind() {
+                    if Some(kind) != current_kind
+                        && self.is_pickup_wasted(self.player, item.get_kind())
+                        && self.can_take_weapon(self.player, kind)
+                        && closest_offer.map_or(true, |(_, _, closest)| distance < closest)
+                    {
+                        closest_offer = Some((item_handle, kind, distance));
+                    }
+                }
+            }
+
+            self.items.get_mut(item_handle).set_highlighted(true);
+        }
+        self.swap_offer = closest_offer.map(|(item, kind, _)| (item, kind));
+    }
+
+    /// Creates projectile fired from given muzzle position. Shooter has moved for one more tick
+    /// by the time projectile is updated, so it is spawned ahead by shooter's velocity to not be
+    /// left behind the muzzle.
+    #[allow(clippy::too_many_arguments)]
+    async fn create_projectile(
+        &mut self,
+        engine: &mut Engine,
+        kind: ProjectileKind,
+        muzzle: Vector3<f32>,
+        direction: Vector3<f32>,
+        initial_velocity: Vector3<f32>,
+        owner: Handle<Weapon>,
+        shooter_name: Option<String>,
+        basis: Matrix3<f32>,
+        time: GameTime,
+    ) -> Handle<Projectile> {
+        let position = muzzle + initial_velocity.scale(time.delta);
+        // Projectiles of map entities like turrets have no weapon, only a name.
+        let shooter = match shooter_name {
+            Some(name) => Shooter {
+                name,
+                ..Default::default()
+            },
+            None => self.projectile_shooter(owner),
+        };
+        let scene = &mut engine.scenes[self.scene];
+        let projectile = match self.projectiles.try_reuse(
+            kind,
+            scene,
+            direction,
+            position,
+            muzzle,
+            owner,
+            shooter.clone(),
+            initial_velocity,
+            basis,
+        ) {
+            Some(projectile) => projectile,
+            None => {
+                let projectile = Projectile::new(
+                    kind,
+                    engine.resource_manager.clone(),
+                    scene,
+                    direction,
+                    position,
+                    muzzle,
+                    owner,
+                    shooter,
+                    initial_velocity,
+                    self.sender.clone(),
+                    basis,
+                    &mut self.rng,
+                )
+                .await;
+                self.projectiles.add(projectile)
+            }
+        };
+        self.apply_projectile_mutators(&mut engine.scenes[self.scene].graph, projectile);
+        projectile
+    }
+
+    /// Actor behind given weapon, projectile keeps it to credit damage after the actor is gone.
+    fn projectile_shooter(&self, weapon: Handle<Weapon>) -> Shooter {
+        if !self.weapons.contains(weapon) {
+            return Shooter::default();
+        }
+        let weapon = &self.weapons[weapon];
+        match self.actors.try_get(weapon.owner()) {
+            Some(actor) => Shooter {
+                actor: weapon.owner(),
+                name: actor.name.clone(),
+                weapon: Some(weapon.get_kind()),
+            },
+            None => Shooter::default(),
+        }
+    }
+
+    async fn shoot_weapon(
+        &mut self,
+        engine: &mut Engine,
+        weapon_handle: Handle<Weapon>,
+        initial_velocity: Vector3<f32>,
+        time: GameTime,
+        direction: Option<Vector3<f32>>,
+        shooter: ShooterState,
+    ) {
+        if self.weapons.contains(weapon_handle) {
+            let scene = &mut engine.scenes[self.scene];
             let weapon = &mut self.weapons[weapon_handle];
-            if weapon.try_shoot(scene, time) {
-                let kind = weapon.definition().projectile;
+            if weapon.try_shoot(scene, time, self.options.infinite_ammo()) {
+                if weapon.owner() == self.player {
+                    self.player_shots += 1;
+                }
+                let definition = weapon.definition();
+                let kind = definition.projectile;
                 let position = weapon.get_shot_position(&scene.graph);
                 let direction = direction
                     .unwrap_or_else(|| weapon.get_shot_direction(&scene.graph))
                     .try_normalize(std::f32::EPSILON)
                     .unwrap_or_else(|| Vector3::z());
+                let zoom = match self.actors.try_get(weapon.owner()) {
+                    Some(Actor::Player(player)) => player.zoom_factor(),
+                    _ => 0.0,
+                };
+                let spread = weapon::shot_spread(definition, zoom, &shooter);
+                let direction = weapon::apply_spread(direction, spread, &mut self.rng);
                 let basis = weapon.world_basis(&scene.graph);
                 self.create_projectile(
                     engine,
@@ -844,15 +2878,146 @@ impl Level {
                     direction,
                     initial_velocity,
                     weapon_handle,
+                    None,
                     basis,
+                    time,
                 )
                 .await;
             }
         }
     }
 
+    fn start_weapon_charge(&mut self, weapon: Handle<Weapon>, time: GameTime) {
+        if self.weapons.contains(weapon) {
+            self.weapons[weapon].start_charge(time, self.options.infinite_ammo());
+        }
+    }
+
+    /// Throws projectile of a charged weapon. The longer weapon was charged the further the
+    /// projectile flies and the sooner it goes off, projectile that was held until its fuse
+    /// burnt down goes off in hand. Dropped projectile falls at owner's feet, it happens when
+    /// owner switches weapon during charge.
+    async fn release_weapon_charge(
+        &mut self,
+        engine: &mut Engine,
+        weapon_handle: Handle<Weapon>,
+        initial_velocity: Vector3<f32>,
+        dropped: bool,
+        time: GameTime,
+    ) {
+        if !self.weapons.contains(weapon_handle) {
+            return;
+        }
+        let scene = &mut engine.scenes[self.scene];
+        let weapon = &mut self.weapons[weapon_handle];
+        let charge = match weapon.definition().charge.as_ref() {
+            Some(charge) => charge,
+            None => return,
+        };
+        let charge_time = match weapon.release_charge(scene, time, self.options.infinite_ammo()) {
+            Some(charge_time) => charge_time,
+            None => return,
+        };
+        if weapon.owner() == self.player {
+            self.player_shots += 1;
+        }
+        let fuse = (charge.fuse - charge_time).max(0.0);
+        let (position, strength) = match self.actors.try_get(weapon.owner()) {
+            Some(owner) if dropped => (owner.position(&scene.graph), 0.0),
+            _ if fuse <= 0.0 => (weapon.get_shot_position(&scene.graph), 0.0),
+            _ => (
+                weapon.get_shot_position(&scene.graph),
+                charge.strength(charge_time),
+            ),
+        };
+        let kind = weapon.definition().projectile;
+        let direction = weapon.get_shot_direction(&scene.graph);
+        let basis = weapon.world_basis(&scene.graph);
+        let initial_velocity = if dropped {
+            Vector3::default()
+        } else {
+            initial_velocity
+        };
+        let projectile = self
+            .create_projectile(
+                engine,
+                kind,
+                position,
+                direction,
+                initial_velocity,
+                weapon_handle,
+                None,
+                basis,
+                time,
+            )
+            .await;
+        self.projectiles.get_mut(projectile).throw(
+            &mut engine.scenes[self.scene].graph,
+            strength,
+            fuse,
+        );
+    }
+
+    /// Returns movement state of the owner of given weapon, it affects spread of shots.
+    fn shooter_state(&self, engine: &Engine, weapon: Handle<Weapon>) -> ShooterState {
+        if !self.weapons.contains(weapon) {
+            return ShooterState::default();
+        }
+        match self.actors.try_get(self.weapons[weapon].owner()) {
+            Some(actor) => {
+                let graph = &engine.scenes[self.scene].graph;
+                ShooterState {
+                    velocity: graph[actor.get_body()].as_rigid_body().lin_vel(),
+                    grounded: actor.has_ground_contact(graph),
+                    crouched: matches!(actor, Actor::Player(player) if player.is_crouching()),
+                }
+            }
+            None => ShooterState::default(),
+        }
+    }
+
     fn show_weapon(&mut self, engine: &mut Engine, weapon_handle: Handle<Weapon>, state: bool) {
-        self.weapons[weapon_handle].set_visibility(state, &mut engine.scenes[self.scene].graph)
+        // Request may come after a switch delay, weapon could be removed with its owner.
+        if !self.weapons.contains(weapon_handle) {
+            return;
+        }
+        let graph = &mut engine.scenes[self.scene].graph;
+        let weapon = &mut self.weapons[weapon_handle];
+        if state {
+            weapon.raise(graph);
+        } else {
+            weapon.set_visibility(false, graph);
+        }
+    }
+
+    fn switch_weapon(&mut self, engine: &mut Engine, from: Handle<Weapon>, to: Handle<Weapon>) {
+        if !self.weapons.contains(to) {
+            return;
+        }
+        if from == to {
+            // First weapon of an actor has nothing to put away.
+            self.show_weapon(engine, to, true);
+            return;
+        }
+
+        // Actor may switch again while previous weapon is still being put away, in this case
+        // the weapon that is being put away should bring out the new one instead.
+        let pending = self
+            .weapons
+            .pair_iter()
+            .find_map(|(handle, weapon)| match weapon.state() {
+                WeaponState::Lowering { next, .. } if next == from => Some(handle),
+                _ => None,
+            });
+
+        match pending {
+            Some(pending) if pending == to => {
+                self.weapons[to].raise(&mut engine.scenes[self.scene].graph)
+            }
+            Some(pending) => self.weapons[pending].lower(to),
+            None if self.weapons.contains(from) => self.weapons[from].lower(to),
+            None => self.show_weapon(engine, to, true),
+        }
     }
 
     async fn spawn_bot(
@@ -861,59 +3026,84 @@ impl Level {
         kind: BotKind,
         name: Option<String>,
     ) -> Handle<Actor> {
+        // Bots without a name get one on spawn, they join a team after that.
+        let team = match name.as_deref() {
+            Some(name) => self.spawn_team(name),
+            None => Team::None,
+        };
         let bot = spawn_bot(
             kind,
             name,
-            &self.spawn_points,
+            &mut self.spawn_points,
             &mut self.actors,
             &mut self.weapons,
             engine.resource_manager.clone(),
-            self.sender.clone().unwrap(),
+            self.sender.clone(),
             &mut self.leader_board,
             &mut engine.scenes[self.scene],
+            team,
+            &mut self.last_spawn_point,
+            &mut self.rng,
+            self.options.is_practice(),
         )
         .await;
 
-        self.sender
-            .as_ref()
-            .unwrap()
-            .send(Message::AddNotification {
+        let name = self.actors.get(bot).name.clone();
+        if !self.bot_names.contains(&name) {
+            self.bot_names.push(name);
+        }
+        self.assign_team(bot);
+
+        // Dummies respawn all the time, notifications about them would be just noise.
+        if !self.options.is_practice() {
+            self.sender.send(Message::AddNotification {
                 text: format!("Bot {} spawned!", self.actors.get(bot).name),
-            })
-            .unwrap();
+                severity: NotificationSeverity::Info,
+                icon: None,
+            });
+        }
 
         bot
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn damage_actor(
         &mut self,
         engine: &Engine,
         actor: Handle<Actor>,
         who: Handle<Actor>,
+        captured_who_name: Option<&str>,
         amount: f32,
+        weapon: Option<WeaponKind>,
         time: GameTime,
     ) {
-        if self.actors.contains(actor)
-            && (who.is_none() || who.is_some() && self.actors.contains(who))
-        {
-            let mut who_name = Default::default();
-            let message = if who.is_some() {
-                who_name = self.actors.get(who).name.clone();
-                format!(
+        let (who, who_name) =
+            damage_dealer(&self.actors, &self.leader_board, who, captured_who_name);
+        // Turrets are not on leader board, their hits are reported but never credited.
+        let turret_hit = who_name.is_none() && captured_who_name == Some(turret::TURRET_NAME);
+        let amount = match self.mutate_damage(actor, who, amount, weapon) {
+            Some(amount) => amount,
+            None => return,
+        };
+        if self.actors.contains(actor) {
+            let dealer_name = who_name
+                .as_deref()
+                .or_else(|| turret_hit.then(|| turret::TURRET_NAME));
+            let message = match dealer_name {
+                Some(who_name) => format!(
                     "{} dealt {} damage to {}!",
                     who_name,
                     amount,
                     self.actors.get(actor).name
-                )
-            } else {
-                format!("{} took {} damage!", self.actors.get(actor).name, amount)
+                ),
+                None => format!("{} took {} damage!", self.actors.get(actor).name, amount),
             };
 
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::AddNotification { text: message })
-                .unwrap();
+            self.sender.send(Message::AddNotification {
+                text: message,
+                severity: NotificationSeverity::Info,
+                icon: None,
+            });
 
             let who_position = if who.is_some() {
                 let scene = &engine.scenes[self.scene];
@@ -921,195 +3111,971 @@ impl Level {
             } else {
                 None
             };
+            let death_zone_kind = self.zone_contacts.get(&actor).map(|contact| contact.kind);
+            let victim = actor;
+            // Names are unique, so this also catches a respawned actor that is hit by its own
+            // rocket fired before death.
+            let is_self_damage = who_name.as_deref() == Some(self.actors.get(victim).name.as_str());
+            let victim_position = self
+                .actors
+                .get(victim)
+                .position(&engine.scenes[self.scene].graph);
             let actor = self.actors.get_mut(actor);
             if let Actor::Bot(bot) = actor {
-                if let Some(who_position) = who_position {
+                // Bot turns to the attacker only if it can hear the shot.
+                let hearing_radius = bot
+                    .definition()
+                    .perception(self.options.mutators())
+                    .hearing_radius;
+                let heard = who_position.filter(|position| {
+                    position.metric_distance(&victim_position) <= hearing_radius
+                });
+                if let Some(who_position) = heard {
                     bot.set_point_of_interest(who_position, time);
                 }
             }
             let was_dead = actor.is_dead();
-            actor.damage(amount);
-            if !was_dead && actor.is_dead() && who.is_some() {
-                self.leader_board.add_frag(who_name)
+            let absorbed = actor.damage(amount);
+            actor.last_damage_time = Some(time.elapsed);
+            if !was_dead {
+                self.sender.send(Message::ReportEvent {
+                    event: GameEvent::Damage {
+                        dealer: dealer_name.map(str::to_owned),
+                        victim: actor.name.clone(),
+                        amount,
+                        weapon,
+                    },
+                });
+                if actor.is_dead() {
+                    self.sender.send(Message::ReportEvent {
+                        event: GameEvent::Kill {
+                            killer: who_name.clone(),
+                            victim: actor.name.clone(),
+                            weapon,
+                        },
+                    });
+                }
+            }
+            if let (Some(weapon), Some(who_name)) = (weapon, who_name.as_ref()) {
+                if !is_self_damage && !was_dead {
+                    self.leader_board
+                        .add_weapon_damage(who_name, weapon, amount.max(0.0));
+                }
+            }
+            if who.is_some() && who == self.player && victim != who && !was_dead {
+                self.player_hits += 1;
+                let kind = if actor.is_dead() {
+                    DamageNumberKind::KillingBlow
+                } else if absorbed > 0.0 {
+                    DamageNumberKind::Armor
+                } else {
+                    DamageNumberKind::Normal
+                };
+                self.sender.send(Message::ShowDamageNumber {
+                    victim,
+                    position: victim_position + Vector3::new(0.0, DAMAGE_NUMBER_HEIGHT, 0.0),
+                    amount: amount.abs(),
+                    kind,
+                });
+            }
+            if !was_dead && actor.is_dead() {
+                if let Some(who_name) = who_name {
+                    let lead = self.leader_board.lead();
+                    let player_led = self.leader_board.is_leading(PLAYER_NAME);
+                    self.leader_board.add_frag(&who_name);
+                    if let Some(weapon) = weapon {
+                        self.leader_board.add_weapon_kill(&who_name, weapon);
+                    }
+                    self.announce_lead_change(engine, lead, player_led, &who_name);
+                } else if turret_hit {
+                    self.sender.send(Message::AddNotification {
+                        text: format!("{} was shot by a turret!", actor.name),
+                        severity: NotificationSeverity::Important,
+                        icon: None,
+                    });
+                } else if let Some(kind) = death_zone_kind {
+                    let text = match kind {
+                        DeathZoneKind::Lava => format!("{} fell into lava!", actor.name),
+                        _ => format!("{} died in a hazard zone!", actor.name),
+                    };
+                    self.sender.send(Message::AddNotification {
+                        text,
+                        severity: NotificationSeverity::Important,
+                        icon: None,
+                    });
+                }
             }
         }
     }
 
-    async fn spawn_item(
-        &mut self,
-        engine: &mut Engine,
-        kind: ItemKind,
-        position: Vector3<f32>,
-        adjust_height: bool,
-        lifetime: Option<f32>,
-    ) {
-        let position = if adjust_height {
-            self.pick(engine, position, position - Vector3::new(0.0, 1000.0, 0.0))
-        } else {
-            position
-        };
-        let scene = &mut engine.scenes[self.scene];
-        let mut item = Item::new(
-            kind,
-            position,
+    /// Tells everyone who leads after a frag, and tells local player if it has taken or
+    /// lost the lead. Only deathmatch has personal leaders.
+    fn announce_lead_change(
+        &self,
+        engine: &Engine,
+        previous_lead: Lead,
+        player_led: bool,
+        scorer_name: &str,
+    ) {
+        if !matches!(self.options, MatchOptions::DeathMatch(_)) {
+            return;
+        }
+
+        let lead = self.leader_board.lead();
+        if lead == previous_lead {
+            return;
+        }
+
+        let text = match &lead {
+            Lead::Nobody => return,
+            Lead::Single(name) => format!("{} took the lead", name),
+            Lead::Tied => format!("{} tied for the lead", scorer_name),
+        };
+        self.sender.send(Message::AddNotification {
+            text,
+            severity: NotificationSeverity::Important,
+            icon: None,
+        });
+
+        let (banner, sound) = if self.leader_board.is_leading(PLAYER_NAME) {
+            match lead {
+                Lead::Tied if player_led => ("Tied for the lead", LEAD_LOST_SOUND),
+                Lead::Tied => ("Tied for the lead", LEAD_TAKEN_SOUND),
+                _ => ("You have taken the lead", LEAD_TAKEN_SOUND),
+            }
+        } else if player_led {
+            ("You have lost the lead", LEAD_LOST_SOUND)
+        } else {
+            return;
+        };
+        self.sender.send(Message::ShowBanner {
+            text: banner.to_owned(),
+        });
+        self.play_player_sound(engine, sound, 1.0);
+    }
+
+    async fn spawn_item(
+        &mut self,
+        engine: &mut Engine,
+        kind: ItemKind,
+        position: Vector3<f32>,
+        adjust_height: bool,
+        lifetime: Option<f32>,
+    ) -> Handle<Item> {
+        let position = if adjust_height {
+            self.pick(engine, position, position - Vector3::new(0.0, 1000.0, 0.0))
+        } else {
+            position
+        };
+        let scene = &mut engine.scenes[self.scene];
+        let mut item = Item::new(
+            kind,
+            position,
+            scene,
+            engine.resource_manager.clone(),
+            self.sender.clone(),
+        )
+        .await;
+        item.set_lifetime(lifetime);
+        self.items.add(item)
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Brings weapons in line with reloaded definitions, see
+    /// [`crate::definitions::DefinitionsWatcher`]. Everything else reads definitions on use.
+    pub fn apply_definitions(&mut self) {
+        for weapon in self.weapons.iter_mut() {
+            weapon.clamp_ammo();
+        }
+    }
+
+    /// Fraction of player's shots that damaged someone, zero if player has not shot at all.
+    pub fn player_accuracy(&self) -> f32 {
+        if self.player_shots > 0 {
+            (self.player_hits as f32 / self.player_shots as f32).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_mirror(&self) -> bool {
+        self.mirror
+    }
+
+    pub fn map_issues(&self) -> &[MapIssue] {
+        &self.map_issues
+    }
+
+    pub fn heatmap(&self) -> Option<&Heatmap> {
+        self.heatmap.as_ref()
+    }
+
+    fn set_heatmap_recording(&mut self, enabled: bool) {
+        if self.heatmap.is_none() && enabled {
+            match self.world_bounds.as_ref() {
+                Some(world_bounds) => self.heatmap = Some(Heatmap::new(world_bounds)),
+                None => {
+                    self.sender.send(Message::AddNotification {
+                        text: "Map has no geometry, heatmap can't be recorded".to_owned(),
+                        severity: NotificationSeverity::Info,
+                        icon: None,
+                    });
+                    return;
+                }
+            }
+        }
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.set_recording(enabled);
+        }
+    }
+
+    fn update_heatmap(&mut self, graph: &Graph, delta: f32) {
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            let positions = self
+                .actors
+                .iter()
+                .filter(|actor| !actor.is_dead())
+                .map(|actor| actor.position(graph));
+            heatmap.update(positions, delta);
+        }
+    }
+
+    pub fn status(&self) -> LevelStatus {
+        let phase = if self.leader_board.is_match_over(&self.options) {
+            MatchPhase::Over
+        } else {
+            MatchPhase::InProgress
+        };
+
+        let time_limit = self.options.time_limit_secs();
+        let time_remaining = if time_limit > 0.0 {
+            Some((time_limit - self.time).max(0.0))
+        } else {
+            None
+        };
+
+        let player = match self.actors.try_get(self.player) {
+            Some(Actor::Player(player)) => {
+                let current_weapon = player.current_weapon();
+                Some(PlayerStatus {
+                    health: player.get_health(),
+                    max_health: BASE_HEALTH,
+                    armor: player.get_armor(),
+                    max_armor: player.armor_tier().cap(),
+                    armor_tier: player.armor_tier(),
+                    ammo: if self.weapons.contains(current_weapon) {
+                        Some(self.weapons[current_weapon].ammo())
+                    } else {
+                        None
+                    },
+                    weapons: player
+                        .weapons()
+                        .iter()
+                        .map(|&handle| {
+                            let weapon = &self.weapons[handle];
+                            (weapon.get_kind(), weapon.ammo(), handle == current_weapon)
+                        })
+                        .collect(),
+                    charge: if self.weapons.contains(current_weapon) {
+                        self.weapons[current_weapon].charge_progress()
+                    } else {
+                        None
+                    },
+                    is_burning: self.is_player_burning(),
+                    is_zoomed: player.is_zoomed(),
+                    flashlight_battery: player.flashlight_battery(),
+                    interaction_prompt: self.swap_offer.map(|(_, kind)| {
+                        let key = self.control_scheme.as_ref().map_or("Use", |scheme| {
+                            scheme.read().unwrap().use_item.button.name()
+                        });
+                        format!("Press {} to swap for {}", key, kind.display_name())
+                    }),
+                    aim_target: self
+                        .actors
+                        .try_get(self.aim_target)
+                        .map(|target| AimTarget {
+                            is_teammate: self.options.is_team_mode()
+                                && target.team() == player.team(),
+                            name: target.name.clone(),
+                            health: target.get_health(),
+                        }),
+                })
+            }
+            _ => None,
+        };
+
+        let respawn_time_left = self.respawn_list.iter().find_map(|entry| match entry {
+            RespawnEntry::Player(entry) => Some(entry.time_left.max(0.0)),
+            _ => None,
+        });
+
+        let (leader, runner_up) = self.leader_board.top_two();
+
+        let team_scores = if self.options.is_team_mode() {
+            Some((
+                self.leader_board.team_score(Team::Red),
+                self.leader_board.team_score(Team::Blue),
+            ))
+        } else {
+            None
+        };
+
+        LevelStatus {
+            phase,
+            time: self.time,
+            time_remaining,
+            player,
+            respawn_time_left,
+            is_afk: self.afk,
+            leader: leader.map(|(name, score)| (name.to_owned(), score)),
+            runner_up_score: runner_up.map(|(_, score)| score),
+            team_scores,
+        }
+    }
+
+    fn answer_chat(&mut self) {
+        if self.rng.gen::<f32>() > BOT_CHAT_REPLY_CHANCE {
+            return;
+        }
+        let bots = self
+            .actors
+            .iter()
+            .filter(|actor| matches!(actor, Actor::Bot(_)))
+            .map(|bot| bot.name.clone())
+            .collect::<Vec<_>>();
+        if bots.is_empty() {
+            return;
+        }
+        let sender_name = bots[self.rng.gen_range(0..bots.len())].clone();
+        let text = BOT_CHAT_REPLIES[self.rng.gen_range(0..BOT_CHAT_REPLIES.len())].to_owned();
+        self.sender.send(Message::ChatMessage { sender_name, text });
+    }
+
+    fn toggle_god_mode(&mut self, actor: Handle<Actor>) {
+        if self.actors.contains(actor) {
+            let actor = self.actors.get_mut(actor);
+            actor.god_mode = !actor.god_mode;
+            let text = format!(
+                "God mode {} for {}",
+                if actor.god_mode {
+                    "enabled"
+                } else {
+                    "disabled"
+                },
+                actor.name
+            );
+            self.sender.send(Message::AddNotification {
+                text,
+                severity: NotificationSeverity::Info,
+                icon: None,
+            });
+        }
+    }
+
+    fn toggle_noclip(&mut self, engine: &mut Engine, actor: Handle<Actor>) {
+        if self.actors.contains(actor) {
+            if let Actor::Player(player) = self.actors.get_mut(actor) {
+                let noclip = !player.is_noclip();
+                player.set_noclip(&mut engine.scenes[self.scene].graph, noclip);
+            }
+        }
+    }
+
+    fn set_camera_enabled(graph: &mut Graph, camera: Handle<Node>, enabled: bool) {
+        if let Some(camera) = graph
+            .try_get_mut(camera)
+            .and_then(|n| n.cast_mut::<Camera>())
+        {
+            camera.set_enabled(enabled);
+        }
+    }
+
+    fn player_camera(&self) -> Handle<Node> {
+        if let Some(Actor::Player(player)) = self.actors.try_get(self.player) {
+            player.camera()
+        } else {
+            Handle::NONE
+        }
+    }
+
+    fn toggle_free_camera(&mut self, engine: &mut Engine) {
+        let player_camera = self.player_camera();
+        let graph = &mut engine.scenes[self.scene].graph;
+
+        if let Some(mut free_camera) = self.free_camera.take() {
+            free_camera.clean_up(graph);
+
+            // Restore camera that was active before.
+            if player_camera.is_some() {
+                Self::set_camera_enabled(graph, player_camera, true);
+            } else {
+                Self::set_camera_enabled(graph, self.spectator_camera, true);
+            }
+        } else {
+            let (position, yaw, pitch) =
+                if let Some(Actor::Player(player)) = self.actors.try_get_mut(self.player) {
+                    player.stop();
+                    (
+                        graph[player.camera()].global_position(),
+                        player.yaw(),
+                        player.pitch(),
+                    )
+                } else {
+                    (graph[self.spectator_camera].global_position(), 0.0, 0.0)
+                };
+
+            self.free_camera = Some(FreeCamera::new(graph, position, yaw, pitch));
+        }
+    }
+
+    /// Height above given point at which intermission camera does not touch the ceiling.
+    fn intermission_height(graph: &Graph, point: Vector3<f32>) -> f32 {
+        let options = RayCastOptions {
+            ray_origin: Point3::from(point),
+            ray_direction: Vector3::y(),
+            max_len: INTERMISSION_HEIGHT * 2.0,
+            groups: InteractionGroups::default(),
+            sort_results: true,
+        };
+        QueryBufferPool::with(|query_buffer| {
+            graph.physics.cast_ray(options, query_buffer);
+            query_buffer.first().map_or(INTERMISSION_HEIGHT, |hit| {
+                // Keep the camera half way between the point and the ceiling.
+                INTERMISSION_HEIGHT.min(hit.toi * 0.5)
+            })
+        })
+    }
+
+    /// Freezes the match and starts camera flythrough behind final results. Spawn points
+    /// are used as waypoints, the highest ones are preferred: they give better view and the
+    /// path between them is less likely to go through walls.
+    pub fn begin_intermission(&mut self, engine: &mut Engine) {
+        if self.intermission.is_some() {
+            return;
+        }
+
+        if let Some(Actor::Player(player)) = self.actors.try_get_mut(self.player) {
+            player.stop();
+        }
+
+        let graph = &mut engine.scenes[self.scene].graph;
+        if let Some(mut free_camera) = self.free_camera.take() {
+            free_camera.clean_up(graph);
+        }
+
+        let mut points = self
+            .spawn_points
+            .iter()
+            .map(|pt| pt.position)
+            .collect::<Vec<_>>();
+        let target = if points.is_empty() {
+            graph[self.spectator_camera].global_position()
+        } else {
+            points
+                .iter()
+                .fold(Vector3::default(), |sum, pt| sum + pt)
+                .scale(1.0 / points.len() as f32)
+        };
+        points.sort_by(|a, b| b.y.partial_cmp(&a.y).unwrap_or(Ordering::Equal));
+        points.truncate(INTERMISSION_WAYPOINTS);
+        // Going around the center in one direction keeps the path from crossing itself.
+        let angle = |pt: &Vector3<f32>| (pt.z - target.z).atan2(pt.x - target.x);
+        points.sort_by(|a, b| angle(a).partial_cmp(&angle(b)).unwrap_or(Ordering::Equal));
+        let waypoints = points
+            .into_iter()
+            .map(|pt| pt + Vector3::new(0.0, Self::intermission_height(graph, pt), 0.0))
+            .collect();
+
+        self.intermission = Some(IntermissionCamera::new(graph, waypoints, target));
+    }
+
+    /// Plays a quiet feedback sound at the position of the player, does nothing if the player
+    /// is dead.
+    pub fn play_player_sound(&self, engine: &Engine, path: &str, gain: f32) {
+        if let Some(player) = self.actors.try_get(self.player) {
+            self.sender.send(Message::PlaySound {
+                path: PathBuf::from(path),
+                position: player.position(&engine.scenes[self.scene].graph),
+                gain,
+                rolloff_factor: 1.0,
+                radius: 10.0,
+            });
+        }
+    }
+
+    /// Plays a sound over final results. Intermission camera keeps moving, so the sound has
+    /// radius large enough to be heard at full volume anywhere on the map.
+    pub fn play_intermission_sound(&self, engine: &Engine, path: &str) {
+        if let Some(intermission) = self.intermission.as_ref() {
+            let graph = &engine.scenes[self.scene].graph;
+            self.sender.send(Message::PlaySound {
+                path: PathBuf::from(path),
+                position: graph[intermission.camera()].global_position(),
+                gain: 1.0,
+                rolloff_factor: 1.0,
+                radius: 1000.0,
+            });
+        }
+    }
+
+    pub fn is_intermission(&self) -> bool {
+        self.intermission.is_some()
+    }
+
+    fn update_respawn(&mut self, time: GameTime) {
+        // Respawn is done in deferred manner: we just gather all info needed
+        // for respawn, wait some time and then re-create actor. Actor is spawned
+        // by sending a message: this is needed because there are some other
+        // systems that catches such messages and updates their own state.
+        for spawn_point in self.spawn_points.iter_mut() {
+            spawn_point.cooldown = (spawn_point.cooldown - time.delta).max(0.0);
+        }
+
+        for respawn_entry in self.respawn_list.iter_mut() {
+            match respawn_entry {
+                RespawnEntry::Bot(v) => {
+                    v.time_left -= time.delta;
+                    if v.time_left <= 0.0 {
+                        self.sender.send(Message::SpawnBot {
+                            kind: v.kind,
+                            name: v.name.clone(),
+                        });
+                    }
+                }
+                RespawnEntry::Player(v) => {
+                    v.time_left -= time.delta;
+                    if v.time_left <= 0.0 {
+                        self.sender.send(Message::SpawnPlayer);
+                    }
+                }
+                RespawnEntry::Guest(v) => {
+                    v.time_left -= time.delta;
+                    if v.time_left <= 0.0 {
+                        self.sender.send(Message::SpawnGuest);
+                    }
+                }
+            }
+        }
+
+        self.respawn_list.retain(|entry| match entry {
+            RespawnEntry::Bot(v) => v.time_left >= 0.0,
+            RespawnEntry::Player(v) | RespawnEntry::Guest(v) => v.time_left >= 0.0,
+        });
+    }
+
+    /// Moves spectator camera down to the floor like a falling head. Floor is looked up every
+    /// frame from current position of the camera, so moving platforms carry it and camera
+    /// that died over a pit just stays where it is.
+    fn update_spectator_camera(&mut self, scene: &mut Scene, dt: f32) {
+        let position = match scene.graph.try_get(self.spectator_camera) {
+            Some(camera) if camera.cast::<Camera>().map_or(false, |c| c.is_enabled()) => {
+                camera.global_position()
+            }
+            _ => return,
+        };
+
+        // Ray starts a bit above the camera to notice floor that rises into it.
+        let options = RayCastOptions {
+            ray_origin: Point3::from(position + Vector3::new(0.0, SPECTATOR_FLOOR_OFFSET, 0.0)),
+            ray_direction: -Vector3::y(),
+            max_len: SPECTATOR_PROBE_DISTANCE + SPECTATOR_FLOOR_OFFSET,
+            groups: InteractionGroups::default(),
+            sort_results: true,
+        };
+        self.target_spectator_position = QueryBufferPool::with(|query_buffer| {
+            scene.graph.physics.cast_ray(options, query_buffer);
+            match query_buffer.first() {
+                Some(hit) => hit.position.coords + Vector3::new(0.0, SPECTATOR_FLOOR_OFFSET, 0.0),
+                None => position,
+            }
+        });
+
+        let mut new_position = position;
+        if self.target_spectator_position.y > position.y {
+            new_position.y = self.target_spectator_position.y;
+        } else {
+            let descent = ((position.y - self.target_spectator_position.y) * 0.1)
+                .min(SPECTATOR_MAX_FALL_SPEED * dt);
+            new_position.y -= descent;
+        }
+
+        let fall = &mut self.spectator_fall;
+        fall.time += dt;
+        let t = (fall.time / SPECTATOR_PITCH_TIME).min(1.0);
+        // Smooth step, so tilt starts and ends gently.
+        let t = t * t * (3.0 - 2.0 * t);
+        let pitch = fall.pitch + (SPECTATOR_PITCH - fall.pitch) * t;
+
+        let transform = scene.graph[self.spectator_camera].local_transform_mut();
+        transform.set_position(new_position);
+        transform.set_rotation(
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), fall.yaw.to_radians())
+                * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), pitch.to_radians()),
+        );
+    }
+
+    // Every mutator is interpreted by functions below (and by `analyze` for pickups), new
+    // mutators should be added here too.
+
+    /// Rule changes that are applied once to a new scene. Jump pads are weakened by `analyze`
+    /// for low gravity, see [`jump_pad_gravity_factor`].
+    fn apply_mutators(scene: &mut Scene, mutators: &Mutators) {
+        if mutators.low_gravity {
+            scene.graph.physics.gravity *= LOW_GRAVITY_SCALE;
+        }
+    }
+
+    /// Rule changes of a new or reused projectile. Grenades and mines keep their usual arcs in
+    /// low gravity, otherwise they would fly far beyond where they are aimed.
+    fn apply_projectile_mutators(&self, graph: &mut Graph, projectile: Handle<Projectile>) {
+        let gravity_scale = if self.options.mutators().low_gravity {
+            1.0 / LOW_GRAVITY_SCALE
+        } else {
+            1.0
+        };
+        self.projectiles
+            .get(projectile)
+            .set_gravity_scale(graph, gravity_scale);
+    }
+
+    /// Rule changes that are applied continuously during the match. Regeneration never heals
+    /// above spawn health, only pickups can.
+    fn update_mutators(&mut self, delta: f32) {
+        if self.options.mutators().regen {
+            for actor in self.actors.iter_mut().filter(|actor| !actor.is_dead()) {
+                actor.heal_up_to(REGEN_HEALTH_PER_SECOND * delta, BASE_HEALTH);
+            }
+        }
+    }
+
+    /// Damage that is dealt to `victim` with rule changes, `None` if the hit does nothing.
+    fn mutate_damage(
+        &self,
+        victim: Handle<Actor>,
+        who: Handle<Actor>,
+        amount: f32,
+        weapon: Option<WeaponKind>,
+    ) -> Option<f32> {
+        let mutators = self.options.mutators();
+        let victim_actor = match self.actors.try_get(victim) {
+            Some(victim_actor) => victim_actor,
+            None => return Some(amount),
+        };
+        let is_teammate = self.actors.try_get(who).map_or(false, |dealer| {
+            who != victim && self.options.is_team_mode() && dealer.team() == victim_actor.team()
+        });
+        if is_teammate && !mutators.friendly_fire {
+            None
+        } else if mutators.instagib && weapon.is_some() && who != victim {
+            // Just enough to kill through any armor, so damage stats are not inflated.
+            Some(amount.max(victim_actor.health + victim_actor.armor.max(0.0) + 1.0))
+        } else {
+            Some(amount)
+        }
+    }
+
+    fn update_turrets(&mut self, scene: &mut Scene, delta: f32) {
+        let targets = self
+            .actors
+            .pair_iter()
+            .filter(|(_, actor)| !actor.is_dead())
+            .map(|(handle, actor)| TurretTarget {
+                handle,
+                position: actor.aim_position(&scene.graph),
+                team: actor.team(),
+            })
+            .collect::<Vec<_>>();
+        self.turrets.update(
             scene,
-            engine.resource_manager.clone(),
-            self.sender.as_ref().unwrap().clone(),
-        )
-        .await;
-        item.set_lifetime(lifetime);
-        self.items.add(item);
+            &targets,
+            self.options.is_team_mode(),
+            delta,
+            &self.sender,
+        );
     }
 
-    pub fn time(&self) -> f32 {
-        self.time
-    }
+    fn update_death_zones(&mut self, scene: &Scene, delta: f32) {
+        let death_zones = &self.death_zones;
+        let grid = self.death_zone_grid.get_or_insert_with(|| {
+            let mut grid = SpatialGrid::new(GRID_CELL_SIZE);
+            for (i, death_zone) in death_zones.iter().enumerate() {
+                grid.insert(i, death_zone.bounds);
+            }
+            grid
+        });
 
-    fn update_respawn(&mut self, time: GameTime) {
-        // Respawn is done in deferred manner: we just gather all info needed
-        // for respawn, wait some time and then re-create actor. Actor is spawned
-        // by sending a message: this is needed because there are some other
-        // systems that catches such messages and updates their own state.
-        for respawn_entry in self.respawn_list.iter_mut() {
-            match respawn_entry {
-                RespawnEntry::Bot(v) => {
-                    v.time_left -= time.delta;
-                    if v.time_left <= 0.0 {
-                        self.sender
-                            .as_mut()
-                            .unwrap()
-                            .send(Message::SpawnBot {
-                                kind: v.kind,
-                                name: v.name.clone(),
-                            })
-                            .unwrap();
+        let mut nearby = Vec::new();
+        let mut contacts = HashMap::new();
+        for (handle, actor) in self.actors.pair_iter() {
+            let position = actor.position(&scene.graph);
+            // Falling out of the world is the same as falling into a kill zone.
+            if let Some(world_bounds) = self.world_bounds.as_ref() {
+                if !world_bounds.is_contains_point(position) {
+                    self.sender.send(Message::RespawnActor { actor: handle });
+                    continue;
+                }
+            }
+            nearby.clear();
+            grid.query_aabb(
+                &AxisAlignedBoundingBox::from_min_max(position, position),
+                &mut nearby,
+            );
+            // Overlapping damaging zones do not stack, the most dangerous one is used.
+            let mut hurting: Option<&DeathZone> = None;
+            for &i in nearby.iter() {
+                let death_zone = &death_zones[i];
+                if !death_zone.bounds.is_contains_point(position) {
+                    continue;
+                }
+                match death_zone.kind {
+                    DeathZoneKind::Kill => {
+                        self.sender.send(Message::RespawnActor { actor: handle });
+                    }
+                    DeathZoneKind::Lava | DeathZoneKind::Hurt => {
+                        if hurting.map_or(true, |current| {
+                            death_zone.damage_per_second > current.damage_per_second
+                        }) {
+                            hurting = Some(death_zone);
+                        }
                     }
                 }
-                RespawnEntry::Player(v) => {
-                    v.time_left -= time.delta;
-                    if v.time_left <= 0.0 {
-                        self.sender
-                            .as_mut()
-                            .unwrap()
-                            .send(Message::SpawnPlayer)
-                            .unwrap();
+            }
+
+            if let Some(death_zone) = hurting {
+                let mut next_tick = self
+                    .zone_contacts
+                    .get(&handle)
+                    .map_or(0.0, |contact| contact.next_tick - delta);
+                if next_tick <= 0.0 {
+                    next_tick += DEATH_ZONE_DAMAGE_INTERVAL;
+                    self.sender.send(Message::DamageActor {
+                        actor: handle,
+                        who: Handle::NONE,
+                        who_name: None,
+                        amount: death_zone.damage_per_second * DEATH_ZONE_DAMAGE_INTERVAL,
+                        weapon: None,
+                    });
+                    if let Actor::Player(_) = actor {
+                        self.sender.send(Message::PlaySound {
+                            path: PathBuf::from(LAVA_SOUND),
+                            position,
+                            gain: 0.6,
+                            rolloff_factor: 3.0,
+                            radius: 2.0,
+                        });
                     }
                 }
+                contacts.insert(
+                    handle,
+                    ZoneContact {
+                        kind: death_zone.kind,
+                        next_tick,
+                    },
+                );
             }
         }
-
-        self.respawn_list.retain(|entry| match entry {
-            RespawnEntry::Bot(v) => v.time_left >= 0.0,
-            RespawnEntry::Player(v) => v.time_left >= 0.0,
-        });
+        self.zone_contacts = contacts;
     }
 
-    fn update_spectator_camera(&mut self, scene: &mut Scene) {
-        if let Some(spectator_camera) = scene.graph[self.spectator_camera].cast_mut::<Camera>() {
-            let mut position = spectator_camera.global_position();
-            position.follow(&self.target_spectator_position, 0.1);
-            spectator_camera
-                .local_transform_mut()
-                .set_position(position);
-        }
+    /// Returns true if player is standing in lava, HUD shows burning effect in this case.
+    pub fn is_player_burning(&self) -> bool {
+        self.zone_contacts
+            .get(&self.player)
+            .map_or(false, |contact| contact.kind == DeathZoneKind::Lava)
     }
 
-    fn update_death_zones(&mut self, scene: &Scene) {
-        for (handle, actor) in self.actors.pair_iter_mut() {
-            for death_zone in self.death_zones.iter() {
-                if death_zone
-                    .bounds
-                    .is_contains_point(actor.position(&scene.graph))
-                {
-                    self.sender
-                        .as_ref()
-                        .unwrap()
-                        .send(Message::RespawnActor { actor: handle })
-                        .unwrap();
-                }
-            }
+    fn update_item_grid(&mut self, graph: &Graph) {
+        self.item_grid.clear();
+        for (handle, item) in self.items.pair_iter() {
+            self.item_grid.insert_point(handle, item.position(graph));
         }
     }
 
     fn update_game_ending(&self) {
         if self.leader_board.is_match_over(&self.options) {
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::EndMatch)
-                .unwrap();
+            self.sender.send(Message::EndMatch);
         }
     }
 
-    pub fn update(&mut self, engine: &mut Engine, time: GameTime) {
+    pub fn update(&mut self, engine: &mut Engine, time: GameTime, profiler: &mut Profiler) {
+        let mut clock = Instant::now();
+
+        if let Some(intermission) = self.intermission.as_mut() {
+            let graph = &mut engine.scenes[self.scene].graph;
+            intermission.update(graph, time.delta);
+            // Player may be respawned right before the end, only one camera must be enabled.
+            let player_camera = self.player_camera();
+            Self::set_camera_enabled(graph, player_camera, false);
+            Self::set_camera_enabled(graph, self.spectator_camera, false);
+            self.capture_render_state(&engine.scenes[self.scene].graph);
+            profiler.lap("level", &mut clock);
+            return;
+        }
+
         self.time += time.delta;
-        self.update_respawn(time);
+        if !self.mirror {
+            self.update_respawn(time);
+        }
         let scene = &mut engine.scenes[self.scene];
-        self.update_spectator_camera(scene);
-        self.update_death_zones(scene);
-        self.weapons.update(scene, &self.actors);
+        self.update_spectator_camera(scene, time.delta);
+        if let Some(free_camera) = self.free_camera.as_mut() {
+            free_camera.update(&mut scene.graph, time.delta);
+            // Player may respawn while free camera is active, only one camera must be enabled.
+            let player_camera = self.player_camera();
+            Self::set_camera_enabled(&mut scene.graph, player_camera, false);
+            Self::set_camera_enabled(&mut scene.graph, self.spectator_camera, false);
+        }
+        if !self.mirror {
+            self.update_death_zones(scene, time.delta);
+            self.update_mutators(time.delta);
+            self.update_turrets(scene, time.delta);
+        }
+        profiler.lap("level", &mut clock);
+        self.weapons.update(scene, &self.actors, time.delta);
+        self.update_aim_target();
+        profiler.lap("weapons", &mut clock);
         self.projectiles
-            .update(scene, &self.actors, &self.weapons, time);
-        self.items.update(scene, time);
+            .update(scene, &self.actors, self.world_bounds.as_ref(), time);
+        profiler.lap("projectiles", &mut clock);
+        let magnets = self.item_magnets(&scene.graph);
+        self.items
+            .update(scene, &magnets, self.world_bounds.as_ref(), time);
+        self.update_item_grid(&scene.graph);
+        self.update_item_highlights(&scene.graph);
+        profiler.lap("items", &mut clock);
+        let view_distance_scale = environment::view_distance_scale(scene.ambient_lighting_color);
         let mut ctx = UpdateContext {
             time,
             scene,
             items: &self.items,
+            item_grid: &self.item_grid,
             jump_pads: &self.jump_pads,
             weapons: &self.weapons,
+            options: &self.options,
+            view_distance_scale,
+        };
+        // Bots of a mirror are puppets of host, they are moved by snapshots.
+        let ai_time = if self.mirror {
+            self.actors.update_mirror(&mut ctx);
+            Duration::default()
+        } else {
+            self.actors.update(&mut ctx)
         };
-        self.actors.update(&mut ctx);
+        profiler.lap("actors", &mut clock);
+        profiler.add_sample("bot ai", ai_time);
+        self.update_heatmap(&engine.scenes[self.scene].graph, time.delta);
+
+        if let Some(Actor::Player(player)) = self.actors.try_get_mut(self.player) {
+            if player.take_laser_toggle_request() && self.weapons.contains(player.current_weapon())
+            {
+                let weapon = &mut self.weapons[player.current_weapon()];
+                weapon.set_laser_enabled(!weapon.is_laser_enabled());
+            }
+            if player.take_use_request() {
+                if let Some((item, _)) = self.swap_offer {
+                    self.sender.send(Message::SwapWeapon {
+                        actor: self.player,
+                        item,
+                    });
+                }
+            }
+        }
+
+        // Host decides when match is over.
+        if !self.mirror {
+            self.update_game_ending();
+        }
+
+        self.capture_render_state(&engine.scenes[self.scene].graph);
+    }
+
+    /// Remembers poses of every moving node for render interpolation. Child nodes that are
+    /// moved relative to their parent (weapon sway, recoil, item bobbing) are captured too,
+    /// otherwise they would still snap at simulation rate.
+    fn capture_render_state(&mut self, graph: &Graph) {
+        let mut nodes = vec![self.spectator_camera];
+        if let Some(intermission) = self.intermission.as_ref() {
+            nodes.push(intermission.camera());
+        }
+        for actor in self.actors.iter() {
+            nodes.push(actor.get_body());
+            nodes.push(actor.weapon_pivot());
+            if let Actor::Player(player) = actor {
+                nodes.push(player.camera_pivot());
+                nodes.push(player.camera());
+            }
+        }
+        nodes.extend(self.weapons.iter().map(|w| w.get_model()));
+        nodes.extend(self.projectiles.iter().map(|p| p.root()));
+        for item in self.items.iter() {
+            nodes.push(item.get_pivot());
+            nodes.push(item.get_model());
+        }
+        self.interpolator.capture(graph, nodes.into_iter());
+    }
+
+    /// Moves level to a pose between two last simulation steps, must be followed by
+    /// [`Self::end_render`] when frame is rendered.
+    pub fn begin_render(&self, engine: &mut Engine, alpha: f32) {
+        self.interpolator
+            .apply(&mut engine.scenes[self.scene].graph, alpha);
+    }
+
+    pub fn end_render(&self, engine: &mut Engine) {
+        self.interpolator
+            .restore(&mut engine.scenes[self.scene].graph);
+    }
+
+    /// Free camera keeps flying while the match is paused, so frozen bots and projectiles
+    /// can be looked at from any side. Uses real time step, game time does not advance.
+    pub fn update_paused(&mut self, engine: &mut Engine, step: f32) {
+        if let Some(free_camera) = self.free_camera.as_mut() {
+            free_camera.update(&mut engine.scenes[self.scene].graph, step);
+        }
+    }
 
-        self.update_game_ending();
+    /// Makes physics and sounds of the level follow game clock: physics steps are scaled
+    /// together with game time and both stop while the game is paused. Sounds keep their
+    /// pitch in slow motion. Sounds are also paused while game window is in background.
+    pub fn sync_time_scale(&self, engine: &mut Engine, time: &GameTime, focused: bool) {
+        let graph = &mut engine.scenes[self.scene].graph;
+        // Zero time step is not valid for physics, so zero time scale stops it like pause.
+        graph.physics.enabled = !time.is_paused() && time.time_scale() > 0.0;
+        graph.physics.integration_parameters.dt = time.step * time.time_scale();
+        graph.sound_context.pause(time.is_paused() || !focused);
     }
 
     pub async fn respawn_actor(&mut self, engine: &mut Engine, actor: Handle<Actor>) {
         if self.actors.contains(actor) {
             let name = self.actors.get(actor).name.clone();
-
             self.leader_board.add_death(&name);
+            self.schedule_respawn(engine, actor).await;
+        }
+    }
+
+    /// Removes actor and creates respawn entry that will bring it back after a while.
+    async fn schedule_respawn(&mut self, engine: &mut Engine, actor: Handle<Actor>) {
+        if self.actors.contains(actor) {
+            let name = self.actors.get(actor).name.clone();
 
             let entry = match self.actors.get(actor) {
+                _ if actor == self.guest => {
+                    self.guest = Handle::NONE;
+                    RespawnEntry::Guest(PlayerRespawnEntry {
+                        time_left: RESPAWN_TIME,
+                    })
+                }
+                // Target dummies are back right away.
                 Actor::Bot(bot) => RespawnEntry::Bot(BotRespawnEntry {
                     name,
                     kind: bot.definition().kind,
-                    time_left: RESPAWN_TIME,
+                    time_left: if bot.is_inert() { 0.0 } else { RESPAWN_TIME },
                 }),
-                Actor::Player(player) => {
-                    // Turn on spectator camera and prepare its target position. Spectator
-                    // camera will be used to render world until player is despawned.
-                    let scene = &mut engine.scenes[self.scene];
-                    let position = scene.graph[player.camera()].global_position();
-                    if let Some(spectator_camera) =
-                        scene.graph[self.spectator_camera].cast_mut::<Camera>()
-                    {
-                        spectator_camera.set_enabled(true);
-                        spectator_camera
-                            .local_transform_mut()
-                            .set_position(position);
-                    }
-                    // Use ray casting to get target position for spectator camera, it is used to
-                    // create "dropping head" effect.
-                    let ray =
-                        Ray::from_two_points(position, position - Vector3::new(0.0, 1000.0, 0.0));
-                    let options = RayCastOptions {
-                        ray_origin: Point3::from(ray.origin),
-                        ray_direction: ray.dir,
-                        max_len: f32::MAX,
-                        groups: InteractionGroups::default(),
-                        sort_results: true,
-                    };
-
-                    let mut query_buffer = Vec::default();
-                    scene.graph.physics.cast_ray(options, &mut query_buffer);
-                    if let Some(hit) = query_buffer.first() {
-                        self.target_spectator_position = hit.position.coords;
-                        // Prevent see-thru-floor
-                        self.target_spectator_position.y += 0.1;
-                    } else {
-                        self.target_spectator_position = position;
-                    }
-
+                Actor::Player(_) => {
+                    self.start_spectating(engine);
                     RespawnEntry::Player(PlayerRespawnEntry {
                         time_left: RESPAWN_TIME,
                     })
@@ -1122,14 +4088,78 @@ impl Level {
         }
     }
 
+    /// Turns on spectator camera at the eyes of the player, it creates "dropping head" effect
+    /// until player respawns, see `update_spectator_camera`. Weapons are removed together with
+    /// the player, so there is no gun floating in front of the camera.
+    fn start_spectating(&mut self, engine: &mut Engine) {
+        if let Some(Actor::Player(player)) = self.actors.try_get(self.player) {
+            let scene = &mut engine.scenes[self.scene];
+            let position = scene.graph[player.camera()].global_position();
+            if let Some(spectator_camera) = scene.graph[self.spectator_camera].cast_mut::<Camera>()
+            {
+                spectator_camera.set_enabled(true);
+                spectator_camera
+                    .local_transform_mut()
+                    .set_position(position);
+            }
+            self.target_spectator_position = position;
+            self.spectator_fall = SpectatorFall {
+                yaw: player.yaw(),
+                pitch: player.pitch(),
+                time: 0.0,
+            };
+        }
+    }
+
+    /// Takes inactive player out of the match, the player comes back on the next key press.
+    async fn set_player_afk(&mut self, engine: &mut Engine) {
+        let is_alive = match self.actors.try_get(self.player) {
+            Some(Actor::Player(player)) => !player.is_dead(),
+            _ => false,
+        };
+        if self.afk || !is_alive {
+            return;
+        }
+
+        Logger::writeln(
+            MessageKind::Information,
+            "Player is AFK, removed from the match.".to_owned(),
+        );
+        self.afk = true;
+        self.leader_board.get_or_add_actor(PLAYER_NAME).is_afk = true;
+        self.start_spectating(engine);
+        self.remove_actor(engine, self.player).await;
+    }
+
+    fn on_player_returned(&mut self) {
+        if !self.afk {
+            return;
+        }
+
+        self.afk = false;
+        self.leader_board.get_or_add_actor(PLAYER_NAME).is_afk = false;
+        self.respawn_list
+            .push(RespawnEntry::Player(PlayerRespawnEntry { time_left: 0.0 }));
+    }
+
+    /// Loads gameplay resources that were not saved together with level.
+    pub async fn preload_resources(&mut self, resource_manager: ResourceManager) {
+        self.sound_manager.preload(resource_manager.clone()).await;
+        self.preloaded_resources = PreloadedResources::load(resource_manager).await;
+    }
+
+    pub fn handle_sound_message(&mut self, engine: &mut Engine, message: &Message) {
+        self.sound_manager.handle_message(
+            &mut engine.scenes[self.scene].graph,
+            &engine.resource_manager,
+            message,
+        );
+    }
+
     pub async fn handle_message(&mut self, engine: &mut Engine, message: &Message, time: GameTime) {
-        self.sound_manager
-            .handle_message(
-                &mut engine.scenes[self.scene].graph,
-                engine.resource_manager.clone(),
-                &message,
-            )
-            .await;
+        if self.mirror && net::is_host_authoritative(message) {
+            return;
+        }
 
         match message {
             &Message::GiveNewWeapon { actor, kind } => {
@@ -1142,46 +4172,91 @@ impl Level {
             } => {
                 self.add_bot(engine, *kind, *position, name.clone()).await;
             }
-            &Message::RemoveActor { actor } => self.remove_actor(engine, actor).await,
+            &Message::RemoveActor { actor } => {
+                self.remove_mines(engine, actor);
+                self.remove_actor(engine, actor).await
+            }
+            Message::RemoveBot { name } => self.remove_bot(engine, name).await,
+            Message::ShuffleTeams => self.shuffle_teams(),
+            &Message::RequestTeamChange { actor, team } => {
+                self.change_team(engine, actor, team).await
+            }
             &Message::GiveItem { actor, kind } => {
                 self.give_item(engine, actor, kind).await;
             }
+            &Message::SwapWeapon { actor, item } => {
+                self.swap_weapon(engine, actor, item).await;
+            }
             &Message::PickUpItem { actor, item } => {
-                self.pickup_item(engine, actor, item).await;
+                self.pickup_item(engine, actor, item, time).await;
             }
             &Message::ShootWeapon {
                 weapon,
                 initial_velocity,
                 direction,
             } => {
-                self.shoot_weapon(engine, weapon, initial_velocity, time, direction)
+                let shooter = self.shooter_state(engine, weapon);
+                self.shoot_weapon(engine, weapon, initial_velocity, time, direction, shooter)
                     .await
             }
-            &Message::CreateProjectile {
+            Message::CreateProjectile {
                 kind,
                 position,
                 direction,
                 initial_velocity,
                 owner,
                 basis,
+                shooter_name,
             } => {
                 self.create_projectile(
                     engine,
-                    kind,
-                    position,
-                    direction,
-                    initial_velocity,
-                    owner,
-                    basis,
+                    *kind,
+                    *position,
+                    *direction,
+                    *initial_velocity,
+                    *owner,
+                    shooter_name.clone(),
+                    *basis,
+                    time,
                 )
-                .await
+                .await;
             }
             &Message::ShowWeapon { weapon, state } => self.show_weapon(engine, weapon, state),
+            &Message::ChargeWeaponStart { weapon } => self.start_weapon_charge(weapon, time),
+            &Message::ChargeWeaponRelease {
+                weapon,
+                initial_velocity,
+            } => {
+                self.release_weapon_charge(engine, weapon, initial_velocity, false, time)
+                    .await
+            }
+            &Message::SwitchWeapon { from, to } => {
+                // Charged projectile can't be put away, it falls down live.
+                if self.weapons.contains(from) && self.weapons[from].is_charging() {
+                    self.release_weapon_charge(engine, from, Vector3::default(), true, time)
+                        .await;
+                }
+                self.switch_weapon(engine, from, to)
+            }
             Message::SpawnBot { kind, name } => {
                 self.spawn_bot(engine, *kind, Some(name.clone())).await;
             }
-            &Message::DamageActor { actor, who, amount } => {
-                self.damage_actor(engine, actor, who, amount, time);
+            Message::DamageActor {
+                actor,
+                who,
+                who_name,
+                amount,
+                weapon,
+            } => {
+                self.damage_actor(
+                    engine,
+                    *actor,
+                    *who,
+                    who_name.as_deref(),
+                    *amount,
+                    *weapon,
+                    time,
+                );
             }
             &Message::CreateEffect { kind, position } => {
                 effects::create(
@@ -1191,9 +4266,36 @@ impl Level {
                     position,
                 );
             }
+            &Message::ProjectileImpact { position, amount } => {
+                let graph = &mut engine.scenes[self.scene].graph;
+                self.breakable_lights
+                    .handle_impact(graph, position, amount, &self.sender);
+                self.turrets
+                    .handle_impact(graph, position, amount, &self.sender);
+                self.props.handle_impact(graph, position, amount);
+            }
+            &Message::BotIntentChanged { bot, intent } => {
+                if let Some(Actor::Bot(bot)) = self.actors.try_get(bot) {
+                    self.sender.send(Message::ShowBotIntent {
+                        text: intent.describe(bot, &self.actors),
+                    });
+                }
+            }
+            Message::PlayerAfk => self.set_player_afk(engine).await,
+            Message::PlayerReturned => self.on_player_returned(),
             Message::SpawnPlayer => {
                 self.player = self.spawn_player(engine).await;
             }
+            Message::SpawnGuest => {
+                self.spawn_guest(engine).await;
+            }
+            Message::ChatMessage { sender_name, .. } => {
+                // Only players are answered, otherwise bots would chat with each other forever.
+                // Mirrored level gets answers from host.
+                if !self.mirror && (sender_name == PLAYER_NAME || sender_name == GUEST_NAME) {
+                    self.answer_chat();
+                }
+            }
             &Message::SpawnItem {
                 kind,
                 position,
@@ -1204,26 +4306,37 @@ impl Level {
                     .await
             }
             &Message::RespawnActor { actor } => self.respawn_actor(engine, actor).await,
+            &Message::ToggleGodMode { actor } => self.toggle_god_mode(actor),
+            &Message::ToggleNoclip { actor } => self.toggle_noclip(engine, actor),
+            Message::ToggleFreeCamera => self.toggle_free_camera(engine),
+            &Message::SetHeatmapRecording { enabled } => self.set_heatmap_recording(enabled),
+            Message::ClearHeatmap => {
+                if let Some(heatmap) = self.heatmap.as_mut() {
+                    heatmap.clear();
+                }
+            }
+            &Message::SetTimeLimit { time_limit_secs } => {
+                self.options.set_time_limit_secs(time_limit_secs)
+            }
             _ => (),
         }
     }
 
-    pub fn set_message_sender(&mut self, sender: Sender<Message>) {
-        self.sender = Some(sender.clone());
-
+    pub fn set_message_sender(&mut self, sender: MessageSender) {
         // Attach new sender to all event sources.
         for actor in self.actors.iter_mut() {
-            actor.sender = Some(sender.clone());
+            actor.sender = sender.clone();
         }
         for weapon in self.weapons.iter_mut() {
-            weapon.sender = Some(sender.clone());
+            weapon.sender = sender.clone();
         }
         for projectile in self.projectiles.iter_mut() {
-            projectile.sender = Some(sender.clone());
+            projectile.sender = sender.clone();
         }
         for item in self.items.iter_mut() {
-            item.sender = Some(sender.clone());
+            item.sender = sender.clone();
         }
+        self.sender = sender;
     }
 
     pub fn debug_draw(&self, engine: &mut Engine) {
@@ -1248,7 +4361,7 @@ impl Level {
 
             for actor in self.actors.iter() {
                 if let Actor::Bot(bot) = actor {
-                    bot.debug_draw(drawing_context);
+                    bot.debug_draw(drawing_context, self.options.mutators());
                 }
             }
         }
@@ -1256,10 +4369,352 @@ impl Level {
         for death_zone in self.death_zones.iter() {
             drawing_context.draw_aabb(&death_zone.bounds, Color::opaque(0, 0, 200));
         }
+
+        for turret in self.turrets.iter() {
+            turret.debug_draw(&scene.graph, drawing_context);
+        }
+
+        if let Some(heatmap) = self.heatmap.as_ref() {
+            heatmap.debug_draw(drawing_context);
+        }
+
+        if let Some(world_bounds) = self.world_bounds.as_ref() {
+            drawing_context.draw_aabb(world_bounds, Color::opaque(200, 0, 200));
+        }
+
+        for prop in self.props.iter() {
+            drawing_context.draw_aabb(
+                &scene.graph[prop.model()].world_bounding_box(),
+                Color::opaque(200, 200, 0),
+            );
+        }
+
+        for item in self.items.iter() {
+            drawing_context.draw_sphere(
+                item.position(&scene.graph),
+                10,
+                10,
+                PICKUP_RADIUS,
+                Color::opaque(200, 200, 0),
+            );
+        }
+
+        for spawn_point in self.spawn_points.iter() {
+            drawing_context.draw_sphere(spawn_point.position, 10, 10, 0.25, Color::GREEN);
+            drawing_context.add_line(scene::debug::Line {
+                begin: spawn_point.position,
+                end: spawn_point.position + Vector3::new(0.0, 2.0, 0.0),
+                color: Color::GREEN,
+            });
+        }
     }
 }
 
-#[derive(Visit, Default)]
+#[derive(Default)]
 pub struct SpawnPoint {
     position: Vector3<f32>,
+    /// Team which members spawn at this point, `Team::None` means any actor can spawn here.
+    team: Team,
+    /// Time left until the point can be used again, see [`SPAWN_COOLDOWN`].
+    cooldown: f32,
+}
+
+impl Visit for SpawnPoint {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.position.visit("position", visitor)?;
+
+        // Saves made before team spawn points and spawn cooldown have no such fields.
+        let _ = self.team.visit("team", visitor);
+        let _ = self.cooldown.visit("cooldown", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fyrox::scene::pivot::PivotBuilder;
+
+    fn neighbour(distance: f32, team: Team) -> SpawnNeighbour {
+        SpawnNeighbour {
+            distance,
+            visible: false,
+            team,
+        }
+    }
+
+    #[test]
+    fn enemy_within_safe_distance_rejects_point() {
+        let neighbours = [neighbour(SPAWN_SAFE_DISTANCE * 0.5, Team::Blue)];
+        assert_eq!(
+            score_spawn_point(Team::None, Team::Red, &neighbours, false),
+            None
+        );
+        // Everyone is an enemy without teams.
+        let neighbours = [neighbour(SPAWN_SAFE_DISTANCE * 0.5, Team::None)];
+        assert_eq!(
+            score_spawn_point(Team::None, Team::None, &neighbours, false),
+            None
+        );
+    }
+
+    #[test]
+    fn teammate_nearby_does_not_block_point() {
+        let neighbours = [
+            neighbour(SPAWN_SAFE_DISTANCE * 0.5, Team::Red),
+            neighbour(SPAWN_SAFE_DISTANCE * 2.0, Team::Blue),
+        ];
+        assert_eq!(
+            score_spawn_point(Team::None, Team::Red, &neighbours, false),
+            Some(SPAWN_SAFE_DISTANCE * 2.0)
+        );
+    }
+
+    #[test]
+    fn team_points_are_for_their_team_only() {
+        assert_eq!(score_spawn_point(Team::Blue, Team::Red, &[], false), None);
+        assert_eq!(
+            score_spawn_point(Team::Red, Team::Red, &[], false),
+            Some(SPAWN_TEAM_BONUS)
+        );
+        assert_eq!(
+            score_spawn_point(Team::None, Team::Red, &[], true),
+            Some(-SPAWN_REUSE_PENALTY)
+        );
+    }
+
+    #[test]
+    fn all_points_occupied_falls_back_to_least_contested() {
+        let neighbours = [neighbour(1.0, Team::None)];
+        let scores = (0..3)
+            .map(|_| score_spawn_point(Team::None, Team::None, &neighbours, false))
+            .collect::<Vec<_>>();
+        assert!(scores.iter().all(Option::is_none));
+
+        assert_eq!(
+            least_contested_spawn_point(&[Some(10.0), Some(30.0), Some(20.0)]),
+            Some(1)
+        );
+        // Points that can't be used are skipped even if they are the farthest.
+        assert_eq!(
+            least_contested_spawn_point(&[Some(10.0), None, Some(20.0)]),
+            Some(2)
+        );
+        assert_eq!(least_contested_spawn_point(&[None, None]), None);
+    }
+
+    fn named_bot(name: &str) -> Actor {
+        let mut bot = Bot::default();
+        bot.name = name.to_owned();
+        Actor::Bot(bot)
+    }
+
+    #[test]
+    fn posthumous_frag_goes_to_dead_shooter() {
+        let mut actors = ActorContainer::new();
+        let mut leader_board = LeaderBoard::default();
+        leader_board.get_or_add_actor("Shooter");
+        leader_board.get_or_add_actor("Victim");
+
+        // Shooter fires a rocket, projectile captures its handle and name.
+        let shooter = actors.add(named_bot("Shooter"));
+        let captured_name = actors.get(shooter).name.clone();
+        // Shooter dies and despawns while the rocket is in flight.
+        actors.free(shooter);
+        leader_board.add_death("Shooter");
+
+        // Rocket kills the victim afterwards.
+        let (who, who_name) = damage_dealer(&actors, &leader_board, shooter, Some(&captured_name));
+        assert!(who.is_none());
+        assert_eq!(who_name.as_deref(), Some("Shooter"));
+        leader_board.add_frag(who_name.unwrap());
+        leader_board.add_death("Victim");
+
+        let scores = leader_board.values();
+        assert_eq!(scores["Shooter"].kills, 1);
+        assert_eq!(scores["Shooter"].deaths, 1);
+        assert_eq!(scores["Victim"].kills, 0);
+        assert_eq!(scores["Victim"].deaths, 1);
+    }
+
+    #[test]
+    fn live_shooter_is_credited_by_handle() {
+        let mut actors = ActorContainer::new();
+        let leader_board = LeaderBoard::default();
+        let shooter = actors.add(named_bot("Shooter"));
+        assert_eq!(
+            damage_dealer(&actors, &leader_board, shooter, Some("Shooter")),
+            (shooter, Some("Shooter".to_owned()))
+        );
+    }
+
+    #[test]
+    fn shooter_that_left_match_is_not_credited() {
+        let mut actors = ActorContainer::new();
+        let mut leader_board = LeaderBoard::default();
+        leader_board.get_or_add_actor("Shooter");
+        let shooter = actors.add(named_bot("Shooter"));
+        actors.free(shooter);
+        leader_board.remove_actor("Shooter");
+        assert_eq!(
+            damage_dealer(&actors, &leader_board, shooter, Some("Shooter")),
+            (Handle::NONE, None)
+        );
+        // Environment damage has no dealer at all.
+        assert_eq!(
+            damage_dealer(&actors, &leader_board, Handle::NONE, None),
+            (Handle::NONE, None)
+        );
+    }
+
+    /// Writes old layout with `write` and reads it back, like an older save is read.
+    fn read_back(name: &str, write: impl FnOnce(&mut Visitor) -> VisitResult) -> Visitor {
+        let path = crate::temp_path(name);
+        let mut visitor = Visitor::new();
+        write(&mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let visitor =
+            fyrox::core::futures::executor::block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        visitor
+    }
+
+    #[test]
+    fn fields_missing_in_old_save_get_defaults() {
+        let min = Vector3::new(-1.0, 0.0, -1.0);
+        let max = Vector3::new(1.0, 2.0, 1.0);
+        let mut visitor = read_back("old_level_parts", |visitor| {
+            visitor.enter_region("DeathZone")?;
+            AxisAlignedBoundingBox::from_min_max(min, max).visit("bounds", visitor)?;
+            visitor.leave_region()?;
+
+            visitor.enter_region("SpawnPoint")?;
+            Vector3::new(1.0, 2.0, 3.0).visit("position", visitor)?;
+            visitor.leave_region()
+        });
+
+        let mut death_zone = DeathZone::default();
+        death_zone.visit("DeathZone", &mut visitor).unwrap();
+        assert_eq!(death_zone.kind, DeathZoneKind::Kill);
+        assert_eq!((death_zone.bounds.min, death_zone.bounds.max), (min, max));
+
+        let mut spawn_point = SpawnPoint::default();
+        spawn_point.visit("SpawnPoint", &mut visitor).unwrap();
+        assert_eq!(spawn_point.position, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(spawn_point.team, Team::None);
+        assert_eq!(spawn_point.cooldown, 0.0);
+    }
+
+    #[test]
+    fn gameplay_draws_are_reproducible_from_seed() {
+        let draws = |seed| {
+            let mut rng = GameRng::new(seed);
+            let scene = Scene::new();
+            let actors = ActorContainer::new();
+            let mut spawn_points = (0..4)
+                .map(|i| SpawnPoint {
+                    position: Vector3::new(i as f32 * 10.0, 0.0, 0.0),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            let mut last_spawn_point = None;
+            let mut spawns = Vec::new();
+            let mut shots = Vec::new();
+            let mut chances = Vec::new();
+            for _ in 0..8 {
+                spawns.push(find_suitable_spawn_point(
+                    &mut spawn_points,
+                    &actors,
+                    &scene,
+                    Team::None,
+                    &mut last_spawn_point,
+                    &mut rng,
+                ));
+                shots.push(weapon::apply_spread(Vector3::z(), 3.0, &mut rng));
+                chances.push(rng.gen::<f32>());
+                for pt in spawn_points.iter_mut() {
+                    pt.cooldown -= SPAWN_COOLDOWN * 0.5;
+                }
+            }
+            let mut names = vec!["Alpha", "Bravo", "Charlie", "Delta", "Echo"];
+            names.shuffle(&mut rng);
+            (spawns, shots, chances, names)
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+    }
+
+    fn pivot(scene: &mut Scene) -> Handle<Node> {
+        PivotBuilder::new(BaseBuilder::new()).build(&mut scene.graph)
+    }
+
+    #[test]
+    fn missing_scene_parts_are_reported() {
+        let mut level = Level::default();
+        let mut scene = Scene::new();
+        let bot = level.actors.add(named_bot("Lost"));
+
+        let errors = level.check_scene(&scene).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("Map root"));
+        assert!(errors[1].contains("Spectator camera"));
+        assert!(errors[2].contains("Lost"));
+
+        level.map_root = scene.graph.get_root();
+        level.spectator_camera = pivot(&mut scene);
+        level.actors.get_mut(bot).body = pivot(&mut scene);
+        // No navigation mesh is only a warning.
+        assert!(level.check_scene(&scene).is_ok());
+    }
+
+    #[test]
+    fn dangling_handles_are_repaired() {
+        let mut level = Level::default();
+        let mut scene = Scene::new();
+
+        // Player handle points at a bot.
+        let owner = level.actors.add(named_bot("Owner"));
+        level.player = owner;
+
+        let kept = level.weapons.add(Weapon::default());
+        level.weapons[kept].set_owner(owner);
+        let gone = level.weapons.add(Weapon::default());
+        level.weapons.free(gone);
+        let actor = level.actors.get_mut(owner);
+        actor.weapons = vec![gone, kept];
+        actor.current_weapon = 1;
+
+        // Weapon of an actor that is not in the match anymore.
+        let left = level.actors.add(named_bot("Left"));
+        level.actors.free(left);
+        let orphan = level.weapons.add(Weapon::default());
+        level.weapons[orphan].set_owner(left);
+
+        let mut projectile = Projectile::default();
+        projectile.owner = orphan;
+        level.projectiles.add(projectile);
+        let mut projectile = Projectile::default();
+        projectile.owner = gone;
+        level.projectiles.add(projectile);
+
+        level.repair_handles(&mut scene);
+
+        assert!(level.player.is_none());
+        assert!(level
+            .respawn_list
+            .iter()
+            .any(|entry| matches!(entry, RespawnEntry::Player(_))));
+        let actor = level.actors.get(owner);
+        assert_eq!(actor.weapons, vec![kept]);
+        assert_eq!(actor.current_weapon, 0);
+        assert!(level.weapons.contains(kept));
+        assert!(!level.weapons.contains(orphan));
+        assert!(level
+            .projectiles
+            .iter()
+            .all(|projectile| projectile.owner.is_none()));
+    }
 }
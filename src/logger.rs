@@ -0,0 +1,80 @@
+//! Thin wrapper over engine's log. Every message is also written into `game.log` in user data
+//! directory with a timestamp and a level, so there is something to look at when game was
+//! launched without a terminal.
+
+use fyrox::{
+    core::parking_lot::{self, Mutex},
+    utils::log::{Log, MessageKind},
+};
+use std::{
+    fs::File,
+    io::Write,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+static FILE: Mutex<Option<File>> = parking_lot::const_mutex(None);
+static MIN_LEVEL: AtomicU32 = AtomicU32::new(MessageKind::Warning as u32);
+
+pub struct Logger;
+
+impl Logger {
+    /// Opens log file and installs panic hook. Verbose mode writes informational messages
+    /// too, otherwise only warnings and errors are written.
+    pub fn init(verbose: bool) {
+        let level = if verbose {
+            MessageKind::Information
+        } else {
+            MessageKind::Warning
+        };
+        MIN_LEVEL.store(level as u32, Ordering::SeqCst);
+        Log::set_verbosity(level);
+
+        let path = crate::user_data_dir().join("game.log");
+        match File::create(&path) {
+            Ok(file) => *FILE.lock() = Some(file),
+            Err(e) => Log::writeln(
+                MessageKind::Error,
+                format!(
+                    "Unable to create log file {}. Reason: {}",
+                    path.display(),
+                    e
+                ),
+            ),
+        }
+
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            Self::writeln(
+                MessageKind::Error,
+                format!(
+                    "{}\nBacktrace:\n{}",
+                    info,
+                    std::backtrace::Backtrace::force_capture()
+                ),
+            );
+            default_hook(info);
+        }));
+    }
+
+    pub fn writeln(kind: MessageKind, message: String) {
+        if (kind as u32) < MIN_LEVEL.load(Ordering::SeqCst) {
+            return;
+        }
+
+        if let Some(file) = FILE.lock().as_mut() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let level = match kind {
+                MessageKind::Information => "INFO",
+                MessageKind::Warning => "WARN",
+                MessageKind::Error => "ERROR",
+            };
+            let _ = writeln!(file, "[{:.3}] [{}] {}", timestamp, level, message);
+        }
+
+        Log::writeln(kind, message);
+    }
+}
@@ -6,60 +6,109 @@ extern crate fyrox;
 
 mod actor;
 mod bot;
+mod breakable_light;
 mod character;
+mod chat;
+mod cli;
+mod command_menu;
+mod console;
 mod control_scheme;
+mod damage_numbers;
+mod definitions;
 mod effects;
+mod environment;
+mod free_camera;
+mod grapple;
 mod gui;
+mod health_bars;
+mod heatmap;
 mod hud;
+mod intermission;
+mod interpolation;
 mod item;
+mod item_timers;
 mod jump_pad;
 mod leader_board;
 mod level;
+mod logger;
 mod match_menu;
 mod menu;
 mod message;
+mod mutators;
+mod net;
+mod notifications;
+mod observer;
 mod options_menu;
 mod player;
+mod profiler;
 mod projectile;
+mod prop;
+mod query_buffer;
+mod records;
+mod rng;
+mod settings;
+mod spatial_grid;
+mod team_select;
+mod turret;
 mod weapon;
 
 use crate::{
-    actor::Actor, control_scheme::ControlScheme, hud::Hud, level::Level, menu::Menu,
-    message::Message,
+    actor::Actor,
+    cli::CommandLineArgs,
+    command_menu::CommandMenu,
+    console::Console,
+    control_scheme::{ControlButton, ControlScheme},
+    definitions::{Definitions, DefinitionsWatcher},
+    hud::Hud,
+    level::Level,
+    logger::Logger,
+    menu::Menu,
+    message::{Message, MessageCategory, MessageSender},
+    mutators::Mutators,
+    net::{NetEvent, NetSession, Packet, GUEST_NAME, HOST_NAME},
+    notifications::NotificationSeverity,
+    observer::{EventLogObserver, GameEvent, GameObserver},
+    player::PLAYER_NAME,
+    profiler::Profiler,
+    records::{MatchResult, RecordKey, Records},
+    rng::GameRng,
+    settings::{MatchSettings, Preferences, Settings},
+    team_select::TeamSelect,
 };
 use fyrox::window::CursorGrabMode;
 use fyrox::{
     core::{
         futures::executor::block_on,
         pool::Handle,
-        visitor::{Visit, VisitResult, Visitor},
+        visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     engine::{resource_manager::ResourceManager, Engine, EngineInitParams, SerializationContext},
-    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
+    event::{DeviceEvent, ElementState, Event, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     gui::{
         grid::{Column, GridBuilder, Row},
         message::MessageDirection,
+        messagebox::{MessageBoxBuilder, MessageBoxButtons, MessageBoxMessage},
         progress_bar::{ProgressBarBuilder, ProgressBarMessage},
         text::{TextBuilder, TextMessage},
         widget::{WidgetBuilder, WidgetMessage},
-        BuildContext, HorizontalAlignment, UiNode, VerticalAlignment,
+        window::{WindowBuilder, WindowTitle},
+        BuildContext, HorizontalAlignment, UiNode, UserInterface, VerticalAlignment,
     },
     scene::{
         base::BaseBuilder,
+        camera::Camera,
         node::Node,
         sound::{SoundBuilder, Status},
         Scene, SceneLoader,
     },
-    utils::{
-        log::{Log, MessageKind},
-        translate_event,
-    },
+    utils::{log::MessageKind, translate_event},
 };
 use std::{
+    fmt::{Display, Formatter},
     fs::File,
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
         mpsc::{self, Receiver, Sender},
         Arc, Mutex, RwLock,
@@ -68,16 +117,82 @@ use std::{
 };
 
 const FIXED_FPS: f32 = 60.0;
+const STATISTICS_UPDATE_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
+/// Version of save file format. Must be increased every time when layout of any visited
+/// entity changes in a backward-incompatible way, and a migration hook must be added to
+/// `SAVE_MIGRATIONS` if old saves can be converted. New fields do not need a new version,
+/// they are read tolerantly and get defaults when an older save has no such fields.
+const SAVE_VERSION: u32 = 1;
+
+/// Migration hooks as pairs of (version to migrate from, hook). Each hook patches visitor
+/// data so it can be read as the next version of save format.
+const SAVE_MIGRATIONS: &[(u32, fn(&mut Visitor) -> VisitResult)] = &[(0, migrate_unversioned)];
+
+/// Saves made before versioning differ from version 1 only by the missing version field.
+fn migrate_unversioned(_visitor: &mut Visitor) -> VisitResult {
+    Ok(())
+}
+
+const MENU_MUSIC_PATH: &str = "data/sounds/Antonio_Bizarro_Berzerker.ogg";
+
+/// Data files that are loaded on start. The game runs without them, but reports missing ones.
+const STARTUP_ASSETS: &[&str] = &[
+    level::HRIR_SPHERE_PATH,
+    MENU_MUSIC_PATH,
+    gui::FONT_PATH,
+    "data/ui/crosshair.tga",
+    "data/ui/circle.png",
+    "data/ui/health_icon.png",
+    "data/ui/ammo_icon.png",
+    "data/ui/shield_icon.png",
+];
+
+/// Directory for everything the game writes on its own, like logs. Created on demand.
+pub fn user_data_dir() -> PathBuf {
+    let base = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_DATA_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default();
+    let dir = base.join("rusty-shooter");
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// Writes visitor into a temporary file next to `path` and then renames it over `path`, so a
+/// crash in the middle of writing never leaves a truncated file behind - either the old or the
+/// new file is there. Temporary file is removed if writing fails.
+pub fn save_binary_atomic(visitor: &Visitor, path: &Path) -> VisitResult {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let result = visitor
+        .save_binary(&temp_path)
+        .and_then(|_| std::fs::rename(&temp_path, path).map_err(VisitError::from));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Path of a scratch file for tests, unique per test `name` and per process.
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rusty_shooter_{}_{}.bin", name, std::process::id()))
+}
 
 pub struct Game {
     menu: Menu,
     hud: Hud,
+    ui_layout: gui::UiLayout,
     engine: Engine,
     level: Option<Level>,
     debug_text: Handle<UiNode>,
     debug_string: String,
     running: bool,
     control_scheme: Arc<RwLock<ControlScheme>>,
+    preferences: Arc<RwLock<Preferences>>,
     time: GameTime,
     events_receiver: Receiver<Message>,
     events_sender: Sender<Message>,
@@ -85,16 +200,133 @@ pub struct Game {
     loading_screen: LoadingScreen,
     menu_scene: Handle<Scene>,
     music: Handle<Node>,
+    error_box: Handle<UiNode>,
+    console: Console,
+    command_menu: CommandMenu,
+    team_select: TeamSelect,
+    /// Path to a map which will be used for new matches.
+    map: PathBuf,
+    bot_count: usize,
+    settings: Settings,
+    records: Records,
+    /// Balance files are reloaded on change while a match runs.
+    definitions_watcher: DefinitionsWatcher,
+    /// Seed for the next match, random seed is used if not specified.
+    seed: Option<u64>,
+    /// Seed of current match, host tells it to client.
+    match_seed: u64,
+    net: Option<NetSession>,
+    /// Name of a save slot, save file will be `<slot>.bin`.
+    save_slot: String,
+    profiler: Profiler,
+    debug_overlay: DebugOverlay,
+    /// Time of last debug text update, `None` forces update on next frame.
+    statistics_update_time: Option<Instant>,
+    debug_draw: bool,
+    /// Frame rate limit, frames are rendered as fast as possible if not specified.
+    max_fps: Option<f32>,
+    last_render: Instant,
+    /// Fraction of fixed step passed since last simulation step, used to interpolate
+    /// visual transforms between simulation steps.
+    interpolation_alpha: f32,
+    /// Game window has input focus, game audio is paused while it is in background.
+    focused: bool,
+    /// Match was paused because window lost focus, it resumes when menu is closed.
+    focus_paused: bool,
+    /// Last applied cursor visibility, `None` forces cursor state to be applied again.
+    cursor_visible: Option<bool>,
+    /// External listeners of match events, see [`observer`].
+    observers: Vec<Box<dyn GameObserver>>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum DebugOverlay {
+    Off,
+    RendererStats,
+    FullProfile,
+}
+
+impl DebugOverlay {
+    fn next(self) -> Self {
+        match self {
+            DebugOverlay::Off => DebugOverlay::RendererStats,
+            DebugOverlay::RendererStats => DebugOverlay::FullProfile,
+            DebugOverlay::FullProfile => DebugOverlay::Off,
+        }
+    }
+}
+
+pub enum LoadError {
+    Visit(VisitError),
+    /// Save was made by a version of the game which format can't be migrated.
+    IncompatibleVersion(u32),
+    /// Save was read, but its content is inconsistent, see [`Level::validate_after_load`].
+    Corrupted(Vec<String>),
+}
+
+impl From<VisitError> for LoadError {
+    fn from(e: VisitError) -> Self {
+        LoadError::Visit(e)
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Visit(e) => write!(f, "{}", e),
+            LoadError::Corrupted(errors) => {
+                write!(f, "This save is damaged and can't be loaded.")?;
+                for error in errors {
+                    write!(f, " {}", error)?;
+                }
+                Ok(())
+            }
+            LoadError::IncompatibleVersion(version) => {
+                if *version < SAVE_VERSION {
+                    write!(
+                        f,
+                        "This save was created by an older version and can't be loaded."
+                    )
+                } else {
+                    write!(
+                        f,
+                        "This save was created by a newer version and can't be loaded."
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn migrate_save(visitor: &mut Visitor, mut version: u32) -> Result<(), LoadError> {
+    if version > SAVE_VERSION {
+        return Err(LoadError::IncompatibleVersion(version));
+    }
+
+    while version < SAVE_VERSION {
+        let (_, hook) = SAVE_MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .ok_or(LoadError::IncompatibleVersion(version))?;
+        hook(visitor)?;
+        version += 1;
+    }
+
+    Ok(())
 }
 
 struct LoadingScreen {
     root: Handle<UiNode>,
     progress_bar: Handle<UiNode>,
+    status: Handle<UiNode>,
+    map_name: String,
+    spinner: usize,
 }
 
 impl LoadingScreen {
     fn new(ctx: &mut BuildContext, width: f32, height: f32) -> Self {
         let progress_bar;
+        let status;
         let root = GridBuilder::new(
             WidgetBuilder::new()
                 .with_width(width)
@@ -111,16 +343,25 @@ impl LoadingScreen {
                                         .build(ctx);
                                 progress_bar
                             })
-                            .with_child(
-                                TextBuilder::new(WidgetBuilder::new().on_row(0))
+                            .with_child({
+                                status = TextBuilder::new(WidgetBuilder::new().on_row(0))
                                     .with_horizontal_text_alignment(HorizontalAlignment::Center)
                                     .with_vertical_text_alignment(VerticalAlignment::Center)
                                     .with_text("Loading... Please wait.")
+                                    .build(ctx);
+                                status
+                            })
+                            .with_child(
+                                TextBuilder::new(WidgetBuilder::new().on_row(2))
+                                    .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                                    .with_vertical_text_alignment(VerticalAlignment::Center)
+                                    .with_text("Press Escape to cancel")
                                     .build(ctx),
                             ),
                     )
                     .add_row(Row::stretch())
                     .add_row(Row::strict(32.0))
+                    .add_row(Row::strict(32.0))
                     .add_column(Column::stretch())
                     .build(ctx),
                 ),
@@ -132,15 +373,152 @@ impl LoadingScreen {
         .add_row(Row::strict(100.0))
         .add_row(Row::stretch())
         .build(ctx);
-        Self { root, progress_bar }
+        Self {
+            root,
+            progress_bar,
+            status,
+            map_name: Default::default(),
+            spinner: 0,
+        }
+    }
+
+    fn set_visible(&self, ui: &mut UserInterface, visible: bool) {
+        ui.send_message(WidgetMessage::visibility(
+            self.root,
+            MessageDirection::ToWidget,
+            visible,
+        ));
+    }
+
+    fn show(&mut self, ui: &mut UserInterface, map: &Path) {
+        self.map_name = map
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.spinner = 0;
+        ui.send_message(ProgressBarMessage::progress(
+            self.progress_bar,
+            MessageDirection::ToWidget,
+            0.0,
+        ));
+        self.set_visible(ui, true);
+    }
+
+    fn update(&mut self, ui: &mut UserInterface, progress: f32) {
+        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+        // Spin a few times per second, update is called at fixed rate.
+        self.spinner = self.spinner.wrapping_add(1);
+        ui.send_message(TextMessage::text(
+            self.status,
+            MessageDirection::ToWidget,
+            format!(
+                "Loading {}... {}",
+                self.map_name,
+                SPINNER[(self.spinner / 8) % SPINNER.len()]
+            ),
+        ));
+        ui.send_message(ProgressBarMessage::progress(
+            self.progress_bar,
+            MessageDirection::ToWidget,
+            progress,
+        ));
     }
 }
 
+/// Time scales that debug key cycles through, see [`GameTime::set_time_scale`].
+const TIME_SCALES: [f32; 3] = [1.0, 0.5, 0.25];
+
+/// Fastest time scale that can be set, faster simulation makes little sense for debugging.
+pub const MAX_TIME_SCALE: f32 = 4.0;
+
 #[derive(Copy, Clone)]
 pub struct GameTime {
     clock: time::Instant,
+    /// Wall clock time that is already simulated, fixed steps are done until it catches up
+    /// with `clock`.
+    wall: f64,
+    /// Game time, it runs slower in slow motion and stops while paused. Every timer of game
+    /// entities must use it instead of wall clock.
     elapsed: f64,
+    /// Game time step: fixed step scaled by `time_scale`, zero while paused.
     delta: f32,
+    /// Fixed step of real time, for things that must not slow down like UI and engine.
+    step: f32,
+    time_scale: f32,
+    paused: bool,
+}
+
+impl GameTime {
+    fn new(step: f32) -> Self {
+        Self {
+            clock: Instant::now(),
+            wall: 0.0,
+            elapsed: 0.0,
+            delta: step,
+            step,
+            time_scale: 1.0,
+            paused: false,
+        }
+    }
+
+    /// Continues simulation from given elapsed time. Time spent on loading is not accumulated,
+    /// so there will be no burst of catch-up fixed updates on the next frame.
+    pub fn reset_after_load(&mut self, elapsed: f64) {
+        self.clock = Instant::now();
+        self.wall = 0.0;
+        self.elapsed = elapsed;
+    }
+
+    /// Returns true and advances clocks by one fixed step if wall clock is ahead of
+    /// simulation by at least one step.
+    fn next_step(&mut self) -> bool {
+        if self.clock.elapsed().as_secs_f64() - self.wall < self.step as f64 {
+            return false;
+        }
+        self.wall += self.step as f64;
+        self.advance();
+        true
+    }
+
+    /// Advances game time by one fixed step, scaled by time scale.
+    fn advance(&mut self) {
+        self.delta = if self.paused {
+            0.0
+        } else {
+            self.step * self.time_scale
+        };
+        self.elapsed += self.delta as f64;
+    }
+
+    /// Fraction of fixed step that passed since last step, used for render interpolation.
+    fn step_fraction(&self) -> f32 {
+        ((self.clock.elapsed().as_secs_f64() - self.wall) / self.step as f64) as f32
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0).min(MAX_TIME_SCALE);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Returns next time scale of debug key cycle.
+    fn next_time_scale(&self) -> f32 {
+        let index = TIME_SCALES
+            .iter()
+            .position(|&scale| scale == self.time_scale)
+            .map_or(0, |i| (i + 1) % TIME_SCALES.len());
+        TIME_SCALES[index]
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
 }
 
 // Disable false-positive lint, isize *is* portable.
@@ -156,18 +534,27 @@ pub enum CollisionGroups {
 pub struct DeathMatch {
     pub time_limit_secs: f32,
     pub frag_limit: u32,
+    pub mutators: Mutators,
 }
 
 #[derive(Copy, Clone, Debug, Visit, Default)]
 pub struct TeamDeathMatch {
     pub time_limit_secs: f32,
     pub team_frag_limit: u32,
+    pub mutators: Mutators,
 }
 
 #[derive(Copy, Clone, Debug, Visit, Default)]
 pub struct CaptureTheFlag {
     pub time_limit_secs: f32,
     pub flag_limit: u32,
+    pub mutators: Mutators,
+}
+
+/// Training on target dummies: no hostile bots, no time or score limit, infinite ammo.
+#[derive(Copy, Clone, Debug, Visit, Default)]
+pub struct Practice {
+    pub mutators: Mutators,
 }
 
 #[derive(Copy, Clone, Debug, Visit)]
@@ -175,6 +562,7 @@ pub enum MatchOptions {
     DeathMatch(DeathMatch),
     TeamDeathMatch(TeamDeathMatch),
     CaptureTheFlag(CaptureTheFlag),
+    Practice(Practice),
 }
 
 impl Default for MatchOptions {
@@ -183,12 +571,116 @@ impl Default for MatchOptions {
     }
 }
 
+impl MatchOptions {
+    /// Names of match modes in the same order as `mode_index` returns them.
+    pub const MODE_NAMES: [&'static str; 4] = [
+        "Deathmatch",
+        "Team Deathmatch",
+        "Capture The Flag",
+        "Practice",
+    ];
+
+    pub fn mode_index(&self) -> usize {
+        match self {
+            MatchOptions::DeathMatch(_) => 0,
+            MatchOptions::TeamDeathMatch(_) => 1,
+            MatchOptions::CaptureTheFlag(_) => 2,
+            MatchOptions::Practice(_) => 3,
+        }
+    }
+
+    /// Builds options of a mode by its index, unknown index gives deathmatch. `score_limit`
+    /// is frag limit or flag limit, depending on mode, practice ignores both limits.
+    pub fn from_mode_index(
+        index: usize,
+        time_limit_secs: f32,
+        score_limit: u32,
+        mutators: Mutators,
+    ) -> Self {
+        match index {
+            1 => MatchOptions::TeamDeathMatch(TeamDeathMatch {
+                time_limit_secs,
+                team_frag_limit: score_limit,
+                mutators,
+            }),
+            2 => MatchOptions::CaptureTheFlag(CaptureTheFlag {
+                time_limit_secs,
+                flag_limit: score_limit,
+                mutators,
+            }),
+            3 => MatchOptions::Practice(Practice { mutators }),
+            _ => MatchOptions::DeathMatch(DeathMatch {
+                time_limit_secs,
+                frag_limit: score_limit,
+                mutators,
+            }),
+        }
+    }
+
+    /// Score at which match ends: frags, team frags or captured flags. Zero means no limit.
+    pub fn score_limit(&self) -> u32 {
+        match self {
+            MatchOptions::DeathMatch(dm) => dm.frag_limit,
+            MatchOptions::TeamDeathMatch(tdm) => tdm.team_frag_limit,
+            MatchOptions::CaptureTheFlag(ctf) => ctf.flag_limit,
+            MatchOptions::Practice(_) => 0,
+        }
+    }
+
+    pub fn set_time_limit_secs(&mut self, time_limit_secs: f32) {
+        match self {
+            MatchOptions::DeathMatch(dm) => dm.time_limit_secs = time_limit_secs,
+            MatchOptions::TeamDeathMatch(tdm) => tdm.time_limit_secs = time_limit_secs,
+            MatchOptions::CaptureTheFlag(ctf) => ctf.time_limit_secs = time_limit_secs,
+            MatchOptions::Practice(_) => (),
+        }
+    }
+
+    pub fn time_limit_secs(&self) -> f32 {
+        match self {
+            MatchOptions::DeathMatch(dm) => dm.time_limit_secs,
+            MatchOptions::TeamDeathMatch(tdm) => tdm.time_limit_secs,
+            MatchOptions::CaptureTheFlag(ctf) => ctf.time_limit_secs,
+            MatchOptions::Practice(_) => 0.0,
+        }
+    }
+
+    /// Returns true if actors are split into teams in this mode.
+    pub fn is_team_mode(&self) -> bool {
+        !matches!(
+            self,
+            MatchOptions::DeathMatch(_) | MatchOptions::Practice(_)
+        )
+    }
+
+    pub fn is_practice(&self) -> bool {
+        matches!(self, MatchOptions::Practice(_))
+    }
+
+    /// Weapons do not spend ammo, practice always has it.
+    pub fn infinite_ammo(&self) -> bool {
+        self.is_practice() || self.mutators().infinite_ammo
+    }
+
+    pub fn mutators(&self) -> &Mutators {
+        match self {
+            MatchOptions::DeathMatch(dm) => &dm.mutators,
+            MatchOptions::TeamDeathMatch(tdm) => &tdm.mutators,
+            MatchOptions::CaptureTheFlag(ctf) => &ctf.mutators,
+            MatchOptions::Practice(practice) => &practice.mutators,
+        }
+    }
+}
+
 pub struct LoadContext {
-    level: Option<(Level, Scene)>,
+    /// Result of loading, error describes why level can't be loaded.
+    level: Option<Result<(Level, Scene), String>>,
+    /// Set when player cancelled loading, loaded level will be thrown away.
+    cancelled: bool,
 }
 
 impl Game {
-    pub fn run() {
+    pub fn run(args: CommandLineArgs) {
         let events_loop = EventLoop::<()>::new();
 
         let primary_monitor = events_loop.primary_monitor().unwrap();
@@ -208,35 +700,43 @@ impl Game {
             resource_manager: ResourceManager::new(serialization_context.clone()),
             serialization_context,
             events_loop: &events_loop,
-            vsync: false,
+            vsync: args.vsync,
         })
         .unwrap();
 
-        let control_scheme = Arc::new(RwLock::new(ControlScheme::default()));
+        let settings = Settings::load();
+        let control_scheme = Arc::new(RwLock::new(settings.controls.active().clone()));
+        let preferences = Arc::new(RwLock::new(settings.preferences.clone()));
+        settings.flush_on_panic(control_scheme.clone(), preferences.clone());
+
+        // Load balance tables right away, so problems with data files are reported on start.
+        Definitions::get();
 
         let fixed_timestep = 1.0 / FIXED_FPS;
 
-        let time = GameTime {
-            clock: Instant::now(),
-            elapsed: 0.0,
-            delta: fixed_timestep,
-        };
+        let time = GameTime::new(fixed_timestep);
 
         let (tx, rx) = mpsc::channel();
-        let buffer = fyrox::core::futures::executor::block_on(
+        let mut menu_scene = Scene::new();
+        let music = match fyrox::core::futures::executor::block_on(
             engine
                 .resource_manager
-                .request_sound_buffer("data/sounds/Antonio_Bizarro_Berzerker.ogg"),
-        )
-        .unwrap();
-
-        let mut menu_scene = Scene::new();
-        let music = SoundBuilder::new(BaseBuilder::new())
-            .with_buffer(Some(buffer))
-            .with_looping(true)
-            .with_status(Status::Playing)
-            .with_gain(0.25)
-            .build(&mut menu_scene.graph);
+                .request_sound_buffer(MENU_MUSIC_PATH),
+        ) {
+            Ok(buffer) => SoundBuilder::new(BaseBuilder::new())
+                .with_buffer(Some(buffer))
+                .with_looping(true)
+                .with_status(Status::Playing)
+                .with_gain(0.25)
+                .build(&mut menu_scene.graph),
+            Err(_) => {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!("Unable to load menu music {}.", MENU_MUSIC_PATH),
+                );
+                Handle::NONE
+            }
+        };
 
         let mut game = Game {
             loading_screen: LoadingScreen::new(
@@ -247,9 +747,23 @@ impl Game {
             menu_scene: engine.scenes.add(menu_scene),
             music,
             hud: Hud::new(&mut engine),
+            ui_layout: gui::UiLayout::new(engine.renderer.get_frame_size()),
             running: true,
-            menu: Menu::new(&mut engine, control_scheme.clone(), tx.clone()),
+            menu: Menu::new(
+                &mut engine,
+                control_scheme.clone(),
+                preferences.clone(),
+                tx.clone(),
+                &settings,
+            ),
+            console: Console::new(&mut engine, tx.clone()),
+            command_menu: CommandMenu::new(
+                &mut engine.user_interface,
+                MessageSender::new(tx.clone()),
+            ),
+            team_select: TeamSelect::new(&mut engine.user_interface, tx.clone()),
             control_scheme,
+            preferences,
             debug_text: Handle::NONE,
             engine,
             level: None,
@@ -258,50 +772,142 @@ impl Game {
             events_receiver: rx,
             events_sender: tx,
             load_context: None,
+            error_box: Handle::NONE,
+            map: args.map.unwrap_or_else(|| settings.last_match.map.clone()),
+            bot_count: args.bots.unwrap_or(settings.last_match.bot_count as usize),
+            settings,
+            records: Records::load(),
+            definitions_watcher: Default::default(),
+            seed: args.seed,
+            match_seed: 0,
+            net: None,
+            save_slot: args.load.clone().unwrap_or_else(|| "save".to_owned()),
+            profiler: Default::default(),
+            debug_overlay: DebugOverlay::RendererStats,
+            statistics_update_time: None,
+            debug_draw: false,
+            max_fps: args.max_fps,
+            last_render: Instant::now(),
+            interpolation_alpha: 0.0,
+            focused: true,
+            focus_paused: false,
+            cursor_visible: None,
+            observers: Vec::new(),
         };
 
+        if args.event_log {
+            game.add_observer(Box::new(EventLogObserver::open()));
+        }
+
         game.create_debug_ui();
+        game.create_error_box();
+        game.report_missing_assets();
+
+        if let Some(port) = args.host {
+            match NetSession::host(port) {
+                Ok(session) => game.net = Some(session),
+                Err(e) => {
+                    Logger::writeln(
+                        MessageKind::Error,
+                        format!("Unable to host a match on port {}: {}", port, e),
+                    );
+                    game.show_error("Unable to host a match", e.to_string());
+                }
+            }
+        } else if let Some(address) = args.connect.as_ref() {
+            match NetSession::connect(address) {
+                Ok(session) => game.net = Some(session),
+                Err(e) => {
+                    Logger::writeln(
+                        MessageKind::Error,
+                        format!("Unable to connect to {}: {}", address, e),
+                    );
+                    game.show_error("Unable to connect", e.to_string());
+                }
+            }
+        }
+
+        if args.load.is_some() {
+            game.events_sender.send(Message::LoadGame).unwrap();
+        } else if args.skip_menu {
+            game.events_sender
+                .send(Message::StartNewGame {
+                    options: args.options,
+                })
+                .unwrap();
+        }
 
         events_loop.run(move |event, _, control_flow| {
             game.process_input_event(&event);
 
             match event {
                 Event::MainEventsCleared => {
-                    let mut dt = game.time.clock.elapsed().as_secs_f64() - game.time.elapsed;
-                    while dt >= fixed_timestep as f64 {
-                        dt -= fixed_timestep as f64;
-                        game.time.elapsed += fixed_timestep as f64;
-
+                    while game.time.next_step() {
                         game.update(game.time, control_flow);
 
+                        let mut clock = Instant::now();
                         while let Some(ui_event) = game.engine.user_interface.poll_message() {
                             game.menu.handle_ui_event(&mut game.engine, &ui_event);
+                            game.console.handle_ui_message(&ui_event);
+                            game.command_menu.handle_ui_event(
+                                &mut game.engine.user_interface,
+                                &ui_event,
+                                game.level.as_ref(),
+                            );
+                            game.team_select.handle_ui_event(
+                                &mut game.engine.user_interface,
+                                &ui_event,
+                                game.level.as_ref(),
+                            );
+                            game.hud.handle_ui_message(&ui_event);
                         }
+                        game.profiler.lap("ui poll", &mut clock);
                     }
+                    // Level does not move while paused, so there is nothing to interpolate.
+                    game.interpolation_alpha = if game.time.is_paused() {
+                        1.0
+                    } else {
+                        game.time.step_fraction()
+                    };
                     if !game.running {
                         *control_flow = ControlFlow::Exit;
                     }
-                    game.engine.get_window().request_redraw();
+                    let frame_ready = game.max_fps.map_or(true, |max_fps| {
+                        game.last_render.elapsed().as_secs_f32() >= 1.0 / max_fps
+                    });
+                    if frame_ready {
+                        game.engine.get_window().request_redraw();
+                    }
                 }
                 Event::RedrawRequested(_) => {
+                    game.last_render = Instant::now();
                     game.update_statistics(game.time.elapsed);
 
-                    // <<<<< ENABLE THIS TO SHOW DEBUG GEOMETRY >>>>>
-                    if false {
+                    if game.debug_draw {
                         game.debug_render();
                     }
 
-                    // Render at max speed
+                    // Render at max speed, moving objects are interpolated between simulation
+                    // steps so every frame shows something new.
+                    if let Some(level) = game.level.as_ref() {
+                        level.begin_render(&mut game.engine, game.interpolation_alpha);
+                    }
                     game.engine.render().unwrap();
+                    if let Some(level) = game.level.as_ref() {
+                        level.end_render(&mut game.engine);
+                    }
                 }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => {
-                        game.destroy_level();
+                        game.shutdown("window closed");
                         *control_flow = ControlFlow::Exit
                     }
                     WindowEvent::Resized(new_size) => {
                         game.engine.set_frame_size(new_size.into()).unwrap();
                     }
+                    WindowEvent::Focused(focused) => {
+                        game.set_focused(focused);
+                    }
                     _ => (),
                 },
                 Event::LoopDestroyed => {
@@ -322,42 +928,120 @@ impl Game {
         }
     }
 
+    fn set_debug_draw(&mut self, debug_draw: bool) {
+        self.debug_draw = debug_draw;
+        self.hud
+            .set_debug_watermark_visible(&mut self.engine.user_interface, debug_draw);
+        if !debug_draw {
+            // Lines are not cleared automatically, remove ones left from last debug render.
+            if let Some(level) = self.level.as_ref() {
+                self.engine.scenes[level.scene]
+                    .drawing_context
+                    .clear_lines();
+            }
+        }
+    }
+
     pub fn create_debug_ui(&mut self) {
         self.debug_text = TextBuilder::new(WidgetBuilder::new().with_width(400.0))
             .build(&mut self.engine.user_interface.build_ctx());
     }
 
+    fn create_error_box(&mut self) {
+        self.error_box = MessageBoxBuilder::new(
+            WindowBuilder::new(WidgetBuilder::new().with_width(400.0).with_height(140.0))
+                .open(false)
+                .with_title(WindowTitle::text("Error")),
+        )
+        .with_buttons(MessageBoxButtons::Ok)
+        .build(&mut self.engine.user_interface.build_ctx());
+    }
+
+    /// Tells player which data files are missing, the game falls back to defaults for them
+    /// but looks or sounds broken.
+    fn report_missing_assets(&mut self) {
+        let missing = STARTUP_ASSETS
+            .iter()
+            .filter(|path| !Path::new(path).exists())
+            .copied()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return;
+        }
+
+        Logger::writeln(
+            MessageKind::Warning,
+            format!("Missing data files: {}", missing.join(", ")),
+        );
+        self.show_error(
+            "Missing data files",
+            format!(
+                "Some data files are missing, the game may look or sound wrong: {}",
+                missing.join(", ")
+            ),
+        );
+    }
+
+    fn show_error<S: AsRef<str>>(&mut self, title: &str, text: S) {
+        self.engine
+            .user_interface
+            .send_message(MessageBoxMessage::open(
+                self.error_box,
+                MessageDirection::ToWidget,
+                Some(title.to_owned()),
+                Some(text.as_ref().to_owned()),
+            ));
+    }
+
     pub fn save_game(&mut self) -> VisitResult {
         if let Some(level) = self.level.as_mut() {
             let mut visitor = Visitor::new();
 
+            let mut version = SAVE_VERSION;
+            version.visit("Version", &mut visitor)?;
+
+            let mut elapsed = self.time.elapsed;
+            elapsed.visit("Elapsed", &mut visitor)?;
+
+            level.prepare_for_save(&mut self.engine);
             self.engine.scenes[level.scene].save("Scene", &mut visitor)?;
             level.visit("Level", &mut visitor)?;
 
             // Debug output
-            if let Ok(mut file) = File::create(Path::new("save.txt")) {
+            if let Ok(mut file) = File::create(format!("{}.txt", self.save_slot)) {
                 file.write_all(visitor.save_text().as_bytes()).unwrap();
             }
 
-            visitor.save_binary(Path::new("save.bin"))
+            save_binary_atomic(&visitor, Path::new(&format!("{}.bin", self.save_slot)))
         } else {
             Ok(())
         }
     }
 
-    pub fn load_game(&mut self) -> VisitResult {
-        Log::writeln(
+    pub fn load_game(&mut self) -> Result<(), LoadError> {
+        Logger::writeln(
             MessageKind::Information,
             "Attempting load a save...".to_owned(),
         );
 
-        let mut visitor = block_on(Visitor::load_binary(Path::new("save.bin")))?;
+        let mut visitor = block_on(Visitor::load_binary(Path::new(&format!(
+            "{}.bin",
+            self.save_slot
+        ))))?;
 
-        // Clean up.
-        self.destroy_level();
+        // Saves made before versioning was introduced have no version field at all, such
+        // saves are treated as version 0.
+        let mut version = 0u32;
+        let _ = version.visit("Version", &mut visitor);
+        migrate_save(&mut visitor, version)?;
+
+        // Game time must be continuous across save/load, otherwise timers stored in entities
+        // (like weapon's last shot time) will be invalid. Old saves may not have it.
+        let mut elapsed = self.time.elapsed;
+        let _ = elapsed.visit("Elapsed", &mut visitor);
 
         // Load engine state first
-        Log::writeln(
+        Logger::writeln(
             MessageKind::Information,
             "Trying to load a save file...".to_owned(),
         );
@@ -373,10 +1057,19 @@ impl Game {
 
         let mut level = Level::default();
         level.visit("Level", &mut visitor)?;
+        block_on(level.preload_resources(self.engine.resource_manager.clone()));
+
         level.scene = self.engine.scenes.add(scene);
+        if let Err(errors) = level.validate_after_load(&mut self.engine) {
+            level.destroy(&mut self.engine);
+            return Err(LoadError::Corrupted(errors));
+        }
+
+        // Clean up only when the save is fully read, so a broken save keeps current match intact.
+        self.destroy_level();
         self.level = Some(level);
 
-        Log::writeln(
+        Logger::writeln(
             MessageKind::Information,
             "Game state successfully loaded!".to_owned(),
         );
@@ -386,23 +1079,30 @@ impl Game {
 
         // Set control scheme for player.
         if let Some(level) = &mut self.level {
-            level.set_message_sender(self.events_sender.clone());
+            level.set_message_sender(MessageSender::new(self.events_sender.clone()));
             level.control_scheme = Some(self.control_scheme.clone());
+            level.preferences = Some(self.preferences.clone());
             let player = level.get_player();
             if let Actor::Player(player) = level.actors_mut().get_mut(player) {
                 player.set_control_scheme(self.control_scheme.clone());
+                player.set_preferences(self.preferences.clone());
             }
         }
 
-        self.time.elapsed = self.time.clock.elapsed().as_secs_f64();
+        self.time.reset_after_load(elapsed);
 
         Ok(())
     }
 
     fn destroy_level(&mut self) {
+        let ui = &mut self.engine.user_interface;
+        self.hud.set_match_over(ui, false);
+        self.hud.set_is_died(ui, false);
+        self.hud.set_personal_record(None);
+
         if let Some(ref mut level) = self.level.take() {
             level.destroy(&mut self.engine);
-            Log::writeln(
+            Logger::writeln(
                 MessageKind::Information,
                 "Current level destroyed!".to_owned(),
             );
@@ -412,40 +1112,175 @@ impl Game {
     pub fn start_new_game(&mut self, options: MatchOptions) {
         self.destroy_level();
 
-        let ctx = Arc::new(Mutex::new(LoadContext { level: None }));
+        self.cancel_loading();
+
+        let ctx = Arc::new(Mutex::new(LoadContext {
+            level: None,
+            cancelled: false,
+        }));
 
         self.load_context = Some(ctx.clone());
 
-        self.engine
-            .user_interface
-            .send_message(WidgetMessage::visibility(
-                self.loading_screen.root,
-                MessageDirection::ToWidget,
-                true,
-            ));
+        self.loading_screen
+            .show(&mut self.engine.user_interface, &self.map);
         self.menu
             .set_visible(&mut self.engine.user_interface, false);
 
         let resource_manager = self.engine.resource_manager.clone();
         let control_scheme = self.control_scheme.clone();
-        let sender = self.events_sender.clone();
+        let preferences = self.preferences.clone();
+        let sender = MessageSender::new(self.events_sender.clone());
+        let map = self.map.clone();
+        let bot_count = self.bot_count;
+        let seed = self.seed.unwrap_or_else(GameRng::random_seed);
+        self.match_seed = seed;
+        Logger::writeln(MessageKind::Information, format!("Match seed: {}", seed));
 
         std::thread::spawn(move || {
             let level = fyrox::core::futures::executor::block_on(Level::new(
+                map,
+                bot_count,
+                seed,
                 resource_manager,
                 control_scheme,
+                preferences,
                 sender,
                 options,
             ));
 
-            ctx.lock().unwrap().level = Some(level);
+            let mut ctx = ctx.lock().unwrap();
+            if ctx.cancelled {
+                Logger::writeln(
+                    MessageKind::Information,
+                    "Level loading was cancelled, loaded level is discarded.".to_owned(),
+                );
+            } else {
+                ctx.level = Some(level);
+            }
         });
     }
 
+    /// Abandons level that is being loaded and returns to main menu.
+    fn cancel_loading(&mut self) {
+        if let Some(ctx) = self.load_context.take() {
+            // Loading thread may hold the lock, it will see the flag when it finishes.
+            ctx.lock().unwrap().cancelled = true;
+            self.loading_screen
+                .set_visible(&mut self.engine.user_interface, false);
+            self.set_menu_visible(true);
+        }
+    }
+
     pub fn set_menu_visible(&mut self, visible: bool) {
         let ui = &mut self.engine.user_interface;
         self.menu.set_visible(ui, visible);
-        self.hud.set_visible(ui, !visible);
+        // HUD stays hidden during intermission, only final results are shown.
+        let intermission = self
+            .level
+            .as_ref()
+            .map_or(false, |level| level.is_intermission());
+        self.hud.set_visible(ui, !visible && !intermission);
+        if visible {
+            self.menu.set_in_match(ui, self.level.is_some());
+            self.command_menu.set_visible(ui, false, None);
+            self.team_select.set_open(ui, false);
+            self.menu
+                .set_best_scores(ui, self.records.best_scores(&self.map));
+        } else if self.focus_paused {
+            self.focus_paused = false;
+            self.time.set_paused(false);
+        }
+    }
+
+    /// Alt-tab must not leave the match running with a grabbed cursor: the menu is opened,
+    /// which releases the cursor, and a local match is paused until the menu is closed. The
+    /// menu stays open when focus comes back. Network match can't be paused, it keeps going.
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        // Some platforms drop cursor grab together with focus, so it is applied again.
+        self.cursor_visible = None;
+        if !focused && self.level.is_some() && self.load_context.is_none() {
+            if !self.is_menu_visible() {
+                self.set_menu_visible(true);
+            }
+            if self.net.is_none() && !self.time.is_paused() {
+                self.time.set_paused(true);
+                self.focus_paused = true;
+            }
+        }
+        self.engine.scenes[self.menu_scene]
+            .graph
+            .sound_context
+            .pause(!focused);
+    }
+
+    /// Applies balance files that were edited since last check. Broken file is reported on
+    /// HUD and its previous version stays in use.
+    fn reload_definitions(&mut self) {
+        let outcome = self.definitions_watcher.poll();
+        if outcome.reloaded {
+            if let Some(level) = self.level.as_mut() {
+                level.apply_definitions();
+            }
+        }
+        for error in outcome.errors {
+            self.events_sender
+                .send(Message::AddNotification {
+                    text: error,
+                    severity: NotificationSeverity::Important,
+                    icon: None,
+                })
+                .unwrap();
+        }
+    }
+
+    /// Puts result of local player into personal records, final results show how it compares
+    /// to the best one. Network client does not keep records, it only shows host's match.
+    fn record_match_result(&mut self) {
+        let level = match self.level.as_ref() {
+            Some(level) if !level.is_mirror() => level,
+            _ => return,
+        };
+        let result = MatchResult {
+            score: level
+                .leader_board
+                .values()
+                .get(PLAYER_NAME)
+                .map_or(0, |score| score.kills),
+            accuracy: level.player_accuracy(),
+            duration_secs: level.time(),
+        };
+        let key = RecordKey::new(&self.map, &level.options);
+        let map = key.map.clone();
+        let outcome = self.records.submit(key, result);
+        if let Some(stats) = level.leader_board.weapon_stats(PLAYER_NAME) {
+            self.records.add_weapon_stats(stats);
+        }
+        self.records.save();
+
+        let text = match outcome.previous_best {
+            Some(best) if outcome.new_best => format!(
+                "New personal best: {} frags, previous best was {}",
+                result.score, best
+            ),
+            Some(best) => format!(
+                "Personal best: {} frags, {} frags this time",
+                best, result.score
+            ),
+            None => format!("Personal best: {} frags", result.score),
+        };
+        self.hud.set_personal_record(Some(text));
+
+        if outcome.new_best {
+            level.play_intermission_sound(&self.engine, level::PERSONAL_BEST_SOUND);
+            self.events_sender
+                .send(Message::AddNotification {
+                    text: format!("New personal best on {}: {} frags!", map, result.score),
+                    severity: NotificationSeverity::Important,
+                    icon: None,
+                })
+                .unwrap();
+        }
     }
 
     pub fn is_menu_visible(&self) -> bool {
@@ -453,128 +1288,547 @@ impl Game {
     }
 
     pub fn update(&mut self, time: GameTime, control_flow: &mut ControlFlow) {
-        let window = self.engine.get_window();
-        window.set_cursor_visible(self.is_menu_visible());
-        let _ = window.set_cursor_grab(if !self.is_menu_visible() {
-            CursorGrabMode::Confined
-        } else {
-            CursorGrabMode::None
-        });
+        let cursor_visible = self.is_menu_visible()
+            || self.console.is_visible()
+            || self.command_menu.is_visible()
+            || self.team_select.is_open();
+        // Cursor is changed only when needed, some window managers make it flicker otherwise.
+        if self.cursor_visible != Some(cursor_visible) {
+            self.cursor_visible = Some(cursor_visible);
+            let window = self.engine.get_window();
+            window.set_cursor_visible(cursor_visible);
+            let _ = window.set_cursor_grab(if !cursor_visible {
+                CursorGrabMode::Confined
+            } else {
+                CursorGrabMode::None
+            });
+        }
 
         if let Some(ctx) = self.load_context.clone() {
             if let Ok(mut ctx) = ctx.try_lock() {
-                if let Some((mut level, scene)) = ctx.level.take() {
-                    level.scene = self.engine.scenes.add(scene);
-                    self.level = Some(level);
-                    self.load_context = None;
-                    self.set_menu_visible(false);
-                    self.engine
-                        .user_interface
-                        .send_message(WidgetMessage::visibility(
-                            self.loading_screen.root,
-                            MessageDirection::ToWidget,
-                            false,
-                        ));
-                } else {
-                    self.engine
-                        .user_interface
-                        .send_message(ProgressBarMessage::progress(
-                            self.loading_screen.progress_bar,
-                            MessageDirection::ToWidget,
-                            self.engine.resource_manager.state().loading_progress() as f32 / 100.0,
-                        ));
+                match ctx.level.take() {
+                    Some(Ok((mut level, scene))) => {
+                        level.scene = self.engine.scenes.add(scene);
+                        if self.is_client() {
+                            level.set_mirror(true);
+                        }
+                        let team_mode = level.options.is_team_mode();
+                        observer::dispatch(
+                            &mut self.observers,
+                            &GameEvent::MatchStart {
+                                map: self.map.clone(),
+                                mode: MatchOptions::MODE_NAMES[level.options.mode_index()],
+                            },
+                        );
+                        self.level = Some(level);
+                        self.load_context = None;
+                        self.set_menu_visible(false);
+                        if team_mode && !self.is_client() {
+                            self.team_select
+                                .set_open(&mut self.engine.user_interface, true);
+                        }
+                        self.loading_screen
+                            .set_visible(&mut self.engine.user_interface, false);
+                        if self
+                            .net
+                            .as_ref()
+                            .map_or(false, |net| net.is_host() && net.is_connected())
+                        {
+                            // Client is already here, tell it about new match.
+                            self.welcome_guest();
+                        }
+                    }
+                    Some(Err(e)) => {
+                        Logger::writeln(
+                            MessageKind::Error,
+                            format!("Failed to load level. Reason: {}", e),
+                        );
+                        self.load_context = None;
+                        self.loading_screen
+                            .set_visible(&mut self.engine.user_interface, false);
+                        self.set_menu_visible(true);
+                        self.show_error("Unable to load a level", e);
+                    }
+                    None => {
+                        let progress =
+                            self.engine.resource_manager.state().loading_progress() as f32 / 100.0;
+                        self.loading_screen
+                            .update(&mut self.engine.user_interface, progress);
+                    }
                 }
             }
         }
 
-        self.engine.update(time.delta, control_flow);
+        let mut clock = Instant::now();
+        self.engine.update(time.step, control_flow);
+        self.profiler.lap("engine", &mut clock);
+
+        self.update_network();
+        self.profiler.lap("network", &mut clock);
+
+        if self.level.is_some() {
+            self.reload_definitions();
+        }
 
         if let Some(ref mut level) = self.level {
-            level.update(&mut self.engine, time);
-            let ui = &mut self.engine.user_interface;
-            self.hud.set_time(ui, level.time());
-            let player = level.get_player();
-            if player.is_some() {
-                // Sync hud with player state.
-                let player = level.actors().get(player);
-                self.hud.set_health(ui, player.get_health());
-                self.hud.set_armor(ui, player.get_armor());
-                let current_weapon = player.current_weapon();
-                if current_weapon.is_some() {
-                    self.hud
-                        .set_ammo(ui, level.weapons()[current_weapon].ammo());
+            if let Some(net) = self.net.as_mut() {
+                if !net.is_host() {
+                    if let Some(input) = level.local_input() {
+                        net.send(&Packet::Input(input));
+                    }
                 }
-                self.hud.set_is_died(ui, false);
+            }
+
+            level.sync_time_scale(&mut self.engine, &time, self.focused);
+            if time.is_paused() {
+                level.update_paused(&mut self.engine, time.step);
             } else {
-                self.hud.set_is_died(ui, true);
+                level.update(&mut self.engine, time, &mut self.profiler);
+            }
+
+            if let Some(net) = self.net.as_mut() {
+                if net.is_host() && net.is_connected() {
+                    net.send(&Packet::Snapshot(level.snapshot(&self.engine)));
+                }
+            }
+            clock = Instant::now();
+            let health_bars = level.health_bars(&self.engine, &time);
+            let item_timers = level.item_timers(&self.engine);
+            let ui = &mut self.engine.user_interface;
+            {
+                let preferences = self.preferences.read().unwrap();
+                self.hud
+                    .set_show_pickup_notifications(preferences.show_pickup_notifications);
+                self.hud
+                    .set_show_damage_numbers(preferences.show_damage_numbers);
+                self.hud.set_show_health_bars(preferences.show_health_bars);
+                self.hud
+                    .set_show_bot_intents(ui, preferences.show_bot_intents);
+            }
+            let status = level.status();
+            self.hud.sync(ui, &status);
+            self.hud.set_paused(ui, time.is_paused());
+            self.hud.set_crosshair(
+                ui,
+                status
+                    .player
+                    .as_ref()
+                    .and_then(|player| player.current_weapon()),
+            );
+            let camera = match level.actors().try_get(level.get_player()) {
+                Some(Actor::Player(player)) => {
+                    self.engine.scenes[level.scene].graph[player.camera()].cast::<Camera>()
+                }
+                _ => None,
+            };
+            self.hud.update_damage_numbers(
+                ui,
+                camera,
+                self.engine.renderer.get_frame_size(),
+                time.delta,
+            );
+            self.hud.update_health_bars(
+                ui,
+                camera,
+                self.engine.renderer.get_frame_size(),
+                &health_bars,
+            );
+            let spectator_camera = level
+                .spectator_view()
+                .and_then(|camera| self.engine.scenes[level.scene].graph.try_get(camera))
+                .and_then(|camera| camera.cast::<Camera>());
+            self.hud.update_item_timers(
+                ui,
+                spectator_camera,
+                self.engine.renderer.get_frame_size(),
+                &item_timers,
+            );
+            if self.hud.take_low_ammo_click() {
+                level.play_player_sound(&self.engine, level::LOW_AMMO_SOUND, 0.3);
             }
         }
 
+        self.profiler.lap("hud sync", &mut clock);
+
         self.handle_messages(time);
+        self.profiler.lap("messages", &mut clock);
 
         self.hud.update(&mut self.engine.user_interface, &self.time);
+        self.profiler.lap("hud", &mut clock);
     }
 
     fn handle_messages(&mut self, time: GameTime) {
         while let Ok(message) = self.events_receiver.try_recv() {
-            match &message {
-                Message::StartNewGame { options } => {
-                    self.start_new_game(*options);
+            match message.category() {
+                MessageCategory::Game => self.handle_game_message(&message),
+                MessageCategory::Sound => {
+                    if let Some(ref mut level) = self.level {
+                        level.handle_sound_message(&mut self.engine, &message);
+                    }
                 }
-                Message::SaveGame => match self.save_game() {
-                    Ok(_) => {
-                        Log::writeln(MessageKind::Information, "Successfully saved".to_owned())
+                MessageCategory::Notification => self.hud.handle_message(&message),
+                MessageCategory::Gameplay => {
+                    if let Some(ref mut level) = self.level {
+                        fyrox::core::futures::executor::block_on(level.handle_message(
+                            &mut self.engine,
+                            &message,
+                            time,
+                        ));
+
+                        self.hud.handle_message(&message);
                     }
-                    Err(e) => Log::writeln(
-                        MessageKind::Error,
-                        format!("Failed to make a save, reason: {}", e),
+                }
+            }
+
+            if let Some(net) = self.net.as_mut() {
+                let replicate = if net.is_host() {
+                    true
+                } else {
+                    // Client has nothing to tell host except what its player says.
+                    matches!(&message, Message::ChatMessage { sender_name, .. } if sender_name == PLAYER_NAME)
+                };
+                if replicate {
+                    net.send(&Packet::Message(message));
+                }
+            }
+        }
+
+        self.flush_leader_board();
+    }
+
+    /// Registers a listener of match events, observers are called in order of registration.
+    pub fn add_observer(&mut self, observer: Box<dyn GameObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn is_client(&self) -> bool {
+        self.net.as_ref().map_or(false, |net| !net.is_host())
+    }
+
+    /// Tells client which match to load and gives it an actor on host's level.
+    fn welcome_guest(&mut self) {
+        if let (Some(net), Some(level)) = (self.net.as_mut(), self.level.as_mut()) {
+            net.send(&Packet::Welcome {
+                map: self.map.clone(),
+                seed: self.match_seed,
+                bot_count: self.bot_count as u32,
+            });
+            block_on(level.connect_guest(&mut self.engine));
+        }
+    }
+
+    fn update_network(&mut self) {
+        let events = match self.net.as_mut() {
+            Some(net) => net.poll(),
+            None => return,
+        };
+
+        for event in events {
+            let is_host = self.net.as_ref().map_or(false, |net| net.is_host());
+            match event {
+                NetEvent::Connected => {
+                    if self.level.is_some() {
+                        self.welcome_guest();
+                    }
+                    // Otherwise client is welcomed when host starts a match.
+                }
+                NetEvent::Packet(packet) => match packet {
+                    Packet::Welcome {
+                        map,
+                        seed,
+                        bot_count,
+                    } if !is_host => {
+                        self.map = map;
+                        self.seed = Some(seed);
+                        self.bot_count = bot_count as usize;
+                        self.start_new_game(Default::default());
+                    }
+                    Packet::Snapshot(states) if !is_host => {
+                        if let Some(level) = self.level.as_mut() {
+                            level.apply_snapshot(&mut self.engine, &states);
+                        }
+                    }
+                    Packet::Input(input) if is_host => {
+                        if let Some(level) = self.level.as_mut() {
+                            level.apply_guest_input(&input);
+                        }
+                    }
+                    Packet::Message(mut message) => {
+                        if let Message::ChatMessage { sender_name, .. } = &mut message {
+                            if is_host {
+                                *sender_name = GUEST_NAME.to_owned();
+                            } else if sender_name == GUEST_NAME {
+                                // Own line that came back from host, it's already shown.
+                                continue;
+                            } else if sender_name == PLAYER_NAME {
+                                *sender_name = HOST_NAME.to_owned();
+                            }
+                        }
+                        self.events_sender.send(message).unwrap();
+                    }
+                    _ => Logger::writeln(
+                        MessageKind::Warning,
+                        "Unexpected packet received, ignoring it.".to_owned(),
                     ),
                 },
-                Message::LoadGame => {
-                    if let Err(e) = self.load_game() {
-                        Log::writeln(
-                            MessageKind::Error,
-                            format!("Failed to load saved game. Reason: {:?}", e),
-                        );
+                NetEvent::Disconnected(reason) => {
+                    if is_host {
+                        if let Some(level) = self.level.as_mut() {
+                            level.disconnect_guest();
+                        }
+                    } else {
+                        self.net = None;
+                        self.cancel_loading();
+                        self.destroy_level();
+                        self.set_menu_visible(true);
+                        self.show_error("Disconnected from host", reason);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Persists everything that must survive the exit and tears the match down. Panics are
+    /// handled separately, see [`Settings::flush_on_panic`].
+    fn shutdown(&mut self, reason: &str) {
+        Logger::writeln(
+            MessageKind::Information,
+            format!("Shutting down: {}.", reason),
+        );
+        // Bindings and preferences edited in options menu are saved only when it is closed.
+        self.settings.controls = self.menu.pending_control_profiles();
+        self.settings.preferences = self.preferences.read().unwrap().clone();
+        self.settings.save();
+        self.shutdown_network();
+        self.destroy_level();
+    }
+
+    fn shutdown_network(&mut self) {
+        if let Some(mut net) = self.net.take() {
+            net.shutdown();
+        }
+    }
+
+    fn flush_leader_board(&mut self) {
+        if let Some(level) = self.level.as_ref() {
+            self.hud.flush_leader_board(
+                &mut self.engine.user_interface,
+                &level.leader_board,
+                &level.options,
+            );
+        }
+    }
+
+    /// Writes heatmap of current level to `heatmap_<map>.csv` in user data directory.
+    fn dump_heatmap(&mut self) {
+        let ui = &mut self.engine.user_interface;
+        let heatmap = match self.level.as_ref().and_then(|level| level.heatmap()) {
+            Some(heatmap) => heatmap,
+            None => {
+                self.console
+                    .print(ui, "Heatmap is empty, start recording with \"heatmap on\".");
+                return;
+            }
+        };
+        let map = self.map.file_stem().map_or_else(
+            || "map".to_owned(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        let path = user_data_dir().join(format!("heatmap_{}.csv", map));
+        match heatmap.write_csv(&path) {
+            Ok(_) => self.console.print(
+                ui,
+                format!(
+                    "{} samples written to {}{}",
+                    heatmap.sample_count(),
+                    path.display(),
+                    if heatmap.is_recording() {
+                        ", recording goes on."
+                    } else {
+                        "."
+                    }
+                ),
+            ),
+            Err(e) => self.console.print(
+                ui,
+                format!("Unable to write heatmap to {}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    fn handle_game_message(&mut self, message: &Message) {
+        match message {
+            Message::StartNewGame { options } => {
+                self.start_new_game(*options);
+            }
+            Message::SaveGame => match self.save_game() {
+                Ok(_) => Logger::writeln(MessageKind::Information, "Successfully saved".to_owned()),
+                Err(e) => Logger::writeln(
+                    MessageKind::Error,
+                    format!("Failed to make a save, reason: {}", e),
+                ),
+            },
+            Message::LoadGame => {
+                if let Err(e) = self.load_game() {
+                    Logger::writeln(
+                        MessageKind::Error,
+                        format!("Failed to load saved game. Reason: {}", e),
+                    );
+                    self.show_error("Unable to load a save", e.to_string());
+                }
+            }
+            Message::QuitGame => {
+                self.shutdown_network();
+                self.destroy_level();
+                self.running = false;
+            }
+            Message::EndMatch => {
+                // Level stays alive behind final results until new match is started or
+                // menu loads something else, camera flies around the map meanwhile.
+                self.flush_leader_board();
+                if let Some(level) = self.level.as_mut() {
+                    level.begin_intermission(&mut self.engine);
+                    let scores = level
+                        .leader_board
+                        .sorted_values()
+                        .into_iter()
+                        .map(|(name, score)| (name.to_owned(), score.kills))
+                        .collect();
+                    observer::dispatch(&mut self.observers, &GameEvent::MatchEnd { scores });
+                }
+                self.record_match_result();
+                let ui = &mut self.engine.user_interface;
+                self.hud.set_visible(ui, false);
+                self.hud.set_match_over(ui, true);
+                self.command_menu.set_visible(ui, false, None);
+                self.team_select.set_open(ui, false);
+            }
+            Message::ShowMapIssues => {
+                let ui = &mut self.engine.user_interface;
+                match self.level.as_ref() {
+                    None => self.console.print(ui, "There is no level."),
+                    Some(level) if level.map_issues().is_empty() => {
+                        self.console.print(ui, "No issues found.")
+                    }
+                    Some(level) => {
+                        for issue in level.map_issues() {
+                            self.console.print(ui, format!("- {}", issue));
+                        }
                     }
                 }
-                Message::QuitGame => {
-                    self.destroy_level();
-                    self.running = false;
+            }
+            Message::DumpHeatmap => self.dump_heatmap(),
+            Message::ResumeGame => {
+                self.set_menu_visible(false);
+            }
+            Message::ReportEvent { event } => observer::dispatch(&mut self.observers, event),
+            Message::RestartMatch => {
+                // Client can't change the match, host runs it.
+                let options = self
+                    .level
+                    .as_ref()
+                    .filter(|_| !self.is_client())
+                    .map(|level| level.options);
+                if let Some(options) = options {
+                    self.start_new_game(options);
                 }
-                Message::EndMatch => {
-                    self.destroy_level();
-                    self.hud
-                        .leader_board()
-                        .set_visible(true, &mut self.engine.user_interface);
+            }
+            Message::LeaveMatch => {
+                // Leaving a network match ends the session, there is nothing to come back to.
+                self.shutdown_network();
+                self.destroy_level();
+                self.set_menu_visible(true);
+            }
+            Message::ChangeMap { path } => {
+                self.map = path.clone();
+                let options = self
+                    .level
+                    .as_ref()
+                    .map(|level| level.options)
+                    .unwrap_or_default();
+                self.start_new_game(options);
+            }
+            Message::SaveMatchSettings { options, bot_count } => {
+                self.bot_count = *bot_count as usize;
+                self.settings.last_match = MatchSettings {
+                    options: *options,
+                    bot_count: *bot_count,
+                    map: self.map.clone(),
+                };
+                self.settings.save();
+            }
+            Message::SaveControlProfiles { profiles } => {
+                self.settings.controls = profiles.clone();
+                self.settings.save();
+            }
+            Message::SavePreferences { preferences } => {
+                self.settings.preferences = preferences.clone();
+                self.settings.save();
+            }
+            &Message::SetTimeScale { time_scale } => {
+                self.time.set_time_scale(time_scale);
+                Logger::writeln(
+                    MessageKind::Information,
+                    format!("Time scale is {}x.", self.time.time_scale()),
+                );
+            }
+            Message::TogglePause => {
+                // Host would freeze snapshots of the client, and client can't stop the host.
+                if self.net.is_some() {
+                    Logger::writeln(
+                        MessageKind::Warning,
+                        "Network match can't be paused.".to_owned(),
+                    );
+                    return;
                 }
-                Message::SetMusicVolume { volume } => {
+                // Explicit pause state wins over the one set on focus loss.
+                self.focus_paused = false;
+                self.time.set_paused(!self.time.is_paused());
+                Logger::writeln(
+                    MessageKind::Information,
+                    format!(
+                        "Game time {}.",
+                        if self.time.is_paused() {
+                            "paused"
+                        } else {
+                            "resumed"
+                        }
+                    ),
+                );
+            }
+            Message::SetMusicVolume { volume } => {
+                // Music is optional, game runs without its file.
+                if self.music.is_some() {
                     self.engine.scenes[self.menu_scene].graph[self.music]
                         .as_sound_mut()
                         .set_gain(*volume);
                 }
-                _ => (),
-            }
-
-            if let Some(ref mut level) = self.level {
-                fyrox::core::futures::executor::block_on(level.handle_message(
-                    &mut self.engine,
-                    &message,
-                    time,
-                ));
-
-                self.hud.handle_message(
-                    &message,
-                    &mut self.engine.user_interface,
-                    &level.leader_board,
-                    &level.options,
-                );
             }
+            _ => (),
         }
     }
 
     pub fn update_statistics(&mut self, elapsed: f64) {
+        // Statistics are useless if they change faster than anyone can read them.
+        if let Some(time) = self.statistics_update_time {
+            if time.elapsed() < STATISTICS_UPDATE_INTERVAL {
+                return;
+            }
+        }
+        self.statistics_update_time = Some(Instant::now());
+
         self.debug_string.clear();
+
+        if self.debug_overlay == DebugOverlay::Off {
+            self.engine.user_interface.send_message(TextMessage::text(
+                self.debug_text,
+                MessageDirection::ToWidget,
+                Default::default(),
+            ));
+            return;
+        }
+
         use std::fmt::Write;
         let statistics = self.engine.renderer.get_statistics();
         write!(
@@ -596,6 +1850,10 @@ impl Game {
         )
         .unwrap();
 
+        if self.debug_overlay == DebugOverlay::FullProfile {
+            write!(self.debug_string, "\n\n{}", self.profiler).unwrap();
+        }
+
         self.engine.user_interface.send_message(TextMessage::text(
             self.debug_text,
             MessageDirection::ToWidget,
@@ -610,7 +1868,7 @@ impl Game {
             }
         }
 
-        if !self.is_menu_visible() {
+        if !self.is_menu_visible() && !self.console.is_visible() {
             if let Some(ref mut level) = self.level {
                 level.process_input_event(event);
             }
@@ -618,14 +1876,133 @@ impl Game {
     }
 
     pub fn process_input_event(&mut self, event: &Event<()>) {
+        if self.ui_layout.process_event(event) {
+            let size = self.ui_layout.size();
+            let ui = &mut self.engine.user_interface;
+            self.hud.resize(ui, size);
+            self.menu.resize(ui, size);
+            self.console.resize(ui, size);
+        }
+
+        let player = self
+            .level
+            .as_ref()
+            .map(|level| level.get_player())
+            .unwrap_or_default();
+        if self
+            .console
+            .process_input_event(&mut self.engine, event, player)
+        {
+            return;
+        }
+        // Console captures keyboard while it is open, only UI gets events to type into it.
+        if self.console.is_visible() {
+            self.process_dispatched_event(event);
+            return;
+        }
+
+        let in_match = self
+            .level
+            .as_ref()
+            .map_or(false, |level| !level.is_intermission());
+        if in_match && !self.is_menu_visible() {
+            // Client can't change the match, host runs it.
+            if !self.is_client() {
+                if let Some(level) = self.level.as_ref() {
+                    let command_menu_button =
+                        self.control_scheme.read().unwrap().command_menu.button;
+                    self.command_menu.process_input_event(
+                        &mut self.engine.user_interface,
+                        event,
+                        command_menu_button,
+                        level,
+                    );
+                }
+            }
+
+            let chat_button = self.control_scheme.read().unwrap().chat.button;
+            let consumed = self.hud.process_chat_input(
+                &mut self.engine.user_interface,
+                event,
+                chat_button,
+                &self.events_sender,
+            );
+            if let Some(level) = self.level.as_mut() {
+                // Player must not shoot while clicking buttons of command menu or team select.
+                level.set_ui_has_keyboard_focus(
+                    self.hud.is_chat_active()
+                        || self.command_menu.is_visible()
+                        || self.team_select.is_open(),
+                );
+            }
+            if consumed {
+                return;
+            }
+        }
+
         self.process_dispatched_event(event);
 
+        // Scoreboard is shown while its button is held, mouse buttons can be bound too.
+        let scoreboard_button = self.control_scheme.read().unwrap().show_scoreboard.button;
+        let scoreboard_state = match event {
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } if input.virtual_keycode.map(ControlButton::Key) == Some(scoreboard_button) => {
+                Some(input.state)
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::Button { button, state },
+                ..
+            } if ControlButton::Mouse(*button as u16) == scoreboard_button => Some(*state),
+            _ => None,
+        };
+        if let Some(state) = scoreboard_state {
+            self.events_sender
+                .send(Message::SetScoreboardVisible {
+                    visible: state == ElementState::Pressed,
+                })
+                .unwrap();
+        }
+
         if let Event::WindowEvent { event, .. } = event {
             if let WindowEvent::KeyboardInput { input, .. } = event {
                 if let ElementState::Pressed = input.state {
                     if let Some(key) = input.virtual_keycode {
                         if key == VirtualKeyCode::Escape {
-                            self.set_menu_visible(!self.is_menu_visible());
+                            if self.load_context.is_some() {
+                                self.cancel_loading();
+                            } else {
+                                self.set_menu_visible(!self.is_menu_visible());
+                            }
+                        } else {
+                            let button = ControlButton::Key(key);
+                            let (free_camera, time_scale, pause, overlay, debug_draw) = {
+                                let control_scheme = self.control_scheme.read().unwrap();
+                                (
+                                    control_scheme.toggle_free_camera.button,
+                                    control_scheme.cycle_time_scale.button,
+                                    control_scheme.toggle_pause.button,
+                                    control_scheme.cycle_debug_overlay.button,
+                                    control_scheme.toggle_debug_draw.button,
+                                )
+                            };
+                            if button == free_camera {
+                                self.events_sender.send(Message::ToggleFreeCamera).unwrap();
+                            } else if button == time_scale {
+                                self.events_sender
+                                    .send(Message::SetTimeScale {
+                                        time_scale: self.time.next_time_scale(),
+                                    })
+                                    .unwrap();
+                            } else if button == pause {
+                                self.events_sender.send(Message::TogglePause).unwrap();
+                            } else if button == overlay {
+                                self.debug_overlay = self.debug_overlay.next();
+                                self.statistics_update_time = None;
+                            } else if button == debug_draw {
+                                self.set_debug_draw(!self.debug_draw);
+                            }
                         }
                     }
                 }
@@ -633,10 +2010,111 @@ impl Game {
         }
 
         self.menu.process_input_event(&mut self.engine, &event);
-        self.hud.process_event(&mut self.engine, &event);
     }
 }
 
 fn main() {
-    Game::run();
+    let args = match CommandLineArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    Logger::init(args.verbose);
+
+    Game::run(args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 1.0 / 60.0;
+
+    fn steps(time_scale: f32, paused: bool, count: usize) -> GameTime {
+        let mut time = GameTime::new(STEP);
+        time.set_time_scale(time_scale);
+        time.set_paused(paused);
+        for _ in 0..count {
+            time.advance();
+        }
+        time
+    }
+
+    #[test]
+    fn zero_scale_stops_game_time_like_pause() {
+        let frozen = steps(0.0, false, 60);
+        assert_eq!(frozen.delta, 0.0);
+        assert_eq!(frozen.elapsed, 0.0);
+
+        let paused = steps(1.0, true, 60);
+        assert_eq!(paused.delta, 0.0);
+        assert_eq!(paused.elapsed, 0.0);
+    }
+
+    #[test]
+    fn half_scale_runs_at_half_speed() {
+        let time = steps(0.5, false, 60);
+        assert_eq!(time.delta, STEP * 0.5);
+        assert!((time.elapsed - 0.5).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn double_scale_runs_at_double_speed() {
+        let time = steps(2.0, false, 60);
+        assert_eq!(time.time_scale(), 2.0);
+        assert_eq!(time.delta, STEP * 2.0);
+        assert!((time.elapsed - 2.0).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn time_scale_is_clamped() {
+        let mut time = GameTime::new(STEP);
+        time.set_time_scale(-1.0);
+        assert_eq!(time.time_scale(), 0.0);
+        time.set_time_scale(100.0);
+        assert_eq!(time.time_scale(), MAX_TIME_SCALE);
+    }
+
+    #[test]
+    fn pause_does_not_lose_time_scale() {
+        let mut time = steps(0.5, true, 10);
+        time.set_paused(false);
+        time.advance();
+        assert_eq!(time.delta, STEP * 0.5);
+    }
+
+    #[test]
+    fn load_continues_level_time_without_catch_up() {
+        let mut time = steps(1.0, false, 30);
+        // Loading took a while of wall clock, it must not be simulated afterwards.
+        time.clock = Instant::now() - std::time::Duration::from_secs(10);
+        time.reset_after_load(42.0);
+        assert_eq!(time.elapsed, 42.0);
+
+        let mut catch_up = 0;
+        while time.next_step() {
+            catch_up += 1;
+        }
+        assert!(catch_up < 10);
+        assert!((time.elapsed - (42.0 + f64::from(STEP) * catch_up as f64)).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn unversioned_saves_are_migrated_and_newer_ones_are_rejected() {
+        let mut visitor = Visitor::new();
+        assert!(migrate_save(&mut visitor, 0).is_ok());
+        assert!(migrate_save(&mut visitor, SAVE_VERSION).is_ok());
+        assert!(matches!(
+            migrate_save(&mut visitor, SAVE_VERSION + 1),
+            Err(LoadError::IncompatibleVersion(_))
+        ));
+    }
 }
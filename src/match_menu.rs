@@ -1,7 +1,9 @@
 use crate::{
-    gui::{create_scroll_bar, ScrollBarData},
+    gui::{create_check_box, create_scroll_bar, ScrollBarData},
     message::Message,
-    DeathMatch, MatchOptions,
+    mutators::Mutators,
+    settings::MatchSettings,
+    MatchOptions,
 };
 use fyrox::{
     core::pool::Handle,
@@ -9,12 +11,13 @@ use fyrox::{
     gui::{
         border::BorderBuilder,
         button::{ButtonBuilder, ButtonMessage},
+        check_box::{CheckBox, CheckBoxMessage},
         decorator::DecoratorBuilder,
-        dropdown_list::DropdownListBuilder,
+        dropdown_list::{DropdownList, DropdownListBuilder, DropdownListMessage},
         grid::{Column, GridBuilder, Row},
-        message::UiMessage,
-        scroll_bar::ScrollBar,
-        text::TextBuilder,
+        message::{MessageDirection, UiMessage},
+        scroll_bar::{ScrollBar, ScrollBarMessage},
+        text::{TextBuilder, TextMessage},
         text_box::TextBoxBuilder,
         widget::WidgetBuilder,
         window::{WindowBuilder, WindowTitle},
@@ -23,23 +26,44 @@ use fyrox::{
 };
 use std::sync::mpsc::Sender;
 
+/// Upper bound of bot count slider.
+const MAX_BOT_COUNT: u32 = 12;
+
 pub struct MatchMenu {
     sender: Sender<Message>,
     pub window: Handle<UiNode>,
+    dd_mode: Handle<UiNode>,
     sb_frag_limit: Handle<UiNode>,
     sb_time_limit: Handle<UiNode>,
+    sb_bot_count: Handle<UiNode>,
+    /// Check boxes of mutators in the order of [`Mutators::NAMES`].
+    cb_mutators: Vec<Handle<UiNode>>,
+    personal_best: Handle<UiNode>,
+    reset_button: Handle<UiNode>,
     start_button: Handle<UiNode>,
+    /// Best scores of local player on current map for every mode, see
+    /// [`crate::records::Records::best_scores`].
+    best_scores: Vec<Option<u32>>,
 }
 
 impl MatchMenu {
-    pub fn new(ui: &mut UserInterface, sender: Sender<Message>) -> Self {
+    pub fn new(
+        ui: &mut UserInterface,
+        sender: Sender<Message>,
+        last_match: &MatchSettings,
+    ) -> Self {
         let common_row = Row::strict(36.0);
 
         let ctx = &mut ui.build_ctx();
+        let dd_mode;
         let sb_frag_limit;
         let sb_time_limit;
+        let sb_bot_count;
+        let mut cb_mutators = Vec::new();
+        let personal_best;
+        let reset_button;
         let start_button;
-        let window = WindowBuilder::new(WidgetBuilder::new().with_width(500.0))
+        let window = WindowBuilder::new(WidgetBuilder::new().with_width(720.0))
             .with_title(WindowTitle::text("Match Options"))
             .open(false)
             .with_content(
@@ -50,35 +74,37 @@ impl MatchMenu {
                                 .with_text("Match Type")
                                 .build(ctx),
                         )
-                        .with_child(
-                            DropdownListBuilder::new(WidgetBuilder::new().on_column(1).on_row(0))
-                                .with_items({
-                                    let mut items = Vec::new();
-                                    for mode in
-                                        ["Deathmatch", "Team Deathmatch", "Capture The Flag"].iter()
-                                    {
-                                        let item = DecoratorBuilder::new(BorderBuilder::new(
-                                            WidgetBuilder::new().with_height(30.0).with_child(
-                                                TextBuilder::new(
-                                                    WidgetBuilder::new()
-                                                        .with_horizontal_alignment(
-                                                            HorizontalAlignment::Center,
-                                                        )
-                                                        .with_vertical_alignment(
-                                                            VerticalAlignment::Center,
-                                                        ),
-                                                )
-                                                .with_text(mode)
-                                                .build(ctx),
-                                            ),
-                                        ))
-                                        .build(ctx);
-                                        items.push(item);
-                                    }
-                                    items
-                                })
-                                .build(ctx),
-                        )
+                        .with_child({
+                            dd_mode = DropdownListBuilder::new(
+                                WidgetBuilder::new().on_column(1).on_row(0),
+                            )
+                            .with_items({
+                                let mut items = Vec::new();
+                                for mode in MatchOptions::MODE_NAMES.iter() {
+                                    let item = DecoratorBuilder::new(BorderBuilder::new(
+                                        WidgetBuilder::new().with_height(30.0).with_child(
+                                            TextBuilder::new(
+                                                WidgetBuilder::new()
+                                                    .with_horizontal_alignment(
+                                                        HorizontalAlignment::Center,
+                                                    )
+                                                    .with_vertical_alignment(
+                                                        VerticalAlignment::Center,
+                                                    ),
+                                            )
+                                            .with_text(mode)
+                                            .build(ctx),
+                                        ),
+                                    ))
+                                    .build(ctx);
+                                    items.push(item);
+                                }
+                                items
+                            })
+                            .with_selected(0)
+                            .build(ctx);
+                            dd_mode
+                        })
                         .with_child(
                             TextBuilder::new(WidgetBuilder::new().on_row(1).on_column(0))
                                 .with_text("Time Limit (min)")
@@ -103,14 +129,14 @@ impl MatchMenu {
                         })
                         .with_child(
                             TextBuilder::new(WidgetBuilder::new().on_row(2).on_column(0))
-                                .with_text("Frag Limit")
+                                .with_text("Score Limit")
                                 .build(ctx),
                         )
                         .with_child({
                             sb_frag_limit = create_scroll_bar(
                                 ctx,
                                 ScrollBarData {
-                                    min: 10.0,
+                                    min: 1.0,
                                     max: 200.0,
                                     value: 30.0,
                                     step: 1.0,
@@ -123,10 +149,32 @@ impl MatchMenu {
                             );
                             sb_frag_limit
                         })
+                        .with_child(
+                            TextBuilder::new(WidgetBuilder::new().on_row(3).on_column(0))
+                                .with_text("Bots")
+                                .build(ctx),
+                        )
+                        .with_child({
+                            sb_bot_count = create_scroll_bar(
+                                ctx,
+                                ScrollBarData {
+                                    min: 0.0,
+                                    max: MAX_BOT_COUNT as f32,
+                                    value: 3.0,
+                                    step: 1.0,
+                                    row: 3,
+                                    column: 1,
+                                    margin: Thickness::uniform(2.0),
+                                    show_value: true,
+                                    orientation: Orientation::Horizontal,
+                                },
+                            );
+                            sb_bot_count
+                        })
                         .with_child(
                             TextBuilder::new(
                                 WidgetBuilder::new()
-                                    .on_row(3)
+                                    .on_row(4)
                                     .on_column(0)
                                     .with_margin(Thickness::uniform(2.0)),
                             )
@@ -137,23 +185,81 @@ impl MatchMenu {
                         .with_child(
                             TextBoxBuilder::new(
                                 WidgetBuilder::new()
-                                    .on_row(3)
+                                    .on_row(4)
                                     .on_column(1)
                                     .with_margin(Thickness::uniform(2.0)),
                             )
                             .with_text("Unnamed Player".to_owned())
                             .build(ctx),
                         )
+                        .with_child(
+                            TextBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_row(5)
+                                    .on_column(0)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_text("Personal Best")
+                            .with_vertical_text_alignment(VerticalAlignment::Center)
+                            .build(ctx),
+                        )
+                        .with_child({
+                            personal_best = TextBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_row(5)
+                                    .on_column(1)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_vertical_text_alignment(VerticalAlignment::Center)
+                            .build(ctx);
+                            personal_best
+                        })
+                        .with_child({
+                            reset_button = ButtonBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_row(6)
+                                    .on_column(0)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_text("Reset to Defaults")
+                            .build(ctx);
+                            reset_button
+                        })
                         .with_child({
                             start_button =
-                                ButtonBuilder::new(WidgetBuilder::new().on_row(4).on_column(1))
+                                ButtonBuilder::new(WidgetBuilder::new().on_row(6).on_column(1))
                                     .with_text("Start")
                                     .build(ctx);
                             start_button
-                        }),
+                        })
+                        .with_children(
+                            Mutators::NAMES
+                                .iter()
+                                .enumerate()
+                                .flat_map(|(row, name)| {
+                                    let label = TextBuilder::new(
+                                        WidgetBuilder::new()
+                                            .on_row(row)
+                                            .on_column(2)
+                                            .with_margin(Thickness::left(10.0)),
+                                    )
+                                    .with_text(*name)
+                                    .with_vertical_text_alignment(VerticalAlignment::Center)
+                                    .build(ctx);
+                                    let check_box = create_check_box(ctx, row, 3, false);
+                                    cb_mutators.push(check_box);
+                                    [label, check_box]
+                                })
+                                .collect::<Vec<_>>(),
+                        ),
                 )
                 .add_column(Column::strict(200.0))
                 .add_column(Column::stretch())
+                .add_column(Column::strict(160.0))
+                .add_column(Column::strict(40.0))
+                .add_row(common_row)
+                .add_row(common_row)
+                .add_row(common_row)
                 .add_row(common_row)
                 .add_row(common_row)
                 .add_row(common_row)
@@ -163,40 +269,137 @@ impl MatchMenu {
                 .build(ctx),
             )
             .build(ctx);
-        Self {
+
+        let mut menu = Self {
             sender,
             window,
+            dd_mode,
             sb_frag_limit,
             sb_time_limit,
+            sb_bot_count,
+            cb_mutators,
+            personal_best,
+            reset_button,
             start_button,
+            best_scores: Default::default(),
+        };
+        menu.sync_to_settings(ui, last_match);
+        menu
+    }
+
+    /// Puts given settings into widgets of the menu.
+    pub fn sync_to_settings(&mut self, ui: &mut UserInterface, settings: &MatchSettings) {
+        let options = &settings.options;
+        ui.send_message(DropdownListMessage::selection(
+            self.dd_mode,
+            MessageDirection::ToWidget,
+            Some(options.mode_index()),
+        ));
+        let sync_scroll_bar = |handle: Handle<UiNode>, value: f32| {
+            ui.send_message(ScrollBarMessage::value(
+                handle,
+                MessageDirection::ToWidget,
+                value,
+            ));
+        };
+        sync_scroll_bar(self.sb_time_limit, options.time_limit_secs() / 60.0);
+        sync_scroll_bar(self.sb_frag_limit, options.score_limit() as f32);
+        sync_scroll_bar(
+            self.sb_bot_count,
+            settings.bot_count.min(MAX_BOT_COUNT) as f32,
+        );
+        for (&check_box, &flag) in self
+            .cb_mutators
+            .iter()
+            .zip(options.mutators().flags().iter())
+        {
+            ui.send_message(CheckBoxMessage::checked(
+                check_box,
+                MessageDirection::ToWidget,
+                Some(flag),
+            ));
         }
     }
 
+    fn is_checked(ui: &UserInterface, handle: Handle<UiNode>) -> bool {
+        ui.node(handle)
+            .cast::<CheckBox>()
+            .and_then(|check_box| check_box.checked)
+            .unwrap_or(false)
+    }
+
+    /// Sets best scores on current map, menu shows the one of selected mode.
+    pub fn set_best_scores(&mut self, ui: &mut UserInterface, best_scores: Vec<Option<u32>>) {
+        self.best_scores = best_scores;
+        let mode_index = Self::selected_mode(ui, self.dd_mode);
+        self.sync_personal_best(ui, mode_index);
+    }
+
+    fn selected_mode(ui: &UserInterface, dd_mode: Handle<UiNode>) -> usize {
+        ui.node(dd_mode)
+            .cast::<DropdownList>()
+            .and_then(|dropdown_list| dropdown_list.selection())
+            .unwrap_or(0)
+    }
+
+    fn sync_personal_best(&self, ui: &mut UserInterface, mode_index: usize) {
+        let text = match self.best_scores.get(mode_index).copied().flatten() {
+            Some(score) => format!("{} frags", score),
+            None => "No record yet".to_owned(),
+        };
+        ui.send_message(TextMessage::text(
+            self.personal_best,
+            MessageDirection::ToWidget,
+            text,
+        ));
+    }
+
+    fn scroll_bar_value(ui: &UserInterface, handle: Handle<UiNode>) -> f32 {
+        ui.node(handle)
+            .cast::<ScrollBar>()
+            .map_or(0.0, |scroll_bar| scroll_bar.value())
+    }
+
     pub fn handle_ui_event(&mut self, engine: &mut Engine, message: &UiMessage) {
         let ui = &mut engine.user_interface;
 
+        if let Some(DropdownListMessage::SelectionChanged(Some(index))) = message.data() {
+            if message.destination() == self.dd_mode
+                && message.direction() == MessageDirection::FromWidget
+            {
+                self.sync_personal_best(ui, *index);
+            }
+        }
+
         if let Some(ButtonMessage::Click) = message.data() {
             if message.destination() == self.start_button {
-                let time_limit_minutes =
-                    if let Some(scroll_bar) = ui.node(self.sb_time_limit).cast::<ScrollBar>() {
-                        scroll_bar.value()
-                    } else {
-                        0.0
-                    };
-
-                let frag_limit =
-                    if let Some(scroll_bar) = ui.node(self.sb_frag_limit).cast::<ScrollBar>() {
-                        scroll_bar.value()
-                    } else {
-                        0.0
-                    };
-
-                let options = MatchOptions::DeathMatch(DeathMatch {
-                    time_limit_secs: time_limit_minutes * 60.0,
-                    frag_limit: frag_limit as u32,
-                });
+                let mode_index = Self::selected_mode(ui, self.dd_mode);
+
+                let time_limit_minutes = Self::scroll_bar_value(ui, self.sb_time_limit);
+                let frag_limit = Self::scroll_bar_value(ui, self.sb_frag_limit);
+                let bot_count = Self::scroll_bar_value(ui, self.sb_bot_count);
+
+                let mut mutators = Mutators::default();
+                for (flag, &check_box) in mutators.flags_mut().iter_mut().zip(&self.cb_mutators) {
+                    **flag = Self::is_checked(ui, check_box);
+                }
+
+                let options = MatchOptions::from_mode_index(
+                    mode_index,
+                    time_limit_minutes * 60.0,
+                    frag_limit as u32,
+                    mutators,
+                );
 
+                self.sender
+                    .send(Message::SaveMatchSettings {
+                        options,
+                        bot_count: bot_count as u32,
+                    })
+                    .unwrap();
                 self.sender.send(Message::StartNewGame { options }).unwrap();
+            } else if message.destination() == self.reset_button {
+                self.sync_to_settings(ui, &MatchSettings::default());
             }
         }
     }
@@ -1,62 +1,141 @@
 use crate::{
-    control_scheme::ControlScheme, match_menu::MatchMenu, message::Message,
+    control_scheme::{ControlProfiles, ControlScheme},
+    gui,
+    match_menu::MatchMenu,
+    message::Message,
     options_menu::OptionsMenu,
+    settings::{Preferences, Settings},
 };
-use fyrox::core::parking_lot::Mutex;
 use fyrox::{
-    core::pool::Handle,
+    core::{algebra::Vector2, pool::Handle},
     engine::Engine,
-    event::{Event, WindowEvent},
+    event::Event,
     gui::{
         button::{ButtonBuilder, ButtonMessage},
         grid::{Column, GridBuilder, Row},
         message::{MessageDirection, UiMessage},
-        ttf::{Font, SharedFont},
+        messagebox::{MessageBoxBuilder, MessageBoxButtons, MessageBoxMessage, MessageBoxResult},
+        ttf::SharedFont,
         widget::{WidgetBuilder, WidgetMessage},
         window::{WindowBuilder, WindowMessage, WindowTitle},
-        Thickness, UiNode, UserInterface,
+        BuildContext, Thickness, UiNode, UserInterface,
     },
 };
-use std::{
-    path::Path,
-    sync::{mpsc::Sender, Arc, RwLock},
-};
+use std::sync::{mpsc::Sender, Arc, RwLock};
 
 pub struct Menu {
     sender: Sender<Message>,
     root: Handle<UiNode>,
+    /// Buttons shown at startup, when there is no match.
+    main_layout: Handle<UiNode>,
+    /// Buttons shown when menu is opened during a match.
+    match_layout: Handle<UiNode>,
     btn_new_game: Handle<UiNode>,
     btn_save_game: Handle<UiNode>,
     btn_settings: Handle<UiNode>,
     btn_load_game: Handle<UiNode>,
     btn_quit_game: Handle<UiNode>,
+    btn_resume: Handle<UiNode>,
+    btn_restart_match: Handle<UiNode>,
+    btn_match_save_game: Handle<UiNode>,
+    btn_match_load_game: Handle<UiNode>,
+    btn_match_settings: Handle<UiNode>,
+    btn_leave_match: Handle<UiNode>,
+    btn_match_quit_game: Handle<UiNode>,
+    /// Asks whether player really wants to abandon current match.
+    leave_confirmation: Handle<UiNode>,
     options_menu: OptionsMenu,
     match_menu: MatchMenu,
 }
 
+fn create_button(
+    ctx: &mut BuildContext,
+    font: &SharedFont,
+    text: &str,
+    row: usize,
+) -> Handle<UiNode> {
+    ButtonBuilder::new(
+        WidgetBuilder::new()
+            .on_column(0)
+            .on_row(row)
+            .with_margin(Thickness::uniform(4.0)),
+    )
+    .with_text(text)
+    .with_font(font.clone())
+    .build(ctx)
+}
+
+/// Grid with buttons stacked in rows of equal height.
+fn create_layout(
+    ctx: &mut BuildContext,
+    buttons: &[Handle<UiNode>],
+    row_height: f32,
+    visible: bool,
+) -> Handle<UiNode> {
+    GridBuilder::new(
+        WidgetBuilder::new()
+            .with_visibility(visible)
+            .with_children(buttons.iter().copied()),
+    )
+    .add_column(Column::stretch())
+    .add_rows(buttons.iter().map(|_| Row::strict(row_height)).collect())
+    .build(ctx)
+}
+
 impl Menu {
     pub fn new(
         engine: &mut Engine,
         control_scheme: Arc<RwLock<ControlScheme>>,
+        preferences: Arc<RwLock<Preferences>>,
         sender: Sender<Message>,
+        settings: &Settings,
     ) -> Self {
         let frame_size = engine.renderer.get_frame_size();
 
-        let font: Font = fyrox::core::futures::executor::block_on(Font::from_file(
-            Path::new("data/ui/SquaresBold.ttf"),
-            31.0,
-            Font::default_char_set(),
-        ))
-        .unwrap();
-        let font = SharedFont(Arc::new(Mutex::new(font)));
-
         let ctx = &mut engine.user_interface.build_ctx();
+        let font = gui::load_font(ctx, gui::FONT_PATH, 31.0);
+
+        let btn_new_game = create_button(ctx, &font, "New Game", 0);
+        let btn_save_game = create_button(ctx, &font, "Save Game", 1);
+        let btn_load_game = create_button(ctx, &font, "Load Game", 2);
+        let btn_settings = create_button(ctx, &font, "Settings", 3);
+        let btn_quit_game = create_button(ctx, &font, "Quit", 4);
+        let main_layout = create_layout(
+            ctx,
+            &[
+                btn_new_game,
+                btn_save_game,
+                btn_load_game,
+                btn_settings,
+                btn_quit_game,
+            ],
+            75.0,
+            true,
+        );
+
+        // Match can't be abandoned by a misclick, so Resume goes first and New Game is gone.
+        let btn_resume = create_button(ctx, &font, "Resume", 0);
+        let btn_restart_match = create_button(ctx, &font, "Restart Match", 1);
+        let btn_match_save_game = create_button(ctx, &font, "Save Game", 2);
+        let btn_match_load_game = create_button(ctx, &font, "Load Game", 3);
+        let btn_match_settings = create_button(ctx, &font, "Settings", 4);
+        let btn_leave_match = create_button(ctx, &font, "Leave Match", 5);
+        let btn_match_quit_game = create_button(ctx, &font, "Quit", 6);
+        let match_layout = create_layout(
+            ctx,
+            &[
+                btn_resume,
+                btn_restart_match,
+                btn_match_save_game,
+                btn_match_load_game,
+                btn_match_settings,
+                btn_leave_match,
+                btn_match_quit_game,
+            ],
+            62.0,
+            false,
+        );
 
-        let btn_new_game;
-        let btn_settings;
-        let btn_save_game;
-        let btn_load_game;
-        let btn_quit_game;
         let root: Handle<UiNode> = GridBuilder::new(
             WidgetBuilder::new()
                 .with_width(frame_size.0 as f32)
@@ -71,73 +150,11 @@ impl Menu {
                             GridBuilder::new(
                                 WidgetBuilder::new()
                                     .with_margin(Thickness::uniform(20.0))
-                                    .with_child({
-                                        btn_new_game = ButtonBuilder::new(
-                                            WidgetBuilder::new()
-                                                .on_column(0)
-                                                .on_row(0)
-                                                .with_margin(Thickness::uniform(4.0)),
-                                        )
-                                        .with_text("New Game")
-                                        .with_font(font.clone())
-                                        .build(ctx);
-                                        btn_new_game
-                                    })
-                                    .with_child({
-                                        btn_save_game = ButtonBuilder::new(
-                                            WidgetBuilder::new()
-                                                .on_column(0)
-                                                .on_row(1)
-                                                .with_margin(Thickness::uniform(4.0)),
-                                        )
-                                        .with_text("Save Game")
-                                        .with_font(font.clone())
-                                        .build(ctx);
-                                        btn_save_game
-                                    })
-                                    .with_child({
-                                        btn_load_game = ButtonBuilder::new(
-                                            WidgetBuilder::new()
-                                                .on_column(0)
-                                                .on_row(2)
-                                                .with_margin(Thickness::uniform(4.0)),
-                                        )
-                                        .with_text("Load Game")
-                                        .with_font(font.clone())
-                                        .build(ctx);
-                                        btn_load_game
-                                    })
-                                    .with_child({
-                                        btn_settings = ButtonBuilder::new(
-                                            WidgetBuilder::new()
-                                                .on_column(0)
-                                                .on_row(3)
-                                                .with_margin(Thickness::uniform(4.0)),
-                                        )
-                                        .with_text("Settings")
-                                        .with_font(font.clone())
-                                        .build(ctx);
-                                        btn_settings
-                                    })
-                                    .with_child({
-                                        btn_quit_game = ButtonBuilder::new(
-                                            WidgetBuilder::new()
-                                                .on_column(0)
-                                                .on_row(4)
-                                                .with_margin(Thickness::uniform(4.0)),
-                                        )
-                                        .with_text("Quit")
-                                        .with_font(font)
-                                        .build(ctx);
-                                        btn_quit_game
-                                    }),
+                                    .with_child(main_layout)
+                                    .with_child(match_layout),
                             )
                             .add_column(Column::stretch())
-                            .add_row(Row::strict(75.0))
-                            .add_row(Row::strict(75.0))
-                            .add_row(Row::strict(75.0))
-                            .add_row(Row::strict(75.0))
-                            .add_row(Row::strict(75.0))
+                            .add_row(Row::stretch())
                             .build(ctx),
                         )
                         .build(ctx),
@@ -151,16 +168,41 @@ impl Menu {
         .add_column(Column::stretch())
         .build(ctx);
 
+        let leave_confirmation = MessageBoxBuilder::new(
+            WindowBuilder::new(WidgetBuilder::new().with_width(400.0).with_height(140.0))
+                .open(false)
+                .with_title(WindowTitle::text("Leave Match")),
+        )
+        .with_text("Leave current match? Unsaved progress will be lost.")
+        .with_buttons(MessageBoxButtons::YesNo)
+        .build(ctx);
+
         Self {
             sender: sender.clone(),
             root,
+            main_layout,
+            match_layout,
             btn_new_game,
             btn_settings,
             btn_save_game,
             btn_load_game,
             btn_quit_game,
-            options_menu: OptionsMenu::new(engine, control_scheme, sender.clone()),
-            match_menu: MatchMenu::new(&mut engine.user_interface, sender),
+            btn_resume,
+            btn_restart_match,
+            btn_match_save_game,
+            btn_match_load_game,
+            btn_match_settings,
+            btn_leave_match,
+            btn_match_quit_game,
+            leave_confirmation,
+            options_menu: OptionsMenu::new(
+                engine,
+                control_scheme,
+                preferences,
+                settings.controls.clone(),
+                sender.clone(),
+            ),
+            match_menu: MatchMenu::new(&mut engine.user_interface, sender, &settings.last_match),
         }
     }
 
@@ -179,52 +221,87 @@ impl Menu {
                 self.match_menu.window,
                 MessageDirection::ToWidget,
             ));
+            ui.send_message(MessageBoxMessage::close(
+                self.leave_confirmation,
+                MessageDirection::ToWidget,
+                MessageBoxResult::Cancel,
+            ));
         }
     }
 
+    /// Switches between startup buttons and buttons of a running match.
+    pub fn set_in_match(&mut self, ui: &mut UserInterface, in_match: bool) {
+        ui.send_message(WidgetMessage::visibility(
+            self.main_layout,
+            MessageDirection::ToWidget,
+            !in_match,
+        ));
+        ui.send_message(WidgetMessage::visibility(
+            self.match_layout,
+            MessageDirection::ToWidget,
+            in_match,
+        ));
+    }
+
+    pub fn set_best_scores(&mut self, ui: &mut UserInterface, best_scores: Vec<Option<u32>>) {
+        self.match_menu.set_best_scores(ui, best_scores);
+    }
+
     pub fn is_visible(&self, ui: &UserInterface) -> bool {
         ui.node(self.root).visibility()
     }
 
-    pub fn process_input_event(&mut self, engine: &mut Engine, event: &Event<()>) {
-        if let Event::WindowEvent { event, .. } = event {
-            if let WindowEvent::Resized(new_size) = event {
-                engine.user_interface.send_message(WidgetMessage::width(
-                    self.root,
-                    MessageDirection::ToWidget,
-                    new_size.width as f32,
-                ));
-                engine.user_interface.send_message(WidgetMessage::height(
-                    self.root,
-                    MessageDirection::ToWidget,
-                    new_size.height as f32,
-                ));
-            }
-        }
+    pub fn resize(&mut self, ui: &mut UserInterface, size: Vector2<f32>) {
+        gui::fit_to_window(ui, self.root, size);
+    }
+
+    /// Control profiles including bindings that are edited but not saved yet.
+    pub fn pending_control_profiles(&self) -> ControlProfiles {
+        self.options_menu.pending_profiles()
+    }
 
+    pub fn process_input_event(&mut self, engine: &mut Engine, event: &Event<()>) {
         self.options_menu.process_input_event(engine, event);
     }
 
     pub fn handle_ui_event(&mut self, engine: &mut Engine, message: &UiMessage) {
         if let Some(ButtonMessage::Click) = message.data() {
-            if message.destination() == self.btn_new_game {
+            let destination = message.destination();
+            if destination == self.btn_new_game {
                 engine.user_interface.send_message(WindowMessage::open(
                     self.match_menu.window,
                     MessageDirection::ToWidget,
                     true,
                 ));
-            } else if message.destination() == self.btn_save_game {
+            } else if destination == self.btn_save_game || destination == self.btn_match_save_game {
                 self.sender.send(Message::SaveGame).unwrap();
-            } else if message.destination() == self.btn_load_game {
+            } else if destination == self.btn_load_game || destination == self.btn_match_load_game {
                 self.sender.send(Message::LoadGame).unwrap();
-            } else if message.destination() == self.btn_quit_game {
+            } else if destination == self.btn_quit_game || destination == self.btn_match_quit_game {
                 self.sender.send(Message::QuitGame).unwrap();
-            } else if message.destination() == self.btn_settings {
+            } else if destination == self.btn_settings || destination == self.btn_match_settings {
                 engine.user_interface.send_message(WindowMessage::open(
                     self.options_menu.window,
                     MessageDirection::ToWidget,
                     true,
                 ));
+            } else if destination == self.btn_resume {
+                self.sender.send(Message::ResumeGame).unwrap();
+            } else if destination == self.btn_restart_match {
+                self.sender.send(Message::RestartMatch).unwrap();
+            } else if destination == self.btn_leave_match {
+                engine.user_interface.send_message(MessageBoxMessage::open(
+                    self.leave_confirmation,
+                    MessageDirection::ToWidget,
+                    None,
+                    None,
+                ));
+            }
+        } else if let Some(MessageBoxMessage::Close(MessageBoxResult::Yes)) = message.data() {
+            if message.destination() == self.leave_confirmation
+                && message.direction() == MessageDirection::FromWidget
+            {
+                self.sender.send(Message::LeaveMatch).unwrap();
             }
         }
 
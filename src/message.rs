@@ -8,21 +8,37 @@
 //! board know nothing about bots, it just knows the fact that bot died. In other way bot knows
 //! nothing about leader board - its can just die. Not sure if this mechanism is suitable for
 //! all kinds of games, but at least it very useful for first-person shooters.
+//!
+//! However most of messages are interesting only for a single system, so every message has
+//! a category and game dispatches it only to systems that are interested in such category.
 
 use crate::{
     actor::Actor,
-    bot::BotKind,
+    bot::{BotIntent, BotKind},
+    character::Team,
+    control_scheme::ControlProfiles,
+    damage_numbers::DamageNumberKind,
     effects::EffectKind,
     item::{Item, ItemKind},
+    logger::Logger,
+    notifications::NotificationSeverity,
+    observer::GameEvent,
     projectile::ProjectileKind,
+    settings::Preferences,
     weapon::{Weapon, WeaponKind},
     MatchOptions,
 };
-use fyrox::core::{
-    algebra::{Matrix3, Vector3},
-    pool::Handle,
+use fyrox::{
+    core::{
+        algebra::{Matrix3, Vector3},
+        pool::Handle,
+    },
+    utils::log::MessageKind,
+};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
 };
-use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum Message {
@@ -38,6 +54,19 @@ pub enum Message {
     RemoveActor {
         actor: Handle<Actor>,
     },
+    /// Kicks bot out of the match: removes its actor (or pending respawn) together with its
+    /// weapons and its leader board entry.
+    RemoveBot {
+        name: String,
+    },
+    /// Randomly splits everyone in the match into two teams, ignored in modes without teams.
+    ShuffleTeams,
+    /// Moves actor to another team, it respawns there. Rejected if teams would become
+    /// unbalanced.
+    RequestTeamChange {
+        actor: Handle<Actor>,
+        team: Team,
+    },
     /// Spawns new bot at random spawn point. Selection of spawn point can be based on some
     /// particular heuristic, leading to good selection (like do spawn at a point with least
     /// enemies nearby, which will increase survival probability)
@@ -57,6 +86,11 @@ pub enum Message {
         actor: Handle<Actor>,
         item: Handle<Item>,
     },
+    /// Drops current weapon of the actor as an item and takes weapon from given item instead.
+    SwapWeapon {
+        actor: Handle<Actor>,
+        item: Handle<Item>,
+    },
     SpawnItem {
         kind: ItemKind,
         position: Vector3<f32>,
@@ -70,6 +104,9 @@ pub enum Message {
         initial_velocity: Vector3<f32>,
         owner: Handle<Weapon>,
         basis: Matrix3<f32>,
+        /// Name credited for damage of projectiles that are not shot from a weapon, like
+        /// turret ones. `None` credits owner of the weapon.
+        shooter_name: Option<String>,
     },
     ShootWeapon {
         weapon: Handle<Weapon>,
@@ -83,25 +120,86 @@ pub enum Message {
         rolloff_factor: f32,
         radius: f32,
     },
+    /// Fire button was pressed with a weapon that is charged before it shoots, see
+    /// [`crate::weapon::WeaponCharge`].
+    ChargeWeaponStart {
+        weapon: Handle<Weapon>,
+    },
+    /// Fire button was released or fuse of charged weapon burnt down, charged weapon shoots.
+    ChargeWeaponRelease {
+        weapon: Handle<Weapon>,
+        initial_velocity: Vector3<f32>,
+    },
     ShowWeapon {
         weapon: Handle<Weapon>,
         state: bool,
     },
+    /// Puts `from` weapon away and then takes `to` weapon out, `from` can be none.
+    SwitchWeapon {
+        from: Handle<Weapon>,
+        to: Handle<Weapon>,
+    },
     DamageActor {
         actor: Handle<Actor>,
         /// Actor who damaged target actor, can be Handle::NONE if damage came from environment
         /// or not from any actor.
         who: Handle<Actor>,
+        /// Name of `who` captured when the damage source was created. Damage is credited to
+        /// this name if `who` is already gone, for example it died while its rocket was in flight.
+        who_name: Option<String>,
         amount: f32,
+        /// Weapon that dealt the damage, `None` for melee attacks and environment.
+        weapon: Option<WeaponKind>,
     },
     CreateEffect {
         kind: EffectKind,
         position: Vector3<f32>,
     },
+    /// Projectile hit something at given position, used to damage everything that is not
+    /// an actor.
+    ProjectileImpact {
+        position: Vector3<f32>,
+        amount: f32,
+    },
     SpawnPlayer,
-    /// HUD listens such events and puts them into queue.
+    /// Player gave no input for too long: player's actor is removed without respawn and
+    /// player's score is put on hold.
+    PlayerAfk,
+    /// Player pressed a key while AFK, player respawns.
+    PlayerReturned,
+    /// Spawns actor of network client, see [`crate::net`].
+    SpawnGuest,
+    /// HUD listens such events and shows them in notification stack.
     AddNotification {
         text: String,
+        severity: NotificationSeverity,
+        /// Path to a texture that is shown next to the text.
+        icon: Option<PathBuf>,
+    },
+    /// Large text in the middle of the screen, for events that concern local player.
+    ShowBanner {
+        text: String,
+    },
+    /// Damage dealt by local player, shown as a number floating above the victim.
+    ShowDamageNumber {
+        victim: Handle<Actor>,
+        position: Vector3<f32>,
+        amount: f32,
+        kind: DamageNumberKind,
+    },
+    /// Bot started doing something else, level turns it into a line of intent feed.
+    BotIntentChanged {
+        bot: Handle<Actor>,
+        intent: BotIntent,
+    },
+    /// Line of bot intent feed, shown on HUD if the feed is turned on in options.
+    ShowBotIntent {
+        text: String,
+    },
+    /// Line of text chat, shown on HUD.
+    ChatMessage {
+        sender_name: String,
+        text: String,
     },
     /// Removes specified actor and creates new one at random spawn point.
     RespawnActor {
@@ -114,9 +212,145 @@ pub enum Message {
     StartNewGame {
         options: MatchOptions,
     },
+    /// Remembers options chosen in match menu, so the menu starts from them next time.
+    SaveMatchSettings {
+        options: MatchOptions,
+        bot_count: u32,
+    },
+    /// Stores control profiles in settings file.
+    SaveControlProfiles {
+        profiles: ControlProfiles,
+    },
+    /// Stores display and gameplay preferences in settings file.
+    SavePreferences {
+        preferences: Preferences,
+    },
     QuitGame,
     SetMusicVolume {
         volume: f32,
     },
     EndMatch,
+    /// Starts new match with current options on a map at given path.
+    ChangeMap {
+        path: PathBuf,
+    },
+    /// Makes actor invulnerable or vice versa.
+    ToggleGodMode {
+        actor: Handle<Actor>,
+    },
+    /// Switches player between normal movement and free flight through level geometry.
+    ToggleNoclip {
+        actor: Handle<Actor>,
+    },
+    /// Detaches input from player and enables free-fly debug camera, or vice versa.
+    ToggleFreeCamera,
+    SetTimeLimit {
+        time_limit_secs: f32,
+    },
+    /// Slows down game clock for debugging, 1.0 is normal speed.
+    SetTimeScale {
+        time_scale: f32,
+    },
+    /// Stops or resumes game clock.
+    TogglePause,
+    /// Prints problems of current map into console.
+    ShowMapIssues,
+    /// Closes menu and returns to current match.
+    ResumeGame,
+    /// Starts current match over with the same options on the same map.
+    RestartMatch,
+    /// Destroys current match and returns to main menu.
+    LeaveMatch,
+    /// Scoreboard key was pressed or released.
+    SetScoreboardVisible {
+        visible: bool,
+    },
+    /// Starts or stops sampling of actor positions into level heatmap, see [`crate::heatmap`].
+    SetHeatmapRecording {
+        enabled: bool,
+    },
+    /// Drops every sample of level heatmap.
+    ClearHeatmap,
+    /// Writes level heatmap to a CSV file in user data directory.
+    DumpHeatmap,
+    /// Something happened that game observers should know about, see [`crate::observer`].
+    ReportEvent {
+        event: GameEvent,
+    },
+}
+
+/// Defines which systems will receive a message.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MessageCategory {
+    /// Handled only by game itself: match flow, saves, global settings.
+    Game,
+    /// Handled only by sound manager of current level.
+    Sound,
+    /// Handled only by HUD.
+    Notification,
+    /// Handled by level first and then by HUD, so leader board will see the result.
+    Gameplay,
+}
+
+impl Message {
+    pub fn category(&self) -> MessageCategory {
+        match self {
+            Message::StartNewGame { .. }
+            | Message::SaveGame
+            | Message::LoadGame
+            | Message::QuitGame
+            | Message::EndMatch
+            | Message::ChangeMap { .. }
+            | Message::SaveMatchSettings { .. }
+            | Message::SaveControlProfiles { .. }
+            | Message::SavePreferences { .. }
+            | Message::SetTimeScale { .. }
+            | Message::TogglePause
+            | Message::ResumeGame
+            | Message::RestartMatch
+            | Message::LeaveMatch
+            | Message::ShowMapIssues
+            | Message::SetMusicVolume { .. }
+            | Message::DumpHeatmap
+            | Message::ReportEvent { .. } => MessageCategory::Game,
+            Message::PlaySound { .. } => MessageCategory::Sound,
+            Message::AddNotification { .. }
+            | Message::ShowBanner { .. }
+            | Message::ShowDamageNumber { .. }
+            | Message::ShowBotIntent { .. }
+            | Message::SetScoreboardVisible { .. } => MessageCategory::Notification,
+            _ => MessageCategory::Gameplay,
+        }
+    }
+}
+
+/// Sending end of message queue that is always present in game entities. Sending never
+/// panics: if there is nobody to receive messages anymore (for example while level is being
+/// destroyed) the message is logged and dropped.
+#[derive(Clone)]
+pub struct MessageSender {
+    sender: Sender<Message>,
+}
+
+impl Default for MessageSender {
+    /// Creates disconnected sender, it is used until real one is attached.
+    fn default() -> Self {
+        let (sender, _) = mpsc::channel();
+        Self { sender }
+    }
+}
+
+impl MessageSender {
+    pub fn new(sender: Sender<Message>) -> Self {
+        Self { sender }
+    }
+
+    pub fn send(&self, message: Message) {
+        if let Err(e) = self.sender.send(message) {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!("Message {:?} dropped, there is no receiver.", e.0),
+            );
+        }
+    }
 }
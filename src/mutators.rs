@@ -0,0 +1,127 @@
+//! Mutators are optional rule changes that can be combined with any match mode. All of them
+//! are interpreted by level in one place, see `Level::apply_mutators` and functions next to it.
+
+use fyrox::core::visitor::{Visit, VisitResult, Visitor};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mutators {
+    /// Any hit by a weapon kills.
+    pub instagib: bool,
+    /// Living actors slowly restore health.
+    pub regen: bool,
+    /// Gravity of the world is weaker.
+    pub low_gravity: bool,
+    /// Weapons do not spend ammo.
+    pub infinite_ammo: bool,
+    /// Items of the map are not placed.
+    pub no_pickups: bool,
+    /// Teammates damage each other in team modes. It is the only mutator that is on by
+    /// default, turning it off is a rule change.
+    pub friendly_fire: bool,
+    /// Players have grappling hook. It is opt-in, it changes how maps are played too much.
+    pub grapple: bool,
+    /// Every bot kind uses its own view distance, field of view and hearing instead of the
+    /// shared default ones. It is opt-in, it changes balance of matches with bots.
+    pub bot_senses: bool,
+}
+
+impl Default for Mutators {
+    fn default() -> Self {
+        Self {
+            instagib: false,
+            regen: false,
+            low_gravity: false,
+            infinite_ammo: false,
+            no_pickups: false,
+            friendly_fire: true,
+            grapple: false,
+            bot_senses: false,
+        }
+    }
+}
+
+impl Visit for Mutators {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        // Options saved before mutators were added have no such region, they are played
+        // without rule changes.
+        if visitor.enter_region(name).is_err() {
+            *self = Self::default();
+            return Ok(());
+        }
+
+        let _ = self.instagib.visit("Instagib", visitor);
+        let _ = self.regen.visit("Regen", visitor);
+        let _ = self.low_gravity.visit("LowGravity", visitor);
+        let _ = self.infinite_ammo.visit("InfiniteAmmo", visitor);
+        let _ = self.no_pickups.visit("NoPickups", visitor);
+        let _ = self.friendly_fire.visit("FriendlyFire", visitor);
+        let _ = self.grapple.visit("Grapple", visitor);
+        let _ = self.bot_senses.visit("BotSenses", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+impl Mutators {
+    /// Names of mutators in the same order as [`Self::flags_mut`] returns them.
+    pub const NAMES: [&'static str; 8] = [
+        "Instagib",
+        "Regeneration",
+        "Low Gravity",
+        "Infinite Ammo",
+        "No Pickups",
+        "Friendly Fire",
+        "Grappling Hook",
+        "Bot Senses",
+    ];
+
+    pub fn flags(&self) -> [bool; 8] {
+        [
+            self.instagib,
+            self.regen,
+            self.low_gravity,
+            self.infinite_ammo,
+            self.no_pickups,
+            self.friendly_fire,
+            self.grapple,
+            self.bot_senses,
+        ]
+    }
+
+    pub fn flags_mut(&mut self) -> [&mut bool; 8] {
+        [
+            &mut self.instagib,
+            &mut self.regen,
+            &mut self.low_gravity,
+            &mut self.infinite_ammo,
+            &mut self.no_pickups,
+            &mut self.friendly_fire,
+            &mut self.grapple,
+            &mut self.bot_senses,
+        ]
+    }
+
+    /// Short description of rule changes, for example "Instagib, No Friendly Fire". `None`
+    /// if rules are default.
+    pub fn describe(&self) -> Option<String> {
+        let changes = self
+            .flags()
+            .iter()
+            .zip(Self::default().flags().iter())
+            .zip(Self::NAMES.iter())
+            .filter(|((flag, default), _)| flag != default)
+            .map(|((&flag, _), name)| {
+                if flag {
+                    (*name).to_owned()
+                } else {
+                    format!("No {}", name)
+                }
+            })
+            .collect::<Vec<_>>();
+        if changes.is_empty() {
+            None
+        } else {
+            Some(changes.join(", "))
+        }
+    }
+}
@@ -0,0 +1,865 @@
+//! First slice of multiplayer. Host runs the match as usual and accepts a single client over
+//! TCP, client runs a mirror of host's level: it spawns the same map with the same seed, does
+//! not simulate gameplay on its own and only shows what host tells it. Host streams
+//! presentation messages (sounds, effects, notifications, chat) and state of every actor
+//! (position, orientation, health) after each simulation step, client sends its input back and
+//! host moves guest's actor. Messages that refer to handles (shots, projectiles, damage) are
+//! not sent, handles differ between host and client, their results arrive with snapshots.
+//!
+//! Every packet is a frame of `u32` little-endian payload length followed by payload. First
+//! byte of payload is a packet tag, the rest depends on the tag.
+
+use crate::{
+    effects::EffectKind, logger::Logger, message::Message, notifications::NotificationSeverity,
+};
+use fyrox::{
+    core::algebra::{Quaternion, UnitQuaternion, Vector3},
+    utils::log::MessageKind,
+};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Name of actor that represents a client on host.
+pub const GUEST_NAME: &str = "Guest";
+/// Name of actor that represents host's player on client.
+pub const HOST_NAME: &str = "Host";
+
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Malformed(String),
+    Closed,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "{}", e),
+            NetError::Malformed(reason) => write!(f, "malformed packet: {}", reason),
+            NetError::Closed => write!(f, "connection closed"),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> Self {
+        NetError::Io(e)
+    }
+}
+
+#[derive(Default)]
+pub struct Encoder {
+    buffer: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn vec3(&mut self, value: Vector3<f32>) {
+        self.f32(value.x);
+        self.f32(value.y);
+        self.f32(value.z);
+    }
+
+    pub fn quat(&mut self, value: UnitQuaternion<f32>) {
+        let value = value.into_inner();
+        self.f32(value.w);
+        self.f32(value.i);
+        self.f32(value.j);
+        self.f32(value.k);
+    }
+}
+
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], NetError> {
+        let end = self.position + count;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or_else(|| NetError::Malformed("unexpected end of packet".to_owned()))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, NetError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, NetError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, NetError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, NetError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(f32::from_le_bytes(bytes))
+    }
+
+    pub fn string(&mut self) -> Result<String, NetError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| NetError::Malformed("invalid utf-8 string".to_owned()))
+    }
+
+    pub fn vec3(&mut self) -> Result<Vector3<f32>, NetError> {
+        Ok(Vector3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    pub fn quat(&mut self) -> Result<UnitQuaternion<f32>, NetError> {
+        let (w, i, j, k) = (self.f32()?, self.f32()?, self.f32()?, self.f32()?);
+        Ok(UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k)))
+    }
+}
+
+/// Returns true if given message changes simulation state. Only host handles such messages,
+/// a mirrored level on client ignores them and waits for results from host.
+pub fn is_host_authoritative(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::GiveNewWeapon { .. }
+            | Message::RemoveActor { .. }
+            | Message::RemoveBot { .. }
+            | Message::ShuffleTeams
+            | Message::RequestTeamChange { .. }
+            | Message::SpawnBot { .. }
+            | Message::GiveItem { .. }
+            | Message::PickUpItem { .. }
+            | Message::SwapWeapon { .. }
+            | Message::SpawnItem { .. }
+            | Message::CreateProjectile { .. }
+            | Message::ShootWeapon { .. }
+            | Message::ChargeWeaponStart { .. }
+            | Message::ChargeWeaponRelease { .. }
+            | Message::DamageActor { .. }
+            | Message::SpawnPlayer
+            | Message::PlayerAfk
+            | Message::SpawnGuest
+            | Message::RespawnActor { .. }
+    )
+}
+
+/// Writes message if it must be sent over network, messages that are meaningful only
+/// locally (saves, menus, simulation) are skipped and false is returned.
+pub fn encode_message(message: &Message, encoder: &mut Encoder) -> bool {
+    match message {
+        Message::PlaySound {
+            path,
+            position,
+            gain,
+            rolloff_factor,
+            radius,
+        } => {
+            encoder.u8(0);
+            encoder.str(&path.to_string_lossy());
+            encoder.vec3(*position);
+            encoder.f32(*gain);
+            encoder.f32(*rolloff_factor);
+            encoder.f32(*radius);
+        }
+        Message::CreateEffect { kind, position } => {
+            encoder.u8(1);
+            let index = EffectKind::ALL.iter().position(|k| k == kind).unwrap();
+            encoder.u8(index as u8);
+            encoder.vec3(*position);
+        }
+        Message::ProjectileImpact { position, amount } => {
+            encoder.u8(2);
+            encoder.vec3(*position);
+            encoder.f32(*amount);
+        }
+        Message::AddNotification {
+            text,
+            severity,
+            icon,
+        } => {
+            encoder.u8(3);
+            encoder.str(text);
+            let index = NotificationSeverity::ALL
+                .iter()
+                .position(|s| s == severity)
+                .unwrap();
+            encoder.u8(index as u8);
+            // Empty path means no icon.
+            encoder.str(
+                &icon
+                    .as_ref()
+                    .map_or_else(Default::default, |icon| icon.to_string_lossy().into_owned()),
+            );
+        }
+        Message::ChatMessage { sender_name, text } => {
+            encoder.u8(4);
+            encoder.str(sender_name);
+            encoder.str(text);
+        }
+        Message::EndMatch => encoder.u8(5),
+        _ => return false,
+    }
+    true
+}
+
+pub fn decode_message(decoder: &mut Decoder) -> Result<Message, NetError> {
+    Ok(match decoder.u8()? {
+        0 => Message::PlaySound {
+            path: PathBuf::from(decoder.string()?),
+            position: decoder.vec3()?,
+            gain: decoder.f32()?,
+            rolloff_factor: decoder.f32()?,
+            radius: decoder.f32()?,
+        },
+        1 => Message::CreateEffect {
+            kind: *EffectKind::ALL
+                .get(decoder.u8()? as usize)
+                .ok_or_else(|| NetError::Malformed("unknown effect kind".to_owned()))?,
+            position: decoder.vec3()?,
+        },
+        2 => Message::ProjectileImpact {
+            position: decoder.vec3()?,
+            amount: decoder.f32()?,
+        },
+        3 => Message::AddNotification {
+            text: decoder.string()?,
+            severity: *NotificationSeverity::ALL
+                .get(decoder.u8()? as usize)
+                .ok_or_else(|| NetError::Malformed("unknown notification severity".to_owned()))?,
+            icon: Some(decoder.string()?)
+                .filter(|icon| !icon.is_empty())
+                .map(PathBuf::from),
+        },
+        4 => Message::ChatMessage {
+            sender_name: decoder.string()?,
+            text: decoder.string()?,
+        },
+        5 => Message::EndMatch,
+        tag => return Err(NetError::Malformed(format!("unknown message tag {}", tag))),
+    })
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ActorRole {
+    /// Player of host.
+    Host,
+    /// Player of client.
+    Guest,
+    Bot,
+}
+
+/// State of an actor after a simulation step on host. Actors are identified by name, because
+/// handles on client and host may differ.
+#[derive(Clone, Debug)]
+pub struct ActorState {
+    pub role: ActorRole,
+    pub name: String,
+    pub position: Vector3<f32>,
+    /// Rotation of the body, bots and players turn around vertical axis only.
+    pub rotation: UnitQuaternion<f32>,
+    pub health: f32,
+    pub armor: f32,
+    pub alive: bool,
+}
+
+/// Input of client's player, host applies it to guest's actor.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct RemoteInput {
+    pub move_forward: bool,
+    pub move_backward: bool,
+    pub move_left: bool,
+    pub move_right: bool,
+    pub jump: bool,
+    pub crouch: bool,
+    pub run: bool,
+    pub shoot: bool,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl RemoteInput {
+    fn flags(&self) -> [bool; 8] {
+        [
+            self.move_forward,
+            self.move_backward,
+            self.move_left,
+            self.move_right,
+            self.jump,
+            self.crouch,
+            self.run,
+            self.shoot,
+        ]
+    }
+}
+
+pub enum Packet {
+    /// First packet from host, client loads the same map with the same seed.
+    Welcome {
+        map: PathBuf,
+        seed: u64,
+        bot_count: u32,
+    },
+    Snapshot(Vec<ActorState>),
+    Message(Message),
+    Input(RemoteInput),
+    Disconnect,
+}
+
+impl Packet {
+    fn encode(&self, encoder: &mut Encoder) -> bool {
+        match self {
+            Packet::Welcome {
+                map,
+                seed,
+                bot_count,
+            } => {
+                encoder.u8(0);
+                encoder.str(&map.to_string_lossy());
+                encoder.u64(*seed);
+                encoder.u32(*bot_count);
+            }
+            Packet::Snapshot(actors) => {
+                encoder.u8(1);
+                encoder.u32(actors.len() as u32);
+                for actor in actors {
+                    encoder.u8(actor.role as u8);
+                    encoder.str(&actor.name);
+                    encoder.vec3(actor.position);
+                    encoder.quat(actor.rotation);
+                    encoder.f32(actor.health);
+                    encoder.f32(actor.armor);
+                    encoder.u8(actor.alive as u8);
+                }
+            }
+            Packet::Message(message) => {
+                encoder.u8(2);
+                return encode_message(message, encoder);
+            }
+            Packet::Input(input) => {
+                encoder.u8(3);
+                let flags = input
+                    .flags()
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |flags, (i, &flag)| flags | ((flag as u8) << i));
+                encoder.u8(flags);
+                encoder.f32(input.yaw);
+                encoder.f32(input.pitch);
+            }
+            Packet::Disconnect => encoder.u8(4),
+        }
+        true
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, NetError> {
+        Ok(match decoder.u8()? {
+            0 => Packet::Welcome {
+                map: PathBuf::from(decoder.string()?),
+                seed: decoder.u64()?,
+                bot_count: decoder.u32()?,
+            },
+            1 => {
+                let count = decoder.u32()? as usize;
+                let mut actors = Vec::with_capacity(count.min(256));
+                for _ in 0..count {
+                    let role = match decoder.u8()? {
+                        0 => ActorRole::Host,
+                        1 => ActorRole::Guest,
+                        2 => ActorRole::Bot,
+                        role => return Err(NetError::Malformed(format!("unknown role {}", role))),
+                    };
+                    actors.push(ActorState {
+                        role,
+                        name: decoder.string()?,
+                        position: decoder.vec3()?,
+                        rotation: decoder.quat()?,
+                        health: decoder.f32()?,
+                        armor: decoder.f32()?,
+                        alive: decoder.u8()? != 0,
+                    });
+                }
+                Packet::Snapshot(actors)
+            }
+            2 => Packet::Message(decode_message(decoder)?),
+            3 => {
+                let flags = decoder.u8()?;
+                let flag = |i: u8| flags & (1 << i) != 0;
+                Packet::Input(RemoteInput {
+                    move_forward: flag(0),
+                    move_backward: flag(1),
+                    move_left: flag(2),
+                    move_right: flag(3),
+                    jump: flag(4),
+                    crouch: flag(5),
+                    run: flag(6),
+                    shoot: flag(7),
+                    yaw: decoder.f32()?,
+                    pitch: decoder.f32()?,
+                })
+            }
+            4 => Packet::Disconnect,
+            tag => return Err(NetError::Malformed(format!("unknown packet tag {}", tag))),
+        })
+    }
+}
+
+/// Non-blocking framed connection.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            incoming: Default::default(),
+            outgoing: Default::default(),
+        })
+    }
+
+    /// Queues packet for sending, packets that have nothing to send are dropped.
+    pub fn send(&mut self, packet: &Packet) {
+        let mut encoder = Encoder::default();
+        if packet.encode(&mut encoder) {
+            self.outgoing
+                .extend_from_slice(&(encoder.buffer.len() as u32).to_le_bytes());
+            self.outgoing.extend_from_slice(&encoder.buffer);
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), NetError> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(NetError::Closed),
+                Ok(count) => {
+                    self.outgoing.drain(..count);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, packets: &mut Vec<Packet>) -> Result<(), NetError> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(NetError::Closed),
+                Ok(count) => self.incoming.extend_from_slice(&buffer[..count]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        while self.incoming.len() >= 4 {
+            let mut size = [0; 4];
+            size.copy_from_slice(&self.incoming[..4]);
+            let size = u32::from_le_bytes(size) as usize;
+            if size > MAX_PACKET_SIZE {
+                return Err(NetError::Malformed(format!("packet is too large {}", size)));
+            }
+            if self.incoming.len() < 4 + size {
+                break;
+            }
+            let packet = Packet::decode(&mut Decoder::new(&self.incoming[4..4 + size]))?;
+            self.incoming.drain(..4 + size);
+            packets.push(packet);
+        }
+        Ok(())
+    }
+
+    /// Exchanges data with other side.
+    fn poll(&mut self, packets: &mut Vec<Packet>) -> Result<(), NetError> {
+        self.receive(packets)?;
+        self.flush()
+    }
+}
+
+pub enum NetEvent {
+    Connected,
+    Packet(Packet),
+    Disconnected(String),
+}
+
+pub enum NetSession {
+    /// Accepts one client at a time.
+    Host {
+        listener: TcpListener,
+        client: Option<Connection>,
+    },
+    Client {
+        connection: Option<Connection>,
+    },
+}
+
+impl NetSession {
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Logger::writeln(
+            MessageKind::Information,
+            format!("Waiting for a client on port {}", port),
+        );
+        Ok(NetSession::Host {
+            listener,
+            client: None,
+        })
+    }
+
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid address {}", address),
+            )
+        })?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        Logger::writeln(
+            MessageKind::Information,
+            format!("Connected to {}", address),
+        );
+        Ok(NetSession::Client {
+            connection: Some(Connection::new(stream)?),
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self, NetSession::Host { .. })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        match self {
+            NetSession::Host { client, .. } => client.is_some(),
+            NetSession::Client { connection } => connection.is_some(),
+        }
+    }
+
+    fn connection_mut(&mut self) -> Option<&mut Connection> {
+        match self {
+            NetSession::Host { client, .. } => client.as_mut(),
+            NetSession::Client { connection } => connection.as_mut(),
+        }
+    }
+
+    /// Queues packet for sending to other side, does nothing if there is no connection.
+    pub fn send(&mut self, packet: &Packet) {
+        if let Some(connection) = self.connection_mut() {
+            connection.send(packet);
+        }
+    }
+
+    /// Accepts new clients, sends queued packets and returns everything that happened since
+    /// last call. Must be called once per tick.
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        let mut events = Vec::new();
+
+        if let NetSession::Host { listener, client } = self {
+            match listener.accept() {
+                Ok((stream, address)) if client.is_none() => match Connection::new(stream) {
+                    Ok(connection) => {
+                        Logger::writeln(
+                            MessageKind::Information,
+                            format!("Client {} connected", address),
+                        );
+                        *client = Some(connection);
+                        events.push(NetEvent::Connected);
+                    }
+                    Err(e) => Logger::writeln(
+                        MessageKind::Error,
+                        format!("Unable to accept client {}: {}", address, e),
+                    ),
+                },
+                Ok((mut stream, address)) => {
+                    // Only one client is supported for now.
+                    let _ = stream.write_all(&[1, 0, 0, 0, 4]);
+                    Logger::writeln(
+                        MessageKind::Warning,
+                        format!("Client {} rejected, server is full", address),
+                    );
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => Logger::writeln(
+                    MessageKind::Error,
+                    format!("Unable to accept client: {}", e),
+                ),
+            }
+        }
+
+        let slot = match self {
+            NetSession::Host { client, .. } => client,
+            NetSession::Client { connection } => connection,
+        };
+        if let Some(connection) = slot.as_mut() {
+            let mut packets = Vec::new();
+            let result = connection.poll(&mut packets);
+            let mut disconnected = false;
+            for packet in packets {
+                if let Packet::Disconnect = packet {
+                    disconnected = true;
+                    break;
+                }
+                events.push(NetEvent::Packet(packet));
+            }
+            let reason = match result {
+                Err(e) => Some(e.to_string()),
+                Ok(()) if disconnected => Some("other side left".to_owned()),
+                Ok(()) => None,
+            };
+            if let Some(reason) = reason {
+                Logger::writeln(
+                    MessageKind::Information,
+                    format!("Disconnected: {}", reason),
+                );
+                *slot = None;
+                events.push(NetEvent::Disconnected(reason));
+            }
+        }
+
+        events
+    }
+
+    /// Tells other side that connection is closed on purpose.
+    pub fn shutdown(&mut self) {
+        if let Some(connection) = self.connection_mut() {
+            connection.send(&Packet::Disconnect);
+            let _ = connection.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip_message(message: Message) {
+        let mut encoder = Encoder::default();
+        assert!(encode_message(&message, &mut encoder));
+        let mut decoder = Decoder::new(&encoder.buffer);
+        let decoded = decode_message(&mut decoder).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", message));
+        assert_eq!(decoder.position, encoder.buffer.len());
+    }
+
+    fn round_trip_packet(packet: &Packet) -> Packet {
+        let mut encoder = Encoder::default();
+        assert!(packet.encode(&mut encoder));
+        let mut decoder = Decoder::new(&encoder.buffer);
+        let decoded = Packet::decode(&mut decoder).unwrap();
+        assert_eq!(decoder.position, encoder.buffer.len());
+        decoded
+    }
+
+    #[test]
+    fn messages_survive_round_trip() {
+        round_trip_message(Message::PlaySound {
+            path: PathBuf::from("data/sounds/explosion.ogg"),
+            position: Vector3::new(1.0, -2.0, 3.5),
+            gain: 0.5,
+            rolloff_factor: 2.0,
+            radius: 3.0,
+        });
+        for &kind in EffectKind::ALL.iter() {
+            round_trip_message(Message::CreateEffect {
+                kind,
+                position: Vector3::new(0.0, 1.0, 0.0),
+            });
+        }
+        round_trip_message(Message::ProjectileImpact {
+            position: Vector3::new(4.0, 5.0, 6.0),
+            amount: 25.0,
+        });
+        for &severity in NotificationSeverity::ALL.iter() {
+            round_trip_message(Message::AddNotification {
+                text: "Lead taken".to_owned(),
+                severity,
+                icon: None,
+            });
+        }
+        round_trip_message(Message::AddNotification {
+            text: "Ünïcode".to_owned(),
+            severity: NotificationSeverity::ALL[0],
+            icon: Some(PathBuf::from("data/ui/icon.png")),
+        });
+        round_trip_message(Message::ChatMessage {
+            sender_name: GUEST_NAME.to_owned(),
+            text: "gg".to_owned(),
+        });
+        round_trip_message(Message::EndMatch);
+    }
+
+    #[test]
+    fn local_messages_are_not_encoded() {
+        let mut encoder = Encoder::default();
+        assert!(!encode_message(&Message::ShuffleTeams, &mut encoder));
+    }
+
+    #[test]
+    fn truncated_message_is_malformed() {
+        let mut encoder = Encoder::default();
+        encode_message(
+            &Message::ChatMessage {
+                sender_name: HOST_NAME.to_owned(),
+                text: "hello".to_owned(),
+            },
+            &mut encoder,
+        );
+        let truncated = &encoder.buffer[..encoder.buffer.len() - 1];
+        assert!(matches!(
+            decode_message(&mut Decoder::new(truncated)),
+            Err(NetError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn welcome_survives_round_trip() {
+        match round_trip_packet(&Packet::Welcome {
+            map: PathBuf::from("data/levels/dm6.rgs"),
+            seed: u64::MAX - 7,
+            bot_count: 3,
+        }) {
+            Packet::Welcome {
+                map,
+                seed,
+                bot_count,
+            } => {
+                assert_eq!(map, PathBuf::from("data/levels/dm6.rgs"));
+                assert_eq!(seed, u64::MAX - 7);
+                assert_eq!(bot_count, 3);
+            }
+            _ => panic!("expected welcome"),
+        }
+    }
+
+    #[test]
+    fn snapshot_survives_round_trip() {
+        let states = vec![
+            ActorState {
+                role: ActorRole::Host,
+                name: HOST_NAME.to_owned(),
+                position: Vector3::new(1.0, 2.0, 3.0),
+                rotation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 1.25),
+                health: 75.0,
+                armor: 20.0,
+                alive: true,
+            },
+            ActorState {
+                role: ActorRole::Guest,
+                name: GUEST_NAME.to_owned(),
+                position: Vector3::new(-4.0, 0.5, 8.0),
+                rotation: UnitQuaternion::identity(),
+                health: -12.0,
+                armor: 0.0,
+                alive: false,
+            },
+            ActorState {
+                role: ActorRole::Bot,
+                name: "Bot 1".to_owned(),
+                position: Vector3::default(),
+                rotation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -2.5),
+                health: 100.0,
+                armor: 50.0,
+                alive: true,
+            },
+        ];
+        match round_trip_packet(&Packet::Snapshot(states.clone())) {
+            Packet::Snapshot(decoded) => {
+                assert_eq!(decoded.len(), states.len());
+                for (decoded, state) in decoded.iter().zip(states.iter()) {
+                    assert_eq!(decoded.role, state.role);
+                    assert_eq!(decoded.name, state.name);
+                    assert_eq!(decoded.position, state.position);
+                    assert!(decoded.rotation.angle_to(&state.rotation) < 1.0e-5);
+                    assert_eq!(decoded.health, state.health);
+                    assert_eq!(decoded.armor, state.armor);
+                    assert_eq!(decoded.alive, state.alive);
+                }
+            }
+            _ => panic!("expected snapshot"),
+        }
+    }
+
+    #[test]
+    fn input_survives_round_trip() {
+        let input = RemoteInput {
+            move_forward: true,
+            move_left: true,
+            jump: true,
+            shoot: true,
+            yaw: 123.5,
+            pitch: -45.0,
+            ..Default::default()
+        };
+        match round_trip_packet(&Packet::Input(input)) {
+            Packet::Input(decoded) => {
+                assert_eq!(decoded.flags(), input.flags());
+                assert_eq!(decoded.yaw, input.yaw);
+                assert_eq!(decoded.pitch, input.pitch);
+            }
+            _ => panic!("expected input"),
+        }
+    }
+
+    #[test]
+    fn message_and_disconnect_survive_round_trip() {
+        match round_trip_packet(&Packet::Message(Message::EndMatch)) {
+            Packet::Message(Message::EndMatch) => (),
+            _ => panic!("expected end of match"),
+        }
+        assert!(matches!(
+            round_trip_packet(&Packet::Disconnect),
+            Packet::Disconnect
+        ));
+    }
+
+    #[test]
+    fn unknown_packet_tag_is_malformed() {
+        assert!(matches!(
+            Packet::decode(&mut Decoder::new(&[200])),
+            Err(NetError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+//! Stack of short notifications on HUD. Every notification has its own lifetime that depends
+//! on its severity, several of them are visible at once, so important events are not lost
+//! behind a stream of minor ones.
+
+use fyrox::{
+    core::{color::Color, pool::Handle},
+    engine::resource_manager::ResourceManager,
+    gui::{
+        brush::Brush,
+        image::{ImageBuilder, ImageMessage},
+        message::MessageDirection,
+        stack_panel::StackPanelBuilder,
+        text::{TextBuilder, TextMessage},
+        widget::{WidgetBuilder, WidgetMessage},
+        BuildContext, HorizontalAlignment, Orientation, Thickness, UiNode, UserInterface,
+        VerticalAlignment,
+    },
+    utils,
+};
+use std::path::PathBuf;
+
+/// Amount of notifications shown at once.
+const MAX_ENTRIES: usize = 4;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum NotificationSeverity {
+    Info,
+    /// Items picked up by anyone, these can be disabled in options.
+    Pickup,
+    Important,
+}
+
+impl NotificationSeverity {
+    pub const ALL: [NotificationSeverity; 3] = [
+        NotificationSeverity::Info,
+        NotificationSeverity::Pickup,
+        NotificationSeverity::Important,
+    ];
+
+    /// Time in seconds for which a notification stays on screen.
+    pub fn lifetime(self) -> f32 {
+        match self {
+            NotificationSeverity::Info => 2.5,
+            NotificationSeverity::Pickup => 1.5,
+            NotificationSeverity::Important => 5.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            NotificationSeverity::Info => Color::opaque(230, 230, 230),
+            NotificationSeverity::Pickup => Color::opaque(160, 200, 160),
+            NotificationSeverity::Important => Color::opaque(255, 200, 0),
+        }
+    }
+}
+
+struct Entry {
+    text: String,
+    severity: NotificationSeverity,
+    icon: Option<PathBuf>,
+    /// Amount of identical notifications that came in a row, shown as "(xN)".
+    count: u32,
+    time_left: f32,
+}
+
+impl Entry {
+    fn display_text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// Entries of a stack apart from its widgets.
+struct Queue {
+    entries: Vec<Entry>,
+    capacity: usize,
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: Default::default(),
+            capacity,
+        }
+    }
+
+    /// Adds an entry, the same notification that comes in a row increases count of the last
+    /// entry instead and restarts its lifetime.
+    fn push(&mut self, text: &str, severity: NotificationSeverity, icon: Option<PathBuf>) {
+        if let Some(last) = self.entries.last_mut() {
+            if last.text == text && last.severity == severity {
+                last.count += 1;
+                last.time_left = severity.lifetime();
+                return;
+            }
+        }
+
+        self.entries.push(Entry {
+            text: text.to_owned(),
+            severity,
+            icon,
+            count: 1,
+            time_left: severity.lifetime(),
+        });
+
+        if self.entries.len() > self.capacity {
+            // Make room by dropping the least important entry, the oldest one among equals.
+            let (index, _) = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.severity)
+                .unwrap();
+            self.entries.remove(index);
+        }
+    }
+
+    /// Removes expired entries, returns true if there were any.
+    fn advance(&mut self, dt: f32) -> bool {
+        let count = self.entries.len();
+        for entry in self.entries.iter_mut() {
+            entry.time_left -= dt;
+        }
+        self.entries.retain(|entry| entry.time_left > 0.0);
+        self.entries.len() != count
+    }
+}
+
+/// Widgets of a single notification, they are reused for any entry.
+struct Slot {
+    root: Handle<UiNode>,
+    icon: Handle<UiNode>,
+    text: Handle<UiNode>,
+}
+
+pub struct Notifications {
+    root: Handle<UiNode>,
+    slots: Vec<Slot>,
+    queue: Queue,
+    resource_manager: ResourceManager,
+    show_pickups: bool,
+    /// Color of every entry regardless of its severity.
+    color: Option<Color>,
+    dirty: bool,
+}
+
+impl Notifications {
+    /// Creates main stack of notifications in the top left corner of the screen.
+    pub fn new(ctx: &mut BuildContext, resource_manager: ResourceManager) -> Self {
+        Self::with_placement(
+            ctx,
+            resource_manager,
+            MAX_ENTRIES,
+            HorizontalAlignment::Left,
+            Thickness {
+                left: 45.0,
+                top: 80.0,
+                right: 0.0,
+                bottom: 0.0,
+            },
+            None,
+        )
+    }
+
+    /// Creates a separate stack, for example a feed of minor events that should not push
+    /// important notifications out of the main stack.
+    pub fn with_placement(
+        ctx: &mut BuildContext,
+        resource_manager: ResourceManager,
+        max_entries: usize,
+        horizontal_alignment: HorizontalAlignment,
+        margin: Thickness,
+        color: Option<Color>,
+    ) -> Self {
+        let slots = (0..max_entries)
+            .map(|_| {
+                let icon = ImageBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_width(24.0)
+                        .with_height(24.0)
+                        .with_margin(Thickness::right(5.0)),
+                )
+                .build(ctx);
+                let text = TextBuilder::new(
+                    WidgetBuilder::new().with_vertical_alignment(VerticalAlignment::Center),
+                )
+                .build(ctx);
+                let root = StackPanelBuilder::new(
+                    WidgetBuilder::new()
+                        .with_visibility(false)
+                        .with_height(28.0)
+                        .with_child(icon)
+                        .with_child(text),
+                )
+                .with_orientation(Orientation::Horizontal)
+                .build(ctx);
+                Slot { root, icon, text }
+            })
+            .collect::<Vec<_>>();
+
+        let root = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_vertical_alignment(VerticalAlignment::Top)
+                .with_horizontal_alignment(horizontal_alignment)
+                .with_margin(margin)
+                .with_width(400.0)
+                .with_children(slots.iter().map(|slot| slot.root)),
+        )
+        .build(ctx);
+
+        Self {
+            root,
+            slots,
+            queue: Queue::new(max_entries),
+            resource_manager,
+            show_pickups: true,
+            color,
+            dirty: false,
+        }
+    }
+
+    pub fn root(&self) -> Handle<UiNode> {
+        self.root
+    }
+
+    pub fn set_show_pickups(&mut self, show: bool) {
+        self.show_pickups = show;
+    }
+
+    pub fn add(&mut self, text: &str, severity: NotificationSeverity, icon: Option<PathBuf>) {
+        if severity == NotificationSeverity::Pickup && !self.show_pickups {
+            return;
+        }
+
+        self.dirty = true;
+        self.queue.push(text, severity, icon);
+    }
+
+    pub fn update(&mut self, ui: &mut UserInterface, dt: f32) {
+        if self.queue.advance(dt) {
+            self.dirty = true;
+        }
+
+        if self.dirty {
+            self.dirty = false;
+            self.sync(ui);
+        }
+    }
+
+    fn sync(&self, ui: &mut UserInterface) {
+        for (i, slot) in self.slots.iter().enumerate() {
+            let entry = self.queue.entries.get(i);
+
+            ui.send_message(WidgetMessage::visibility(
+                slot.root,
+                MessageDirection::ToWidget,
+                entry.is_some(),
+            ));
+
+            if let Some(entry) = entry {
+                ui.send_message(TextMessage::text(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    entry.display_text(),
+                ));
+                ui.send_message(WidgetMessage::foreground(
+                    slot.text,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(self.color.unwrap_or_else(|| entry.severity.color())),
+                ));
+                ui.send_message(WidgetMessage::visibility(
+                    slot.icon,
+                    MessageDirection::ToWidget,
+                    entry.icon.is_some(),
+                ));
+                if let Some(icon) = entry.icon.as_ref() {
+                    ui.send_message(ImageMessage::texture(
+                        slot.icon,
+                        MessageDirection::ToWidget,
+                        Some(utils::into_gui_texture(
+                            self.resource_manager.request_texture(icon),
+                        )),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_in_a_row_collapse_with_count() {
+        let mut queue = Queue::new(MAX_ENTRIES);
+        queue.push("Bot 1 got Ak47", NotificationSeverity::Info, None);
+        queue.advance(1.0);
+        queue.push("Bot 1 got Ak47", NotificationSeverity::Info, None);
+        queue.push("Bot 1 got Ak47", NotificationSeverity::Info, None);
+        assert_eq!(queue.entries.len(), 1);
+        assert_eq!(queue.entries[0].display_text(), "Bot 1 got Ak47 (x3)");
+        // Lifetime restarts with every duplicate.
+        assert_eq!(
+            queue.entries[0].time_left,
+            NotificationSeverity::Info.lifetime()
+        );
+
+        // Same text of another severity, or not in a row, is a separate entry.
+        queue.push("Bot 1 got Ak47", NotificationSeverity::Important, None);
+        queue.push("Bot 2 got M4", NotificationSeverity::Info, None);
+        queue.push("Bot 1 got Ak47", NotificationSeverity::Info, None);
+        assert_eq!(queue.entries.len(), 4);
+    }
+
+    #[test]
+    fn entries_expire_after_lifetime() {
+        let mut queue = Queue::new(MAX_ENTRIES);
+        queue.push("Pickup", NotificationSeverity::Pickup, None);
+        queue.push("Important", NotificationSeverity::Important, None);
+
+        let pickup = NotificationSeverity::Pickup.lifetime();
+        assert!(!queue.advance(pickup * 0.5));
+        assert_eq!(queue.entries.len(), 2);
+
+        assert!(queue.advance(pickup * 0.5));
+        assert_eq!(queue.entries.len(), 1);
+        assert_eq!(queue.entries[0].text, "Important");
+
+        assert!(queue.advance(NotificationSeverity::Important.lifetime()));
+        assert!(queue.entries.is_empty());
+    }
+
+    #[test]
+    fn least_important_entry_makes_room() {
+        let mut queue = Queue::new(2);
+        queue.push("First", NotificationSeverity::Important, None);
+        queue.push("Second", NotificationSeverity::Info, None);
+        queue.push("Third", NotificationSeverity::Important, None);
+        let texts = queue
+            .entries
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["First", "Third"]);
+    }
+}
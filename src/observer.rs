@@ -0,0 +1,345 @@
+//! Observers let external tools (stat trackers, stream overlays) follow a match without
+//! touching gameplay code. Level reports what happened with [`Message::ReportEvent`] and game
+//! passes every such event to all registered observers, see [`dispatch`].
+//!
+//! [`Message::ReportEvent`]: crate::message::Message::ReportEvent
+
+use crate::{item::ItemKind, logger::Logger, user_data_dir, weapon::WeaponKind};
+use fyrox::utils::log::MessageKind;
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{LineWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const EVENT_LOG_FILE_NAME: &str = "events.log";
+
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    MatchStart {
+        map: PathBuf,
+        /// See [`crate::MatchOptions::MODE_NAMES`].
+        mode: &'static str,
+    },
+    /// Final scores, in the order of the scoreboard.
+    MatchEnd {
+        scores: Vec<(String, u32)>,
+    },
+    Kill {
+        /// `None` if victim was killed by environment or by a turret.
+        killer: Option<String>,
+        victim: String,
+        weapon: Option<WeaponKind>,
+    },
+    Damage {
+        dealer: Option<String>,
+        victim: String,
+        /// Damage after mutators are applied, before armor absorbs it.
+        amount: f32,
+        weapon: Option<WeaponKind>,
+    },
+    Pickup {
+        actor: String,
+        item: ItemKind,
+    },
+}
+
+/// Receives events of a match. Every method does nothing by default, so an observer only
+/// implements what it's interested in. Observers are called on the main thread in the middle
+/// of a frame, they should not block.
+pub trait GameObserver {
+    fn on_match_start(&mut self, _map: &Path, _mode: &str) {}
+
+    fn on_match_end(&mut self, _scores: &[(String, u32)]) {}
+
+    fn on_kill(&mut self, _killer: Option<&str>, _victim: &str, _weapon: Option<WeaponKind>) {}
+
+    fn on_damage(
+        &mut self,
+        _dealer: Option<&str>,
+        _victim: &str,
+        _amount: f32,
+        _weapon: Option<WeaponKind>,
+    ) {
+    }
+
+    fn on_pickup(&mut self, _actor: &str, _item: ItemKind) {}
+}
+
+/// Passes event to every observer in order of registration.
+pub fn dispatch(observers: &mut [Box<dyn GameObserver>], event: &GameEvent) {
+    for observer in observers.iter_mut() {
+        match event {
+            GameEvent::MatchStart { map, mode } => observer.on_match_start(map, mode),
+            GameEvent::MatchEnd { scores } => observer.on_match_end(scores),
+            GameEvent::Kill {
+                killer,
+                victim,
+                weapon,
+            } => observer.on_kill(killer.as_deref(), victim, *weapon),
+            GameEvent::Damage {
+                dealer,
+                victim,
+                amount,
+                weapon,
+            } => observer.on_damage(dealer.as_deref(), victim, *amount, *weapon),
+            GameEvent::Pickup { actor, item } => observer.on_pickup(actor, *item),
+        }
+    }
+}
+
+/// Appends every event as a line of JSON to `events.log` in user data directory, for example
+/// `{"time":1700000000.5,"event":"kill","killer":"Player","victim":"Bot 1","weapon":"Ak47"}`.
+/// The file is never truncated, tools are expected to tail it.
+pub struct EventLogObserver {
+    /// `None` if the file can't be opened, the observer does nothing then.
+    writer: Option<LineWriter<File>>,
+}
+
+impl EventLogObserver {
+    pub fn open() -> Self {
+        let path = user_data_dir().join(EVENT_LOG_FILE_NAME);
+        let writer = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => {
+                Logger::writeln(
+                    MessageKind::Information,
+                    format!("Game events are written to {}", path.display()),
+                );
+                Some(LineWriter::new(file))
+            }
+            Err(e) => {
+                Logger::writeln(
+                    MessageKind::Error,
+                    format!("Unable to open event log {}: {}", path.display(), e),
+                );
+                None
+            }
+        };
+        Self { writer }
+    }
+
+    /// Writes a line with given event name and already formatted `,"key":value` fields.
+    fn write(&mut self, event: &str, fields: &str) {
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return,
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |time| time.as_secs_f64());
+        let line = format!(
+            "{{\"time\":{:.3},\"event\":\"{}\"{}}}\n",
+            time, event, fields
+        );
+        if let Err(e) = writer.write_all(line.as_bytes()) {
+            Logger::writeln(
+                MessageKind::Error,
+                format!("Unable to write event log: {}. Event log is closed.", e),
+            );
+            self.writer = None;
+        }
+    }
+}
+
+/// JSON string literal with quotes.
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// JSON has no NaN and infinities, such values are written as `null`.
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_owned()
+    }
+}
+
+fn json_name(name: Option<&str>) -> String {
+    name.map_or_else(|| "null".to_owned(), json_string)
+}
+
+fn json_weapon(weapon: Option<WeaponKind>) -> String {
+    weapon.map_or_else(
+        || "null".to_owned(),
+        |weapon| json_string(&format!("{:?}", weapon)),
+    )
+}
+
+impl GameObserver for EventLogObserver {
+    fn on_match_start(&mut self, map: &Path, mode: &str) {
+        let fields = format!(
+            ",\"map\":{},\"mode\":{}",
+            json_string(&map.to_string_lossy()),
+            json_string(mode)
+        );
+        self.write("match_start", &fields);
+    }
+
+    fn on_match_end(&mut self, scores: &[(String, u32)]) {
+        let scores = scores
+            .iter()
+            .map(|(name, kills)| format!("{{\"name\":{},\"kills\":{}}}", json_string(name), kills))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.write("match_end", &format!(",\"scores\":[{}]", scores));
+    }
+
+    fn on_kill(&mut self, killer: Option<&str>, victim: &str, weapon: Option<WeaponKind>) {
+        let fields = format!(
+            ",\"killer\":{},\"victim\":{},\"weapon\":{}",
+            json_name(killer),
+            json_string(victim),
+            json_weapon(weapon)
+        );
+        self.write("kill", &fields);
+    }
+
+    fn on_damage(
+        &mut self,
+        dealer: Option<&str>,
+        victim: &str,
+        amount: f32,
+        weapon: Option<WeaponKind>,
+    ) {
+        let fields = format!(
+            ",\"dealer\":{},\"victim\":{},\"amount\":{},\"weapon\":{}",
+            json_name(dealer),
+            json_string(victim),
+            json_number(amount),
+            json_weapon(weapon)
+        );
+        self.write("damage", &fields);
+    }
+
+    fn on_pickup(&mut self, actor: &str, item: ItemKind) {
+        let fields = format!(
+            ",\"actor\":{},\"item\":{}",
+            json_string(actor),
+            json_string(&format!("{:?}", item))
+        );
+        self.write("pickup", &fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        time::{Duration, Instant},
+    };
+
+    /// Writes calls it receives into a log shared with the test.
+    struct Recorder {
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl GameObserver for Recorder {
+        fn on_kill(&mut self, _killer: Option<&str>, victim: &str, _weapon: Option<WeaponKind>) {
+            self.log.borrow_mut().push(format!("kill {}", victim));
+        }
+
+        fn on_damage(
+            &mut self,
+            _dealer: Option<&str>,
+            victim: &str,
+            amount: f32,
+            _weapon: Option<WeaponKind>,
+        ) {
+            self.log
+                .borrow_mut()
+                .push(format!("damage {} {}", victim, amount));
+        }
+    }
+
+    fn damage(amount: f32) -> GameEvent {
+        GameEvent::Damage {
+            dealer: Some("Player".to_owned()),
+            victim: "Bot 1".to_owned(),
+            amount,
+            weapon: Some(WeaponKind::Ak47),
+        }
+    }
+
+    #[test]
+    fn dispatch_without_observers_is_cheap() {
+        // Level reports every hit, dispatch with nobody listening must cost next to nothing.
+        let event = damage(10.0);
+        let clock = Instant::now();
+        for _ in 0..100_000 {
+            dispatch(&mut [], &event);
+        }
+        let elapsed = clock.elapsed();
+        println!("100000 dispatches without observers took {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn dispatch_reaches_every_observer_in_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut observers: Vec<Box<dyn GameObserver>> = vec![
+            Box::new(Recorder { log: log.clone() }),
+            Box::new(Recorder { log: log.clone() }),
+        ];
+        dispatch(&mut observers, &damage(25.0));
+        dispatch(
+            &mut observers,
+            &GameEvent::Kill {
+                killer: None,
+                victim: "Bot 1".to_owned(),
+                weapon: None,
+            },
+        );
+        // Events an observer doesn't implement are ignored.
+        dispatch(
+            &mut observers,
+            &GameEvent::Pickup {
+                actor: "Bot 1".to_owned(),
+                item: ItemKind::Medkit,
+            },
+        );
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "damage Bot 1 25",
+                "damage Bot 1 25",
+                "kill Bot 1",
+                "kill Bot 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers_are_written_as_null() {
+        assert_eq!(json_number(12.5), "12.5");
+        assert_eq!(json_number(f32::NAN), "null");
+        assert_eq!(json_number(f32::INFINITY), "null");
+        assert_eq!(json_number(f32::NEG_INFINITY), "null");
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(json_string("Bot \"1\""), "\"Bot \\\"1\\\"\"");
+        assert_eq!(json_string("a\\b\n\u{1}"), "\"a\\\\b\\n\\u0001\"");
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
-    control_scheme::{ControlButton, ControlScheme},
+    control_scheme::{ControlButton, ControlProfiles, ControlScheme},
     gui::{create_check_box, create_scroll_bar, create_scroll_viewer, ScrollBarData},
+    logger::Logger,
     message::Message,
+    settings::Preferences,
+    weapon::{Weapon, WeaponKind},
 };
 use fyrox::{
     core::pool::Handle,
@@ -12,18 +15,20 @@ use fyrox::{
         button::{Button, ButtonBuilder, ButtonMessage},
         check_box::CheckBoxMessage,
         decorator::DecoratorBuilder,
+        dropdown_list::{DropdownListBuilder, DropdownListMessage},
         grid::{Column, GridBuilder, Row},
         list_view::{ListViewBuilder, ListViewMessage},
         message::{MessageDirection, UiMessage},
         scroll_bar::ScrollBarMessage,
         tab_control::{TabControlBuilder, TabDefinition},
         text::{TextBuilder, TextMessage},
-        widget::WidgetBuilder,
-        window::{WindowBuilder, WindowTitle},
-        HorizontalAlignment, Orientation, Thickness, UiNode, VerticalAlignment,
+        widget::{WidgetBuilder, WidgetMessage},
+        window::{WindowBuilder, WindowMessage, WindowTitle},
+        BuildContext, HorizontalAlignment, Orientation, Thickness, UiNode, UserInterface,
+        VerticalAlignment,
     },
     monitor::VideoMode,
-    utils::log::{Log, MessageKind},
+    utils::log::MessageKind,
     window::Fullscreen,
 };
 use std::sync::{mpsc::Sender, Arc, RwLock};
@@ -44,12 +49,27 @@ pub struct OptionsMenu {
     cb_use_light_scatter: Handle<UiNode>,
     video_modes: Vec<VideoMode>,
     control_scheme: Arc<RwLock<ControlScheme>>,
+    /// Shared with the game, edits are picked up live.
+    preferences: Arc<RwLock<Preferences>>,
+    /// All control profiles, active one is edited through `control_scheme`.
+    profiles: ControlProfiles,
+    dd_profiles: Handle<UiNode>,
+    btn_duplicate_profile: Handle<UiNode>,
+    btn_delete_profile: Handle<UiNode>,
     control_scheme_buttons: Vec<Handle<UiNode>>,
     active_control_button: Option<usize>,
     sb_mouse_sens: Handle<UiNode>,
     cb_mouse_y_inverse: Handle<UiNode>,
     cb_smooth_mouse: Handle<UiNode>,
     cb_shake_camera: Handle<UiNode>,
+    cb_auto_switch_weapon: Handle<UiNode>,
+    cb_show_pickup_notifications: Handle<UiNode>,
+    cb_show_damage_numbers: Handle<UiNode>,
+    cb_show_health_bars: Handle<UiNode>,
+    cb_show_bot_intents: Handle<UiNode>,
+    cb_laser_sight: Handle<UiNode>,
+    /// Skin list of every weapon kind.
+    dd_weapon_skins: Vec<(WeaponKind, Handle<UiNode>)>,
     btn_reset_control_scheme: Handle<UiNode>,
     cb_use_hrtf: Handle<UiNode>,
     btn_reset_audio_settings: Handle<UiNode>,
@@ -59,6 +79,8 @@ impl OptionsMenu {
     pub fn new(
         engine: &mut Engine,
         control_scheme: Arc<RwLock<ControlScheme>>,
+        preferences: Arc<RwLock<Preferences>>,
+        profiles: ControlProfiles,
         sender: Sender<Message>,
     ) -> Self {
         let video_modes: Vec<VideoMode> = engine
@@ -91,11 +113,54 @@ impl OptionsMenu {
         let cb_mouse_y_inverse;
         let cb_smooth_mouse;
         let cb_shake_camera;
+        let cb_auto_switch_weapon;
+        let cb_show_pickup_notifications;
+        let cb_show_damage_numbers;
+        let cb_show_health_bars;
+        let cb_show_bot_intents;
+        let cb_laser_sight;
         let btn_reset_control_scheme;
+        let dd_profiles;
+        let btn_duplicate_profile;
+        let btn_delete_profile;
         let mut control_scheme_buttons = Vec::new();
         let cb_use_hrtf;
         let btn_reset_audio_settings;
         let cb_use_light_scatter;
+
+        let mut dd_weapon_skins = Vec::new();
+        let mut weapon_skin_rows = Vec::new();
+        for (row, &kind) in WeaponKind::ALL.iter().enumerate() {
+            weapon_skin_rows.push(
+                TextBuilder::new(
+                    WidgetBuilder::new()
+                        .on_row(row)
+                        .on_column(0)
+                        .with_margin(margin),
+                )
+                .with_text(kind.display_name())
+                .with_vertical_text_alignment(VerticalAlignment::Center)
+                .build(ctx),
+            );
+            let names = Weapon::get_definition(kind).skin_names();
+            let selected = preferences
+                .read()
+                .unwrap()
+                .weapon_skin(kind)
+                .min(names.len() - 1);
+            let dropdown = DropdownListBuilder::new(
+                WidgetBuilder::new()
+                    .on_row(row)
+                    .on_column(1)
+                    .with_margin(margin),
+            )
+            .with_items(names.iter().map(|name| make_list_item(ctx, name)).collect())
+            .with_selected(selected)
+            .build(ctx);
+            weapon_skin_rows.push(dropdown);
+            dd_weapon_skins.push((kind, dropdown));
+        }
+
         let tab_control = TabControlBuilder::new(WidgetBuilder::new())
             .with_tab(TabDefinition {
                 header: {
@@ -453,7 +518,7 @@ impl OptionsMenu {
                     for (row, button) in control_scheme.read().unwrap().buttons().iter().enumerate()
                     {
                         // Offset by total amount of rows that goes before
-                        let row = row + 4;
+                        let row = row + 10;
 
                         let text = TextBuilder::new(
                             WidgetBuilder::new()
@@ -478,8 +543,73 @@ impl OptionsMenu {
                         control_scheme_buttons.push(button);
                     }
 
-                    GridBuilder::new(
+                    let profile_bar = GridBuilder::new(
+                        WidgetBuilder::new()
+                            .on_row(0)
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(0)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Profile")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                dd_profiles = DropdownListBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(0)
+                                        .on_column(1)
+                                        .with_margin(margin),
+                                )
+                                .with_items(
+                                    profiles
+                                        .names()
+                                        .map(|name| make_list_item(ctx, name))
+                                        .collect(),
+                                )
+                                .with_selected(profiles.active_index())
+                                .build(ctx);
+                                dd_profiles
+                            })
+                            .with_child({
+                                btn_duplicate_profile = ButtonBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(0)
+                                        .on_column(2)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Duplicate")
+                                .build(ctx);
+                                btn_duplicate_profile
+                            })
+                            .with_child({
+                                btn_delete_profile = ButtonBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(0)
+                                        .on_column(3)
+                                        .with_margin(margin)
+                                        .with_enabled(
+                                            !profiles.is_builtin(profiles.active_index()),
+                                        ),
+                                )
+                                .with_text("Delete")
+                                .build(ctx);
+                                btn_delete_profile
+                            }),
+                    )
+                    .add_column(Column::strict(250.0))
+                    .add_column(Column::stretch())
+                    .add_column(Column::strict(80.0))
+                    .add_column(Column::strict(80.0))
+                    .add_row(common_row)
+                    .build(ctx);
+
+                    let bindings = GridBuilder::new(
                         WidgetBuilder::new()
+                            .on_row(1)
                             .with_child(
                                 TextBuilder::new(
                                     WidgetBuilder::new()
@@ -568,10 +698,130 @@ impl OptionsMenu {
                                 );
                                 cb_shake_camera
                             })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(4)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Auto-switch Weapon On Pickup")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_auto_switch_weapon = create_check_box(
+                                    ctx,
+                                    4,
+                                    1,
+                                    preferences.read().unwrap().auto_switch_weapon,
+                                );
+                                cb_auto_switch_weapon
+                            })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(5)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Show Pickup Notifications")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_show_pickup_notifications = create_check_box(
+                                    ctx,
+                                    5,
+                                    1,
+                                    preferences.read().unwrap().show_pickup_notifications,
+                                );
+                                cb_show_pickup_notifications
+                            })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(6)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Show Damage Numbers")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_show_damage_numbers = create_check_box(
+                                    ctx,
+                                    6,
+                                    1,
+                                    preferences.read().unwrap().show_damage_numbers,
+                                );
+                                cb_show_damage_numbers
+                            })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(7)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Show Health Bars")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_show_health_bars = create_check_box(
+                                    ctx,
+                                    7,
+                                    1,
+                                    preferences.read().unwrap().show_health_bars,
+                                );
+                                cb_show_health_bars
+                            })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(8)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Laser Sight")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_laser_sight = create_check_box(
+                                    ctx,
+                                    8,
+                                    1,
+                                    preferences.read().unwrap().laser_sight,
+                                );
+                                cb_laser_sight
+                            })
+                            .with_child(
+                                TextBuilder::new(
+                                    WidgetBuilder::new()
+                                        .on_row(9)
+                                        .on_column(0)
+                                        .with_margin(margin),
+                                )
+                                .with_text("Bot Intent Feed")
+                                .with_vertical_text_alignment(VerticalAlignment::Center)
+                                .build(ctx),
+                            )
+                            .with_child({
+                                cb_show_bot_intents = create_check_box(
+                                    ctx,
+                                    9,
+                                    1,
+                                    preferences.read().unwrap().show_bot_intents,
+                                );
+                                cb_show_bot_intents
+                            })
                             .with_child({
                                 btn_reset_control_scheme = ButtonBuilder::new(
                                     WidgetBuilder::new()
-                                        .on_row(4 + control_scheme.read().unwrap().buttons().len())
+                                        .on_row(10 + control_scheme.read().unwrap().buttons().len())
                                         .with_margin(margin),
                                 )
                                 .with_text("Reset")
@@ -586,15 +836,45 @@ impl OptionsMenu {
                     .add_row(common_row)
                     .add_row(common_row)
                     .add_row(common_row)
+                    .add_row(common_row)
+                    .add_row(common_row)
+                    .add_row(common_row)
+                    .add_row(common_row)
+                    .add_row(common_row)
+                    .add_row(common_row)
                     .add_rows(
                         (0..control_scheme.read().unwrap().buttons().len())
                             .map(|_| common_row)
                             .collect(),
                     )
                     .add_row(common_row)
+                    .build(ctx);
+
+                    GridBuilder::new(
+                        WidgetBuilder::new()
+                            .with_child(profile_bar)
+                            .with_child(bindings),
+                    )
+                    .add_column(Column::stretch())
+                    .add_row(common_row)
+                    .add_row(Row::stretch())
                     .build(ctx)
                 },
             })
+            .with_tab(TabDefinition {
+                header: {
+                    TextBuilder::new(WidgetBuilder::new().with_width(100.0).with_height(30.0))
+                        .with_text("Weapons")
+                        .build(ctx)
+                },
+                content: {
+                    GridBuilder::new(WidgetBuilder::new().with_children(weapon_skin_rows))
+                        .add_rows(WeaponKind::ALL.iter().map(|_| common_row).collect())
+                        .add_column(Column::strict(250.0))
+                        .add_column(Column::stretch())
+                        .build(ctx)
+                },
+            })
             .build(ctx);
 
         let options_window: Handle<UiNode> =
@@ -619,12 +899,24 @@ impl OptionsMenu {
             sb_spot_shadow_distance,
             video_modes,
             control_scheme,
+            preferences,
+            profiles,
+            dd_profiles,
+            btn_duplicate_profile,
+            btn_delete_profile,
             control_scheme_buttons,
             active_control_button: None,
             sb_mouse_sens,
             cb_mouse_y_inverse,
             cb_smooth_mouse,
             cb_shake_camera,
+            cb_auto_switch_weapon,
+            cb_show_pickup_notifications,
+            cb_show_damage_numbers,
+            cb_show_health_bars,
+            cb_show_bot_intents,
+            cb_laser_sight,
+            dd_weapon_skins,
             btn_reset_control_scheme,
             cb_use_hrtf,
             btn_reset_audio_settings,
@@ -636,6 +928,7 @@ impl OptionsMenu {
         let sound_gain = engine.sound_gain();
         let ui = &mut engine.user_interface;
         let control_scheme = self.control_scheme.read().unwrap();
+        let preferences = self.preferences.read().unwrap();
         let settings = engine.renderer.get_quality_settings();
 
         let sync_check_box = |handle: Handle<UiNode>, value: bool| {
@@ -653,6 +946,22 @@ impl OptionsMenu {
         sync_check_box(self.cb_mouse_y_inverse, control_scheme.mouse_y_inverse);
         sync_check_box(self.cb_smooth_mouse, control_scheme.smooth_mouse);
         sync_check_box(self.cb_shake_camera, control_scheme.shake_camera);
+        sync_check_box(self.cb_auto_switch_weapon, preferences.auto_switch_weapon);
+        sync_check_box(
+            self.cb_show_pickup_notifications,
+            preferences.show_pickup_notifications,
+        );
+        sync_check_box(self.cb_show_damage_numbers, preferences.show_damage_numbers);
+        sync_check_box(self.cb_show_health_bars, preferences.show_health_bars);
+        sync_check_box(self.cb_show_bot_intents, preferences.show_bot_intents);
+        sync_check_box(self.cb_laser_sight, preferences.laser_sight);
+        for &(kind, dropdown) in self.dd_weapon_skins.iter() {
+            ui.send_message(DropdownListMessage::selection(
+                dropdown,
+                MessageDirection::ToWidget,
+                Some(preferences.weapon_skin(kind)),
+            ));
+        }
         let is_hrtf = true; /*if let fyrox::sound::renderer::Renderer::HrtfRenderer(_) =
                                 engine.sound_context.lock().unwrap().renderer()
                             {
@@ -692,6 +1001,55 @@ impl OptionsMenu {
         }
     }
 
+    /// Rebuilds list of profiles after a profile was added or removed.
+    fn sync_profiles(&mut self, ui: &mut UserInterface) {
+        let items = self
+            .profiles
+            .names()
+            .map(|name| make_list_item(&mut ui.build_ctx(), name))
+            .collect();
+        ui.send_message(DropdownListMessage::items(
+            self.dd_profiles,
+            MessageDirection::ToWidget,
+            items,
+        ));
+        ui.send_message(DropdownListMessage::selection(
+            self.dd_profiles,
+            MessageDirection::ToWidget,
+            Some(self.profiles.active_index()),
+        ));
+        ui.send_message(WidgetMessage::enabled(
+            self.btn_delete_profile,
+            MessageDirection::ToWidget,
+            !self.profiles.is_builtin(self.profiles.active_index()),
+        ));
+    }
+
+    /// Refreshes widgets after another profile became active. Profiles are saved right away,
+    /// so crash flush of settings always stores bindings into the right profile.
+    fn on_profile_changed(&mut self, engine: &mut Engine) {
+        self.active_control_button = None;
+        self.sync_profiles(&mut engine.user_interface);
+        self.sync_to_model(engine);
+        self.save_profiles();
+    }
+
+    /// All profiles with not yet saved changes of active one.
+    pub fn pending_profiles(&self) -> ControlProfiles {
+        let mut profiles = self.profiles.clone();
+        profiles.store(&self.control_scheme.read().unwrap());
+        profiles
+    }
+
+    fn save_profiles(&mut self) {
+        self.profiles = self.pending_profiles();
+        self.sender
+            .send(Message::SaveControlProfiles {
+                profiles: self.profiles.clone(),
+            })
+            .unwrap();
+    }
+
     pub fn process_input_event(&mut self, engine: &mut Engine, event: &Event<()>) {
         if let Event::WindowEvent { event, .. } = event {
             let mut control_button = None;
@@ -770,6 +1128,39 @@ impl OptionsMenu {
                         .unwrap();
                 }
             }
+        } else if let Some(DropdownListMessage::SelectionChanged(Some(index))) = message.data() {
+            if message.destination() == self.dd_profiles
+                && message.direction() == MessageDirection::FromWidget
+                && *index != self.profiles.active_index()
+            {
+                self.profiles
+                    .select(*index, &mut self.control_scheme.write().unwrap());
+                self.on_profile_changed(engine);
+            } else if message.direction() == MessageDirection::FromWidget {
+                // Skin applies to weapons given after the change.
+                if let Some(&(kind, _)) = self
+                    .dd_weapon_skins
+                    .iter()
+                    .find(|(_, dropdown)| message.destination() == *dropdown)
+                {
+                    self.preferences
+                        .write()
+                        .unwrap()
+                        .weapon_skins
+                        .insert(kind, *index as u32);
+                }
+            }
+        } else if let Some(WindowMessage::Close) = message.data() {
+            if message.destination() == self.window {
+                // Bindings and preferences are saved once options are closed, not on every
+                // change.
+                self.save_profiles();
+                self.sender
+                    .send(Message::SavePreferences {
+                        preferences: self.preferences.read().unwrap().clone(),
+                    })
+                    .unwrap();
+            }
         } else if let Some(ListViewMessage::SelectionChanged(new_value)) = message.data() {
             if message.destination() == self.lb_video_modes {
                 if let Some(index) = new_value {
@@ -782,6 +1173,7 @@ impl OptionsMenu {
         } else if let Some(CheckBoxMessage::Check(value)) = message.data() {
             let value = value.unwrap_or(false);
             let mut control_scheme = self.control_scheme.write().unwrap();
+            let mut preferences = self.preferences.write().unwrap();
             if message.destination() == self.cb_point_shadows {
                 settings.point_shadows_enabled = value;
             } else if message.destination() == self.cb_spot_shadows {
@@ -796,6 +1188,18 @@ impl OptionsMenu {
                 control_scheme.smooth_mouse = value;
             } else if message.destination() == self.cb_shake_camera {
                 control_scheme.shake_camera = value;
+            } else if message.destination() == self.cb_auto_switch_weapon {
+                preferences.auto_switch_weapon = value;
+            } else if message.destination() == self.cb_show_pickup_notifications {
+                preferences.show_pickup_notifications = value;
+            } else if message.destination() == self.cb_show_damage_numbers {
+                preferences.show_damage_numbers = value;
+            } else if message.destination() == self.cb_show_health_bars {
+                preferences.show_health_bars = value;
+            } else if message.destination() == self.cb_show_bot_intents {
+                preferences.show_bot_intents = value;
+            } else if message.destination() == self.cb_laser_sight {
+                preferences.laser_sight = value;
             } else if message.destination() == self.cb_use_light_scatter {
                 settings.light_scatter_enabled = value;
             }
@@ -806,6 +1210,17 @@ impl OptionsMenu {
             } else if message.destination() == self.btn_reset_audio_settings {
                 engine.set_sound_gain(1.0);
                 self.sync_to_model(engine);
+            } else if message.destination() == self.btn_duplicate_profile {
+                self.profiles
+                    .duplicate(&mut self.control_scheme.write().unwrap());
+                self.on_profile_changed(engine);
+            } else if message.destination() == self.btn_delete_profile {
+                if self
+                    .profiles
+                    .delete_active(&mut self.control_scheme.write().unwrap())
+                {
+                    self.on_profile_changed(engine);
+                }
             }
 
             for (i, button) in self.control_scheme_buttons.iter().enumerate() {
@@ -825,7 +1240,7 @@ impl OptionsMenu {
 
         if settings != old_settings {
             if let Err(err) = engine.renderer.set_quality_settings(&settings) {
-                Log::writeln(
+                Logger::writeln(
                     MessageKind::Error,
                     format!("Failed to set renderer quality settings! Reason: {:?}", err),
                 );
@@ -833,3 +1248,18 @@ impl OptionsMenu {
         }
     }
 }
+
+fn make_list_item(ctx: &mut BuildContext, name: &str) -> Handle<UiNode> {
+    DecoratorBuilder::new(BorderBuilder::new(
+        WidgetBuilder::new().with_height(30.0).with_child(
+            TextBuilder::new(
+                WidgetBuilder::new()
+                    .with_horizontal_alignment(HorizontalAlignment::Center)
+                    .with_vertical_alignment(VerticalAlignment::Center),
+            )
+            .with_text(name)
+            .build(ctx),
+        ),
+    ))
+    .build(ctx)
+}
@@ -1,12 +1,17 @@
 use crate::{
-    character::Character,
+    character::{Character, FOOTSTEP_SOUNDS},
     control_scheme::{ControlButton, ControlScheme},
+    grapple::Grapple,
     level::UpdateContext,
-    message::Message,
+    message::{Message, MessageSender},
+    net::RemoteInput,
+    settings::Preferences,
+    weapon::{WeaponContainer, WeaponKind},
 };
 use fyrox::{
     core::{
         algebra::{UnitQuaternion, Vector3},
+        color::Color,
         math::Vector3Ext,
         pool::Handle,
         rand::Rng,
@@ -16,9 +21,10 @@ use fyrox::{
     rand,
     scene::{
         base::BaseBuilder,
-        camera::CameraBuilder,
+        camera::{CameraBuilder, Projection},
         collider::{ColliderBuilder, ColliderShape},
-        graph::physics::CoefficientCombineRule,
+        graph::{physics::CoefficientCombineRule, Graph},
+        light::{spot::SpotLightBuilder, BaseLightBuilder},
         node::Node,
         pivot::PivotBuilder,
         rigidbody::{RigidBodyBuilder, RigidBodyType},
@@ -29,9 +35,84 @@ use fyrox::{
 };
 use std::{
     ops::{Deref, DerefMut},
-    sync::{mpsc::Sender, Arc, RwLock},
+    sync::{Arc, RwLock},
 };
 
+pub const PLAYER_NAME: &str = "Player";
+/// Time in seconds to zoom in or out completely.
+const ZOOM_TIME: f32 = 0.15;
+/// Damage taken in one tick that throws player out of zoom.
+const ZOOM_BREAK_DAMAGE: f32 = 25.0;
+/// Time in seconds for which full battery of flashlight lasts.
+const FLASHLIGHT_BATTERY_TIME: f32 = 60.0;
+/// Time in seconds to charge empty battery while flashlight is off.
+const FLASHLIGHT_RECHARGE_TIME: f32 = 30.0;
+const FLASHLIGHT_SOUND: &str = "data/sounds/bullet_impact_metal.ogg";
+/// Player that gives no input for this time in seconds during a timed match is taken out of
+/// the match until the next key press, so bots can't farm frags on an empty body.
+const AFK_TIME: f32 = 60.0;
+
+/// Returns true if event is a press of a key or a mouse button.
+pub fn is_press(event: &Event<()>) -> bool {
+    match event {
+        Event::DeviceEvent {
+            event: DeviceEvent::Button { state, .. },
+            ..
+        } => *state == ElementState::Pressed,
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { input, .. },
+            ..
+        } => input.state == ElementState::Pressed,
+        _ => false,
+    }
+}
+
+/// Tunables of player movement.
+pub struct MovementParams;
+
+impl MovementParams {
+    /// Vertical speed in m/s that is given by a jump.
+    pub const JUMP_SPEED: f32 = 4.2;
+    /// Jump pressed in the air is performed if player lands within this time in seconds.
+    pub const JUMP_BUFFER_TIME: f32 = 0.12;
+    /// Player can still jump within this time in seconds after losing ground contact, for
+    /// example right after walking off a ledge.
+    pub const COYOTE_TIME: f32 = 0.1;
+}
+
+/// Decides on which tick jump happens, see [`MovementParams`].
+#[derive(Default)]
+struct JumpState {
+    /// Time left until pressed jump is forgotten, zero if there is no pressed jump.
+    buffered: f32,
+    /// Time since player lost ground contact.
+    air_time: f32,
+}
+
+impl JumpState {
+    /// Returns true if player must jump on this tick.
+    fn update(&mut self, jump_pressed: bool, has_ground_contact: bool, dt: f32) -> bool {
+        if jump_pressed {
+            self.buffered = MovementParams::JUMP_BUFFER_TIME;
+        }
+        if has_ground_contact {
+            self.air_time = 0.0;
+        } else {
+            self.air_time += dt;
+        }
+
+        if self.buffered > 0.0 && self.air_time <= MovementParams::COYOTE_TIME {
+            self.buffered = 0.0;
+            // No second jump in the air, coyote time is for falling off ledges only.
+            self.air_time = f32::MAX;
+            true
+        } else {
+            self.buffered = (self.buffered - dt).max(0.0);
+            false
+        }
+    }
+}
+
 pub struct Controller {
     move_forward: bool,
     move_backward: bool,
@@ -41,6 +122,11 @@ pub struct Controller {
     jump: bool,
     run: bool,
     shoot: bool,
+    zoom: bool,
+    toggle_flashlight: bool,
+    grapple: bool,
+    use_item: bool,
+    toggle_laser: bool,
 }
 
 impl Default for Controller {
@@ -54,16 +140,19 @@ impl Default for Controller {
             jump: false,
             run: false,
             shoot: false,
+            zoom: false,
+            toggle_flashlight: false,
+            grapple: false,
+            use_item: false,
+            toggle_laser: false,
         }
     }
 }
 
-#[derive(Visit)]
 pub struct Player {
     character: Character,
     camera: Handle<Node>,
     camera_pivot: Handle<Node>,
-    #[visit(skip)]
     controller: Controller,
     yaw: f32,
     dest_yaw: f32,
@@ -85,8 +174,97 @@ pub struct Player {
     weapon_shake_factor: f32,
     crouch_speed: f32,
     stand_up_speed: f32,
-    #[visit(skip)]
     control_scheme: Option<Arc<RwLock<ControlScheme>>>,
+    preferences: Option<Arc<RwLock<Preferences>>>,
+    noclip: bool,
+    /// Zoom progress, 0.0 - no zoom, 1.0 - fully zoomed in.
+    zoom: f32,
+    /// Set when zoom was broken by running or heavy hit, zoom button must be pressed again.
+    zoom_interrupted: bool,
+    zoom_fov: f32,
+    base_fov: f32,
+    last_health: f32,
+    flashlight: Handle<Node>,
+    flashlight_on: bool,
+    /// Charge of flashlight battery, 0.0 - empty, 1.0 - full.
+    flashlight_battery: f32,
+    grapple: Grapple,
+    /// Hook is fired on press of the key, holding the key does not fire it again.
+    grapple_key_held: bool,
+    jump_state: JumpState,
+    /// Time in seconds since last input, see [`AFK_TIME`].
+    idle_time: f32,
+    /// Fire button was held on previous update, charged weapons react to press and release.
+    shoot_held: bool,
+}
+
+impl Visit for Player {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.character.visit("character", visitor)?;
+        self.camera.visit("camera", visitor)?;
+        self.camera_pivot.visit("camera_pivot", visitor)?;
+        self.yaw.visit("yaw", visitor)?;
+        self.dest_yaw.visit("dest_yaw", visitor)?;
+        self.pitch.visit("pitch", visitor)?;
+        self.dest_pitch.visit("dest_pitch", visitor)?;
+        self.run_speed_multiplier
+            .visit("run_speed_multiplier", visitor)?;
+        self.stand_body_height.visit("stand_body_height", visitor)?;
+        self.crouch_body_height
+            .visit("crouch_body_height", visitor)?;
+        self.move_speed.visit("move_speed", visitor)?;
+        self.camera_offset.visit("camera_offset", visitor)?;
+        self.camera_dest_offset
+            .visit("camera_dest_offset", visitor)?;
+        self.path_len.visit("path_len", visitor)?;
+        self.feet_position.visit("feet_position", visitor)?;
+        self.head_position.visit("head_position", visitor)?;
+        self.look_direction.visit("look_direction", visitor)?;
+        self.up_direction.visit("up_direction", visitor)?;
+        self.weapon_offset.visit("weapon_offset", visitor)?;
+        self.weapon_dest_offset
+            .visit("weapon_dest_offset", visitor)?;
+        self.weapon_shake_factor
+            .visit("weapon_shake_factor", visitor)?;
+        self.crouch_speed.visit("crouch_speed", visitor)?;
+        self.stand_up_speed.visit("stand_up_speed", visitor)?;
+
+        // Saves made before flashlight and grappling hook have no such fields, their nodes are
+        // created after load, see `restore_missing_nodes`.
+        let _ = self.flashlight.visit("flashlight", visitor);
+        let _ = self.flashlight_on.visit("flashlight_on", visitor);
+        let _ = self.flashlight_battery.visit("flashlight_battery", visitor);
+        let _ = self.grapple.visit("grapple", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+/// Creates flashlight, it must be attached to camera to shine where player looks.
+fn build_flashlight(graph: &mut Graph) -> Handle<Node> {
+    SpotLightBuilder::new(
+        BaseLightBuilder::new(
+            BaseBuilder::new()
+                .with_visibility(false)
+                // Spot light shines along -Y, turn it to look where camera looks.
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_rotation(UnitQuaternion::from_axis_angle(
+                            &Vector3::x_axis(),
+                            -90.0f32.to_radians(),
+                        ))
+                        .build(),
+                ),
+        )
+        .with_color(Color::opaque(255, 250, 230))
+        .cast_shadows(false),
+    )
+    .with_distance(20.0)
+    .with_hotspot_cone_angle(30.0f32.to_radians())
+    .with_falloff_angle_delta(10.0f32.to_radians())
+    .build(graph)
 }
 
 impl Deref for Player {
@@ -131,18 +309,34 @@ impl Default for Player {
             crouch_speed: 0.15,
             stand_up_speed: 0.12,
             control_scheme: None,
+            preferences: None,
+            noclip: false,
+            zoom: 0.0,
+            zoom_interrupted: false,
+            zoom_fov: 0.0,
+            base_fov: 0.0,
+            last_health: 0.0,
+            flashlight: Handle::NONE,
+            flashlight_on: false,
+            flashlight_battery: 1.0,
+            grapple: Default::default(),
+            grapple_key_held: false,
+            jump_state: Default::default(),
+            idle_time: 0.0,
+            shoot_held: false,
         }
     }
 }
 
 impl Player {
-    pub fn new(scene: &mut Scene, sender: Sender<Message>) -> Player {
+    pub fn new(scene: &mut Scene, sender: MessageSender) -> Player {
         let height = Self::default().stand_body_height;
 
         let camera_handle;
         let camera_pivot_handle;
         let weapon_base_pivot_handle;
         let weapon_pivot_handle;
+        let flashlight;
         let collider;
         let body_handle = RigidBodyBuilder::new(BaseBuilder::new().with_children(&[
             {
@@ -180,6 +374,10 @@ impl Player {
                                     },
                                     ListenerBuilder::new(BaseBuilder::new())
                                         .build(&mut scene.graph),
+                                    {
+                                        flashlight = build_flashlight(&mut scene.graph);
+                                        flashlight
+                                    },
                                 ]),
                             )
                             .build(&mut scene.graph);
@@ -205,12 +403,14 @@ impl Player {
                 body: body_handle,
                 collider,
                 weapon_pivot: weapon_pivot_handle,
-                sender: Some(sender),
-                name: "Player".to_owned(),
+                sender,
+                name: PLAYER_NAME.to_owned(),
                 ..Default::default()
             },
             camera: camera_handle,
             camera_pivot: camera_pivot_handle,
+            flashlight,
+            grapple: Grapple::new(scene),
             ..Default::default()
         }
     }
@@ -252,15 +452,306 @@ impl Player {
         self.camera
     }
 
+    pub fn camera_pivot(&self) -> Handle<Node> {
+        self.camera_pivot
+    }
+
     pub fn set_control_scheme(&mut self, control_scheme: Arc<RwLock<ControlScheme>>) {
         self.control_scheme = Some(control_scheme);
     }
 
+    pub fn set_preferences(&mut self, preferences: Arc<RwLock<Preferences>>) {
+        self.preferences = Some(preferences);
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Releases all controls, so player will stand still.
+    pub fn stop(&mut self) {
+        self.controller = Default::default();
+    }
+
+    /// Returns current state of controls, it is sent to host when playing over network.
+    pub fn remote_input(&self) -> RemoteInput {
+        RemoteInput {
+            move_forward: self.controller.move_forward,
+            move_backward: self.controller.move_backward,
+            move_left: self.controller.move_left,
+            move_right: self.controller.move_right,
+            jump: self.controller.jump,
+            crouch: self.controller.crouch,
+            run: self.controller.run,
+            shoot: self.controller.shoot,
+            yaw: self.dest_yaw,
+            pitch: self.dest_pitch,
+        }
+    }
+
+    /// Controls player by input received from network instead of local devices.
+    pub fn apply_remote_input(&mut self, input: &RemoteInput) {
+        self.controller = Controller {
+            move_forward: input.move_forward,
+            move_backward: input.move_backward,
+            move_left: input.move_left,
+            move_right: input.move_right,
+            crouch: input.crouch,
+            // Jump is reset when performed, so it must not be released by next input.
+            jump: self.controller.jump || input.jump,
+            run: input.run,
+            shoot: input.shoot,
+            zoom: false,
+            toggle_flashlight: false,
+            grapple: false,
+            use_item: false,
+            toggle_laser: false,
+        };
+        self.dest_yaw = input.yaw;
+        self.dest_pitch = input.pitch;
+    }
+
+    /// Returns zoom progress, 0.0 - no zoom, 1.0 - fully zoomed in.
+    pub fn zoom_factor(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn is_shooting(&self) -> bool {
+        self.controller.shoot
+    }
+
+    pub fn is_crouching(&self) -> bool {
+        self.controller.crouch
+    }
+
+    /// Returns true once after Use key was pressed, level decides what it means.
+    pub fn take_use_request(&mut self) -> bool {
+        std::mem::take(&mut self.controller.use_item)
+    }
+
+    /// Returns true once after laser sight key was pressed, level toggles laser of current
+    /// weapon.
+    pub fn take_laser_toggle_request(&mut self) -> bool {
+        std::mem::take(&mut self.controller.toggle_laser)
+    }
+
+    /// Whether new weapons of the player get laser sight turned on, it comes from settings.
+    pub fn laser_sight_default(&self) -> bool {
+        self.preferences
+            .as_ref()
+            .map_or(true, |preferences| preferences.read().unwrap().laser_sight)
+    }
+
+    /// Index in [`crate::weapon::WeaponDefinition::skins`] of a skin chosen in settings for
+    /// given weapon, `None` if weapon has default look.
+    pub fn weapon_skin(&self, kind: WeaponKind) -> Option<usize> {
+        self.preferences
+            .as_ref()
+            .and_then(|preferences| preferences.read().unwrap().weapon_skin(kind).checked_sub(1))
+    }
+
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom > 0.5
+    }
+
+    /// Mouse is slowed down while zoomed in, so aim moves on screen at the same speed.
+    fn sensitivity_scale(&self) -> f32 {
+        if self.base_fov > 0.0 {
+            1.0 + (self.zoom_fov / self.base_fov - 1.0) * self.zoom
+        } else {
+            1.0
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.controller.move_forward
+            || self.controller.move_backward
+            || self.controller.move_left
+            || self.controller.move_right
+    }
+
+    fn update_zoom(&mut self, context: &mut UpdateContext) {
+        let health = self.character.health;
+        if self.last_health - health >= ZOOM_BREAK_DAMAGE
+            || (self.controller.run && self.is_moving())
+        {
+            self.zoom_interrupted = true;
+        }
+        self.last_health = health;
+
+        let zoom = self.current_weapon_zoom_fov(context.weapons);
+        if let Some(fov) = zoom {
+            self.zoom_fov = fov.to_radians();
+        }
+        let target = if zoom.is_some() && self.controller.zoom && !self.zoom_interrupted {
+            1.0
+        } else {
+            0.0
+        };
+        let step = context.time.delta / ZOOM_TIME;
+        let previous_zoom = self.zoom;
+        self.zoom = if target > self.zoom {
+            (self.zoom + step).min(target)
+        } else {
+            (self.zoom - step).max(target)
+        };
+
+        if let Projection::Perspective(perspective) = context.scene.graph[self.camera]
+            .as_camera_mut()
+            .projection_mut()
+        {
+            if previous_zoom == 0.0 {
+                self.base_fov = perspective.fov;
+            }
+            perspective.fov = self.base_fov + (self.zoom_fov - self.base_fov) * self.zoom;
+        }
+    }
+
+    fn current_weapon_zoom_fov(&self, weapons: &WeaponContainer) -> Option<f32> {
+        let weapon = *self
+            .character
+            .weapons
+            .get(self.character.current_weapon as usize)?;
+        weapons[weapon]
+            .definition()
+            .zoom
+            .as_ref()
+            .map(|zoom| zoom.fov)
+    }
+
+    /// Returns charge of flashlight battery, 0.0 - empty, 1.0 - full.
+    pub fn flashlight_battery(&self) -> f32 {
+        self.flashlight_battery
+    }
+
+    /// Returns direction of flashlight beam if flashlight is on. Bots notice the beam.
+    pub fn flashlight_direction(&self) -> Option<Vector3<f32>> {
+        if self.flashlight_on {
+            Some(self.look_direction)
+        } else {
+            None
+        }
+    }
+
+    /// Saves made before flashlight and grappling hook have no nodes for them, such nodes are
+    /// created again. Returns true if something was created.
+    pub fn restore_missing_nodes(&mut self, scene: &mut Scene) -> bool {
+        let mut restored = self.grapple.restore_missing_nodes(scene);
+        if !scene.graph.is_valid_handle(self.flashlight) {
+            self.flashlight = build_flashlight(&mut scene.graph);
+            scene.graph.link_nodes(self.flashlight, self.camera);
+            self.flashlight_on = false;
+            restored = true;
+        }
+        restored
+    }
+
+    fn set_flashlight_on(&mut self, graph: &mut Graph, on: bool) {
+        self.flashlight_on = on;
+        graph[self.flashlight].set_visibility(on);
+    }
+
+    /// Reports player as AFK once idle time reaches [`AFK_TIME`], matches without time limit
+    /// have nothing to farm for, so it is not done there.
+    fn update_idle_time(&mut self, context: &mut UpdateContext) {
+        let was_idle = self.idle_time >= AFK_TIME;
+        self.idle_time += context.time.delta;
+        if !was_idle
+            && self.idle_time >= AFK_TIME
+            && context.options.time_limit_secs() > 0.0
+            && !self.is_dead()
+        {
+            self.character.sender.send(Message::PlayerAfk);
+        }
+    }
+
+    fn update_flashlight(&mut self, context: &mut UpdateContext) {
+        let graph = &mut context.scene.graph;
+        let dt = context.time.delta;
+
+        if std::mem::take(&mut self.controller.toggle_flashlight) {
+            let on = !self.flashlight_on && self.flashlight_battery > 0.0;
+            if on != self.flashlight_on {
+                self.set_flashlight_on(graph, on);
+                self.character.sender.send(Message::PlaySound {
+                    path: FLASHLIGHT_SOUND.into(),
+                    position: self.head_position,
+                    gain: 0.2,
+                    rolloff_factor: 2.0,
+                    radius: 1.0,
+                });
+            }
+        }
+
+        if self.flashlight_on {
+            self.flashlight_battery -= dt / FLASHLIGHT_BATTERY_TIME;
+            if self.flashlight_battery <= 0.0 {
+                self.flashlight_battery = 0.0;
+                self.set_flashlight_on(graph, false);
+            }
+        } else {
+            self.flashlight_battery =
+                (self.flashlight_battery + dt / FLASHLIGHT_RECHARGE_TIME).min(1.0);
+        }
+    }
+
+    fn update_grapple(&mut self, context: &mut UpdateContext) {
+        let graph = &mut context.scene.graph;
+        let has_ground_contact = !self.noclip && self.character.has_ground_contact(graph);
+
+        let key_held =
+            self.controller.grapple && context.options.mutators().grapple && !self.noclip;
+        if key_held && !self.grapple_key_held {
+            self.grapple.fire(
+                graph,
+                self.head_position,
+                self.look_direction,
+                self.character.collider,
+            );
+        } else if !key_held {
+            self.grapple.release(graph, has_ground_contact);
+        }
+        self.grapple_key_held = key_held;
+
+        let hand = graph[self.character.weapon_pivot].global_position();
+        self.grapple.update(
+            graph,
+            self.character.body,
+            hand,
+            has_ground_contact,
+            context.time.delta,
+        );
+    }
+
+    pub fn is_noclip(&self) -> bool {
+        self.noclip
+    }
+
+    pub fn set_noclip(&mut self, graph: &mut Graph, noclip: bool) {
+        self.noclip = noclip;
+        graph[self.character.collider]
+            .as_collider_mut()
+            .set_is_sensor(noclip);
+        let body = graph[self.character.body].as_rigid_body_mut();
+        body.set_gravity_scale(if noclip { 0.0 } else { 1.0 });
+        body.set_lin_vel(Vector3::default());
+    }
+
     fn update_movement(&mut self, context: &mut UpdateContext) {
-        let has_ground_contact = self.character.has_ground_contact(&context.scene.graph);
+        let has_ground_contact =
+            !self.noclip && self.character.has_ground_contact(&context.scene.graph);
 
         let body = context.scene.graph[self.character.body].as_rigid_body_mut();
-        let look = body.look_vector();
+        // In noclip mode player flies where camera looks.
+        let look = if self.noclip {
+            self.look_direction
+        } else {
+            body.look_vector()
+        };
         let side = body.side_vector();
 
         let mut velocity = Vector3::default();
@@ -284,11 +775,18 @@ impl Player {
         };
 
         if let Some(normalized_velocity) = velocity.try_normalize(std::f32::EPSILON) {
-            body.set_lin_vel(Vector3::new(
-                normalized_velocity.x * self.move_speed * speed_mult,
-                body.lin_vel().y,
-                normalized_velocity.z * self.move_speed * speed_mult,
-            ));
+            // Velocity gained from grappling hook is not overridden by input.
+            if !self.grapple.controls_velocity() {
+                body.set_lin_vel(Vector3::new(
+                    normalized_velocity.x * self.move_speed * speed_mult,
+                    if self.noclip {
+                        normalized_velocity.y * self.move_speed * speed_mult
+                    } else {
+                        body.lin_vel().y
+                    },
+                    normalized_velocity.z * self.move_speed * speed_mult,
+                ));
+            }
 
             self.weapon_dest_offset.x = 0.01 * (self.weapon_shake_factor * 0.5).cos();
             self.weapon_dest_offset.y = 0.005 * self.weapon_shake_factor.sin();
@@ -302,21 +800,26 @@ impl Player {
             }
         } else {
             self.weapon_dest_offset = Vector3::default();
+
+            if self.noclip {
+                body.set_lin_vel(Vector3::default());
+            }
         }
 
         self.weapon_offset.follow(&self.weapon_dest_offset, 0.1);
 
-        if self.controller.jump {
-            if has_ground_contact {
-                let mut vel = body.lin_vel();
-                vel.y = 4.2;
-                body.set_lin_vel(vel);
-            }
-            self.controller.jump = false;
+        let jump =
+            self.jump_state
+                .update(self.controller.jump, has_ground_contact, context.time.delta);
+        self.controller.jump = false;
+        if jump {
+            let mut vel = body.lin_vel();
+            vel.y = MovementParams::JUMP_SPEED;
+            body.set_lin_vel(vel);
         }
 
         // Apply damping in XZ plane to prevent sliding.
-        if has_ground_contact {
+        if has_ground_contact && !self.grapple.is_attached() {
             let mut lin_vel = body.lin_vel();
             lin_vel.x *= 0.9;
             lin_vel.z *= 0.9;
@@ -395,6 +898,17 @@ impl Player {
         };
         let control_scheme = control_scheme.read().unwrap();
 
+        let is_motion = match event {
+            Event::DeviceEvent { event, .. } => matches!(
+                event,
+                DeviceEvent::MouseMotion { .. } | DeviceEvent::MouseWheel { .. }
+            ),
+            _ => false,
+        };
+        if is_motion || is_press(event) {
+            self.idle_time = 0.0;
+        }
+
         let mut control_button = None;
         let mut control_button_state = ElementState::Released;
 
@@ -402,12 +916,13 @@ impl Player {
         if let Event::DeviceEvent { event, .. } = event {
             match event {
                 DeviceEvent::MouseMotion { delta } => {
-                    self.dest_yaw -= delta.0 as f32 * control_scheme.mouse_sens;
+                    let mouse_sens = control_scheme.mouse_sens * self.sensitivity_scale();
+                    self.dest_yaw -= delta.0 as f32 * mouse_sens;
 
                     let sens = if control_scheme.mouse_y_inverse {
-                        -control_scheme.mouse_sens
+                        -mouse_sens
                     } else {
-                        control_scheme.mouse_sens
+                        mouse_sens
                     };
 
                     self.dest_pitch += delta.1 as f32 * sens;
@@ -475,6 +990,16 @@ impl Player {
                     self.controller.run = true;
                 } else if control_button == control_scheme.jump.button {
                     self.controller.jump = true;
+                } else if control_button == control_scheme.zoom.button {
+                    self.controller.zoom = true;
+                } else if control_button == control_scheme.flashlight.button {
+                    self.controller.toggle_flashlight = true;
+                } else if control_button == control_scheme.grapple.button {
+                    self.controller.grapple = true;
+                } else if control_button == control_scheme.use_item.button {
+                    self.controller.use_item = true;
+                } else if control_button == control_scheme.toggle_laser.button {
+                    self.controller.toggle_laser = true;
                 }
             }
             ElementState::Released => {
@@ -492,6 +1017,11 @@ impl Player {
                     self.controller.crouch = false;
                 } else if control_button == control_scheme.run.button {
                     self.controller.run = false;
+                } else if control_button == control_scheme.zoom.button {
+                    self.controller.zoom = false;
+                    self.zoom_interrupted = false;
+                } else if control_button == control_scheme.grapple.button {
+                    self.controller.grapple = false;
                 }
             }
         }
@@ -500,9 +1030,13 @@ impl Player {
     }
 
     pub fn update(&mut self, context: &mut UpdateContext) {
+        self.update_idle_time(context);
         self.update_movement(context);
+        self.update_grapple(context);
+        self.update_zoom(context);
+        self.update_flashlight(context);
 
-        if let Some(current_weapon_handle) = self
+        if let Some(&current_weapon_handle) = self
             .character
             .weapons
             .get(self.character.current_weapon as usize)
@@ -511,45 +1045,109 @@ impl Player {
                 .as_rigid_body()
                 .lin_vel();
 
-            if self.controller.shoot {
-                self.character
-                    .sender
-                    .as_ref()
-                    .unwrap()
-                    .send(Message::ShootWeapon {
-                        weapon: *current_weapon_handle,
+            let weapon = &context.weapons[current_weapon_handle];
+            if weapon.definition().charge.is_some() {
+                // Charge starts on press and the weapon shoots on release, or right away when
+                // fuse burns down in hand.
+                let burnt_down = weapon.charge_progress().map_or(false, |p| p >= 1.0);
+                if self.controller.shoot && !self.shoot_held {
+                    self.character.sender.send(Message::ChargeWeaponStart {
+                        weapon: current_weapon_handle,
+                    });
+                } else if weapon.is_charging() && (!self.controller.shoot || burnt_down) {
+                    self.character.sender.send(Message::ChargeWeaponRelease {
+                        weapon: current_weapon_handle,
                         initial_velocity,
-                        direction: None,
-                    })
-                    .unwrap();
+                    });
+                }
+            } else if self.controller.shoot {
+                self.character.sender.send(Message::ShootWeapon {
+                    weapon: current_weapon_handle,
+                    initial_velocity,
+                    direction: None,
+                });
             }
         }
+        self.shoot_held = self.controller.shoot;
 
         if self.path_len > 2.0 {
-            let footsteps = [
-                "data/sounds/footsteps/FootStep_shoe_stone_step1.wav",
-                "data/sounds/footsteps/FootStep_shoe_stone_step2.wav",
-                "data/sounds/footsteps/FootStep_shoe_stone_step3.wav",
-                "data/sounds/footsteps/FootStep_shoe_stone_step4.wav",
-            ];
-            self.character
-                .sender
-                .as_ref()
-                .unwrap()
-                .send(Message::PlaySound {
-                    path: footsteps[rand::thread_rng().gen_range(0..footsteps.len())].into(),
-                    position: self.character.position(&context.scene.graph),
-                    gain: 1.0,
-                    rolloff_factor: 2.0,
-                    radius: 3.0,
-                })
-                .unwrap();
+            self.character.sender.send(Message::PlaySound {
+                path: FOOTSTEP_SOUNDS[rand::thread_rng().gen_range(0..FOOTSTEP_SOUNDS.len())]
+                    .into(),
+                position: self.character.position(&context.scene.graph),
+                gain: 1.0,
+                rolloff_factor: 2.0,
+                radius: 3.0,
+            });
 
             self.path_len = 0.0;
         }
     }
 
     pub fn clean_up(&mut self, scene: &mut Scene) {
+        scene.remove_node(self.flashlight);
+        self.grapple.clean_up(scene);
         self.character.clean_up(scene)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// Runs given amount of ticks without jump input, returns true if player jumped.
+    fn run(state: &mut JumpState, ticks: usize, has_ground_contact: bool) -> bool {
+        (0..ticks).any(|_| state.update(false, has_ground_contact, DT))
+    }
+
+    fn falling() -> JumpState {
+        let mut state = JumpState::default();
+        assert!(!run(&mut state, 30, false));
+        state
+    }
+
+    #[test]
+    fn jump_on_ground_is_immediate() {
+        let mut state = JumpState::default();
+        assert!(!run(&mut state, 5, true));
+        assert!(state.update(true, true, DT));
+    }
+
+    #[test]
+    fn jump_pressed_just_before_landing_is_buffered() {
+        let mut state = falling();
+        assert!(!state.update(true, false, DT));
+        assert!(!run(&mut state, 4, false));
+        assert!(state.update(false, true, DT));
+        // Buffered jump is done once.
+        assert!(!run(&mut state, 10, true));
+    }
+
+    #[test]
+    fn jump_pressed_long_before_landing_is_forgotten() {
+        let mut state = falling();
+        assert!(!state.update(true, false, DT));
+        assert!(!run(&mut state, 15, false));
+        assert!(!state.update(false, true, DT));
+    }
+
+    #[test]
+    fn jump_just_after_leaving_ledge_is_allowed() {
+        let mut state = JumpState::default();
+        assert!(!run(&mut state, 5, true));
+        assert!(!run(&mut state, 3, false));
+        assert!(state.update(true, false, DT));
+        // Coyote time gives no second jump in the air.
+        assert!(!state.update(true, false, DT));
+    }
+
+    #[test]
+    fn jump_long_after_leaving_ledge_is_not_allowed() {
+        let mut state = JumpState::default();
+        assert!(!run(&mut state, 5, true));
+        assert!(!run(&mut state, 10, false));
+        assert!(!state.update(true, false, DT));
+    }
+}
@@ -0,0 +1,132 @@
+//! Simple per-section profiler for game-side code. Each section keeps rolling average of its
+//! last samples, so numbers on screen are stable enough to be readable.
+
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Formatter},
+    time::{Duration, Instant},
+};
+
+/// Amount of samples (frames) used to calculate average.
+pub const WINDOW_SIZE: usize = 60;
+
+/// Width of the longest bar in the chart, in characters.
+const BAR_WIDTH: usize = 30;
+
+#[derive(Default, Clone, Debug)]
+pub struct RollingAverage {
+    samples: VecDeque<f32>,
+    sum: f32,
+}
+
+impl RollingAverage {
+    pub fn push(&mut self, sample: f32) {
+        self.samples.push_back(sample);
+        self.sum += sample;
+        if self.samples.len() > WINDOW_SIZE {
+            if let Some(oldest) = self.samples.pop_front() {
+                self.sum -= oldest;
+            }
+        }
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.sum / self.samples.len() as f32
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    /// Sections in order of their first appearance, average values are in milliseconds.
+    sections: Vec<(&'static str, RollingAverage)>,
+}
+
+impl Profiler {
+    pub fn add_sample(&mut self, section: &'static str, duration: Duration) {
+        let ms = duration.as_secs_f32() * 1000.0;
+        if let Some((_, average)) = self.sections.iter_mut().find(|(name, _)| *name == section) {
+            average.push(ms);
+        } else {
+            let mut average = RollingAverage::default();
+            average.push(ms);
+            self.sections.push((section, average));
+        }
+    }
+
+    /// Adds time passed since `clock` to given section and restarts the clock, so sequential
+    /// sections can be measured with a single clock.
+    pub fn lap(&mut self, section: &'static str, clock: &mut Instant) {
+        let now = Instant::now();
+        self.add_sample(section, now - *clock);
+        *clock = now;
+    }
+}
+
+impl Display for Profiler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let max = self
+            .sections
+            .iter()
+            .map(|(_, average)| average.average())
+            .fold(0.0f32, f32::max);
+
+        for (name, average) in self.sections.iter() {
+            let value = average.average();
+            let bar_len = if max > 0.0 {
+                ((value / max) * BAR_WIDTH as f32) as usize
+            } else {
+                0
+            };
+            writeln!(f, "{:<12} {:>6.3} ms {}", name, value, "|".repeat(bar_len))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_average_is_zero() {
+        assert_eq!(RollingAverage::default().average(), 0.0);
+    }
+
+    #[test]
+    fn warm_up_averages_available_samples() {
+        let mut average = RollingAverage::default();
+        average.push(2.0);
+        assert_eq!(average.average(), 2.0);
+        average.push(4.0);
+        average.push(6.0);
+        assert_eq!(average.average(), 4.0);
+    }
+
+    #[test]
+    fn oldest_samples_leave_window() {
+        let mut average = RollingAverage::default();
+        for _ in 0..WINDOW_SIZE {
+            average.push(10.0);
+        }
+        assert_eq!(average.average(), 10.0);
+
+        // Replace half of the window.
+        for _ in 0..WINDOW_SIZE / 2 {
+            average.push(20.0);
+        }
+        assert_eq!(average.samples.len(), WINDOW_SIZE);
+        assert_eq!(average.average(), 15.0);
+
+        // Replace whole window, nothing of first samples is left in the sum.
+        for _ in 0..WINDOW_SIZE {
+            average.push(1.0);
+        }
+        assert_eq!(average.samples.len(), WINDOW_SIZE);
+        assert_eq!(average.average(), 1.0);
+    }
+}
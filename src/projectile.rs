@@ -1,25 +1,30 @@
 use crate::{
     actor::{Actor, ActorContainer},
+    definitions::Definitions,
     effects::EffectKind,
-    message::Message,
-    weapon::{Weapon, WeaponContainer},
+    message::{Message, MessageSender},
+    query_buffer::QueryBufferPool,
+    rng::GameRng,
+    weapon::{Weapon, WeaponKind},
     GameTime,
 };
 use fyrox::{
     core::{
         algebra::{Matrix3, Point3, UnitQuaternion, Vector3},
         color::Color,
-        math::{ray::Ray, Vector3Ext},
+        math::{aabb::AxisAlignedBoundingBox, ray::Ray, Vector3Ext},
         pool::{Handle, Pool},
         rand::Rng,
         visitor::{Visit, VisitResult, Visitor},
     },
     engine::resource_manager::ResourceManager,
-    rand,
     scene::{
         base::BaseBuilder,
-        collider::{ColliderBuilder, ColliderShape, InteractionGroups},
-        graph::{physics::RayCastOptions, Graph},
+        collider::{Collider, ColliderBuilder, ColliderShape, InteractionGroups},
+        graph::{
+            physics::{Intersection, RayCastOptions},
+            Graph,
+        },
         light::{point::PointLightBuilder, BaseLightBuilder},
         node::Node,
         rigidbody::{RigidBodyBuilder, RigidBodyType},
@@ -28,16 +33,79 @@ use fyrox::{
         Scene,
     },
 };
-use std::{collections::HashSet, path::PathBuf, sync::mpsc::Sender};
+use serde::Deserialize;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Visit)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Visit, Deserialize)]
 pub enum ProjectileKind {
     Plasma,
     Bullet,
     Rocket,
+    /// Proximity mine, sticks to level geometry and goes off when someone comes close.
+    Mine,
+    /// Bounces around and goes off when its fuse burns down.
+    Grenade,
+}
+
+impl ProjectileKind {
+    pub const ALL: [ProjectileKind; 5] = [
+        ProjectileKind::Plasma,
+        ProjectileKind::Bullet,
+        ProjectileKind::Rocket,
+        ProjectileKind::Mine,
+        ProjectileKind::Grenade,
+    ];
+
+    /// Projectiles that are thrown and then moved by physics.
+    fn is_thrown(self) -> bool {
+        matches!(self, ProjectileKind::Mine | ProjectileKind::Grenade)
+    }
+}
+
+const MINE_SIZE: f32 = 0.12;
+/// Time in seconds after mine sticks to a surface before it reacts to actors.
+const MINE_ARM_TIME: f32 = 1.0;
+/// Armed mine goes off when an actor other than its owner comes this close.
+const MINE_TRIGGER_RADIUS: f32 = 1.5;
+const GRENADE_SIZE: f32 = 0.1;
+/// Each owner can have this many mines at once, the oldest one goes off when the next one is
+/// thrown.
+const MAX_MINES_PER_OWNER: usize = 3;
+/// Time in seconds after shot during which projectile passes through its owner, so it won't
+/// hit the shooter right at the muzzle. Owner can be hit afterwards, for example by own rocket.
+const OWNER_GRACE_TIME: f32 = 0.1;
+
+/// Whether projectile of given age in seconds passes through its owner. Grace time is over
+/// on the tick at which age reaches it.
+fn owner_is_immune(age: f32) -> bool {
+    age < OWNER_GRACE_TIME
+}
+
+/// Actor that fired a projectile, captured at the shot. Damage is credited to it even if the
+/// actor or its weapon is gone by the time the projectile hits, so kills by a rocket of an
+/// already dead shooter still count.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct Shooter {
+    pub actor: Handle<Actor>,
+    pub name: String,
+    pub weapon: Option<WeaponKind>,
+}
+
+impl Shooter {
+    /// Name for `who_name` of [`Message::DamageActor`], `None` if projectile has no shooter.
+    fn name(&self) -> Option<String> {
+        if self.actor.is_some() {
+            Some(self.name.clone())
+        } else {
+            None
+        }
+    }
 }
 
-#[derive(Visit)]
 pub struct Projectile {
     kind: ProjectileKind,
     model: Handle<Node>,
@@ -51,14 +119,60 @@ pub struct Projectile {
     rotation_angle: f32,
     /// Handle of weapons from which projectile was fired.
     pub owner: Handle<Weapon>,
+    /// Actor that fired the projectile, damage is credited to it.
+    shooter: Shooter,
     initial_velocity: Vector3<f32>,
     /// Position of projectile on the previous frame, it is used to simulate
     /// continuous intersection detection from fast moving projectiles.
     last_position: Vector3<f32>,
-    #[visit(skip)]
-    pub sender: Option<Sender<Message>>,
-    #[visit(skip)]
-    hits: HashSet<Hit>,
+    /// Time in seconds since projectile was fired.
+    age: f32,
+    pub sender: MessageSender,
+    /// Hits found on current update, both by ray cast and by contacts of projectile's body.
+    hits: Vec<Hit>,
+    /// Actors that were already damaged by the projectile, every actor is damaged at most
+    /// once during whole lifetime of projectile.
+    damaged: HashSet<Handle<Actor>>,
+    /// Blinking light of a mine.
+    light: Handle<Node>,
+    /// Mine stuck to a surface, its body does not move anymore.
+    stuck: bool,
+    /// Time left until stuck mine is armed.
+    arm_time: f32,
+    /// Set when mine must go off on next update, for example when it was shot.
+    detonate: bool,
+    /// Body of a mine that was hit by this projectile.
+    struck_mine: Option<Handle<Node>>,
+}
+
+impl Visit for Projectile {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.kind.visit("kind", visitor)?;
+        self.model.visit("model", visitor)?;
+        self.body.visit("body", visitor)?;
+        self.dir.visit("dir", visitor)?;
+        self.lifetime.visit("lifetime", visitor)?;
+        self.rotation_angle.visit("rotation_angle", visitor)?;
+        self.owner.visit("owner", visitor)?;
+        self.initial_velocity.visit("initial_velocity", visitor)?;
+        self.last_position.visit("last_position", visitor)?;
+
+        // Saves made before shooters were captured have no shooter, damage of such projectiles is
+        // not credited to anyone.
+        let _ = self.shooter.visit("shooter", visitor);
+
+        // Saves made before owner immunity was limited have no age, owner is immune for a moment.
+        let _ = self.age.visit("age", visitor);
+
+        // Saves made before mines have no mine state.
+        let _ = self.light.visit("light", visitor);
+        let _ = self.stuck.visit("stuck", visitor);
+        let _ = self.arm_time.visit("arm_time", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 impl Default for Projectile {
@@ -71,60 +185,134 @@ impl Default for Projectile {
             lifetime: 0.0,
             rotation_angle: 0.0,
             owner: Default::default(),
+            shooter: Default::default(),
             initial_velocity: Default::default(),
             last_position: Default::default(),
-            sender: None,
+            age: 0.0,
+            sender: Default::default(),
             hits: Default::default(),
+            damaged: Default::default(),
+            light: Default::default(),
+            stuck: false,
+            arm_time: MINE_ARM_TIME,
+            detonate: false,
+            struck_mine: None,
+        }
+    }
+}
+
+/// Visual representation of projectile.
+#[derive(Deserialize, Debug)]
+pub enum ProjectileVisual {
+    /// Path to a texture of a sprite.
+    Sprite(String),
+    /// Path to a model.
+    Model(String),
+}
+
+impl ProjectileVisual {
+    pub fn path(&self) -> &str {
+        match self {
+            ProjectileVisual::Sprite(path) | ProjectileVisual::Model(path) => path,
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
 pub struct ProjectileDefinition {
     damage: f32,
     speed: f32,
     lifetime: f32,
     /// Means that movement of projectile controlled by code, not physics.
     /// However projectile still could have rigid body to detect collisions.
+    /// Speed of other projectiles is their initial speed in meters per second.
     is_kinematic: bool,
-    impact_sound: &'static str,
+    /// Actors within this radius from explosion are damaged, damage falls off with distance.
+    /// Zero for projectiles that only damage what they hit.
+    #[serde(default)]
+    pub splash_radius: f32,
+    pub impact_sound: String,
+    pub visual: ProjectileVisual,
+}
+
+impl ProjectileDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.damage < 0.0 {
+            return Err(format!("damage must not be negative, got {}", self.damage));
+        }
+        if self.speed <= 0.0 {
+            return Err(format!("speed must be positive, got {}", self.speed));
+        }
+        if self.lifetime <= 0.0 {
+            return Err(format!("lifetime must be positive, got {}", self.lifetime));
+        }
+        if self.splash_radius < 0.0 {
+            return Err(format!(
+                "splash_radius must not be negative, got {}",
+                self.splash_radius
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl Projectile {
-    pub fn get_definition(kind: ProjectileKind) -> &'static ProjectileDefinition {
+    /// Definition that is used when no valid data file is available, see
+    /// [`crate::definitions`].
+    pub fn builtin_definition(kind: ProjectileKind) -> ProjectileDefinition {
         match kind {
-            ProjectileKind::Plasma => {
-                static DEFINITION: ProjectileDefinition = ProjectileDefinition {
-                    damage: 30.0,
-                    speed: 0.15,
-                    lifetime: 10.0,
-                    is_kinematic: true,
-                    impact_sound: "data/sounds/bullet_impact_concrete.ogg",
-                };
-                &DEFINITION
-            }
-            ProjectileKind::Bullet => {
-                static DEFINITION: ProjectileDefinition = ProjectileDefinition {
-                    damage: 15.0,
-                    speed: 0.75,
-                    lifetime: 10.0,
-                    is_kinematic: true,
-                    impact_sound: "data/sounds/bullet_impact_concrete.ogg",
-                };
-                &DEFINITION
-            }
-            ProjectileKind::Rocket => {
-                static DEFINITION: ProjectileDefinition = ProjectileDefinition {
-                    damage: 30.0,
-                    speed: 0.5,
-                    lifetime: 10.0,
-                    is_kinematic: true,
-                    impact_sound: "data/sounds/explosion.ogg",
-                };
-                &DEFINITION
-            }
+            ProjectileKind::Plasma => ProjectileDefinition {
+                damage: 30.0,
+                speed: 0.15,
+                lifetime: 10.0,
+                is_kinematic: true,
+                splash_radius: 0.0,
+                impact_sound: "data/sounds/bullet_impact_concrete.ogg".to_owned(),
+                visual: ProjectileVisual::Sprite("data/particles/light_01.png".to_owned()),
+            },
+            ProjectileKind::Bullet => ProjectileDefinition {
+                damage: 15.0,
+                speed: 0.75,
+                lifetime: 10.0,
+                is_kinematic: true,
+                splash_radius: 0.0,
+                impact_sound: "data/sounds/bullet_impact_concrete.ogg".to_owned(),
+                visual: ProjectileVisual::Sprite("data/particles/light_01.png".to_owned()),
+            },
+            ProjectileKind::Rocket => ProjectileDefinition {
+                damage: 30.0,
+                speed: 0.5,
+                lifetime: 10.0,
+                is_kinematic: true,
+                splash_radius: 0.0,
+                impact_sound: "data/sounds/explosion.ogg".to_owned(),
+                visual: ProjectileVisual::Model("data/models/rocket.FBX".to_owned()),
+            },
+            ProjectileKind::Mine => ProjectileDefinition {
+                damage: 80.0,
+                speed: 9.0,
+                lifetime: 120.0,
+                is_kinematic: false,
+                splash_radius: 3.5,
+                impact_sound: "data/sounds/explosion.ogg".to_owned(),
+                visual: ProjectileVisual::Sprite("data/particles/circle_05.png".to_owned()),
+            },
+            ProjectileKind::Grenade => ProjectileDefinition {
+                damage: 100.0,
+                speed: 14.0,
+                lifetime: 3.0,
+                is_kinematic: false,
+                splash_radius: 4.0,
+                impact_sound: "data/sounds/explosion.ogg".to_owned(),
+                visual: ProjectileVisual::Sprite("data/particles/circle_05.png".to_owned()),
+            },
         }
     }
 
+    pub fn get_definition(kind: ProjectileKind) -> &'static ProjectileDefinition {
+        Definitions::get().projectile(kind)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         kind: ProjectileKind,
@@ -132,46 +320,61 @@ impl Projectile {
         scene: &mut Scene,
         dir: Vector3<f32>,
         position: Vector3<f32>,
+        muzzle: Vector3<f32>,
         owner: Handle<Weapon>,
+        shooter: Shooter,
         initial_velocity: Vector3<f32>,
-        sender: Sender<Message>,
+        sender: MessageSender,
         basis: Matrix3<f32>,
+        rng: &mut GameRng,
     ) -> Self {
         let definition = Self::get_definition(kind);
 
+        let mut light = Handle::NONE;
         let (model, body) = {
             match &kind {
                 ProjectileKind::Plasma => {
-                    let size = rand::thread_rng().gen_range(0.09..0.12);
+                    let size = rng.gen_range(0.09..0.12);
 
                     let color = Color::opaque(0, 162, 232);
 
                     let model;
                     let collider;
-                    let body = RigidBodyBuilder::new(BaseBuilder::new().with_children(&[
-                        {
-                            model = SpriteBuilder::new(
-                                BaseBuilder::new().with_children(&[PointLightBuilder::new(
-                                    BaseLightBuilder::new(BaseBuilder::new()).with_color(color),
-                                )
-                                .with_radius(1.5)
-                                .build(&mut scene.graph)]),
-                            )
-                            .with_size(size)
-                            .with_color(color)
-                            .with_texture(
-                                resource_manager.request_texture("data/particles/light_01.png"),
+                    let body = RigidBodyBuilder::new(
+                        BaseBuilder::new()
+                            .with_local_transform(
+                                TransformBuilder::new()
+                                    .with_local_position(position)
+                                    .build(),
                             )
-                            .build(&mut scene.graph);
-                            model
-                        },
-                        {
-                            collider = ColliderBuilder::new(BaseBuilder::new())
-                                .with_shape(ColliderShape::ball(size))
-                                .build(&mut scene.graph);
-                            collider
-                        },
-                    ]))
+                            .with_children(&[
+                                {
+                                    model = SpriteBuilder::new(
+                                        BaseBuilder::new().with_children(&[
+                                            PointLightBuilder::new(
+                                                BaseLightBuilder::new(BaseBuilder::new())
+                                                    .with_color(color),
+                                            )
+                                            .with_radius(1.5)
+                                            .build(&mut scene.graph),
+                                        ]),
+                                    )
+                                    .with_size(size)
+                                    .with_color(color)
+                                    .with_texture(
+                                        resource_manager.request_texture(definition.visual.path()),
+                                    )
+                                    .build(&mut scene.graph);
+                                    model
+                                },
+                                {
+                                    collider = ColliderBuilder::new(BaseBuilder::new())
+                                        .with_shape(ColliderShape::ball(size))
+                                        .build(&mut scene.graph);
+                                    collider
+                                },
+                            ]),
+                    )
                     .with_body_type(RigidBodyType::KinematicPositionBased)
                     .build(&mut scene.graph);
 
@@ -186,14 +389,14 @@ impl Projectile {
                         ),
                     )
                     .with_size(0.05)
-                    .with_texture(resource_manager.request_texture("data/particles/light_01.png"))
+                    .with_texture(resource_manager.request_texture(definition.visual.path()))
                     .build(&mut scene.graph);
 
                     (model, None)
                 }
                 ProjectileKind::Rocket => {
                     let resource = resource_manager
-                        .request_model("data/models/rocket.FBX")
+                        .request_model(definition.visual.path())
                         .await
                         .unwrap();
                     let model = resource.instantiate_geometry(scene);
@@ -210,6 +413,90 @@ impl Projectile {
                     scene.graph.link_nodes(light, model);
                     (model, None)
                 }
+                ProjectileKind::Mine => {
+                    let color = Color::opaque(255, 60, 60);
+
+                    let model;
+                    let collider;
+                    let body = RigidBodyBuilder::new(
+                        BaseBuilder::new()
+                            .with_local_transform(
+                                TransformBuilder::new()
+                                    .with_local_position(position)
+                                    .build(),
+                            )
+                            .with_children(&[
+                                {
+                                    model =
+                                        SpriteBuilder::new(BaseBuilder::new().with_children(&[{
+                                            light = PointLightBuilder::new(
+                                                BaseLightBuilder::new(
+                                                    BaseBuilder::new().with_visibility(false),
+                                                )
+                                                .with_color(color)
+                                                .with_scatter_enabled(false)
+                                                .cast_shadows(false),
+                                            )
+                                            .with_radius(0.6)
+                                            .build(&mut scene.graph);
+                                            light
+                                        }]))
+                                        .with_size(MINE_SIZE)
+                                        .with_color(color)
+                                        .with_texture(
+                                            resource_manager
+                                                .request_texture(definition.visual.path()),
+                                        )
+                                        .build(&mut scene.graph);
+                                    model
+                                },
+                                {
+                                    collider = ColliderBuilder::new(BaseBuilder::new())
+                                        .with_shape(ColliderShape::ball(MINE_SIZE))
+                                        .build(&mut scene.graph);
+                                    collider
+                                },
+                            ]),
+                    )
+                    .with_lin_vel(Self::throw_velocity(definition, dir, initial_velocity))
+                    .with_body_type(RigidBodyType::Dynamic)
+                    .build(&mut scene.graph);
+
+                    (model, Some(body))
+                }
+                ProjectileKind::Grenade => {
+                    let model;
+                    let body = RigidBodyBuilder::new(
+                        BaseBuilder::new()
+                            .with_local_transform(
+                                TransformBuilder::new()
+                                    .with_local_position(position)
+                                    .build(),
+                            )
+                            .with_children(&[
+                                {
+                                    model = SpriteBuilder::new(BaseBuilder::new())
+                                        .with_size(GRENADE_SIZE)
+                                        .with_color(Color::opaque(90, 140, 60))
+                                        .with_texture(
+                                            resource_manager
+                                                .request_texture(definition.visual.path()),
+                                        )
+                                        .build(&mut scene.graph);
+                                    model
+                                },
+                                ColliderBuilder::new(BaseBuilder::new())
+                                    .with_shape(ColliderShape::ball(GRENADE_SIZE))
+                                    .with_restitution(0.4)
+                                    .build(&mut scene.graph),
+                            ]),
+                    )
+                    .with_lin_vel(Self::throw_velocity(definition, dir, initial_velocity))
+                    .with_body_type(RigidBodyType::Dynamic)
+                    .build(&mut scene.graph);
+
+                    (model, Some(body))
+                }
             }
         };
 
@@ -220,13 +507,99 @@ impl Projectile {
             dir: dir.try_normalize(std::f32::EPSILON).unwrap_or(Vector3::y()),
             kind,
             model,
-            last_position: position,
+            // Segment from muzzle to spawn position is checked on first update.
+            last_position: muzzle,
             owner,
-            sender: Some(sender),
+            shooter,
+            sender,
+            light,
             ..Default::default()
         }
     }
 
+    /// Gives thrown projectile its strength and fuse, used for charged weapons. Full strength
+    /// throws projectile at the speed from definition.
+    pub fn throw(&mut self, graph: &mut Graph, strength: f32, fuse: f32) {
+        self.lifetime = fuse;
+        if let Some(body) = self.body {
+            let velocity =
+                self.dir.scale(self.definition().speed * strength) + self.initial_velocity;
+            graph[body].as_rigid_body_mut().set_lin_vel(velocity);
+        }
+    }
+
+    /// Velocity of physics-driven projectile when it leaves the weapon.
+    fn throw_velocity(
+        definition: &ProjectileDefinition,
+        dir: Vector3<f32>,
+        initial_velocity: Vector3<f32>,
+    ) -> Vector3<f32> {
+        dir.try_normalize(std::f32::EPSILON)
+            .unwrap_or_else(Vector3::z)
+            .scale(definition.speed)
+            + initial_velocity
+    }
+
+    /// Brings dead projectile back to life at new place, scene nodes of projectile are reused.
+    #[allow(clippy::too_many_arguments)]
+    fn revive(
+        &mut self,
+        scene: &mut Scene,
+        dir: Vector3<f32>,
+        position: Vector3<f32>,
+        muzzle: Vector3<f32>,
+        owner: Handle<Weapon>,
+        shooter: Shooter,
+        initial_velocity: Vector3<f32>,
+        basis: Matrix3<f32>,
+    ) {
+        let root = &mut scene.graph[self.root()];
+        root.set_visibility(true);
+        root.local_transform_mut().set_position(position);
+        if self.kind == ProjectileKind::Rocket {
+            root.local_transform_mut()
+                .set_rotation(UnitQuaternion::from_matrix(&basis));
+        }
+        if self.kind.is_thrown() {
+            let velocity = Self::throw_velocity(self.definition(), dir, initial_velocity);
+            let body = root.as_rigid_body_mut();
+            body.set_body_type(RigidBodyType::Dynamic);
+            body.set_lin_vel(velocity);
+        }
+        if self.kind == ProjectileKind::Mine {
+            scene.graph[self.light].set_visibility(false);
+        }
+
+        self.lifetime = self.definition().lifetime;
+        self.dir = dir.try_normalize(std::f32::EPSILON).unwrap_or(Vector3::y());
+        self.rotation_angle = 0.0;
+        self.owner = owner;
+        self.shooter = shooter;
+        self.initial_velocity = initial_velocity;
+        self.last_position = muzzle;
+        self.age = 0.0;
+        self.hits.clear();
+        self.damaged.clear();
+        self.stuck = false;
+        self.arm_time = MINE_ARM_TIME;
+        self.detonate = false;
+        self.struck_mine = None;
+    }
+
+    /// Hides dead projectile until it is revived. Projectile is moved out of the world, so its
+    /// collider won't stop other projectiles.
+    fn park(&self, scene: &mut Scene) {
+        let root = &mut scene.graph[self.root()];
+        root.set_visibility(false);
+        root.local_transform_mut().set_position(PARKING_POSITION);
+        if self.kind.is_thrown() {
+            // Otherwise mine that went off mid-air would keep falling while it waits.
+            let body = root.as_rigid_body_mut();
+            body.set_body_type(RigidBodyType::KinematicPositionBased);
+            body.set_lin_vel(Vector3::default());
+        }
+    }
+
     pub fn is_dead(&self) -> bool {
         self.lifetime <= 0.0
     }
@@ -239,13 +612,33 @@ impl Projectile {
         Self::get_definition(self.kind)
     }
 
+    /// Scales gravity of thrown projectile, other kinds are not affected by gravity anyway.
+    pub fn set_gravity_scale(&self, graph: &mut Graph, scale: f32) {
+        if let (true, Some(body)) = (self.kind.is_thrown(), self.body) {
+            graph[body].as_rigid_body_mut().set_gravity_scale(scale);
+        }
+    }
+
     pub fn update(
         &mut self,
         scene: &mut Scene,
         actors: &ActorContainer,
-        weapons: &WeaponContainer,
         time: GameTime,
+        query_buffer: &mut Vec<Intersection>,
+        mine_bodies: &[Handle<Node>],
     ) {
+        match self.kind {
+            ProjectileKind::Mine => {
+                self.update_mine(scene, actors, time, query_buffer);
+                return;
+            }
+            ProjectileKind::Grenade => {
+                self.update_grenade(scene, actors, time);
+                return;
+            }
+            _ => (),
+        }
+
         // Fetch current position of projectile.
         let position = if let Some(body) = self.body.as_ref() {
             scene.graph[*body].global_position()
@@ -254,20 +647,23 @@ impl Projectile {
         };
 
         let mut effect_position = None;
+        // Distance along travel segment at which projectile hit level geometry or a mine, hits
+        // behind it are impossible.
+        let mut stop_distance = None;
 
         // Do ray based intersection tests for every kind of projectiles. This will help to handle
         // fast moving projectiles.
         let ray = Ray::from_two_points(self.last_position, position);
-        let mut query_buffer = Vec::default();
+        let segment_length = ray.dir.norm();
         scene.graph.physics.cast_ray(
             RayCastOptions {
                 ray_origin: Point3::from(ray.origin),
-                ray_direction: ray.origin,
+                ray_direction: ray.dir,
                 max_len: ray.dir.norm(),
                 groups: InteractionGroups::default(),
                 sort_results: true,
             },
-            &mut query_buffer,
+            query_buffer,
         );
 
         // List of hits sorted by distance from ray origin.
@@ -277,28 +673,56 @@ impl Projectile {
 
             if matches!(collider.shape(), ColliderShape::Trimesh(_)) {
                 self.kill();
+                stop_distance = Some(hit.toi);
                 effect_position = Some(hit.position.coords);
                 break 'hit_loop;
-            } else {
-                for (actor_handle, actor) in actors.pair_iter() {
-                    if actor.get_body() == body && self.owner.is_some() {
-                        let weapon = &weapons[self.owner];
-                        // Ignore intersections with owners of weapon.
-                        if weapon.owner() != actor_handle {
-                            self.hits.insert(Hit {
-                                actor: actor_handle,
-                                who: weapon.owner(),
-                            });
-
-                            self.kill();
-                            effect_position = Some(hit.position.coords);
-                            break 'hit_loop;
+            } else if Some(body) != self.body && mine_bodies.contains(&body) {
+                // Mines can be shot to make them go off early.
+                self.struck_mine = Some(body);
+                self.kill();
+                stop_distance = Some(hit.toi);
+                effect_position = Some(hit.position.coords);
+                break 'hit_loop;
+            } else if let Some(hit) = self.actor_hit(actors, body, hit.toi, hit.position) {
+                self.hits.push(hit);
+                break 'hit_loop;
+            }
+        }
+
+        // Ray starts at previous position, so it misses actors that projectile's body touches
+        // right from the start of the segment. Contacts are at the end of the segment.
+        if let Some(projectile_body) = self.body {
+            for &child in scene.graph[projectile_body].children() {
+                if let Some(collider) = scene.graph[child].cast::<Collider>() {
+                    for contact in collider.contacts(&scene.graph.physics) {
+                        if !contact.has_any_active_contact {
+                            continue;
+                        }
+                        let other = if contact.collider1 == child {
+                            contact.collider2
+                        } else {
+                            contact.collider1
+                        };
+                        let other_body = scene.graph[other].parent();
+                        if let Some(hit) = self.actor_hit(
+                            actors,
+                            other_body,
+                            segment_length,
+                            Point3::from(position),
+                        ) {
+                            self.hits.push(hit);
                         }
                     }
                 }
             }
         }
 
+        merge_hits(&mut self.hits, stop_distance, &mut self.damaged);
+        if let Some(first) = self.hits.first() {
+            self.kill();
+            effect_position = Some(first.position);
+        }
+
         // Movement of kinematic projectiles are controlled explicitly.
         if self.definition().is_kinematic {
             let total_velocity = self.dir.scale(self.definition().speed);
@@ -327,48 +751,198 @@ impl Projectile {
         self.initial_velocity.follow(&Vector3::default(), 0.15);
 
         self.lifetime -= time.delta;
+        self.age += time.delta;
 
         if self.lifetime <= 0.0 {
             let pos = effect_position.unwrap_or_else(|| self.get_position(&scene.graph));
-
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::CreateEffect {
-                    kind: EffectKind::BulletImpact,
-                    position: pos,
-                })
-                .unwrap();
-
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::PlaySound {
-                    path: PathBuf::from(self.definition().impact_sound),
-                    position: pos,
-                    gain: 1.0,
-                    rolloff_factor: 4.0,
-                    radius: 3.0,
-                })
-                .unwrap();
+            self.impact(pos);
         }
 
         let definition = self.definition();
-        for hit in self.hits.drain() {
-            self.sender
-                .as_ref()
-                .unwrap()
-                .send(Message::DamageActor {
-                    actor: hit.actor,
-                    who: hit.who,
-                    amount: definition.damage,
-                })
-                .unwrap();
+        for hit in self.hits.drain(..) {
+            self.sender.send(Message::DamageActor {
+                actor: hit.actor,
+                who: self.shooter.actor,
+                who_name: self.shooter.name(),
+                amount: definition.damage,
+                weapon: self.shooter.weapon,
+            });
         }
 
         self.last_position = position;
     }
 
+    /// Returns hit of an actor that owns given body, owner of projectile can't be hit during
+    /// [`OWNER_GRACE_TIME`] after the shot.
+    fn actor_hit(
+        &self,
+        actors: &ActorContainer,
+        body: Handle<Node>,
+        distance: f32,
+        position: Point3<f32>,
+    ) -> Option<Hit> {
+        let ignored = if owner_is_immune(self.age) {
+            self.shooter.actor
+        } else {
+            Handle::NONE
+        };
+        actors
+            .pair_iter()
+            .find(|(handle, actor)| actor.get_body() == body && *handle != ignored)
+            .map(|(actor, _)| Hit {
+                actor,
+                distance,
+                position: position.coords,
+            })
+    }
+
+    /// Plays effects of projectile death at given position.
+    fn impact(&self, position: Vector3<f32>) {
+        self.sender.send(Message::CreateEffect {
+            kind: EffectKind::BulletImpact,
+            position,
+        });
+
+        self.sender.send(Message::PlaySound {
+            path: PathBuf::from(&self.definition().impact_sound),
+            position,
+            gain: 1.0,
+            rolloff_factor: 4.0,
+            radius: 3.0,
+        });
+
+        self.sender.send(Message::ProjectileImpact {
+            position,
+            amount: self.definition().damage,
+        });
+    }
+
+    fn update_mine(
+        &mut self,
+        scene: &mut Scene,
+        actors: &ActorContainer,
+        time: GameTime,
+        query_buffer: &mut Vec<Intersection>,
+    ) {
+        let body = self.root();
+        let position = scene.graph[body].global_position();
+        let owner = self.shooter.actor;
+
+        if !self.stuck {
+            // Look a bit ahead along the path, physics won't let mine go into a wall, so a ray
+            // between previous and current position would never reach the surface.
+            let velocity = scene.graph[body].as_rigid_body().lin_vel();
+            let direction = (position - self.last_position)
+                .try_normalize(std::f32::EPSILON)
+                .or_else(|| velocity.try_normalize(std::f32::EPSILON))
+                .unwrap_or_else(|| -Vector3::y());
+            let length = position.metric_distance(&self.last_position) + MINE_SIZE * 2.0;
+            scene.graph.physics.cast_ray(
+                RayCastOptions {
+                    ray_origin: Point3::from(self.last_position),
+                    ray_direction: direction,
+                    max_len: length,
+                    groups: InteractionGroups::default(),
+                    sort_results: true,
+                },
+                query_buffer,
+            );
+
+            let surface = query_buffer.iter().find_map(|hit| {
+                let collider = scene.graph[hit.collider].as_collider();
+                if matches!(collider.shape(), ColliderShape::Trimesh(_)) {
+                    let normal = hit
+                        .normal
+                        .try_normalize(std::f32::EPSILON)
+                        .unwrap_or_default();
+                    Some(hit.position.coords + normal.scale(MINE_SIZE * 0.5))
+                } else {
+                    None
+                }
+            });
+
+            if let Some(stick_position) = surface {
+                self.stuck = true;
+                let body = scene.graph[body].as_rigid_body_mut();
+                body.set_body_type(RigidBodyType::KinematicPositionBased);
+                body.set_lin_vel(Vector3::default());
+                body.local_transform_mut().set_position(stick_position);
+            }
+        } else if self.arm_time > 0.0 {
+            self.arm_time -= time.delta;
+        } else if !self.detonate {
+            for (handle, actor) in actors.pair_iter() {
+                if handle != owner
+                    && !actor.is_dead()
+                    && actor.position(&scene.graph).metric_distance(&position)
+                        <= MINE_TRIGGER_RADIUS
+                {
+                    self.detonate = true;
+                    break;
+                }
+            }
+        }
+
+        // Light blinks slowly while mine is arming, and faster once it is armed.
+        let blink_rate = if self.arm_time > 0.0 { 1.0 } else { 3.0 };
+        let light_on = self.stuck && (time.elapsed * blink_rate).fract() < 0.3;
+        scene.graph[self.light].set_visibility(light_on);
+
+        self.lifetime -= time.delta;
+
+        if self.detonate || self.lifetime <= 0.0 {
+            self.detonate = false;
+            let radius = self.definition().splash_radius;
+            self.explode(&scene.graph, actors, position, radius);
+        }
+
+        self.last_position = position;
+    }
+
+    fn update_grenade(&mut self, scene: &Scene, actors: &ActorContainer, time: GameTime) {
+        let position = scene.graph[self.root()].global_position();
+
+        self.lifetime -= time.delta;
+
+        if self.lifetime <= 0.0 {
+            let radius = self.definition().splash_radius;
+            self.explode(&scene.graph, actors, position, radius);
+        }
+
+        self.last_position = position;
+    }
+
+    /// Kills projectile and damages every actor within given radius, thrower included. Damage
+    /// falls off with distance.
+    fn explode(
+        &mut self,
+        graph: &Graph,
+        actors: &ActorContainer,
+        position: Vector3<f32>,
+        radius: f32,
+    ) {
+        self.kill();
+        self.impact(position);
+        let damage = self.definition().damage;
+        for (handle, actor) in actors.pair_iter() {
+            let distance = actor.position(graph).metric_distance(&position);
+            if distance < radius {
+                self.sender.send(Message::DamageActor {
+                    actor: handle,
+                    who: self.shooter.actor,
+                    who_name: self.shooter.name(),
+                    amount: damage * (1.0 - distance / radius),
+                    weapon: self.shooter.weapon,
+                });
+            }
+        }
+    }
+
+    /// Returns topmost node of projectile, it is either rigid body or model.
+    pub fn root(&self) -> Handle<Node> {
+        self.body.unwrap_or(self.model)
+    }
+
     pub fn get_position(&self, graph: &Graph) -> Vector3<f32> {
         graph[self.model].global_position()
     }
@@ -384,44 +958,331 @@ impl Projectile {
     }
 }
 
-#[derive(Hash, Eq, PartialEq)]
 struct Hit {
     actor: Handle<Actor>,
-    who: Handle<Actor>,
+    /// Distance from the start of travel segment of current update.
+    distance: f32,
+    position: Vector3<f32>,
+}
+
+/// Merges hits found by ray and by contacts during an update: earliest one along the segment
+/// comes first, and every actor is damaged once during lifetime of the projectile, `damaged`
+/// keeps actors that were hit already. Nothing can be hit behind a wall or a mine.
+fn merge_hits(
+    hits: &mut Vec<Hit>,
+    stop_distance: Option<f32>,
+    damaged: &mut HashSet<Handle<Actor>>,
+) {
+    hits.retain(|hit| stop_distance.map_or(true, |stop| hit.distance <= stop));
+    hits.sort_by(|a, b| {
+        a.distance
+            .partial_cmp(&b.distance)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    hits.retain(|hit| damaged.insert(hit.actor));
 }
 
+/// Amount of dead projectiles of each kind kept for reuse, the rest are destroyed.
+const MAX_FREE_PER_KIND: usize = 32;
+/// Where dead projectiles wait for reuse.
+const PARKING_POSITION: Vector3<f32> = Vector3::new(0.0, -10000.0, 0.0);
+
 #[derive(Visit)]
 pub struct ProjectileContainer {
     pool: Pool<Projectile>,
+    /// Dead projectiles which nodes are still in scene. Automatic weapons fire a lot, reusing
+    /// nodes is much cheaper than adding and removing them all the time.
+    #[visit(skip)]
+    free: Vec<Projectile>,
 }
 
 impl ProjectileContainer {
     pub fn new() -> Self {
-        Self { pool: Pool::new() }
+        Self {
+            pool: Pool::new(),
+            free: Default::default(),
+        }
     }
 
     pub fn add(&mut self, projectile: Projectile) -> Handle<Projectile> {
         self.pool.spawn(projectile)
     }
 
+    /// Revives dead projectile of given kind if there is any, returns `None` if new projectile
+    /// must be created.
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_reuse(
+        &mut self,
+        kind: ProjectileKind,
+        scene: &mut Scene,
+        dir: Vector3<f32>,
+        position: Vector3<f32>,
+        muzzle: Vector3<f32>,
+        owner: Handle<Weapon>,
+        shooter: Shooter,
+        initial_velocity: Vector3<f32>,
+        basis: Matrix3<f32>,
+    ) -> Option<Handle<Projectile>> {
+        let index = self.free.iter().position(|p| p.kind == kind)?;
+        let mut projectile = self.free.swap_remove(index);
+        projectile.revive(
+            scene,
+            dir,
+            position,
+            muzzle,
+            owner,
+            shooter,
+            initial_velocity,
+            basis,
+        );
+        Some(self.pool.spawn(projectile))
+    }
+
+    /// Destroys every projectile that waits for reuse. Must be called before scene is saved,
+    /// only live projectiles are saved.
+    pub fn release_free(&mut self, scene: &mut Scene) {
+        for mut projectile in self.free.drain(..) {
+            projectile.clean_up(scene);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Projectile> {
+        self.pool.iter()
+    }
+
+    /// Sets off the oldest mines of owners that have more than [`MAX_MINES_PER_OWNER`] of
+    /// them.
+    fn enforce_mine_limit(&mut self) {
+        let mut mines = self
+            .pool
+            .pair_iter()
+            .filter(|(_, projectile)| {
+                projectile.kind == ProjectileKind::Mine
+                    && projectile.owner.is_some()
+                    && !projectile.detonate
+                    && !projectile.is_dead()
+            })
+            .map(|(handle, projectile)| (projectile.owner, projectile.lifetime, handle))
+            .collect::<Vec<_>>();
+        // Mines have the same lifetime, newer ones have more of it left.
+        mines.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        let mut counts = HashMap::new();
+        for (owner, _, handle) in mines {
+            let count = counts.entry(owner).or_insert(0);
+            *count += 1;
+            if *count > MAX_MINES_PER_OWNER {
+                self.pool[handle].detonate = true;
+            }
+        }
+    }
+
+    /// Removes mines thrown from given weapon without an explosion, used when their owner
+    /// leaves the match.
+    pub fn remove_mines(&mut self, owner: Handle<Weapon>, scene: &mut Scene) {
+        let mines = self
+            .pool
+            .pair_iter()
+            .filter(|(_, projectile)| {
+                projectile.kind == ProjectileKind::Mine && projectile.owner == owner
+            })
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        for handle in mines {
+            self.pool.free(handle).clean_up(scene);
+        }
+    }
+
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Projectile> {
         self.pool.iter_mut()
     }
 
+    pub fn get(&self, projectile: Handle<Projectile>) -> &Projectile {
+        &self.pool[projectile]
+    }
+
+    pub fn get_mut(&mut self, projectile: Handle<Projectile>) -> &mut Projectile {
+        &mut self.pool[projectile]
+    }
+
     pub fn update(
         &mut self,
         scene: &mut Scene,
         actors: &ActorContainer,
-        weapons: &WeaponContainer,
+        world_bounds: Option<&AxisAlignedBoundingBox>,
         time: GameTime,
     ) {
+        self.enforce_mine_limit();
+
+        let mine_bodies = self
+            .pool
+            .iter()
+            .filter(|projectile| projectile.kind == ProjectileKind::Mine)
+            .filter_map(|projectile| projectile.body)
+            .collect::<Vec<_>>();
+
+        // Single buffer is shared by every projectile, there could be hundreds of them.
+        QueryBufferPool::with(|query_buffer| {
+            for projectile in self.pool.iter_mut() {
+                projectile.update(scene, actors, time, query_buffer, &mine_bodies);
+            }
+        });
+
+        // Projectiles that left the world vanish silently, there is nothing to hit out there.
+        if let Some(world_bounds) = world_bounds {
+            for projectile in self.pool.iter_mut() {
+                if !projectile.is_dead()
+                    && !world_bounds.is_contains_point(projectile.get_position(&scene.graph))
+                {
+                    projectile.kill();
+                }
+            }
+        }
+
+        // Shot mines go off on next update.
+        let struck_mines = self
+            .pool
+            .iter_mut()
+            .filter_map(|projectile| projectile.struck_mine.take())
+            .collect::<Vec<_>>();
         for projectile in self.pool.iter_mut() {
-            projectile.update(scene, actors, weapons, time);
-            if projectile.is_dead() {
+            if projectile
+                .body
+                .map_or(false, |body| struck_mines.contains(&body))
+            {
+                projectile.detonate = true;
+            }
+        }
+
+        let dead = self
+            .pool
+            .pair_iter()
+            .filter(|(_, projectile)| projectile.is_dead())
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        for handle in dead {
+            let mut projectile = self.pool.free(handle);
+            let free_count = self
+                .free
+                .iter()
+                .filter(|other| other.kind == projectile.kind)
+                .count();
+            if free_count < MAX_FREE_PER_KIND {
+                projectile.park(scene);
+                self.free.push(projectile);
+            } else {
                 projectile.clean_up(scene);
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Age after every tick of given length, accumulated the same way as in `update`.
+    fn ages(delta: f32, ticks: usize) -> Vec<f32> {
+        let mut age = 0.0f32;
+        (0..ticks)
+            .map(|_| {
+                age += delta;
+                age
+            })
+            .collect()
+    }
+
+    fn first_tick_without_immunity(delta: f32) -> usize {
+        ages(delta, 100)
+            .iter()
+            .position(|&age| !owner_is_immune(age))
+            .unwrap()
+            + 1
+    }
+
+    #[test]
+    fn owner_is_immune_right_after_shot() {
+        assert!(owner_is_immune(0.0));
+        assert!(owner_is_immune(OWNER_GRACE_TIME * 0.5));
+    }
+
+    #[test]
+    fn owner_can_be_hit_at_expiry_tick_and_after() {
+        assert!(!owner_is_immune(OWNER_GRACE_TIME));
+        assert!(!owner_is_immune(OWNER_GRACE_TIME + 1.0 / 60.0));
+
+        // 0.1s is 6 ticks at 60 Hz, accumulated rounding must not delay expiry by a tick.
+        let delta = 1.0 / 60.0;
+        let ages = ages(delta, 7);
+        assert!(owner_is_immune(ages[4]));
+        assert!(!owner_is_immune(ages[5]));
+        assert!(!owner_is_immune(ages[6]));
+        assert_eq!(first_tick_without_immunity(delta), 6);
+    }
+
+    #[test]
+    fn grace_time_follows_time_scale() {
+        assert_eq!(first_tick_without_immunity(1.0 / 60.0 * 0.5), 12);
+        assert_eq!(first_tick_without_immunity(1.0 / 60.0 * 2.0), 3);
+    }
+
+    fn hit(actor: Handle<Actor>, distance: f32) -> Hit {
+        Hit {
+            actor,
+            distance,
+            position: Vector3::new(0.0, 0.0, distance),
+        }
+    }
+
+    fn actors(hits: &[Hit]) -> Vec<Handle<Actor>> {
+        hits.iter().map(|hit| hit.actor).collect()
+    }
+
+    #[test]
+    fn overlapping_colliders_of_actor_damage_it_once() {
+        let actor = Handle::new(1, 1);
+        let mut damaged = HashSet::new();
+        // Body of projectile touches two colliders of the same actor.
+        let mut hits = vec![hit(actor, 1.0), hit(actor, 1.0)];
+        merge_hits(&mut hits, None, &mut damaged);
+        assert_eq!(actors(&hits), vec![actor]);
+    }
+
+    #[test]
+    fn ray_and_contact_hit_of_same_actor_damage_it_once() {
+        let actor = Handle::new(1, 1);
+        let other = Handle::new(2, 1);
+        let mut damaged = HashSet::new();
+        // Contacts are found at the end of the segment, ray hit is earlier.
+        let mut hits = vec![hit(actor, 2.0), hit(other, 2.0), hit(actor, 0.5)];
+        merge_hits(&mut hits, None, &mut damaged);
+        assert_eq!(actors(&hits), vec![actor, other]);
+        assert_eq!(hits[0].distance, 0.5);
+    }
+
+    #[test]
+    fn actor_is_damaged_once_per_lifetime() {
+        let actor = Handle::new(1, 1);
+        let mut damaged = HashSet::new();
+        let mut hits = vec![hit(actor, 1.0)];
+        merge_hits(&mut hits, None, &mut damaged);
+        assert_eq!(hits.len(), 1);
+
+        // Projectile still touches the actor on the next update.
+        let mut hits = vec![hit(actor, 0.0)];
+        merge_hits(&mut hits, None, &mut damaged);
+        assert!(hits.is_empty());
+    }
 
-        self.pool.retain(|proj| !proj.is_dead());
+    #[test]
+    fn actor_behind_wall_is_not_hit() {
+        let near = Handle::new(1, 1);
+        let far = Handle::new(2, 1);
+        let mut damaged = HashSet::new();
+        let mut hits = vec![hit(far, 3.0), hit(near, 1.0)];
+        merge_hits(&mut hits, Some(2.0), &mut damaged);
+        assert_eq!(actors(&hits), vec![near]);
+        // Actor behind the wall can still be hit by another projectile.
+        assert!(!damaged.contains(&far));
     }
 }
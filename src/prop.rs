@@ -0,0 +1,100 @@
+use fyrox::{
+    core::{
+        algebra::Vector3,
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        base::BaseBuilder,
+        collider::{ColliderBuilder, ColliderShape},
+        graph::Graph,
+        node::Node,
+        rigidbody::{RigidBodyBuilder, RigidBodyType},
+        transform::TransformBuilder,
+    },
+};
+
+const MASS: f32 = 20.0;
+/// Impacts closer than this distance to bounds of a prop push it.
+const PUSH_RADIUS: f32 = 1.5;
+/// Impulse per one point of damage of impact.
+const IMPULSE_PER_DAMAGE: f32 = 1.5;
+
+/// Dynamic physics object that can be pushed around by actors and shot. Prop is a node named
+/// "Prop_*" in a map, level makes a rigid body for it when map is loaded.
+#[derive(Default, Visit)]
+pub struct Prop {
+    body: Handle<Node>,
+    model: Handle<Node>,
+}
+
+impl Prop {
+    /// Creates rigid body with box collider that fits given model. Model must be attached to
+    /// the body by `attach`, after global transform of body is calculated.
+    pub fn new(model: Handle<Node>, graph: &mut Graph) -> Self {
+        let bounds = graph[model].world_bounding_box();
+        let body = RigidBodyBuilder::new(
+            BaseBuilder::new()
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(bounds.center())
+                        .build(),
+                )
+                .with_children(&[ColliderBuilder::new(BaseBuilder::new())
+                    .with_shape(ColliderShape::cuboid(
+                        bounds.half_extents().x,
+                        bounds.half_extents().y,
+                        bounds.half_extents().z,
+                    ))
+                    .build(graph)]),
+        )
+        .with_body_type(RigidBodyType::Dynamic)
+        .with_mass(MASS)
+        .with_can_sleep(true)
+        .build(graph);
+
+        Self { body, model }
+    }
+
+    pub fn attach(&self, graph: &mut Graph) {
+        graph.link_nodes_keep_global_transform(self.model, self.body);
+    }
+
+    pub fn model(&self) -> Handle<Node> {
+        self.model
+    }
+}
+
+#[derive(Default, Visit)]
+pub struct PropContainer {
+    pool: Pool<Prop>,
+}
+
+impl PropContainer {
+    pub fn add(&mut self, prop: Prop) -> Handle<Prop> {
+        self.pool.spawn(prop)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Prop> {
+        self.pool.iter()
+    }
+
+    /// Pushes every prop near impact point away from it, harder hits push stronger.
+    pub fn handle_impact(&self, graph: &mut Graph, position: Vector3<f32>, amount: f32) {
+        for prop in self.pool.iter() {
+            if !graph[prop.model]
+                .world_bounding_box()
+                .is_intersects_sphere(position, PUSH_RADIUS)
+            {
+                continue;
+            }
+
+            let body = graph[prop.body].as_rigid_body_mut();
+            let direction = (body.global_position() - position)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::y);
+            body.apply_impulse(direction.scale(amount.abs() * IMPULSE_PER_DAMAGE));
+            body.wake_up();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Ray casts are done lots of times every tick (laser sights, projectiles, bot target
+//! selection), each of them needs a buffer for results. Buffers are taken from a pool instead
+//! of allocating a new one for every ray cast.
+
+use fyrox::scene::graph::physics::Intersection;
+use std::cell::RefCell;
+
+/// Most of ray casts hit only a few colliders, this capacity is enough to never reallocate
+/// buffers in practice.
+const DEFAULT_CAPACITY: usize = 64;
+
+thread_local! {
+    static POOL: RefCell<Vec<Vec<Intersection>>> = RefCell::new(Vec::new());
+}
+
+pub struct QueryBufferPool;
+
+impl QueryBufferPool {
+    /// Calls given closure with an empty buffer from the pool. Calls can be nested, every
+    /// nested call gets its own buffer.
+    pub fn with<R, F: FnOnce(&mut Vec<Intersection>) -> R>(func: F) -> R {
+        let mut buffer = POOL
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_else(|| Vec::with_capacity(DEFAULT_CAPACITY));
+        buffer.clear();
+        let result = func(&mut buffer);
+        POOL.with(|pool| pool.borrow_mut().push(buffer));
+        result
+    }
+}
@@ -0,0 +1,257 @@
+//! Personal records of local player, they're kept between runs in a binary file in user data
+//! directory next to settings. Every map and match mode has its own record. Missing or broken
+//! file is never an error - records just start over.
+
+use crate::{
+    leader_board::WeaponStats, logger::Logger, save_binary_atomic, user_data_dir, MatchOptions,
+};
+use fyrox::{
+    core::{
+        futures::executor::block_on,
+        visitor::{Visit, VisitError, VisitResult, Visitor},
+    },
+    utils::log::MessageKind,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const RECORDS_FILE_NAME: &str = "records.bin";
+
+/// Version of records file format. Must be increased every time when layout of records
+/// changes, records of other versions are dropped.
+const RECORDS_VERSION: u32 = 1;
+
+/// Map and match mode of a record.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, Visit)]
+pub struct RecordKey {
+    /// File name of the map without extension, so records survive moving of data folder.
+    pub map: String,
+    /// See [`MatchOptions::mode_index`].
+    pub mode: u32,
+}
+
+impl RecordKey {
+    pub fn new(map: &Path, options: &MatchOptions) -> Self {
+        Self {
+            map: map
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            mode: options.mode_index() as u32,
+        }
+    }
+}
+
+/// Result of local player in a finished match.
+#[derive(Copy, Clone, Debug, Default, Visit)]
+pub struct MatchResult {
+    /// Frags of the player.
+    pub score: u32,
+    /// Fraction of shots that damaged someone, in 0.0..=1.0 range.
+    pub accuracy: f32,
+    pub duration_secs: f32,
+}
+
+#[derive(Clone, Debug, Default, Visit)]
+pub struct MapRecord {
+    /// Result with highest score, the first one wins between equal scores.
+    pub best: MatchResult,
+    /// Amount of matches played till the end.
+    pub completions: u32,
+}
+
+/// What a finished match meant for records, see [`Records::submit`].
+#[derive(Copy, Clone, Debug)]
+pub struct RecordOutcome {
+    /// Best score before the match, `None` if this is the first completed match.
+    pub previous_best: Option<u32>,
+    /// Match result beat previous best score.
+    pub new_best: bool,
+}
+
+#[derive(Default)]
+pub struct Records {
+    records: HashMap<RecordKey, MapRecord>,
+    /// Damage and kills of every weapon over all finished matches.
+    weapon_totals: WeaponStats,
+}
+
+impl Visit for Records {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut version = RECORDS_VERSION;
+        version.visit("Version", visitor)?;
+        if version != RECORDS_VERSION {
+            return Err(VisitError::User(format!(
+                "records of version {} are not supported, expected {}",
+                version, RECORDS_VERSION
+            )));
+        }
+        self.records.visit("Records", visitor)?;
+        // Records written before weapon totals were tracked have no such field, totals
+        // just start from zero.
+        let _ = self.weapon_totals.visit("WeaponTotals", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+impl Records {
+    fn path() -> PathBuf {
+        user_data_dir().join(RECORDS_FILE_NAME)
+    }
+
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let mut records = Self::default();
+        let result = block_on(Visitor::load_binary(path))
+            .map_err(|e| e.to_string())
+            .and_then(|mut visitor| {
+                records
+                    .visit("Records", &mut visitor)
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(_) => records,
+            Err(e) => {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Unable to load records from {}: {}. Records start over.",
+                        path.display(),
+                        e
+                    ),
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&mut self) {
+        let path = Self::path();
+        let mut visitor = Visitor::new();
+        let result = self
+            .visit("Records", &mut visitor)
+            .and_then(|_| save_binary_atomic(&visitor, &path));
+        if let Err(e) = result {
+            Logger::writeln(
+                MessageKind::Error,
+                format!("Unable to save records to {}: {}", path.display(), e),
+            );
+        }
+    }
+
+    pub fn get(&self, key: &RecordKey) -> Option<&MapRecord> {
+        self.records.get(key)
+    }
+
+    /// Best scores on given map in every mode, in the order of [`MatchOptions::MODE_NAMES`].
+    pub fn best_scores(&self, map: &Path) -> Vec<Option<u32>> {
+        (0..MatchOptions::MODE_NAMES.len())
+            .map(|mode| {
+                let key = RecordKey {
+                    mode: mode as u32,
+                    ..RecordKey::new(map, &MatchOptions::default())
+                };
+                self.get(&key).map(|record| record.best.score)
+            })
+            .collect()
+    }
+
+    /// Folds weapon stats of a finished match into career totals.
+    pub fn add_weapon_stats(&mut self, stats: &WeaponStats) {
+        self.weapon_totals.merge(stats);
+    }
+
+    /// Counts completed match and keeps its result if it beats the best one. Records are not
+    /// saved, call [`Self::save`] after.
+    pub fn submit(&mut self, key: RecordKey, result: MatchResult) -> RecordOutcome {
+        let previous_best = self.records.get(&key).map(|record| record.best.score);
+        let record = self.records.entry(key).or_insert_with(|| MapRecord {
+            best: result,
+            completions: 0,
+        });
+        record.completions += 1;
+        let new_best = previous_best.map_or(false, |best| result.score > best);
+        if new_best {
+            record.best = result;
+        }
+        RecordOutcome {
+            previous_best,
+            new_best,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weapon::WeaponKind;
+
+    fn result(score: u32) -> MatchResult {
+        MatchResult {
+            score,
+            accuracy: 0.5,
+            duration_secs: 600.0,
+        }
+    }
+
+    #[test]
+    fn aggregated_stats_survive_save_and_load() {
+        let key = RecordKey {
+            map: "dm6".to_owned(),
+            mode: 0,
+        };
+        let mut records = Records::default();
+
+        let mut first = WeaponStats::default();
+        first.add_damage(WeaponKind::Ak47, 150.0);
+        first.add_kill(WeaponKind::Ak47);
+        first.add_damage(WeaponKind::RocketLauncher, 80.0);
+        let outcome = records.submit(key.clone(), result(12));
+        assert_eq!(outcome.previous_best, None);
+        records.add_weapon_stats(&first);
+
+        let mut second = WeaponStats::default();
+        second.add_damage(WeaponKind::Ak47, 50.0);
+        second.add_damage(WeaponKind::RocketLauncher, 300.0);
+        second.add_kill(WeaponKind::RocketLauncher);
+        second.add_kill(WeaponKind::RocketLauncher);
+        let outcome = records.submit(key.clone(), result(20));
+        assert_eq!(outcome.previous_best, Some(12));
+        assert!(outcome.new_best);
+        records.add_weapon_stats(&second);
+
+        let path = crate::temp_path("records");
+        let mut visitor = Visitor::new();
+        records.visit("Records", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let loaded = Records::load_from(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let record = loaded.get(&key).unwrap();
+        assert_eq!(record.completions, 2);
+        assert_eq!(record.best.score, 20);
+        let ak47 = loaded.weapon_totals.get(WeaponKind::Ak47);
+        assert_eq!((ak47.damage, ak47.kills), (200.0, 1));
+        let rocket_launcher = loaded.weapon_totals.get(WeaponKind::RocketLauncher);
+        assert_eq!((rocket_launcher.damage, rocket_launcher.kills), (380.0, 2));
+        assert_eq!(
+            loaded.weapon_totals.most_used(),
+            Some(WeaponKind::RocketLauncher)
+        );
+        assert_eq!(
+            loaded.weapon_totals.most_lethal(),
+            Some(WeaponKind::RocketLauncher)
+        );
+    }
+}
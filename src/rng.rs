@@ -0,0 +1,85 @@
+//! Seedable random number generator for gameplay code. Every random decision that affects
+//! simulation must use level's generator instead of `thread_rng`, so two runs with the same
+//! seed and the same input will be identical. Its state is saved together with level.
+
+use fyrox::{
+    core::visitor::{Visit, VisitResult, Visitor},
+    rand::{self, Error, Rng, RngCore},
+};
+
+/// SplitMix64 generator, it is tiny, fast and its whole state is a single number which is
+/// trivial to save.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generates random seed for a new match.
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().gen()
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(rng: &mut GameRng) -> Vec<u64> {
+        (0..32).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_draws() {
+        assert_eq!(draws(&mut GameRng::new(42)), draws(&mut GameRng::new(42)));
+        assert_ne!(draws(&mut GameRng::new(42)), draws(&mut GameRng::new(43)));
+    }
+
+    #[test]
+    fn loaded_generator_continues_sequence() {
+        let mut rng = GameRng::new(42);
+        draws(&mut rng);
+
+        let path = crate::temp_path("rng");
+        let mut visitor = Visitor::new();
+        rng.clone().visit("Rng", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let mut visitor =
+            fyrox::core::futures::executor::block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut loaded = GameRng::default();
+        loaded.visit("Rng", &mut visitor).unwrap();
+
+        assert_eq!(draws(&mut loaded), draws(&mut rng));
+    }
+}
@@ -0,0 +1,312 @@
+//! Settings that are kept between runs of the game. They're stored in a binary file in user
+//! data directory, missing or broken file is never an error - defaults are used in this case.
+
+use crate::{
+    control_scheme::{ControlProfiles, ControlScheme},
+    level,
+    logger::Logger,
+    save_binary_atomic, user_data_dir,
+    weapon::WeaponKind,
+    DeathMatch, MatchOptions,
+};
+use fyrox::{
+    core::{
+        futures::executor::block_on,
+        parking_lot::{self, Mutex},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    utils::log::MessageKind,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.bin";
+
+/// Copy of last saved settings, panic hook writes it back together with current bindings.
+static LAST_SAVED: Mutex<Option<Settings>> = parking_lot::const_mutex(None);
+
+/// Options of the last match started from match menu, menu starts from them next time.
+#[derive(Clone, Debug, Visit)]
+pub struct MatchSettings {
+    pub options: MatchOptions,
+    pub bot_count: u32,
+    pub map: PathBuf,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            options: MatchOptions::DeathMatch(DeathMatch {
+                time_limit_secs: 10.0 * 60.0,
+                frag_limit: 30,
+                mutators: Default::default(),
+            }),
+            bot_count: level::DEFAULT_BOT_COUNT as u32,
+            map: PathBuf::from(level::DEFAULT_MAP),
+        }
+    }
+}
+
+/// Display and gameplay preferences. Unlike bindings they don't belong to a control profile,
+/// so switching profile keeps them.
+#[derive(Clone, Debug)]
+pub struct Preferences {
+    /// Take out picked up weapon if it is better than current one.
+    pub auto_switch_weapon: bool,
+    /// Show notifications about items picked up by anyone.
+    pub show_pickup_notifications: bool,
+    /// Show damage dealt by player as numbers floating above victims.
+    pub show_damage_numbers: bool,
+    /// Show health bars above bots that were damaged recently.
+    pub show_health_bars: bool,
+    /// Show what bots are busy with in a separate panel, see [`crate::bot::BotIntent`].
+    pub show_bot_intents: bool,
+    /// Laser sight of newly given weapons is on, it can be toggled for each weapon by key.
+    pub laser_sight: bool,
+    /// Skin of each weapon of the player as an index in skin list of options menu: 0 is
+    /// default look, the rest are [`crate::weapon::WeaponDefinition::skins`].
+    pub weapon_skins: HashMap<WeaponKind, u32>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            auto_switch_weapon: true,
+            show_pickup_notifications: true,
+            show_damage_numbers: false,
+            show_health_bars: true,
+            show_bot_intents: false,
+            laser_sight: true,
+            weapon_skins: Default::default(),
+        }
+    }
+}
+
+impl Visit for Preferences {
+    // Missing fields keep their defaults, so adding a preference doesn't reset the others.
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let _ = self.auto_switch_weapon.visit("auto_switch_weapon", visitor);
+        let _ = self
+            .show_pickup_notifications
+            .visit("show_pickup_notifications", visitor);
+        let _ = self
+            .show_damage_numbers
+            .visit("show_damage_numbers", visitor);
+        let _ = self.show_health_bars.visit("show_health_bars", visitor);
+        let _ = self.show_bot_intents.visit("show_bot_intents", visitor);
+        let _ = self.laser_sight.visit("laser_sight", visitor);
+        let _ = self.weapon_skins.visit("weapon_skins", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+impl Preferences {
+    /// Index of weapon skin in skin list of options menu, 0 is default look.
+    pub fn weapon_skin(&self, kind: WeaponKind) -> usize {
+        self.weapon_skins
+            .get(&kind)
+            .map_or(0, |&skin| skin as usize)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Settings {
+    pub last_match: MatchSettings,
+    pub controls: ControlProfiles,
+    pub preferences: Preferences,
+}
+
+impl Visit for Settings {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.last_match.visit("last_match", visitor)?;
+        // Settings saved before control profiles were added have no such field.
+        if self.controls.visit("controls", visitor).is_err() {
+            self.controls = Default::default();
+        }
+        // Preferences used to be stored in control profiles, older settings get defaults.
+        let _ = self.preferences.visit("preferences", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+impl Settings {
+    fn path() -> PathBuf {
+        user_data_dir().join(SETTINGS_FILE_NAME)
+    }
+
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let mut settings = Self::default();
+        let result = block_on(Visitor::load_binary(path))
+            .map_err(|e| e.to_string())
+            .and_then(|mut visitor| {
+                settings
+                    .visit("Settings", &mut visitor)
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(_) => {
+                // Map could be removed or renamed since last run.
+                if !settings.last_match.map.exists() {
+                    settings.last_match.map = PathBuf::from(level::DEFAULT_MAP);
+                }
+                settings.controls.validate();
+                settings
+            }
+            Err(e) => {
+                Logger::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Unable to load settings from {}: {}. Defaults are used.",
+                        path.display(),
+                        e
+                    ),
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Installs panic hook that saves these settings, or ones saved later, with bindings of
+    /// the shared control scheme and shared preferences, so changes made in options menu are
+    /// not lost on a crash. Profiles are added, removed and switched with an immediate save, so
+    /// the shared scheme always belongs to the active profile of the saved settings.
+    pub fn flush_on_panic(
+        &self,
+        control_scheme: Arc<RwLock<ControlScheme>>,
+        preferences: Arc<RwLock<Preferences>>,
+    ) {
+        *LAST_SAVED.lock() = Some(self.clone());
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous_hook(info);
+            let last_saved = LAST_SAVED.lock().take();
+            if let Some(mut settings) = last_saved {
+                Logger::writeln(
+                    MessageKind::Error,
+                    "Shutting down after panic, flushing settings.".to_owned(),
+                );
+                // Panic could happen while the scheme is locked, bindings are lost then.
+                if let Ok(scheme) = control_scheme.try_read() {
+                    settings.controls.store(&scheme);
+                }
+                if let Ok(preferences) = preferences.try_read() {
+                    settings.preferences = preferences.clone();
+                }
+                settings.save();
+            }
+        }));
+    }
+
+    pub fn save(&mut self) {
+        let path = Self::path();
+        *LAST_SAVED.lock() = Some(self.clone());
+        let mut visitor = Visitor::new();
+        let result = self
+            .visit("Settings", &mut visitor)
+            .and_then(|_| save_binary_atomic(&visitor, &path));
+        if let Err(e) = result {
+            Logger::writeln(
+                MessageKind::Error,
+                format!("Unable to save settings to {}: {}", path.display(), e),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_path;
+
+    fn assert_defaults(settings: &Settings) {
+        let defaults = Settings::default();
+        assert_eq!(
+            format!("{:?}", settings.last_match),
+            format!("{:?}", defaults.last_match)
+        );
+        assert_eq!(
+            format!("{:?}", settings.controls),
+            format!("{:?}", defaults.controls)
+        );
+        assert_eq!(
+            format!("{:?}", settings.preferences),
+            format!("{:?}", defaults.preferences)
+        );
+    }
+
+    fn changed_settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.last_match.bot_count = 7;
+        settings.preferences.show_damage_numbers = true;
+        settings
+    }
+
+    /// Tests run in parallel, so each one saves into its own file.
+    fn saved_bytes(settings: &mut Settings, name: &str) -> Vec<u8> {
+        let path = temp_path(name);
+        let mut visitor = Visitor::new();
+        settings.visit("Settings", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        bytes
+    }
+
+    #[test]
+    fn saved_settings_are_loaded_back() {
+        let path = temp_path("round_trip");
+        std::fs::write(
+            &path,
+            saved_bytes(&mut changed_settings(), "round_trip_saved"),
+        )
+        .unwrap();
+        let settings = Settings::load_from(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(settings.last_match.bot_count, 7);
+        assert!(settings.preferences.show_damage_numbers);
+    }
+
+    #[test]
+    fn truncated_file_gives_defaults() {
+        let bytes = saved_bytes(&mut changed_settings(), "truncated_saved");
+        let path = temp_path("truncated");
+        for &length in [0, 4, bytes.len() / 3, bytes.len() / 2, bytes.len() - 1].iter() {
+            std::fs::write(&path, &bytes[..length]).unwrap();
+            assert_defaults(&Settings::load_from(&path));
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn garbage_file_gives_defaults() {
+        let path = temp_path("garbage");
+        let garbage = (0..256u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        std::fs::write(&path, &garbage).unwrap();
+        assert_defaults(&Settings::load_from(&path));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        assert_defaults(&Settings::load_from(&temp_path("missing")));
+    }
+}
@@ -0,0 +1,134 @@
+//! Uniform grid for proximity queries. Level has lots of static or rarely moving entities
+//! (items, death zones) and every actor checks them every tick, linear scans over all of them
+//! quickly become noticeable with lots of bots and items.
+
+use fyrox::core::{algebra::Vector3, math::aabb::AxisAlignedBoundingBox};
+use std::collections::HashMap;
+
+#[derive(Clone)]
+struct Entry<T> {
+    value: T,
+    bounds: AxisAlignedBoundingBox,
+}
+
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<Entry<T>>>,
+    /// Bounds of every inserted entry, used to stop expanding nearest neighbour search.
+    bounds: Option<AxisAlignedBoundingBox>,
+}
+
+impl<T: Copy + PartialEq> SpatialGrid<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: Default::default(),
+            bounds: None,
+        }
+    }
+
+    fn cell(&self, point: Vector3<f32>) -> (i32, i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+            (point.z / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn cells_of(&self, bounds: &AxisAlignedBoundingBox) -> impl Iterator<Item = (i32, i32, i32)> {
+        let (min_x, min_y, min_z) = self.cell(bounds.min);
+        let (max_x, max_y, max_z) = self.cell(bounds.max);
+        (min_x..=max_x).flat_map(move |x| {
+            (min_y..=max_y).flat_map(move |y| (min_z..=max_z).map(move |z| (x, y, z)))
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds = None;
+    }
+
+    pub fn insert(&mut self, value: T, bounds: AxisAlignedBoundingBox) {
+        let cells = self.cells_of(&bounds).collect::<Vec<_>>();
+        for cell in cells {
+            self.cells
+                .entry(cell)
+                .or_default()
+                .push(Entry { value, bounds });
+        }
+        match self.bounds.as_mut() {
+            Some(total) => {
+                total.add_point(bounds.min);
+                total.add_point(bounds.max);
+            }
+            None => self.bounds = Some(bounds),
+        }
+    }
+
+    pub fn insert_point(&mut self, value: T, point: Vector3<f32>) {
+        self.insert(value, AxisAlignedBoundingBox::from_min_max(point, point));
+    }
+
+    /// Collects every value which bounds intersect given box. Each value is reported once.
+    pub fn query_aabb(&self, bounds: &AxisAlignedBoundingBox, out: &mut Vec<T>) {
+        for cell in self.cells_of(bounds) {
+            if let Some(entries) = self.cells.get(&cell) {
+                for entry in entries {
+                    if entry.bounds.is_intersects_aabb(bounds) && !out.contains(&entry.value) {
+                        out.push(entry.value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn sphere_entries(
+        &self,
+        center: Vector3<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item = &Entry<T>> + '_ {
+        let bounds = AxisAlignedBoundingBox::from_min_max(
+            center - Vector3::repeat(radius),
+            center + Vector3::repeat(radius),
+        );
+        self.cells_of(&bounds)
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flat_map(|entries| entries.iter())
+            .filter(move |entry| entry.bounds.is_intersects_sphere(center, radius))
+    }
+
+    /// Collects every value which bounds intersect given sphere. Each value is reported once.
+    pub fn query_sphere(&self, center: Vector3<f32>, radius: f32, out: &mut Vec<T>) {
+        for entry in self.sphere_entries(center, radius) {
+            if !out.contains(&entry.value) {
+                out.push(entry.value);
+            }
+        }
+    }
+
+    /// Returns value closest to given point among values that satisfy given predicate.
+    /// Distance is measured to center of value bounds.
+    pub fn closest<F: FnMut(T) -> bool>(&self, point: Vector3<f32>, mut filter: F) -> Option<T> {
+        let total = self.bounds?;
+        // Search radius that is enough to cover every entry from given point.
+        let max_radius = total.center().metric_distance(&point) + total.half_extents().norm();
+        let mut radius = self.cell_size;
+        loop {
+            let mut closest = None;
+            let mut closest_distance = f32::MAX;
+            for entry in self.sphere_entries(point, radius) {
+                let distance = entry.bounds.center().metric_distance(&point);
+                if distance < closest_distance && filter(entry.value) {
+                    closest_distance = distance;
+                    closest = Some(entry.value);
+                }
+            }
+            // Anything found farther than the radius might be beaten by an entry that is not
+            // covered by the query yet.
+            if closest.is_some() && closest_distance <= radius || radius >= max_radius {
+                return closest;
+            }
+            radius *= 2.0;
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Window that lets player pick a team when a team match starts. Balancer has already put
+//! player into one of the teams, so "Auto" just keeps it.
+
+use crate::{character::Team, level::Level, message::Message};
+use fyrox::{
+    core::pool::Handle,
+    gui::{
+        button::{ButtonBuilder, ButtonMessage},
+        grid::{Column, GridBuilder, Row},
+        message::{MessageDirection, UiMessage},
+        widget::WidgetBuilder,
+        window::{WindowBuilder, WindowMessage, WindowTitle},
+        Thickness, UiNode, UserInterface,
+    },
+};
+use std::sync::mpsc::Sender;
+
+pub struct TeamSelect {
+    sender: Sender<Message>,
+    window: Handle<UiNode>,
+    red: Handle<UiNode>,
+    blue: Handle<UiNode>,
+    auto: Handle<UiNode>,
+    open: bool,
+}
+
+impl TeamSelect {
+    pub fn new(ui: &mut UserInterface, sender: Sender<Message>) -> Self {
+        let ctx = &mut ui.build_ctx();
+        let red;
+        let blue;
+        let auto;
+        let window = WindowBuilder::new(WidgetBuilder::new().with_width(360.0))
+            .with_title(WindowTitle::text("Select Team"))
+            .can_resize(false)
+            .can_minimize(false)
+            .can_close(false)
+            .open(false)
+            .with_content(
+                GridBuilder::new(
+                    WidgetBuilder::new()
+                        .with_child({
+                            red = ButtonBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_column(0)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_text("Red")
+                            .build(ctx);
+                            red
+                        })
+                        .with_child({
+                            blue = ButtonBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_column(1)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_text("Blue")
+                            .build(ctx);
+                            blue
+                        })
+                        .with_child({
+                            auto = ButtonBuilder::new(
+                                WidgetBuilder::new()
+                                    .on_column(2)
+                                    .with_margin(Thickness::uniform(2.0)),
+                            )
+                            .with_text("Auto")
+                            .build(ctx);
+                            auto
+                        }),
+                )
+                .add_column(Column::stretch())
+                .add_column(Column::stretch())
+                .add_column(Column::stretch())
+                .add_row(Row::strict(36.0))
+                .build(ctx),
+            )
+            .build(ctx);
+
+        Self {
+            sender,
+            window,
+            red,
+            blue,
+            auto,
+            open: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, ui: &mut UserInterface, open: bool) {
+        if self.open == open {
+            return;
+        }
+        self.open = open;
+        if open {
+            ui.send_message(WindowMessage::open(
+                self.window,
+                MessageDirection::ToWidget,
+                true,
+            ));
+        } else {
+            ui.send_message(WindowMessage::close(
+                self.window,
+                MessageDirection::ToWidget,
+            ));
+        }
+    }
+
+    pub fn handle_ui_event(
+        &mut self,
+        ui: &mut UserInterface,
+        message: &UiMessage,
+        level: Option<&Level>,
+    ) {
+        let level = match level {
+            Some(level) if self.open => level,
+            _ => return,
+        };
+
+        if let Some(ButtonMessage::Click) = message.data() {
+            let team = if message.destination() == self.red {
+                Team::Red
+            } else if message.destination() == self.blue {
+                Team::Blue
+            } else if message.destination() == self.auto {
+                Team::None
+            } else {
+                return;
+            };
+
+            if team != Team::None {
+                self.sender
+                    .send(Message::RequestTeamChange {
+                        actor: level.get_player(),
+                        team,
+                    })
+                    .unwrap();
+            }
+            self.set_open(ui, false);
+        }
+    }
+}
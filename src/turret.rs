@@ -0,0 +1,327 @@
+//! Turrets are map entities that shoot at actors. Turret is a node named "Turret*", its child
+//! named "Head" turns towards targets (the node itself turns if there is no head). Suffix
+//! "_Red" or "_Blue" puts turret into a team, in team modes it never shoots its teammates.
+//! Like breakable lights, turrets are damaged by projectile impacts nearby. Destroyed turret
+//! stays on the map as a wreck: if it has children named "Intact" and "Broken", they're
+//! swapped.
+
+use crate::{
+    actor::Actor,
+    character::Team,
+    effects::EffectKind,
+    level,
+    message::{Message, MessageSender},
+    projectile::ProjectileKind,
+};
+use fyrox::{
+    core::{
+        algebra::{Matrix3, UnitQuaternion, Vector3},
+        color::Color,
+        pool::{Handle, Pool},
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{self, debug::SceneDrawingContext, graph::Graph, node::Node, Scene},
+};
+use std::{cmp::Ordering, path::PathBuf};
+
+/// Shooter name of turret projectiles, it is shown in notifications.
+pub const TURRET_NAME: &str = "Turret";
+pub const SHOT_SOUND: &str = "data/sounds/m4_shot.ogg";
+pub const DESTROY_SOUND: &str = "data/sounds/explosion.ogg";
+const HEALTH: f32 = 150.0;
+/// Projectile impacts closer than this distance to turret damage it.
+const HIT_RADIUS: f32 = 1.0;
+/// Turret sees and shoots actors only within this distance.
+const RANGE: f32 = 18.0;
+/// Time between shots in seconds.
+const FIRE_INTERVAL: f32 = 0.3;
+/// Head turns towards target at this speed, degrees per second.
+const TURN_SPEED: f32 = 120.0;
+/// Head sweeps this many degrees to each side while there is nobody to shoot.
+const SWEEP_ANGLE: f32 = 60.0;
+/// Sweep is slower than turning towards a target, degrees per second.
+const SWEEP_SPEED: f32 = 40.0;
+/// Turret fires only when its head points at target within this angle, in degrees.
+const FIRE_CONE: f32 = 5.0;
+/// Distance from head to the point where projectiles appear, so they don't hit turret itself.
+const MUZZLE_OFFSET: f32 = 0.7;
+
+/// Actor that turret may shoot at.
+pub struct TurretTarget {
+    pub handle: Handle<Actor>,
+    /// Chest of the actor, see [`crate::character::Character::aim_position`].
+    pub position: Vector3<f32>,
+    pub team: Team,
+}
+
+#[derive(Visit)]
+pub struct Turret {
+    node: Handle<Node>,
+    head: Handle<Node>,
+    team: Team,
+    health: f32,
+    /// Local rotation of head in the map, yaw is applied on top of it.
+    base_rotation: UnitQuaternion<f32>,
+    /// Direction of head in the map, yaw is measured from it.
+    base_forward: Vector3<f32>,
+    yaw: f32,
+    /// Direction of sweep, 1.0 or -1.0.
+    sweep_sign: f32,
+    fire_timer: f32,
+    #[visit(skip)]
+    target: Handle<Actor>,
+}
+
+impl Default for Turret {
+    fn default() -> Self {
+        Self {
+            node: Default::default(),
+            head: Default::default(),
+            team: Team::None,
+            health: HEALTH,
+            base_rotation: Default::default(),
+            base_forward: Vector3::z(),
+            yaw: 0.0,
+            sweep_sign: 1.0,
+            fire_timer: 0.0,
+            target: Handle::NONE,
+        }
+    }
+}
+
+/// Angle of rotation around vertical axis that turns `from` towards `to`, in -PI..PI range.
+fn yaw_between(from: Vector3<f32>, to: Vector3<f32>) -> f32 {
+    wrap_angle(to.x.atan2(to.z) - from.x.atan2(from.z))
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    let pi = std::f32::consts::PI;
+    (angle + pi).rem_euclid(2.0 * pi) - pi
+}
+
+impl Turret {
+    pub fn new(node: Handle<Node>, team: Team, graph: &Graph) -> Self {
+        let head = match graph.find_by_name(node, "Head") {
+            head if head.is_some() => head,
+            _ => node,
+        };
+        Self {
+            node,
+            head,
+            team,
+            base_rotation: **graph[head].local_transform().rotation(),
+            base_forward: graph[head].look_vector(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    pub fn position(&self, graph: &Graph) -> Vector3<f32> {
+        graph[self.head].global_position()
+    }
+
+    fn forward(&self) -> Vector3<f32> {
+        UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw)
+            .transform_vector(&self.base_forward)
+    }
+
+    fn is_enemy(&self, target: &TurretTarget, team_mode: bool) -> bool {
+        !team_mode || self.team == Team::None || target.team != self.team
+    }
+
+    /// Closest enemy within range and line of sight.
+    fn select_target<'a>(
+        &self,
+        scene: &Scene,
+        targets: &'a [TurretTarget],
+        team_mode: bool,
+    ) -> Option<&'a TurretTarget> {
+        let position = self.position(&scene.graph);
+        targets
+            .iter()
+            .filter(|target| self.is_enemy(target, team_mode))
+            .map(|target| (target, target.position.metric_distance(&position)))
+            .filter(|(target, distance)| {
+                *distance <= RANGE && {
+                    // Ray starts outside of turret model, otherwise it would block the view.
+                    let dir = (target.position - position).scale(1.0 / distance.max(f32::EPSILON));
+                    level::is_visible(scene, position + dir.scale(MUZZLE_OFFSET), target.position)
+                }
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(target, _)| target)
+    }
+
+    fn update(
+        &mut self,
+        scene: &mut Scene,
+        targets: &[TurretTarget],
+        team_mode: bool,
+        delta: f32,
+        sender: &MessageSender,
+    ) {
+        if self.is_destroyed() {
+            return;
+        }
+
+        let target = self.select_target(scene, targets, team_mode);
+        self.target = target.map_or(Handle::NONE, |target| target.handle);
+        let position = self.position(&scene.graph);
+
+        let aim_error = match target {
+            Some(target) => {
+                let error = yaw_between(self.forward(), target.position - position);
+                let max_turn = TURN_SPEED.to_radians() * delta;
+                self.yaw = wrap_angle(self.yaw + error.clamp(-max_turn, max_turn));
+                Some(error)
+            }
+            None => {
+                self.yaw += self.sweep_sign * SWEEP_SPEED.to_radians() * delta;
+                if self.yaw.abs() >= SWEEP_ANGLE.to_radians() {
+                    self.yaw = self
+                        .yaw
+                        .clamp(-SWEEP_ANGLE.to_radians(), SWEEP_ANGLE.to_radians());
+                    self.sweep_sign = -self.sweep_sign;
+                }
+                None
+            }
+        };
+        scene.graph[self.head].local_transform_mut().set_rotation(
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw) * self.base_rotation,
+        );
+
+        self.fire_timer -= delta;
+        if let (Some(target), Some(error)) = (target, aim_error) {
+            if error.abs() <= FIRE_CONE.to_radians() && self.fire_timer <= 0.0 {
+                self.fire_timer = FIRE_INTERVAL;
+                let muzzle = position + self.forward().scale(MUZZLE_OFFSET);
+                sender.send(Message::CreateProjectile {
+                    kind: ProjectileKind::Bullet,
+                    position: muzzle,
+                    direction: target.position - muzzle,
+                    initial_velocity: Vector3::default(),
+                    owner: Handle::NONE,
+                    basis: Matrix3::identity(),
+                    shooter_name: Some(TURRET_NAME.to_owned()),
+                });
+                sender.send(Message::PlaySound {
+                    path: PathBuf::from(SHOT_SOUND),
+                    position: muzzle,
+                    gain: 0.8,
+                    rolloff_factor: 3.0,
+                    radius: 3.0,
+                });
+            }
+        }
+    }
+
+    fn destroy(&self, graph: &mut Graph, sender: &MessageSender) {
+        let intact = graph.find_by_name(self.node, "Intact");
+        if intact.is_some() {
+            graph[intact].set_visibility(false);
+        }
+        let broken = graph.find_by_name(self.node, "Broken");
+        if broken.is_some() {
+            graph[broken].set_visibility(true);
+        }
+
+        let position = self.position(graph);
+        sender.send(Message::CreateEffect {
+            kind: EffectKind::BulletImpact,
+            position,
+        });
+        sender.send(Message::CreateEffect {
+            kind: EffectKind::Smoke,
+            position,
+        });
+        sender.send(Message::PlaySound {
+            path: PathBuf::from(DESTROY_SOUND),
+            position,
+            gain: 1.0,
+            rolloff_factor: 3.0,
+            radius: 4.0,
+        });
+    }
+
+    /// Range circle, yellow while turret sees a target, and direction of the head.
+    pub fn debug_draw(&self, graph: &Graph, context: &mut SceneDrawingContext) {
+        let position = self.position(graph);
+        let color = if self.is_destroyed() {
+            Color::opaque(80, 80, 80)
+        } else if self.target.is_some() {
+            Color::opaque(255, 200, 0)
+        } else {
+            Color::opaque(200, 0, 0)
+        };
+        let segments = 32;
+        let ring_point = |i: usize| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            position + Vector3::new(angle.cos(), 0.0, angle.sin()).scale(RANGE)
+        };
+        for i in 0..segments {
+            context.add_line(scene::debug::Line {
+                begin: ring_point(i),
+                end: ring_point(i + 1),
+                color,
+            });
+        }
+        context.add_line(scene::debug::Line {
+            begin: position,
+            end: position + self.forward().scale(RANGE),
+            color,
+        });
+    }
+}
+
+#[derive(Default, Visit)]
+pub struct TurretContainer {
+    pool: Pool<Turret>,
+}
+
+impl TurretContainer {
+    pub fn add(&mut self, turret: Turret) -> Handle<Turret> {
+        self.pool.spawn(turret)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Turret> {
+        self.pool.iter()
+    }
+
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        targets: &[TurretTarget],
+        team_mode: bool,
+        delta: f32,
+        sender: &MessageSender,
+    ) {
+        for turret in self.pool.iter_mut() {
+            turret.update(scene, targets, team_mode, delta, sender);
+        }
+    }
+
+    /// Damages every intact turret near impact point.
+    pub fn handle_impact(
+        &mut self,
+        graph: &mut Graph,
+        position: Vector3<f32>,
+        amount: f32,
+        sender: &MessageSender,
+    ) {
+        for turret in self.pool.iter_mut() {
+            if turret.is_destroyed()
+                || turret.position(graph).metric_distance(&position) > HIT_RADIUS
+            {
+                continue;
+            }
+
+            turret.health -= amount.abs();
+            if turret.is_destroyed() {
+                turret.destroy(graph, sender);
+            }
+        }
+    }
+}
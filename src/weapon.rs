@@ -1,44 +1,145 @@
 use crate::{
-    actor::Actor, actor::ActorContainer, message::Message, projectile::ProjectileKind, GameTime,
+    actor::Actor,
+    actor::ActorContainer,
+    definitions::Definitions,
+    logger::Logger,
+    message::{Message, MessageSender},
+    projectile::ProjectileKind,
+    query_buffer::QueryBufferPool,
+    GameTime,
 };
 use fyrox::{
     core::{
-        algebra::{Matrix3, Point3, Vector3},
+        algebra::{Matrix3, Matrix4, Point3, UnitQuaternion, Vector3},
         color::Color,
         math::{ray::Ray, Matrix4Ext, Vector3Ext},
+        parking_lot::Mutex,
         pool::{Handle, Pool},
+        rand::Rng,
+        sstorage::ImmutableString,
         visitor::{Visit, VisitResult, Visitor},
     },
     engine::resource_manager::ResourceManager,
+    material::{shader::SamplerFallback, Material, PropertyValue},
     scene::{
         base::BaseBuilder,
         collider::InteractionGroups,
         graph::{physics::RayCastOptions, Graph},
-        light::{point::PointLightBuilder, BaseLightBuilder},
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+            Mesh, MeshBuilder, RenderPath,
+        },
         node::Node,
+        pivot::PivotBuilder,
+        sprite::SpriteBuilder,
         Scene,
     },
-    utils::log::{Log, MessageKind},
+    utils::log::MessageKind,
 };
+use serde::Deserialize;
 use std::{
     ops::{Index, IndexMut},
     path::{Path, PathBuf},
-    sync::mpsc::Sender,
+    sync::Arc,
 };
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Visit)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Visit, Deserialize)]
 pub enum WeaponKind {
     M4,
     Ak47,
     PlasmaRifle,
     RocketLauncher,
+    MineLayer,
+    /// Hand grenade, it is cooked while fire button is held, see [`WeaponCharge`].
+    Grenade,
+}
+
+impl Default for WeaponKind {
+    fn default() -> Self {
+        Self::M4
+    }
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 6] = [
+        WeaponKind::M4,
+        WeaponKind::Ak47,
+        WeaponKind::PlasmaRifle,
+        WeaponKind::RocketLauncher,
+        WeaponKind::MineLayer,
+        WeaponKind::Grenade,
+    ];
+
+    /// Higher is better. Actors switch to picked up weapon only if it is better than current.
+    pub fn priority(self) -> u32 {
+        match self {
+            // Mines are a tool for ambushes, nobody should switch to them automatically.
+            WeaponKind::MineLayer => 0,
+            WeaponKind::Grenade => 0,
+            WeaponKind::Ak47 => 0,
+            WeaponKind::M4 => 1,
+            WeaponKind::PlasmaRifle => 2,
+            WeaponKind::RocketLauncher => 3,
+        }
+    }
+
+    /// Name that is shown to players, it comes from weapon definition.
+    pub fn display_name(self) -> &'static str {
+        &Definitions::get().weapon(self).name
+    }
+
+    /// Abbreviation that fits into a slot of HUD weapon bar.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            WeaponKind::M4 => "M4",
+            WeaponKind::Ak47 => "AK",
+            WeaponKind::PlasmaRifle => "PLS",
+            WeaponKind::RocketLauncher => "RL",
+            WeaponKind::MineLayer => "MINE",
+            WeaponKind::Grenade => "GRN",
+        }
+    }
+}
+
+/// Time in seconds to put a weapon away, and the same time to take the next one out.
+pub const SWITCH_TIME: f32 = 0.2;
+/// How far down a weapon model is moved when it is put away.
+const SWITCH_DROP: f32 = 0.3;
+/// Crosshair of weapons that do not specify their own one, it is also shown without weapon.
+pub const DEFAULT_CROSSHAIR: &str = "data/ui/crosshair.tga";
+const LASER_COLOR: Color = Color::from_rgba(255, 20, 20, 140);
+const LASER_DOT_TEXTURE: &str = "data/particles/circle_05.png";
+const LASER_DOT_SIZE: f32 = 0.03;
+/// Bots' beams are thick enough to see where they aim.
+const LASER_THICKNESS: f32 = 0.006;
+/// Player's own beam goes right into the crosshair, so it is barely visible.
+const PLAYER_LASER_THICKNESS: f32 = 0.0015;
+const LASER_RANGE: f32 = 100.0;
+
+/// Stage of weapon switch, weapon can shoot only when it is ready.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum WeaponState {
+    Ready,
+    Hidden,
+    /// Weapon is being put away, `next` weapon is shown when it is done.
+    Lowering {
+        elapsed: f32,
+        next: Handle<Weapon>,
+    },
+    Raising {
+        elapsed: f32,
+    },
 }
 
-#[derive(Visit)]
 pub struct Weapon {
     kind: WeaponKind,
     model: Handle<Node>,
+    /// Parent of laser beam and dot, both of them are positioned in world coordinates.
+    laser: Handle<Node>,
+    laser_beam: Handle<Node>,
     laser_dot: Handle<Node>,
+    /// Laser sight can be turned on and off by player.
+    laser_enabled: bool,
     shot_point: Handle<Node>,
     offset: Vector3<f32>,
     dest_offset: Vector3<f32>,
@@ -46,23 +147,315 @@ pub struct Weapon {
     shot_position: Vector3<f32>,
     owner: Handle<Actor>,
     ammo: u32,
-    #[visit(skip)]
-    pub sender: Option<Sender<Message>>,
+    pub sender: MessageSender,
+    /// Switch is short enough to not be saved, weapons are ready after load.
+    state: WeaponState,
+    /// Actor on which laser sight rests, it is found again on every update so it is not saved.
+    aimed_actor: Handle<Actor>,
+    /// Time in seconds for which weapon is being charged, `None` if it is not charged. Charge
+    /// lasts a few seconds, so it is not saved.
+    charge_time: Option<f32>,
+}
+
+impl Visit for Weapon {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.kind.visit("kind", visitor)?;
+        self.model.visit("model", visitor)?;
+        // Saves made before laser beam have only a dot, laser is rebuilt after load, see
+        // `restore_missing_nodes`.
+        let _ = self.laser.visit("laser", visitor);
+        let _ = self.laser_beam.visit("laser_beam", visitor);
+        let _ = self.laser_enabled.visit("laser_enabled", visitor);
+        self.laser_dot.visit("laser_dot", visitor)?;
+        self.shot_point.visit("shot_point", visitor)?;
+        self.offset.visit("offset", visitor)?;
+        self.dest_offset.visit("dest_offset", visitor)?;
+        self.last_shot_time.visit("last_shot_time", visitor)?;
+        self.shot_position.visit("shot_position", visitor)?;
+        self.owner.visit("owner", visitor)?;
+        self.ammo.visit("ammo", visitor)?;
+
+        // Saves made before ammo was capped may have more than weapon can hold.
+        if visitor.is_reading() {
+            self.clamp_ammo();
+        }
+
+        visitor.leave_region()
+    }
 }
 
+#[derive(Deserialize, Debug)]
 pub struct WeaponDefinition {
-    pub model: &'static str,
-    pub shot_sound: &'static str,
+    /// Name that is shown to players.
+    pub name: String,
+    pub model: String,
+    pub shot_sound: String,
+    /// Ammo of newly given weapon.
     pub ammo: u32,
+    /// Weapon can't hold more ammo than this, extra ammo from pickups is lost.
+    pub max_ammo: u32,
     pub projectile: ProjectileKind,
     pub shoot_interval: f64,
+    /// Half-angle in degrees of a cone in which shots are scattered.
+    pub spread: f32,
+    /// Weapons without zoom can't aim down sights.
+    pub zoom: Option<WeaponZoom>,
+    /// How movement of shooter affects spread, default multipliers are used if not specified.
+    #[serde(default)]
+    pub movement_spread: MovementSpread,
+    /// Default crosshair is used if not specified.
+    #[serde(default)]
+    pub crosshair: WeaponCrosshair,
+    /// Weapons with charge shoot when fire button is released instead of when it is pressed.
+    #[serde(default)]
+    pub charge: Option<WeaponCharge>,
+    /// Diffuse textures that can replace the one of the model, player picks one in options
+    /// and bots pick a random one.
+    #[serde(default)]
+    pub skins: Vec<String>,
+}
+
+/// Charge of a weapon that is held in hand before it is thrown, like a cooked grenade. Fuse
+/// starts to burn when charge starts.
+#[derive(Deserialize, Debug)]
+pub struct WeaponCharge {
+    /// Time in seconds from the start of charge until projectile goes off. If it is still in
+    /// hand by then, it goes off in hand.
+    pub fuse: f32,
+    /// Charge time in seconds that gives the strongest throw.
+    pub full_charge_time: f32,
+    /// Strength of a throw after a short tap, full charge gives strength of 1.0.
+    pub min_strength: f32,
+}
+
+impl WeaponCharge {
+    /// Returns throw strength in `min_strength..=1.0` range for given charge time.
+    pub fn strength(&self, charge_time: f32) -> f32 {
+        let factor = (charge_time / self.full_charge_time).clamp(0.0, 1.0);
+        self.min_strength + (1.0 - self.min_strength) * factor
+    }
+}
+
+/// Crosshair that HUD shows while weapon is in hands.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WeaponCrosshair {
+    pub texture: String,
+    /// Width and height of crosshair on screen, in pixels.
+    pub size: f32,
+}
+
+impl Default for WeaponCrosshair {
+    fn default() -> Self {
+        Self {
+            texture: DEFAULT_CROSSHAIR.to_owned(),
+            size: 33.0,
+        }
+    }
+}
+
+/// Multipliers of spread that depend on movement state of shooter at the moment of a shot.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MovementSpread {
+    /// Multiplier when shooter moves at [`MOVING_SPREAD_SPEED`] or faster, slower movement
+    /// gives proportionally smaller penalty.
+    pub moving: f32,
+    /// Multiplier when shooter has no ground contact.
+    pub airborne: f32,
+    /// Multiplier when shooter crouches.
+    pub crouched: f32,
+}
+
+impl Default for MovementSpread {
+    fn default() -> Self {
+        Self {
+            moving: 1.3,
+            airborne: 2.0,
+            crouched: 0.6,
+        }
+    }
+}
+
+/// Horizontal speed in m/s at which moving spread multiplier applies fully.
+pub const MOVING_SPREAD_SPEED: f32 = 5.0;
+
+/// Movement state of an actor at the moment it shoots.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ShooterState {
+    pub velocity: Vector3<f32>,
+    pub grounded: bool,
+    pub crouched: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WeaponZoom {
+    /// Vertical field of view in degrees when fully zoomed in.
+    pub fov: f32,
+    /// Multiplier of spread when fully zoomed in.
+    pub spread_scale: f32,
+}
+
+impl WeaponDefinition {
+    /// Names of skins for options menu: default look first, then file names of skins.
+    pub fn skin_names(&self) -> Vec<String> {
+        std::iter::once("Default".to_owned())
+            .chain(self.skins.iter().map(|path| {
+                Path::new(path)
+                    .file_stem()
+                    .map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned())
+            }))
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name must not be empty".to_owned());
+        }
+        if self.shoot_interval <= 0.0 {
+            return Err(format!(
+                "shoot_interval must be positive, got {}",
+                self.shoot_interval
+            ));
+        }
+        if self.max_ammo < self.ammo {
+            return Err(format!(
+                "max_ammo must not be less than ammo, got {} < {}",
+                self.max_ammo, self.ammo
+            ));
+        }
+        if self.spread < 0.0 {
+            return Err(format!("spread must not be negative, got {}", self.spread));
+        }
+        let movement = &self.movement_spread;
+        if movement.moving < 0.0 || movement.airborne < 0.0 || movement.crouched < 0.0 {
+            return Err(format!(
+                "movement spread multipliers must not be negative, got {:?}",
+                movement
+            ));
+        }
+        if self.crosshair.size <= 0.0 {
+            return Err(format!(
+                "crosshair size must be positive, got {}",
+                self.crosshair.size
+            ));
+        }
+        if let Some(charge) = self.charge.as_ref() {
+            if charge.fuse <= 0.0 || charge.full_charge_time <= 0.0 {
+                return Err(format!(
+                    "charge fuse and full_charge_time must be positive, got {} and {}",
+                    charge.fuse, charge.full_charge_time
+                ));
+            }
+            if !(0.0..=1.0).contains(&charge.min_strength) {
+                return Err(format!(
+                    "charge min_strength must be between 0 and 1, got {}",
+                    charge.min_strength
+                ));
+            }
+        }
+        if let Some(zoom) = self.zoom.as_ref() {
+            if zoom.fov <= 0.0 || zoom.fov >= 180.0 {
+                return Err(format!(
+                    "zoom fov must be between 0 and 180 degrees, got {}",
+                    zoom.fov
+                ));
+            }
+            if zoom.spread_scale < 0.0 {
+                return Err(format!(
+                    "zoom spread_scale must not be negative, got {}",
+                    zoom.spread_scale
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns half-angle in degrees of spread cone of a shot. Base spread is scaled by zoom
+/// (`zoom` is in 0.0..=1.0 range) and by movement state of the shooter.
+pub fn shot_spread(definition: &WeaponDefinition, zoom: f32, shooter: &ShooterState) -> f32 {
+    // Aiming down sights tightens spread.
+    let zoom_scale = definition.zoom.as_ref().map_or(1.0, |zoom_definition| {
+        1.0 + (zoom_definition.spread_scale - 1.0) * zoom
+    });
+
+    let movement = &definition.movement_spread;
+    let horizontal_speed = Vector3::new(shooter.velocity.x, 0.0, shooter.velocity.z).norm();
+    let moving_factor = (horizontal_speed / MOVING_SPREAD_SPEED).min(1.0);
+    let mut movement_scale = 1.0 + (movement.moving - 1.0) * moving_factor;
+    if !shooter.grounded {
+        movement_scale *= movement.airborne;
+    } else if shooter.crouched {
+        movement_scale *= movement.crouched;
+    }
+
+    definition.spread * zoom_scale * movement_scale
+}
+
+/// Creates laser sight: a beam and a dot at its end under a common pivot. Returns handles of
+/// the pivot, the beam and the dot.
+fn build_laser(
+    graph: &mut Graph,
+    resource_manager: &ResourceManager,
+) -> (Handle<Node>, Handle<Node>, Handle<Node>) {
+    let mut material = Material::standard();
+    if let Err(e) = material.set_property(
+        &ImmutableString::new("diffuseColor"),
+        PropertyValue::Color(LASER_COLOR),
+    ) {
+        Logger::writeln(
+            MessageKind::Warning,
+            format!("Unable to set color of laser beam: {:?}", e),
+        );
+    }
+    // Unit cylinder along Y axis, it is stretched from muzzle to hit point every frame.
+    let laser_beam = MeshBuilder::new(BaseBuilder::new())
+        .with_surfaces(vec![SurfaceBuilder::new(SurfaceSharedData::new(
+            SurfaceData::make_cylinder(6, 1.0, 1.0, false, &Matrix4::identity()),
+        ))
+        .with_material(Arc::new(Mutex::new(material)))
+        .build()])
+        .with_cast_shadows(false)
+        .with_render_path(RenderPath::Forward)
+        .build(graph);
+    let laser_dot = SpriteBuilder::new(BaseBuilder::new())
+        .with_size(LASER_DOT_SIZE)
+        .with_color(LASER_COLOR)
+        .with_texture(resource_manager.request_texture(LASER_DOT_TEXTURE))
+        .build(graph);
+    let laser =
+        PivotBuilder::new(BaseBuilder::new().with_children(&[laser_beam, laser_dot])).build(graph);
+    (laser, laser_beam, laser_dot)
+}
+
+/// Deviates direction by a random angle within a cone with given half-angle in degrees.
+pub fn apply_spread<R: Rng>(direction: Vector3<f32>, spread: f32, rng: &mut R) -> Vector3<f32> {
+    if spread <= 0.0 {
+        return direction;
+    }
+    let side = direction
+        .cross(&Vector3::y())
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vector3::x);
+    let up = side.cross(&direction);
+    let angle = rng.gen_range(0.0..spread).to_radians();
+    let roll = rng.gen_range(0.0..std::f32::consts::TAU);
+    (direction.scale(angle.cos())
+        + (side.scale(roll.cos()) + up.scale(roll.sin())).scale(angle.sin()))
+    .normalize()
 }
 
 impl Default for Weapon {
     fn default() -> Self {
         Self {
             kind: WeaponKind::M4,
+            laser: Handle::NONE,
+            laser_beam: Handle::NONE,
             laser_dot: Handle::NONE,
+            laser_enabled: true,
             model: Handle::NONE,
             offset: Vector3::default(),
             shot_point: Handle::NONE,
@@ -71,113 +464,333 @@ impl Default for Weapon {
             shot_position: Vector3::default(),
             owner: Handle::NONE,
             ammo: 250,
-            sender: None,
+            sender: Default::default(),
+            state: WeaponState::Ready,
+            aimed_actor: Handle::NONE,
+            charge_time: None,
+        }
+    }
+}
+
+/// Replaces diffuse texture of every mesh of weapon model with a skin. Materials of a model
+/// resource are shared between its instances, so each surface gets its own copy. Skin that
+/// can't be loaded leaves default look.
+async fn apply_skin(
+    model: Handle<Node>,
+    path: &str,
+    resource_manager: &ResourceManager,
+    graph: &mut Graph,
+) {
+    let texture = match resource_manager.request_texture(path).await {
+        Ok(texture) => texture,
+        Err(e) => {
+            Logger::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Unable to load weapon skin {}: {:?}. Default look is used.",
+                    path, e
+                ),
+            );
+            return;
+        }
+    };
+
+    let meshes = graph.traverse_handle_iter(model).collect::<Vec<_>>();
+    for handle in meshes {
+        if let Some(mesh) = graph[handle].cast_mut::<Mesh>() {
+            for surface in mesh.surfaces_mut() {
+                let mut material = surface.material().lock().clone();
+                if let Err(e) = material.set_property(
+                    &ImmutableString::new("diffuseTexture"),
+                    PropertyValue::Sampler {
+                        value: Some(texture.clone()),
+                        fallback: SamplerFallback::White,
+                    },
+                ) {
+                    Logger::writeln(
+                        MessageKind::Warning,
+                        format!("Unable to apply weapon skin {}: {:?}", path, e),
+                    );
+                    return;
+                }
+                surface.set_material(Arc::new(Mutex::new(material)));
+            }
         }
     }
 }
 
 impl Weapon {
-    pub fn get_definition(kind: WeaponKind) -> &'static WeaponDefinition {
+    /// Definition that is used when no valid data file is available, see
+    /// [`crate::definitions`].
+    pub fn builtin_definition(kind: WeaponKind) -> WeaponDefinition {
         match kind {
-            WeaponKind::M4 => {
-                static DEFINITION: WeaponDefinition = WeaponDefinition {
-                    model: "data/models/m4.FBX",
-                    shot_sound: "data/sounds/m4_shot.ogg",
-                    ammo: 200,
-                    projectile: ProjectileKind::Bullet,
-                    shoot_interval: 0.15,
-                };
-                &DEFINITION
-            }
-            WeaponKind::Ak47 => {
-                static DEFINITION: WeaponDefinition = WeaponDefinition {
-                    model: "data/models/ak47.FBX",
-                    shot_sound: "data/sounds/ak47.ogg",
-                    ammo: 200,
-                    projectile: ProjectileKind::Bullet,
-                    shoot_interval: 0.15,
-                };
-                &DEFINITION
-            }
-            WeaponKind::PlasmaRifle => {
-                static DEFINITION: WeaponDefinition = WeaponDefinition {
-                    model: "data/models/plasma_rifle.FBX",
-                    shot_sound: "data/sounds/plasma_shot.ogg",
-                    ammo: 100,
-                    projectile: ProjectileKind::Plasma,
-                    shoot_interval: 0.25,
-                };
-                &DEFINITION
-            }
-            WeaponKind::RocketLauncher => {
-                static DEFINITION: WeaponDefinition = WeaponDefinition {
-                    model: "data/models/Rpg7.FBX",
-                    shot_sound: "data/sounds/grenade_launcher_fire.ogg",
-                    ammo: 100,
-                    projectile: ProjectileKind::Rocket,
-                    shoot_interval: 1.5,
-                };
-                &DEFINITION
-            }
+            WeaponKind::M4 => WeaponDefinition {
+                name: "M4".to_owned(),
+                model: "data/models/m4.FBX".to_owned(),
+                shot_sound: "data/sounds/m4_shot.ogg".to_owned(),
+                ammo: 200,
+                max_ammo: 400,
+                projectile: ProjectileKind::Bullet,
+                shoot_interval: 0.15,
+                spread: 1.5,
+                zoom: Some(WeaponZoom {
+                    fov: 40.0,
+                    spread_scale: 0.25,
+                }),
+                movement_spread: Default::default(),
+                crosshair: Default::default(),
+                charge: None,
+                skins: vec!["data/models/camouflage.jpg".to_owned()],
+            },
+            WeaponKind::Ak47 => WeaponDefinition {
+                name: "AK-47".to_owned(),
+                model: "data/models/ak47.FBX".to_owned(),
+                shot_sound: "data/sounds/ak47.ogg".to_owned(),
+                ammo: 200,
+                max_ammo: 400,
+                projectile: ProjectileKind::Bullet,
+                shoot_interval: 0.15,
+                spread: 2.0,
+                zoom: None,
+                movement_spread: Default::default(),
+                crosshair: Default::default(),
+                charge: None,
+                skins: Vec::new(),
+            },
+            WeaponKind::PlasmaRifle => WeaponDefinition {
+                name: "Plasma Rifle".to_owned(),
+                model: "data/models/plasma_rifle.FBX".to_owned(),
+                shot_sound: "data/sounds/plasma_shot.ogg".to_owned(),
+                ammo: 100,
+                max_ammo: 300,
+                projectile: ProjectileKind::Plasma,
+                shoot_interval: 0.25,
+                spread: 0.5,
+                zoom: None,
+                movement_spread: Default::default(),
+                crosshair: Default::default(),
+                charge: None,
+                skins: Vec::new(),
+            },
+            WeaponKind::RocketLauncher => WeaponDefinition {
+                name: "Rocket Launcher".to_owned(),
+                model: "data/models/Rpg7.FBX".to_owned(),
+                shot_sound: "data/sounds/grenade_launcher_fire.ogg".to_owned(),
+                ammo: 10,
+                max_ammo: 25,
+                projectile: ProjectileKind::Rocket,
+                shoot_interval: 1.5,
+                spread: 0.0,
+                zoom: None,
+                movement_spread: Default::default(),
+                crosshair: WeaponCrosshair {
+                    size: 56.0,
+                    ..Default::default()
+                },
+                charge: None,
+                skins: Vec::new(),
+            },
+            WeaponKind::MineLayer => WeaponDefinition {
+                name: "Mine Layer".to_owned(),
+                model: "data/models/Rpg7.FBX".to_owned(),
+                shot_sound: "data/sounds/grenade_launcher_fire.ogg".to_owned(),
+                ammo: 10,
+                max_ammo: 20,
+                projectile: ProjectileKind::Mine,
+                shoot_interval: 1.0,
+                spread: 0.0,
+                zoom: None,
+                movement_spread: Default::default(),
+                crosshair: Default::default(),
+                charge: None,
+                skins: Vec::new(),
+            },
+            WeaponKind::Grenade => WeaponDefinition {
+                name: "Frag Grenade".to_owned(),
+                model: "data/models/Rpg7.FBX".to_owned(),
+                shot_sound: "data/sounds/grenade_launcher_fire.ogg".to_owned(),
+                ammo: 5,
+                max_ammo: 10,
+                projectile: ProjectileKind::Grenade,
+                shoot_interval: 1.0,
+                spread: 0.0,
+                zoom: None,
+                movement_spread: Default::default(),
+                crosshair: Default::default(),
+                charge: Some(WeaponCharge {
+                    fuse: 3.0,
+                    full_charge_time: 1.0,
+                    min_strength: 0.3,
+                }),
+                skins: Vec::new(),
+            },
         }
     }
 
+    pub fn get_definition(kind: WeaponKind) -> &'static WeaponDefinition {
+        Definitions::get().weapon(kind)
+    }
+
     pub async fn new(
         kind: WeaponKind,
         resource_manager: ResourceManager,
         scene: &mut Scene,
-        sender: Sender<Message>,
+        sender: MessageSender,
+        skin: Option<usize>,
     ) -> Weapon {
         let definition = Self::get_definition(kind);
 
         let model = resource_manager
-            .request_model(Path::new(definition.model))
+            .request_model(Path::new(&definition.model))
             .await
             .unwrap()
             .instantiate_geometry(scene);
 
-        let laser_dot = PointLightBuilder::new(
-            BaseLightBuilder::new(BaseBuilder::new())
-                .with_color(Color::opaque(255, 0, 0))
-                .with_scatter_enabled(false)
-                .cast_shadows(false),
-        )
-        .with_radius(0.5)
-        .build(&mut scene.graph);
+        if let Some(path) = skin.and_then(|skin| definition.skins.get(skin)) {
+            apply_skin(model, path, &resource_manager, &mut scene.graph).await;
+        }
+
+        let (laser, laser_beam, laser_dot) = build_laser(&mut scene.graph, &resource_manager);
 
         let shot_point = scene.graph.find_by_name(model, "Weapon:ShotPoint");
 
         if shot_point.is_none() {
-            Log::writeln(MessageKind::Warning, "Shot point not found!".to_owned());
+            Logger::writeln(MessageKind::Warning, "Shot point not found!".to_owned());
         }
 
         Weapon {
             kind,
+            laser,
+            laser_beam,
             laser_dot,
             model,
             shot_point,
             ammo: definition.ammo,
-            sender: Some(sender),
+            sender,
             ..Default::default()
         }
     }
 
-    pub fn set_visibility(&self, visibility: bool, graph: &mut Graph) {
+    /// Saves made before laser beam have only a point light as laser dot, such laser is
+    /// replaced with a new one. Returns true if laser was replaced.
+    pub fn restore_missing_nodes(
+        &mut self,
+        graph: &mut Graph,
+        resource_manager: &ResourceManager,
+    ) -> bool {
+        if graph.is_valid_handle(self.laser) {
+            return false;
+        }
+        if graph.is_valid_handle(self.laser_dot) {
+            graph.remove_node(self.laser_dot);
+        }
+        let (laser, laser_beam, laser_dot) = build_laser(graph, resource_manager);
+        self.laser = laser;
+        self.laser_beam = laser_beam;
+        self.laser_dot = laser_dot;
+        true
+    }
+
+    /// Shows or hides weapon instantly, use [`Self::lower`] and [`Self::raise`] to animate.
+    pub fn set_visibility(&mut self, visibility: bool, graph: &mut Graph) {
         graph[self.model].set_visibility(visibility);
-        graph[self.laser_dot].set_visibility(visibility);
+        self.state = if visibility {
+            WeaponState::Ready
+        } else {
+            WeaponState::Hidden
+        };
+    }
+
+    pub fn state(&self) -> WeaponState {
+        self.state
+    }
+
+    /// Starts putting weapon away, `next` weapon will be shown by [`Message::ShowWeapon`] when
+    /// weapon is hidden. Weapon that is already hidden requests next weapon immediately.
+    pub fn lower(&mut self, next: Handle<Weapon>) {
+        self.state = match self.state {
+            WeaponState::Hidden => {
+                self.request_show(next);
+                WeaponState::Hidden
+            }
+            WeaponState::Lowering { elapsed, .. } => WeaponState::Lowering { elapsed, next },
+            // Lowering starts from the height at which raising was interrupted.
+            WeaponState::Raising { elapsed } => WeaponState::Lowering {
+                elapsed: SWITCH_TIME - elapsed,
+                next,
+            },
+            WeaponState::Ready => WeaponState::Lowering { elapsed: 0.0, next },
+        };
+    }
+
+    /// Takes weapon out, it becomes ready after [`SWITCH_TIME`].
+    pub fn raise(&mut self, graph: &mut Graph) {
+        graph[self.model].set_visibility(true);
+        self.state = match self.state {
+            WeaponState::Ready => WeaponState::Ready,
+            WeaponState::Lowering { elapsed, .. } => WeaponState::Raising {
+                elapsed: SWITCH_TIME - elapsed,
+            },
+            WeaponState::Raising { elapsed } => WeaponState::Raising { elapsed },
+            WeaponState::Hidden => WeaponState::Raising { elapsed: 0.0 },
+        };
+    }
+
+    fn request_show(&self, weapon: Handle<Weapon>) {
+        if weapon.is_some() {
+            self.sender.send(Message::ShowWeapon {
+                weapon,
+                state: true,
+            });
+        }
+    }
+
+    /// Advances switch animation and returns vertical offset of weapon model.
+    fn update_state(&mut self, graph: &mut Graph, dt: f32) -> f32 {
+        match self.state {
+            WeaponState::Ready | WeaponState::Hidden => 0.0,
+            WeaponState::Lowering { elapsed, next } => {
+                let elapsed = elapsed + dt;
+                if elapsed >= SWITCH_TIME {
+                    self.set_visibility(false, graph);
+                    self.request_show(next);
+                    0.0
+                } else {
+                    self.state = WeaponState::Lowering { elapsed, next };
+                    -SWITCH_DROP * elapsed / SWITCH_TIME
+                }
+            }
+            WeaponState::Raising { elapsed } => {
+                let elapsed = elapsed + dt;
+                if elapsed >= SWITCH_TIME {
+                    self.state = WeaponState::Ready;
+                    0.0
+                } else {
+                    self.state = WeaponState::Raising { elapsed };
+                    -SWITCH_DROP * (1.0 - elapsed / SWITCH_TIME)
+                }
+            }
+        }
     }
 
     pub fn get_model(&self) -> Handle<Node> {
         self.model
     }
 
-    pub fn update(&mut self, scene: &mut Scene, actors: &ActorContainer) {
+    pub fn update(&mut self, scene: &mut Scene, actors: &ActorContainer, dt: f32) {
         self.offset.follow(&self.dest_offset, 0.2);
 
+        let drop = self.update_state(&mut scene.graph, dt);
+        if let Some(charge_time) = self.charge_time.as_mut() {
+            *charge_time += dt;
+        }
+
         self.update_laser_sight(&mut scene.graph, actors);
+        self.update_laser_visual(&mut scene.graph, actors);
 
         let node = &mut scene.graph[self.model];
-        node.local_transform_mut().set_position(self.offset);
+        node.local_transform_mut()
+            .set_position(self.offset + Vector3::new(0.0, drop, 0.0));
         self.shot_position = node.global_position();
     }
 
@@ -202,53 +815,101 @@ impl Weapon {
         graph[self.model].global_transform().basis()
     }
 
-    pub fn add_ammo(&mut self, amount: u32) {
-        self.ammo += amount;
+    /// Adds ammo up to [`WeaponDefinition::max_ammo`], returns how much was actually added.
+    pub fn add_ammo(&mut self, amount: u32) -> u32 {
+        let added = amount.min(self.definition().max_ammo.saturating_sub(self.ammo));
+        self.ammo += added;
+        added
+    }
+
+    pub fn is_ammo_full(&self) -> bool {
+        self.ammo >= self.definition().max_ammo
     }
 
-    fn update_laser_sight(&self, graph: &mut Graph, actors: &ActorContainer) {
-        let mut laser_dot_position = Vector3::default();
+    fn update_laser_sight(&mut self, graph: &mut Graph, actors: &ActorContainer) {
         let model = &graph[self.model];
         let begin = model.global_position();
-        let end = begin + model.look_vector().scale(100.0);
+        let end = begin + model.look_vector().scale(LASER_RANGE);
         let ray = Ray::from_two_points(begin, end);
-        let mut query_buffer = Vec::default();
-        graph.physics.cast_ray(
-            RayCastOptions {
-                ray_origin: Point3::from(ray.origin),
-                ray_direction: ray.dir,
-                max_len: f32::MAX,
-                groups: InteractionGroups::default(),
-                sort_results: true,
-            },
-            &mut query_buffer,
-        );
-        'hit_loop: for hit in query_buffer.iter() {
-            // Filter hit with owner capsule
-            for (handle, actor) in actors.pair_iter() {
-                if self.owner == handle && actor.collider == hit.collider {
-                    continue 'hit_loop;
+        let (laser_dot_position, aimed_actor) = QueryBufferPool::with(|query_buffer| {
+            graph.physics.cast_ray(
+                RayCastOptions {
+                    ray_origin: Point3::from(ray.origin),
+                    ray_direction: ray.dir,
+                    max_len: f32::MAX,
+                    groups: InteractionGroups::default(),
+                    sort_results: true,
+                },
+                query_buffer,
+            );
+            'hit_loop: for hit in query_buffer.iter() {
+                let mut hit_actor = Handle::NONE;
+                for (handle, actor) in actors.pair_iter() {
+                    if actor.collider == hit.collider {
+                        // Filter hit with owner capsule
+                        if self.owner == handle {
+                            continue 'hit_loop;
+                        }
+                        hit_actor = handle;
+                    }
                 }
-            }
 
-            let offset = hit
-                .normal
-                .try_normalize(std::f32::EPSILON)
-                .unwrap_or_default()
-                .scale(0.2);
-            laser_dot_position = hit.position.coords + offset;
-            break 'hit_loop;
-        }
+                let offset = hit
+                    .normal
+                    .try_normalize(std::f32::EPSILON)
+                    .unwrap_or_default()
+                    .scale(0.02);
+                return (hit.position.coords + offset, hit_actor);
+            }
+            (end, Handle::NONE)
+        });
+        self.aimed_actor = aimed_actor;
 
         graph[self.laser_dot]
             .local_transform_mut()
             .set_position(laser_dot_position);
     }
 
+    /// Stretches laser beam from muzzle to the dot. Beam is shown only together with weapon.
+    fn update_laser_visual(&mut self, graph: &mut Graph, actors: &ActorContainer) {
+        let visible = self.laser_enabled && graph[self.model].visibility();
+        graph[self.laser].set_visibility(visible);
+        if !visible {
+            return;
+        }
+
+        let begin = self.get_shot_position(graph);
+        let beam = graph[self.laser_dot].global_position() - begin;
+        let thickness = match actors.try_get(self.owner) {
+            Some(Actor::Player(_)) => PLAYER_LASER_THICKNESS,
+            _ => LASER_THICKNESS,
+        };
+        let rotation = UnitQuaternion::rotation_between(&Vector3::y(), &beam)
+            .unwrap_or_else(UnitQuaternion::identity);
+        graph[self.laser_beam]
+            .local_transform_mut()
+            .set_position(begin)
+            .set_rotation(rotation)
+            .set_scale(Vector3::new(thickness, beam.norm(), thickness));
+    }
+
+    pub fn is_laser_enabled(&self) -> bool {
+        self.laser_enabled
+    }
+
+    pub fn set_laser_enabled(&mut self, enabled: bool) {
+        self.laser_enabled = enabled;
+    }
+
     pub fn ammo(&self) -> u32 {
         self.ammo
     }
 
+    /// Returns actor on which laser sight rests, if any.
+    pub fn aimed_actor(&self) -> Handle<Actor> {
+        self.aimed_actor
+    }
+
     pub fn owner(&self) -> Handle<Actor> {
         self.owner
     }
@@ -261,37 +922,94 @@ impl Weapon {
         Self::get_definition(self.kind)
     }
 
-    pub fn try_shoot(&mut self, scene: &mut Scene, time: GameTime) -> bool {
-        if self.ammo != 0 && time.elapsed - self.last_shot_time >= self.definition().shoot_interval
-        {
-            self.ammo -= 1;
-
-            self.offset = Vector3::new(0.0, 0.0, -0.05);
-            self.last_shot_time = time.elapsed;
-
-            let position = self.get_shot_position(&scene.graph);
-
-            if let Some(sender) = self.sender.as_ref() {
-                sender
-                    .send(Message::PlaySound {
-                        path: PathBuf::from(self.definition().shot_sound),
-                        position,
-                        gain: 1.0,
-                        rolloff_factor: 5.0,
-                        radius: 3.0,
-                    })
-                    .unwrap();
-            }
+    /// Drops ammo that weapon can't hold, definition may have changed since ammo was given.
+    pub fn clamp_ammo(&mut self) {
+        self.ammo = self.ammo.min(self.definition().max_ammo);
+    }
 
+    fn can_shoot(&self, time: GameTime, infinite_ammo: bool) -> bool {
+        self.state == WeaponState::Ready
+            && (self.ammo != 0 || infinite_ammo)
+            && time.elapsed - self.last_shot_time >= self.definition().shoot_interval
+    }
+
+    /// Spends one shot: takes ammo (unless it is infinite), kicks weapon back and plays shot
+    /// sound.
+    fn fire(&mut self, scene: &mut Scene, time: GameTime, infinite_ammo: bool) {
+        if !infinite_ammo {
+            self.ammo = self.ammo.saturating_sub(1);
+        }
+
+        self.offset = Vector3::new(0.0, 0.0, -0.05);
+        self.last_shot_time = time.elapsed;
+
+        let position = self.get_shot_position(&scene.graph);
+
+        self.sender.send(Message::PlaySound {
+            path: PathBuf::from(&self.definition().shot_sound),
+            position,
+            gain: 1.0,
+            rolloff_factor: 5.0,
+            radius: 3.0,
+        });
+    }
+
+    /// Shoots if weapon is ready, `infinite_ammo` lets it shoot without spending ammo.
+    pub fn try_shoot(&mut self, scene: &mut Scene, time: GameTime, infinite_ammo: bool) -> bool {
+        if self.can_shoot(time, infinite_ammo) {
+            self.fire(scene, time, infinite_ammo);
             true
         } else {
             false
         }
     }
 
+    /// Starts charge of a weapon with [`WeaponDefinition::charge`], returns false if weapon
+    /// can't be charged or could not shoot right now.
+    pub fn start_charge(&mut self, time: GameTime, infinite_ammo: bool) -> bool {
+        if self.definition().charge.is_some()
+            && self.charge_time.is_none()
+            && self.can_shoot(time, infinite_ammo)
+        {
+            self.charge_time = Some(0.0);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Ends charge and spends a shot, returns charge time in seconds. Returns `None` if weapon
+    /// was not charged.
+    pub fn release_charge(
+        &mut self,
+        scene: &mut Scene,
+        time: GameTime,
+        infinite_ammo: bool,
+    ) -> Option<f32> {
+        let charge_time = self.charge_time.take()?;
+        self.fire(scene, time, infinite_ammo);
+        Some(charge_time)
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.charge_time.is_some()
+    }
+
+    /// Returns how much of the fuse has burnt down in 0.0..=1.0 range, `None` if weapon is
+    /// not charged.
+    pub fn charge_progress(&self) -> Option<f32> {
+        let charge = self.definition().charge.as_ref()?;
+        self.charge_time
+            .map(|charge_time| (charge_time / charge.fuse).min(1.0))
+    }
+
     pub fn clean_up(&mut self, scene: &mut Scene) {
-        scene.graph.remove_node(self.model);
-        scene.graph.remove_node(self.laser_dot);
+        // Weapon of a damaged save may have lost its nodes already.
+        for node in [self.model, self.laser] {
+            if scene.graph.is_valid_handle(node) {
+                scene.graph.remove_node(node);
+            }
+        }
     }
 }
 
@@ -317,13 +1035,21 @@ impl WeaponContainer {
         self.pool.free(weapon);
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Weapon> {
+        self.pool.iter()
+    }
+
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Weapon> {
         self.pool.iter_mut()
     }
 
-    pub fn update(&mut self, scene: &mut Scene, actors: &ActorContainer) {
+    pub fn pair_iter(&self) -> impl Iterator<Item = (Handle<Weapon>, &Weapon)> {
+        self.pool.pair_iter()
+    }
+
+    pub fn update(&mut self, scene: &mut Scene, actors: &ActorContainer, dt: f32) {
         for weapon in self.pool.iter_mut() {
-            weapon.update(scene, actors)
+            weapon.update(scene, actors, dt)
         }
     }
 }
@@ -341,3 +1067,103 @@ impl IndexMut<Handle<Weapon>> for WeaponContainer {
         &mut self.pool[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+
+    fn shooter(speed: f32, grounded: bool, crouched: bool) -> ShooterState {
+        ShooterState {
+            velocity: Vector3::new(speed, -3.0, 0.0),
+            grounded,
+            crouched,
+        }
+    }
+
+    #[test]
+    fn spread_stays_within_movement_bounds() {
+        for &kind in WeaponKind::ALL.iter() {
+            let definition = Weapon::builtin_definition(kind);
+            let movement = &definition.movement_spread;
+            let zoom_scale = definition
+                .zoom
+                .as_ref()
+                .map_or(1.0, |zoom| zoom.spread_scale);
+            let min = definition.spread
+                * zoom_scale.min(1.0)
+                * movement.moving.min(1.0)
+                * movement.airborne.min(movement.crouched).min(1.0);
+            let max = definition.spread
+                * zoom_scale.max(1.0)
+                * movement.moving.max(1.0)
+                * movement.airborne.max(movement.crouched).max(1.0);
+
+            for &zoom in [0.0, 0.5, 1.0].iter() {
+                for &speed in [0.0, MOVING_SPREAD_SPEED * 0.5, MOVING_SPREAD_SPEED * 10.0].iter() {
+                    for &(grounded, crouched) in
+                        [(true, false), (true, true), (false, false), (false, true)].iter()
+                    {
+                        let spread =
+                            shot_spread(&definition, zoom, &shooter(speed, grounded, crouched));
+                        assert!(
+                            spread >= min - f32::EPSILON && spread <= max + f32::EPSILON,
+                            "{:?}: spread {} is out of {}..={}",
+                            kind,
+                            spread,
+                            min,
+                            max
+                        );
+                    }
+                }
+            }
+
+            // Standing still on the ground without zoom gives base spread, vertical speed
+            // does not count as movement.
+            assert_eq!(
+                shot_spread(&definition, 0.0, &shooter(0.0, true, false)),
+                definition.spread
+            );
+            // Penalty of moving does not grow above the speed at which it applies fully.
+            assert_eq!(
+                shot_spread(&definition, 0.0, &shooter(MOVING_SPREAD_SPEED, true, false)),
+                shot_spread(
+                    &definition,
+                    0.0,
+                    &shooter(MOVING_SPREAD_SPEED * 10.0, true, false)
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn scattered_shots_stay_in_cone() {
+        let direction = Vector3::new(1.0, 0.5, -2.0).normalize();
+        let spread = 5.0f32;
+        let mut rng = GameRng::new(7);
+        for _ in 0..1000 {
+            let shot = apply_spread(direction, spread, &mut rng);
+            assert!((shot.norm() - 1.0).abs() < 1.0e-5);
+            let angle = shot.dot(&direction).min(1.0).acos().to_degrees();
+            assert!(angle <= spread + 1.0e-2, "angle {} is out of cone", angle);
+        }
+        // Straight up has no horizontal side vector, fallback one is used.
+        let shot = apply_spread(Vector3::y(), spread, &mut rng);
+        assert!(shot.dot(&Vector3::y()).min(1.0).acos().to_degrees() <= spread + 1.0e-2);
+        // No spread leaves direction as it is.
+        assert_eq!(apply_spread(direction, 0.0, &mut rng), direction);
+    }
+
+    #[test]
+    fn scatter_is_deterministic_for_seed() {
+        let direction = Vector3::new(0.0, 0.0, 1.0);
+        let shots = |seed| {
+            let mut rng = GameRng::new(seed);
+            (0..16)
+                .map(|_| apply_spread(direction, 3.0, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shots(42), shots(42));
+        assert_ne!(shots(42), shots(43));
+    }
+}